@@ -0,0 +1,64 @@
+use crate::report::RunReport;
+use serde::{Deserialize, Serialize};
+
+// 失败数太少时比例没有意义，至少达到这个数量才告警
+pub const MIN_ALERT_FAILURES: usize = 3;
+
+pub fn should_alert(report: &RunReport, failure_ratio: f32) -> bool {
+    let total = report.summarized
+        + report.updated
+        + report.empty
+        + report.gone
+        + report.refused
+        + report.failed;
+    report.failed >= MIN_ALERT_FAILURES && report.failed as f32 >= failure_ratio * total as f32
+}
+
+// 保存在 KV 中，保证每个时间窗口内最多发送一次告警
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AlertState {
+    pub last_sent: Option<u64>,
+}
+
+impl AlertState {
+    pub fn can_send(&self, now: u64, window_ms: u64) -> bool {
+        self.last_sent
+            .is_none_or(|last_sent| now.saturating_sub(last_sent) >= window_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(summarized: usize, failed: usize) -> RunReport {
+        RunReport {
+            summarized,
+            failed,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn alerts_when_failure_ratio_is_reached() {
+        assert!(should_alert(&report(0, 3), 0.5));
+        assert!(should_alert(&report(3, 3), 0.5));
+        assert!(!should_alert(&report(4, 3), 0.5));
+    }
+
+    #[test]
+    fn too_few_failures_never_alert() {
+        assert!(!should_alert(&report(0, MIN_ALERT_FAILURES - 1), 0.0));
+    }
+
+    #[test]
+    fn alerts_are_rate_limited_per_window() {
+        let mut state = AlertState::default();
+        assert!(state.can_send(1_000, 100));
+        state.last_sent = Some(1_000);
+        assert!(!state.can_send(1_099, 100));
+        assert!(state.can_send(1_100, 100));
+        // 时钟回拨时不发送
+        assert!(!state.can_send(500, 100));
+    }
+}
@@ -0,0 +1,56 @@
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+// Basic 认证用第一个冒号分隔用户名和密码，用户名中的冒号会被当成分隔符，密码中的冒号不受影响
+pub fn check_username(username: &str) -> Result<(), String> {
+    if username.contains(':') {
+        return Err(
+            "MINIFLUX_USERNAME contains ':', which Basic auth cannot represent; use MINIFLUX_API_TOKEN instead"
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+// Rust 字符串本身就是 UTF-8，按字节编码即可，非 ASCII 字符不需要额外转义
+pub fn basic_auth(username: &str, password: &str) -> Result<String, String> {
+    check_username(username)?;
+    Ok(format!(
+        "Basic {}",
+        STANDARD.encode(format!("{}:{}", username, password).as_bytes())
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(header: &str) -> String {
+        let encoded = header.strip_prefix("Basic ").unwrap();
+        String::from_utf8(STANDARD.decode(encoded).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn encodes_ascii_credentials() {
+        assert_eq!(
+            basic_auth("admin", "secret").unwrap(),
+            "Basic YWRtaW46c2VjcmV0"
+        );
+    }
+
+    // 非 ASCII 字符按 UTF-8 字节编码
+    #[test]
+    fn encodes_non_ascii_credentials_as_utf8() {
+        let header = basic_auth("用户", "密码🔑").unwrap();
+        assert_eq!(header, "Basic 55So5oi3OuWvhueggfCflJE=");
+        assert_eq!(decode(&header), "用户:密码🔑");
+    }
+
+    #[test]
+    fn colons_are_allowed_only_in_the_password() {
+        let header = basic_auth("admin", "a:b:c").unwrap();
+        assert_eq!(decode(&header).split_once(':'), Some(("admin", "a:b:c")));
+        assert!(check_username("ad:min").is_err());
+        assert!(basic_auth("ad:min", "secret").is_err());
+        assert!(check_username("").is_ok());
+    }
+}
@@ -0,0 +1,38 @@
+use crate::report::SkipReason;
+
+// Miniflux 按 ID 批量修改状态时每个请求带的文章数
+pub const MAX_IDS_PER_REQUEST: usize = 100;
+
+// 只允许由文章本身决定、下次运行结果也不会变的跳过原因，
+// 订阅源停用、预算、抽样等暂时性的原因不能自动标为已读
+pub fn reason(name: &str) -> Result<SkipReason, String> {
+    match name.trim().to_lowercase().as_str() {
+        "non_whitelist" | "not_whitelisted" => Ok(SkipReason::NotWhitelisted),
+        "too_short" => Ok(SkipReason::TooShort),
+        "too_old" => Err("too_old: entries are never skipped by age".to_string()),
+        other => Err(format!(
+            "{}: not a skip reason that can be marked read",
+            other
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_markable_reasons() {
+        assert_eq!(reason(" Non_Whitelist "), Ok(SkipReason::NotWhitelisted));
+        assert_eq!(reason("not_whitelisted"), Ok(SkipReason::NotWhitelisted));
+        assert_eq!(reason("too_short"), Ok(SkipReason::TooShort));
+    }
+
+    // 暂时性的原因下次运行可能不再跳过，不能标为已读
+    #[test]
+    fn rejects_transient_or_unknown_reasons() {
+        for name in ["feed_disabled", "budget", "sampled_out", "too_old", "typo"] {
+            assert!(reason(name).is_err(), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::process::{exit, Command};
+
+const USAGE: &str = "Usage:
+  miniflux-ai sign --secret <secret> --file <payload.json>
+  miniflux-ai size-check [--budget-kb <kb>] [--features <list>] [--file <index.wasm>] [--skip-build]";
+
+// Workers 免费版对压缩后脚本体积的限制
+const DEFAULT_BUDGET_KB: u64 = 1024;
+const DEFAULT_WASM_FILE: &str = "build/worker/index.wasm";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("sign") => sign(&args[1..]),
+        Some("size-check") => size_check(&args[1..]),
+        _ => usage(),
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    exit(2);
+}
+
+// 为保存下来的 Webhook 请求体生成 X-Miniflux-Signature，便于向测试环境重放
+fn sign(args: &[String]) {
+    let Some((secret, file)) = parse_sign_args(args) else {
+        usage();
+    };
+    match sign_file(&secret, &file) {
+        Ok(signature) => println!("{}", signature),
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(1);
+        }
+    }
+}
+
+fn parse_sign_args(args: &[String]) -> Option<(String, String)> {
+    let mut secret = None;
+    let mut file = None;
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--secret" => secret = rest.next().cloned(),
+            "--file" => file = rest.next().cloned(),
+            _ => return None,
+        }
+    }
+    Some((secret?, file?))
+}
+
+fn sign_file(secret: &str, file: &str) -> Result<String, String> {
+    let payload =
+        std::fs::read_to_string(file).map_err(|e| format!("Failed to read {}: {}", file, e))?;
+    Ok(miniflux_ai::sign(secret, &payload))
+}
+
+// 用 worker-build 构建 wasm，检查 gzip 压缩后的体积是否超出预算，可以在 CI 中运行
+fn size_check(args: &[String]) {
+    let mut budget_kb = std::env::var("WASM_SIZE_BUDGET_KB")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(DEFAULT_BUDGET_KB);
+    let mut file = DEFAULT_WASM_FILE.to_string();
+    let mut features = None;
+    let mut build = true;
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--budget-kb" => match rest.next().and_then(|v| v.parse().ok()) {
+                Some(kb) => budget_kb = kb,
+                None => usage(),
+            },
+            "--file" => match rest.next() {
+                Some(path) => file = path.clone(),
+                None => usage(),
+            },
+            // 和 wrangler.toml 中的构建命令保持一致，检查的才是实际部署的体积
+            "--features" => match rest.next() {
+                Some(list) => features = Some(list.clone()),
+                None => usage(),
+            },
+            "--skip-build" => build = false,
+            _ => usage(),
+        }
+    }
+
+    if build {
+        let mut command = Command::new("worker-build");
+        command.arg("--release");
+        if let Some(features) = &features {
+            command.args(["--", "--features", features]);
+        }
+        match command.status() {
+            Ok(status) if status.success() => {}
+            Ok(status) => {
+                eprintln!("worker-build failed: {}", status);
+                exit(1);
+            }
+            Err(e) => {
+                eprintln!("Failed to run worker-build: {}", e);
+                exit(1);
+            }
+        }
+    }
+
+    let wasm = match std::fs::read(&file) {
+        Ok(wasm) => wasm,
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", file, e);
+            exit(1);
+        }
+    };
+    let size = compressed_size(&wasm);
+    let budget = budget_kb * 1024;
+    println!(
+        "{}: {} KiB, {} KiB compressed, budget {} KiB",
+        file,
+        wasm.len() / 1024,
+        size / 1024,
+        budget_kb
+    );
+    if size > budget {
+        eprintln!(
+            "Compressed size exceeds the budget by {} KiB ({} bytes)",
+            (size - budget).div_ceil(1024),
+            size - budget
+        );
+        exit(1);
+    }
+}
+
+#[cfg(feature = "compression")]
+fn compressed_size(data: &[u8]) -> u64 {
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder
+        .write_all(data)
+        .and_then(|_| encoder.finish())
+        .map(|compressed| compressed.len() as u64)
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to compress: {}", e);
+            exit(1);
+        })
+}
+
+#[cfg(not(feature = "compression"))]
+fn compressed_size(_data: &[u8]) -> u64 {
+    eprintln!("size-check needs the compression feature");
+    exit(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_sign_args_in_any_order() {
+        let expected = Some(("s".to_string(), "f.json".to_string()));
+        assert_eq!(
+            parse_sign_args(&args(&["--secret", "s", "--file", "f.json"])),
+            expected
+        );
+        assert_eq!(
+            parse_sign_args(&args(&["--file", "f.json", "--secret", "s"])),
+            expected
+        );
+    }
+
+    #[test]
+    fn rejects_incomplete_sign_args() {
+        assert_eq!(parse_sign_args(&args(&["--secret", "s"])), None);
+        assert_eq!(parse_sign_args(&args(&["--file", "f.json"])), None);
+        assert_eq!(parse_sign_args(&args(&["--secret"])), None);
+        assert_eq!(
+            parse_sign_args(&args(&["--secret", "s", "--file", "f", "--x"])),
+            None
+        );
+    }
+
+    #[test]
+    fn signs_file_contents() {
+        let path =
+            std::env::temp_dir().join(format!("miniflux-ai-sign-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"entry_id": 123}"#).unwrap();
+        let signature = sign_file("secret", path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        let signature = signature.unwrap();
+        assert_eq!(
+            signature,
+            "26731949519cd3fb35447f7a39784d8a6128e6a9f112705596535e74dcbc1f40"
+        );
+        assert!(miniflux_ai::verify(
+            "secret",
+            r#"{"entry_id": 123}"#,
+            &signature
+        ));
+    }
+
+    #[test]
+    fn reports_missing_file() {
+        let error = sign_file("secret", "/nonexistent/miniflux-ai.json").unwrap_err();
+        assert!(error.starts_with("Failed to read /nonexistent/miniflux-ai.json"));
+    }
+}
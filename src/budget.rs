@@ -0,0 +1,92 @@
+use crate::shared_state::Shared;
+
+// 粗略估算的 token 数，按每 4 个字符约 1 个 token 计算
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+// 每次运行的 token 预算，所有并发任务共享同一个计数
+pub struct TokenBudget {
+    limit: Option<usize>,
+    used: Shared<usize>,
+}
+
+impl TokenBudget {
+    pub fn new(limit: Option<usize>) -> Self {
+        TokenBudget {
+            limit,
+            used: Shared::new(0),
+        }
+    }
+
+    // 预算足够时占用并返回 true，否则不占用
+    pub fn try_reserve(&self, tokens: usize) -> bool {
+        self.used.try_update(|used| {
+            let used = used + tokens;
+            self.limit.is_none_or(|limit| used <= limit).then_some(used)
+        })
+    }
+
+    pub fn used(&self) -> usize {
+        self.used.get()
+    }
+
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared_state::yield_now;
+    use futures::executor::block_on;
+    use futures::{stream, StreamExt};
+
+    #[test]
+    fn estimates_four_chars_per_token() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+        assert_eq!(estimate_tokens("摘要摘要摘"), 2);
+    }
+
+    #[test]
+    fn unlimited_budget_always_reserves() {
+        let budget = TokenBudget::new(None);
+        assert!(budget.try_reserve(usize::MAX / 2));
+        assert_eq!(budget.used(), usize::MAX / 2);
+    }
+
+    #[test]
+    fn rejected_reservation_uses_nothing() {
+        let budget = TokenBudget::new(Some(100));
+        assert!(budget.try_reserve(60));
+        assert!(!budget.try_reserve(41));
+        assert_eq!(budget.used(), 60);
+        assert!(budget.try_reserve(40));
+        assert_eq!(budget.used(), 100);
+    }
+
+    // 和 process_entries 一样用 buffer_unordered 并发预留，总量不会超过上限
+    #[test]
+    fn concurrent_reservations_never_exceed_limit() {
+        let budget = TokenBudget::new(Some(100));
+        let reserved = block_on(
+            stream::iter(0..20)
+                .map(|_| {
+                    let budget = &budget;
+                    async move {
+                        yield_now().await;
+                        let reserved = budget.try_reserve(15);
+                        yield_now().await;
+                        reserved
+                    }
+                })
+                .buffer_unordered(5)
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(reserved.iter().filter(|r| **r).count(), 6);
+        assert_eq!(budget.used(), 90);
+    }
+}
@@ -0,0 +1,312 @@
+use crate::feed_stats::DAY_MS;
+use crate::settings::FeedSettings;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// CANARY 的内容，例如 `{"fraction": 0.05, "provider": "workers-ai", "model": "@cf/meta/llama-3.1-8b-instruct"}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Canary {
+    pub fraction: f64,
+    #[serde(default)]
+    pub salt: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+}
+
+// 切换服务商时模型名称通常也不同，所以必须同时指定模型
+pub fn parse(raw: &str) -> Result<Canary, String> {
+    let canary: Canary = serde_json::from_str(raw).map_err(|e| e.to_string())?;
+    if !(canary.fraction > 0.0 && canary.fraction <= 1.0) {
+        return Err("fraction must be greater than 0 and at most 1".to_string());
+    }
+    if canary.provider.is_none() && canary.model.is_none() && canary.prompt.is_none() {
+        return Err("at least one of provider, model and prompt is required".to_string());
+    }
+    if canary.provider.is_some() && canary.model.is_none() {
+        return Err("provider needs a model".to_string());
+    }
+    if let Some(prompt) = &canary.prompt {
+        crate::prompts::resolve(prompt)?;
+    }
+    Ok(canary)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arm {
+    Control,
+    Canary,
+}
+
+impl Arm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Arm::Control => "control",
+            Arm::Canary => "canary",
+        }
+    }
+}
+
+impl Canary {
+    // 按文章 id 的哈希分组，同一篇文章每次运行都在同一组
+    pub fn arm(&self, entry_id: u64) -> Arm {
+        let salt = format!("canary:{}", self.salt);
+        if crate::sampling::is_included(entry_id, &salt, self.fraction) {
+            Arm::Canary
+        } else {
+            Arm::Control
+        }
+    }
+
+    pub fn apply(&self, settings: &FeedSettings) -> FeedSettings {
+        FeedSettings {
+            model: self.model.clone().unwrap_or_else(|| settings.model.clone()),
+            prompt: self
+                .prompt
+                .clone()
+                .unwrap_or_else(|| settings.prompt.clone()),
+            prompt_overridden: settings.prompt_overridden || self.prompt.is_some(),
+            ..settings.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Attempt {
+    Succeeded,
+    Refused,
+    // 输出没有通过校验，例如超出长度
+    Rejected,
+    Failed,
+    // 没有生成摘要，例如内容为空或文章已删除
+    Other,
+}
+
+impl Attempt {
+    pub fn is_failure(&self) -> bool {
+        matches!(self, Attempt::Refused | Attempt::Rejected | Attempt::Failed)
+    }
+}
+
+// 每组一天内的累计值，比例和平均值在查询时计算
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArmStats {
+    pub entries: u32,
+    pub succeeded: u32,
+    pub refused: u32,
+    pub rejected: u32,
+    pub failed: u32,
+    // 金丝雀组失败后改用原设置重新处理的次数
+    pub fallbacks: u32,
+    pub latency_ms: u64,
+}
+
+impl ArmStats {
+    pub fn record(&mut self, attempt: Attempt, latency_ms: u64, fell_back: bool) {
+        self.entries += 1;
+        self.latency_ms += latency_ms;
+        match attempt {
+            Attempt::Succeeded => self.succeeded += 1,
+            Attempt::Refused => self.refused += 1,
+            Attempt::Rejected => self.rejected += 1,
+            Attempt::Failed => self.failed += 1,
+            Attempt::Other => {}
+        }
+        if fell_back {
+            self.fallbacks += 1;
+        }
+    }
+
+    fn add(&mut self, other: &ArmStats) {
+        self.entries += other.entries;
+        self.succeeded += other.succeeded;
+        self.refused += other.refused;
+        self.rejected += other.rejected;
+        self.failed += other.failed;
+        self.fallbacks += other.fallbacks;
+        self.latency_ms += other.latency_ms;
+    }
+}
+
+// 键为 `{天数}|{组名}`
+pub fn day_key(arm: Arm, now: u64) -> String {
+    format!("{}|{}", now / DAY_MS, arm.as_str())
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ArmReport {
+    #[serde(flatten)]
+    pub totals: ArmStats,
+    pub success_rate: Option<f64>,
+    pub failure_rate: Option<f64>,
+    pub avg_latency_ms: Option<u64>,
+}
+
+// 合并最近 `days` 天（含今天）的记录，没有记录的组也会列出
+pub fn report<'a>(
+    records: impl IntoIterator<Item = (&'a str, &'a ArmStats)>,
+    now: u64,
+    days: u64,
+) -> BTreeMap<&'static str, ArmReport> {
+    let today = now / DAY_MS;
+    let first_day = today.saturating_sub(days.saturating_sub(1));
+    let mut totals: BTreeMap<&'static str, ArmStats> = [Arm::Control, Arm::Canary]
+        .into_iter()
+        .map(|arm| (arm.as_str(), ArmStats::default()))
+        .collect();
+    for (key, stats) in records {
+        let Some((day, arm)) = key.split_once('|') else {
+            continue;
+        };
+        let Ok(day) = day.parse::<u64>() else {
+            continue;
+        };
+        if day < first_day || day > today {
+            continue;
+        }
+        if let Some(total) = totals.get_mut(arm) {
+            total.add(stats);
+        }
+    }
+
+    totals
+        .into_iter()
+        .map(|(arm, totals)| {
+            let rate =
+                |count: u32| (totals.entries > 0).then(|| count as f64 / totals.entries as f64);
+            let report = ArmReport {
+                success_rate: rate(totals.succeeded),
+                failure_rate: rate(totals.refused + totals.rejected + totals.failed),
+                avg_latency_ms: (totals.entries > 0)
+                    .then(|| totals.latency_ms / totals.entries as u64),
+                totals,
+            };
+            (arm, report)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::{AggregationPolicy, Features};
+
+    fn settings() -> FeedSettings {
+        FeedSettings {
+            prompt: "default".to_string(),
+            prompt_overridden: false,
+            fetch_full_content: false,
+            summarize_comments: false,
+            extract_entities: false,
+            sticky_summary: false,
+            feed_context: false,
+            context_hints: 0,
+            translate_body: false,
+            aggregation: AggregationPolicy::SummarizeAnyway,
+            model: "gpt-4o-mini".to_string(),
+            style: None,
+            features: Features {
+                summarize: true,
+                tags: false,
+                translate_title: false,
+                score: false,
+            },
+        }
+    }
+
+    #[test]
+    fn parses_valid_canaries() {
+        let canary =
+            parse(r#"{"fraction": 0.05, "provider": "workers-ai", "model": "@cf/x"}"#).unwrap();
+        assert_eq!(canary.fraction, 0.05);
+        assert_eq!(canary.salt, "");
+        assert!(parse(r#"{"fraction": 1, "prompt": "preset:eli5"}"#).is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_canaries() {
+        for raw in [
+            r#"{"fraction": 0, "model": "m"}"#,
+            r#"{"fraction": 1.5, "model": "m"}"#,
+            r#"{"fraction": 0.1}"#,
+            r#"{"fraction": 0.1, "provider": "workers-ai"}"#,
+            r#"{"fraction": 0.1, "prompt": "preset:unknown"}"#,
+            r#"{"fraction": 0.1, "model": "m", "budget": 1}"#,
+        ] {
+            assert!(parse(raw).is_err(), "{}", raw);
+        }
+    }
+
+    // 同一篇文章每次都分到同一组，比例接近 fraction，不同 salt 分组不同
+    #[test]
+    fn assigns_arms_deterministically() {
+        let canary = parse(r#"{"fraction": 0.2, "model": "m"}"#).unwrap();
+        let arms: Vec<Arm> = (0..5_000).map(|id| canary.arm(id)).collect();
+        assert_eq!(
+            arms,
+            (0..5_000).map(|id| canary.arm(id)).collect::<Vec<_>>()
+        );
+        let canaries = arms.iter().filter(|arm| **arm == Arm::Canary).count();
+        assert!((800..1_200).contains(&canaries), "{}", canaries);
+
+        let salted = Canary {
+            salt: "v2".to_string(),
+            ..canary.clone()
+        };
+        assert!((0..5_000).any(|id| salted.arm(id) != canary.arm(id)));
+        // 分组和 SAMPLE_RATE 的抽样互不相关
+        assert!((0..5_000).any(|id| {
+            (canary.arm(id) == Arm::Canary) != crate::sampling::is_included(id, "", 0.2)
+        }));
+    }
+
+    #[test]
+    fn applies_only_the_changed_settings() {
+        let model_only = parse(r#"{"fraction": 0.1, "model": "gpt-4o"}"#).unwrap();
+        let applied = model_only.apply(&settings());
+        assert_eq!(applied.model, "gpt-4o");
+        assert_eq!(applied.prompt, "default");
+        assert!(!applied.prompt_overridden);
+
+        let prompt_only = parse(r#"{"fraction": 0.1, "prompt": "preset:eli5"}"#).unwrap();
+        let applied = prompt_only.apply(&settings());
+        assert_eq!(applied.model, "gpt-4o-mini");
+        assert_eq!(applied.prompt, "preset:eli5");
+        assert!(applied.prompt_overridden);
+    }
+
+    #[test]
+    fn reports_both_arms_over_recent_days() {
+        let now = 50 * DAY_MS;
+        let mut canary = ArmStats::default();
+        canary.record(Attempt::Succeeded, 100, false);
+        canary.record(Attempt::Refused, 300, true);
+        canary.record(Attempt::Other, 200, false);
+        let mut old = ArmStats::default();
+        old.record(Attempt::Failed, 1_000, false);
+        let keys = [
+            day_key(Arm::Canary, now),
+            day_key(Arm::Canary, now - 10 * DAY_MS),
+            "bogus|canary".to_string(),
+            format!("{}|other", now / DAY_MS),
+        ];
+        let records = [&canary, &old, &old, &old];
+        let report = report(keys.iter().map(String::as_str).zip(records), now, 7);
+
+        let canary = &report["canary"];
+        assert_eq!(canary.totals.entries, 3);
+        assert_eq!(canary.totals.fallbacks, 1);
+        assert_eq!(canary.success_rate, Some(1.0 / 3.0));
+        assert_eq!(canary.failure_rate, Some(1.0 / 3.0));
+        assert_eq!(canary.avg_latency_ms, Some(200));
+        // 没有记录的组也会列出
+        assert_eq!(report["control"].totals, ArmStats::default());
+        assert_eq!(report["control"].success_rate, None);
+        assert!(Attempt::Rejected.is_failure() && !Attempt::Other.is_failure());
+    }
+}
@@ -0,0 +1,58 @@
+use std::str::FromStr;
+
+// 文章类别，由模型判断，用于只摘要指定类别的文章
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Category {
+    Longform,
+    News,
+    Other,
+}
+
+impl Category {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Category::Longform => "longform",
+            Category::News => "news",
+            Category::Other => "other",
+        }
+    }
+}
+
+impl FromStr for Category {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().replace(['-', ' '], "_").as_str() {
+            "longform" | "long_form" => Ok(Category::Longform),
+            "news" | "news_brief" | "brief" => Ok(Category::News),
+            "other" => Ok(Category::Other),
+            other => Err(format!("Unknown category: {}", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_model_labels_leniently() {
+        for (raw, category) in [
+            ("longform", Category::Longform),
+            (" Long-Form ", Category::Longform),
+            ("news brief", Category::News),
+            ("NEWS", Category::News),
+            ("other", Category::Other),
+        ] {
+            assert_eq!(raw.parse::<Category>(), Ok(category), "{}", raw);
+        }
+        assert!("opinion".parse::<Category>().is_err());
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for category in [Category::Longform, Category::News, Category::Other] {
+            assert_eq!(category.as_str().parse::<Category>(), Ok(category));
+        }
+    }
+}
@@ -0,0 +1,52 @@
+// 把连续的段落打包成不超过 max_chars 的分块，超长的段落单独成块
+// 返回每个分块的段落下标范围，翻译结果按下标放回原位置，保证段落一一对应
+pub fn pack(paragraphs: &[String], max_chars: usize) -> Vec<std::ops::Range<usize>> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut size = 0;
+
+    for (i, paragraph) in paragraphs.iter().enumerate() {
+        let len = paragraph.chars().count();
+        if i > start && size + len > max_chars {
+            chunks.push(start..i);
+            start = i;
+            size = 0;
+        }
+        size += len;
+    }
+    if start < paragraphs.len() {
+        chunks.push(start..paragraphs.len());
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paragraphs(lens: &[usize]) -> Vec<String> {
+        lens.iter().map(|&len| "字".repeat(len)).collect()
+    }
+
+    #[test]
+    fn packs_paragraphs_up_to_the_limit() {
+        assert_eq!(pack(&paragraphs(&[3, 3, 3, 3]), 6), vec![0..2, 2..4]);
+        assert_eq!(pack(&paragraphs(&[3, 3, 3]), 100), vec![0..3]);
+    }
+
+    // 超长的段落单独成块，不会被拆开或丢弃
+    #[test]
+    fn oversized_paragraph_gets_its_own_chunk() {
+        assert_eq!(pack(&paragraphs(&[2, 10, 2]), 5), vec![0..1, 1..2, 2..3]);
+        assert_eq!(pack(&paragraphs(&[10]), 5), vec![0..1]);
+    }
+
+    #[test]
+    fn chunks_cover_every_paragraph_once() {
+        let lens = [1, 7, 3, 3, 9, 2, 2, 2, 5];
+        let chunks = pack(&paragraphs(&lens), 6);
+        let covered: Vec<usize> = chunks.iter().flat_map(|c| c.clone()).collect();
+        assert_eq!(covered, (0..lens.len()).collect::<Vec<_>>());
+        assert!(pack(&[], 6).is_empty());
+    }
+}
@@ -0,0 +1,107 @@
+use crate::html::Heading;
+use serde::Deserialize;
+
+// 标题太多时只列出前面的部分，避免提示词过长
+pub const MAX_OUTLINE_HEADINGS: usize = 40;
+
+// 开启 SECTION_CITATIONS 且文章有小标题时追加到系统提示词后
+pub const CITATIONS_PROMPT: &str = "Respond with a JSON object only, in the form {\"takeaways\": [{\"text\": \"...\", \"section\": \"...\"}]}. Split the summary described above into 3 to 6 takeaways. \"section\" is the heading from the outline below that the takeaway is taken from, copied exactly; omit it when the takeaway does not come from a single section.\nOutline:\n{outline}";
+
+#[derive(Debug, Deserialize)]
+pub struct Takeaways {
+    pub takeaways: Vec<Takeaway>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Takeaway {
+    pub text: String,
+    #[serde(default)]
+    pub section: Option<String>,
+}
+
+impl Takeaways {
+    // 不带出处的要点文字，用于长度校验和套话检测
+    pub fn plain(&self) -> String {
+        self.takeaways
+            .iter()
+            .map(|t| t.text.trim())
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+pub fn prompt(outline: &[Heading]) -> String {
+    let outline = outline
+        .iter()
+        .take(MAX_OUTLINE_HEADINGS)
+        .map(|h| {
+            let indent = if h.level > 2 { "  " } else { "" };
+            format!("{}- {}", indent, h.text)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    CITATIONS_PROMPT.replace("{outline}", &outline)
+}
+
+// 每条要点一行，出处不在大纲中时不显示，避免模型编造的小节名
+pub fn render(takeaways: &Takeaways, outline: &[Heading]) -> String {
+    takeaways
+        .takeaways
+        .iter()
+        .filter(|t| !t.text.trim().is_empty())
+        .map(|t| {
+            let section = t.section.as_deref().and_then(|section| {
+                let section = section.trim().trim_start_matches('§').trim();
+                outline
+                    .iter()
+                    .find(|h| h.text.to_lowercase() == section.to_lowercase())
+            });
+            match section {
+                Some(heading) => format!("• {} (§ {})", t.text.trim(), heading.text),
+                None => format!("• {}", t.text.trim()),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heading(level: u8, text: &str) -> Heading {
+        Heading {
+            level,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn prompt_lists_the_outline() {
+        let prompt = prompt(&[heading(2, "Intro"), heading(3, "Details")]);
+        assert!(prompt.ends_with("Outline:\n- Intro\n  - Details"));
+        let long: Vec<_> = (0..50).map(|i| heading(2, &i.to_string())).collect();
+        assert!(!super::prompt(&long).contains("- 40"));
+    }
+
+    // 出处不在大纲中时只显示要点
+    #[test]
+    fn renders_known_sections_only() {
+        let takeaways: Takeaways = serde_json::from_str(
+            r#"{"takeaways": [
+                {"text": " Rates rose ", "section": "§ market NEWS"},
+                {"text": "Made up", "section": "Conclusion"},
+                {"text": "No section"},
+                {"text": "  "}
+            ]}"#,
+        )
+        .unwrap();
+        let outline = [heading(2, "Market news")];
+        assert_eq!(
+            render(&takeaways, &outline),
+            "• Rates rose (§ Market news)\n• Made up\n• No section"
+        );
+        assert_eq!(takeaways.plain(), "Rates rose\nMade up\nNo section");
+    }
+}
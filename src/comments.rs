@@ -0,0 +1,84 @@
+use crate::html::extract_text;
+
+// 从讨论页中提取顶层评论：优先按 Hacker News 的结构解析，其次尝试 Lobsters
+pub fn extract_comments(html: &str, max_comments: usize) -> Vec<String> {
+    let comments = extract_hn_comments(html, max_comments);
+    if !comments.is_empty() {
+        return comments;
+    }
+
+    extract_by_class(html, "comment_text", max_comments)
+}
+
+// HN 的每条评论是一个 `<tr class="athing comtr">`，缩进为 0 的是顶层评论
+fn extract_hn_comments(html: &str, max_comments: usize) -> Vec<String> {
+    html.split("class=\"athing comtr")
+        .skip(1)
+        .filter(|row| attr_value(row, "indent") == Some("0"))
+        .filter_map(|row| element_text(row, "class=\"commtext"))
+        .take(max_comments)
+        .collect()
+}
+
+fn extract_by_class(html: &str, class: &str, max_comments: usize) -> Vec<String> {
+    let marker = format!("class=\"{}", class);
+    html.match_indices(marker.as_str())
+        .filter_map(|(i, _)| element_text(&html[i..], &marker))
+        .take(max_comments)
+        .collect()
+}
+
+fn attr_value<'a>(html: &'a str, name: &str) -> Option<&'a str> {
+    let needle = format!("{}=\"", name);
+    let start = html.find(&needle)? + needle.len();
+    let end = html[start..].find('"')? + start;
+    Some(&html[start..end])
+}
+
+// 取 marker 所在元素的文本，直到第一个 `</div>` 为止
+fn element_text(html: &str, marker: &str) -> Option<String> {
+    let start = html.find(marker)?;
+    let body_start = html[start..].find('>')? + start + 1;
+    let body_end = html[body_start..]
+        .find("</div>")
+        .map_or(html.len(), |i| body_start + i);
+    let text = extract_text(&html[body_start..body_end]);
+    (!text.is_empty()).then_some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HN: &str = r#"<table>
+        <tr class="athing comtr" id="1"><td class="ind" indent="0"></td>
+            <div class="commtext c00">First <i>top</i> comment</div></tr>
+        <tr class="athing comtr" id="2"><td class="ind" indent="1"></td>
+            <div class="commtext c00">A reply</div></tr>
+        <tr class="athing comtr" id="3"><td class="ind" indent="0"></td>
+            <div class="commtext c00">Second &amp; last</div></tr>
+    </table>"#;
+
+    #[test]
+    fn extracts_top_level_hn_comments() {
+        assert_eq!(
+            extract_comments(HN, 10),
+            vec!["First top comment", "Second & last"]
+        );
+        assert_eq!(extract_comments(HN, 1), vec!["First top comment"]);
+    }
+
+    #[test]
+    fn falls_back_to_lobsters_comment_text() {
+        let html = r#"<div class="comment_text"><p>One</p></div>
+            <div class="comment_text"></div>
+            <div class="comment_text"><p>Two</p></div>"#;
+        assert_eq!(extract_comments(html, 10), vec!["One", "Two"]);
+    }
+
+    #[test]
+    fn pages_without_comments_give_nothing() {
+        assert!(extract_comments("<p>No discussion yet</p>", 10).is_empty());
+        assert!(extract_comments("", 10).is_empty());
+    }
+}
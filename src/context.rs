@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+
+// 回顾中最多保留的字符数，避免连载类订阅源的提示词越来越长
+const MAX_RECAP_CHARS: usize = 600;
+
+// 每个订阅源最近一篇文章的摘要，为连载内容提供上下文
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedContext {
+    pub entry_id: u64,
+    pub summary: String,
+}
+
+// 上一篇就是当前文章（例如重新摘要）时不加回顾
+pub fn recap(context: &FeedContext, entry_id: u64) -> Option<String> {
+    let summary = context.summary.trim();
+    if context.entry_id == entry_id || summary.is_empty() {
+        return None;
+    }
+    let summary: String = summary.chars().take(MAX_RECAP_CHARS).collect();
+    Some(format!("Previously: {}", summary))
+}
+
+// CONTEXT_HINTS 开启时追加在用户消息前，要求模型只把它当作背景
+const HINTS_INTRO: &str = "Previous coverage from the same feed, for context only. Do not summarize it or mention it in your answer:";
+
+// 保留同一订阅源最近 limit 篇文章的摘要，同一篇文章重新摘要时替换旧的
+pub fn push_hint(hints: &mut Vec<FeedContext>, context: FeedContext, limit: usize) {
+    hints.retain(|hint| hint.entry_id != context.entry_id);
+    hints.push(context);
+    if hints.len() > limit {
+        hints.drain(..hints.len() - limit);
+    }
+}
+
+// 从最新的摘要开始选取，超过 max_tokens 后停止，输出时按时间先后排列
+pub fn hints_block(hints: &[FeedContext], entry_id: u64, max_tokens: usize) -> Option<String> {
+    let mut used = crate::budget::estimate_tokens(HINTS_INTRO);
+    let mut lines = Vec::new();
+    for hint in hints.iter().rev() {
+        let summary = hint.summary.trim();
+        if hint.entry_id == entry_id || summary.is_empty() {
+            continue;
+        }
+        let line = format!(
+            "- {}",
+            summary
+                .chars()
+                .take(MAX_RECAP_CHARS)
+                .collect::<String>()
+                .replace('\n', " ")
+        );
+        used += crate::budget::estimate_tokens(&line);
+        if used > max_tokens {
+            break;
+        }
+        lines.push(line);
+    }
+    if lines.is_empty() {
+        return None;
+    }
+    lines.reverse();
+    Some(format!("{}\n{}", HINTS_INTRO, lines.join("\n")))
+}
+
+// 订阅源简介可能带 HTML，截断后追加到系统提示词
+const MAX_DESCRIPTION_CHARS: usize = 300;
+
+pub fn describe(description: &str) -> Option<String> {
+    let text = crate::html::collapse_whitespace(&crate::html::extract_text(description));
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    let text: String = text.chars().take(MAX_DESCRIPTION_CHARS).collect();
+    Some(format!(
+        "The article comes from a feed described as: \"{}\". Use this only as background for niche terms and topics.",
+        text
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(entry_id: u64, summary: &str) -> FeedContext {
+        FeedContext {
+            entry_id,
+            summary: summary.to_string(),
+        }
+    }
+
+    #[test]
+    fn recaps_previous_entry() {
+        assert_eq!(
+            recap(&context(1, "  上一期要点  "), 2).as_deref(),
+            Some("Previously: 上一期要点")
+        );
+    }
+
+    // 重新摘要同一篇文章或上一篇摘要为空时不加回顾
+    #[test]
+    fn skips_same_entry_or_empty_summary() {
+        assert_eq!(recap(&context(1, "summary"), 1), None);
+        assert_eq!(recap(&context(1, " \n "), 2), None);
+    }
+
+    #[test]
+    fn recap_is_truncated_by_characters() {
+        let recap = recap(&context(1, &"长".repeat(1000)), 2).unwrap();
+        assert_eq!(
+            recap.chars().count(),
+            "Previously: ".len() + MAX_RECAP_CHARS
+        );
+    }
+
+    #[test]
+    fn keeps_latest_hints_per_entry() {
+        let mut hints = Vec::new();
+        for id in 1..=4 {
+            push_hint(&mut hints, context(id, &format!("s{}", id)), 3);
+        }
+        push_hint(&mut hints, context(3, "s3 again"), 3);
+        let kept: Vec<_> = hints
+            .iter()
+            .map(|h| (h.entry_id, h.summary.as_str()))
+            .collect();
+        assert_eq!(kept, vec![(2, "s2"), (4, "s4"), (3, "s3 again")]);
+    }
+
+    // 超出 token 上限时保留最新的摘要，输出按时间先后排列
+    #[test]
+    fn hints_block_prefers_recent_summaries() {
+        let hints = vec![
+            context(1, &"old ".repeat(40)),
+            context(2, "middle\nline"),
+            context(3, "current"),
+            context(4, "newest"),
+        ];
+        let intro = crate::budget::estimate_tokens(HINTS_INTRO);
+        assert_eq!(
+            hints_block(&hints, 3, intro + 10).unwrap(),
+            format!("{}\n- middle line\n- newest", HINTS_INTRO)
+        );
+        assert!(hints_block(&hints, 3, 10_000).unwrap().contains("- old"));
+        assert_eq!(hints_block(&hints, 3, intro), None);
+        assert_eq!(hints_block(&[context(3, "current")], 3, 10_000), None);
+    }
+
+    #[test]
+    fn describes_feed_as_plain_text() {
+        assert_eq!(
+            describe("<p>News about <b>Rust</b> &amp; WebAssembly</p>").unwrap(),
+            "The article comes from a feed described as: \"News about Rust & WebAssembly\". Use this only as background for niche terms and topics."
+        );
+        assert_eq!(describe("  <p> </p> "), None);
+        let long = describe(&"描".repeat(1000)).unwrap();
+        assert!(long.contains(&"描".repeat(MAX_DESCRIPTION_CHARS)));
+        assert!(!long.contains(&"描".repeat(MAX_DESCRIPTION_CHARS + 1)));
+    }
+}
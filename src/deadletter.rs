@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const MAX_DEADLETTERS: usize = 200;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub entry_id: u64,
+    pub site_url: Option<String>,
+    pub error: String,
+    pub failed_at: u64,
+    // 模型拒答的文章没有写失败提示，之后的运行靠这个标记跳过
+    #[serde(default)]
+    pub refused: bool,
+}
+
+// 记录每篇文章跨运行的连续失败次数，超过阈值后放入死信列表
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DeadLetterLog {
+    pub failures: HashMap<u64, u32>,
+    pub entries: Vec<DeadLetter>,
+}
+
+impl DeadLetterLog {
+    pub fn record_success(&mut self, entry_id: u64) -> bool {
+        self.failures.remove(&entry_id).is_some()
+    }
+
+    // Returns true when the entry reached the threshold and should be dead-lettered
+    pub fn record_failure(&mut self, entry_id: u64, threshold: u32) -> bool {
+        let count = self.failures.entry(entry_id).or_default();
+        *count += 1;
+        if threshold > 0 && *count >= threshold {
+            self.failures.remove(&entry_id);
+            return true;
+        }
+        false
+    }
+
+    pub fn is_refused(&self, entry_id: u64) -> bool {
+        self.entries
+            .iter()
+            .any(|letter| letter.entry_id == entry_id && letter.refused)
+    }
+
+    pub fn push(&mut self, letter: DeadLetter) {
+        self.entries.retain(|e| e.entry_id != letter.entry_id);
+        self.entries.push(letter);
+        if self.entries.len() > MAX_DEADLETTERS {
+            let overflow = self.entries.len() - MAX_DEADLETTERS;
+            self.entries.drain(..overflow);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn letter(entry_id: u64, failed_at: u64) -> DeadLetter {
+        DeadLetter {
+            entry_id,
+            site_url: None,
+            error: "timeout".to_string(),
+            failed_at,
+            refused: false,
+        }
+    }
+
+    #[test]
+    fn dead_letters_after_threshold_failures() {
+        let mut log = DeadLetterLog::default();
+        assert!(!log.record_failure(1, 3));
+        assert!(!log.record_failure(1, 3));
+        assert!(!log.record_failure(2, 3));
+        assert!(log.record_failure(1, 3));
+        // 进入死信后重新计数
+        assert!(!log.failures.contains_key(&1));
+        assert_eq!(log.failures[&2], 1);
+    }
+
+    #[test]
+    fn success_resets_the_failure_count() {
+        let mut log = DeadLetterLog::default();
+        log.record_failure(1, 2);
+        assert!(log.record_success(1));
+        assert!(!log.record_success(1));
+        assert!(!log.record_failure(1, 2));
+    }
+
+    #[test]
+    fn zero_threshold_never_dead_letters() {
+        let mut log = DeadLetterLog::default();
+        for _ in 0..10 {
+            assert!(!log.record_failure(1, 0));
+        }
+        assert_eq!(log.failures[&1], 10);
+    }
+
+    #[test]
+    fn push_replaces_the_entry_and_keeps_the_newest() {
+        let mut log = DeadLetterLog::default();
+        log.push(letter(1, 0));
+        log.push(letter(2, 1));
+        log.push(letter(1, 2));
+        assert_eq!(
+            log.entries
+                .iter()
+                .map(|e| (e.entry_id, e.failed_at))
+                .collect::<Vec<_>>(),
+            vec![(2, 1), (1, 2)]
+        );
+
+        for id in 0..MAX_DEADLETTERS as u64 + 5 {
+            log.push(letter(100 + id, id));
+        }
+        assert_eq!(log.entries.len(), MAX_DEADLETTERS);
+        assert_eq!(log.entries[0].entry_id, 105);
+    }
+
+    #[test]
+    fn only_refused_letters_are_reported_as_refused() {
+        let mut log = DeadLetterLog::default();
+        log.push(letter(1, 0));
+        log.push(DeadLetter {
+            refused: true,
+            ..letter(2, 0)
+        });
+        assert!(!log.is_refused(1));
+        assert!(log.is_refused(2));
+        assert!(!log.is_refused(3));
+    }
+}
@@ -0,0 +1,168 @@
+use crate::shared_state::Shared;
+use serde::{Deserialize, Serialize};
+
+// 每段内容最多保留的字符数，避免单个 KV 值过大
+pub const MAX_CAPTURE_CHARS: usize = 8000;
+pub const REDACTED: &str = "[REDACTED]";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapturedMessage {
+    pub role: String,
+    pub content: String,
+}
+
+// 一次模型调用的请求和原始响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Exchange {
+    pub messages: Vec<CapturedMessage>,
+    pub response: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DebugRecord {
+    pub entry_id: u64,
+    pub error: Option<String>,
+    pub captured_at: u64,
+    pub exchanges: Vec<Exchange>,
+}
+
+pub fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((i, _)) => format!("{}…", &text[..i]),
+        None => text.to_string(),
+    }
+}
+
+pub fn redact(text: &str, secrets: &[String]) -> String {
+    secrets
+        .iter()
+        .filter(|secret| !secret.is_empty())
+        .fold(text.to_string(), |text, secret| {
+            text.replace(secret.as_str(), REDACTED)
+        })
+}
+
+// 先脱敏再截断，避免截断后残留半个密钥
+pub fn sanitize(text: &str, secrets: &[String]) -> String {
+    truncate(&redact(text, secrets), MAX_CAPTURE_CHARS)
+}
+
+// 按文章 id 抽样，同一篇文章每次的结果一致
+pub fn is_sampled(entry_id: u64, percent: u64) -> bool {
+    entry_id % 100 < percent
+}
+
+// 单篇文章处理期间的所有模型调用，未开启时不做任何记录
+pub struct Capturer {
+    secrets: Option<Vec<String>>,
+    exchanges: Shared<Vec<Exchange>>,
+}
+
+impl Capturer {
+    pub fn new(secrets: Option<Vec<String>>) -> Self {
+        Capturer {
+            secrets,
+            exchanges: Shared::new(Vec::new()),
+        }
+    }
+
+    pub fn record<'a>(
+        &self,
+        messages: impl IntoIterator<Item = (&'a str, &'a str)>,
+        result: Result<&str, String>,
+    ) {
+        let Some(secrets) = &self.secrets else {
+            return;
+        };
+
+        let messages = messages
+            .into_iter()
+            .map(|(role, content)| CapturedMessage {
+                role: role.to_string(),
+                content: sanitize(content, secrets),
+            })
+            .collect();
+        let (response, error) = match result {
+            Ok(response) => (Some(sanitize(response, secrets)), None),
+            Err(error) => (None, Some(sanitize(&error, secrets))),
+        };
+        let exchange = Exchange {
+            messages,
+            response,
+            error,
+        };
+        self.exchanges.with(|exchanges| exchanges.push(exchange));
+    }
+
+    pub fn into_record(self, entry_id: u64, error: Option<&str>, now: u64) -> Option<DebugRecord> {
+        let secrets = self.secrets?;
+        Some(DebugRecord {
+            entry_id,
+            error: error.map(|e| sanitize(e, &secrets)),
+            captured_at: now,
+            exchanges: self.exchanges.into_inner(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secrets() -> Vec<String> {
+        vec!["sk-secret".to_string(), String::new()]
+    }
+
+    #[test]
+    fn redacts_secrets_before_truncating() {
+        assert_eq!(
+            redact("Authorization: Bearer sk-secret", &secrets()),
+            "Authorization: Bearer [REDACTED]"
+        );
+        let long = format!("{}sk-secret", "x".repeat(MAX_CAPTURE_CHARS - 3));
+        let sanitized = sanitize(&long, &secrets());
+        assert!(!sanitized.contains("sk-"));
+        assert!(sanitized.ends_with("xxx[RE…"));
+    }
+
+    #[test]
+    fn truncates_by_characters() {
+        assert_eq!(truncate("中文内容", 2), "中文…");
+        assert_eq!(truncate("中文", 2), "中文");
+    }
+
+    #[test]
+    fn samples_by_entry_id() {
+        assert!(is_sampled(1203, 5));
+        assert!(!is_sampled(1205, 5));
+        assert!(!is_sampled(1200, 0));
+        assert!(is_sampled(1299, 100));
+    }
+
+    #[test]
+    fn disabled_capturer_records_nothing() {
+        let capturer = Capturer::new(None);
+        capturer.record([("user", "hi")], Ok("hello"));
+        assert!(capturer.into_record(1, None, 0).is_none());
+    }
+
+    #[test]
+    fn captures_sanitized_exchanges() {
+        let capturer = Capturer::new(Some(secrets()));
+        capturer.record([("system", "key sk-secret"), ("user", "hi")], Ok("hello"));
+        capturer.record([("user", "again")], Err("401 for sk-secret".to_string()));
+        let record = capturer
+            .into_record(7, Some("failed: sk-secret"), 42)
+            .unwrap();
+        assert_eq!((record.entry_id, record.captured_at), (7, 42));
+        assert_eq!(record.error.as_deref(), Some("failed: [REDACTED]"));
+        assert_eq!(record.exchanges.len(), 2);
+        assert_eq!(record.exchanges[0].messages[0].content, "key [REDACTED]");
+        assert_eq!(record.exchanges[0].response.as_deref(), Some("hello"));
+        assert_eq!(
+            record.exchanges[1].error.as_deref(),
+            Some("401 for [REDACTED]")
+        );
+    }
+}
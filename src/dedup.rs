@@ -0,0 +1,81 @@
+// 跟踪参数不影响文章内容，比较地址时去掉
+const TRACKING_PARAMS: &[&str] = &["fbclid", "gclid", "mc_cid", "mc_eid", "ref", "spm"];
+
+// 用于跨订阅源去重的地址键：忽略协议、www 前缀、主机名大小写、锚点、跟踪参数和末尾斜杠
+pub fn url_key(url: &str) -> Option<String> {
+    let url = url.trim();
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let rest = rest.split('#').next().unwrap_or_default();
+    let (location, query) = rest.split_once('?').unwrap_or((rest, ""));
+
+    let (host, path) = location.split_once('/').unwrap_or((location, ""));
+    let host = host.to_lowercase();
+    let host = host.trim_start_matches("www.");
+    if host.is_empty() {
+        return None;
+    }
+
+    let query: Vec<&str> = query
+        .split('&')
+        .filter(|param| {
+            let name = param.split('=').next().unwrap_or_default().to_lowercase();
+            !name.is_empty()
+                && !name.starts_with("utm_")
+                && !TRACKING_PARAMS.contains(&name.as_str())
+        })
+        .collect();
+
+    let mut key = format!("{}/{}", host, path.trim_end_matches('/'));
+    if key.ends_with('/') {
+        key.pop();
+    }
+    if !query.is_empty() {
+        key = format!("{}?{}", key, query.join("&"));
+    }
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn equivalent_urls_share_a_key() {
+        let key = url_key("https://example.com/post/1").unwrap();
+        for url in [
+            "http://www.Example.com/post/1/",
+            " https://EXAMPLE.com/post/1#comments ",
+            "https://example.com/post/1?utm_source=rss&utm_medium=feed",
+            "https://example.com/post/1?fbclid=abc&ref=hn",
+        ] {
+            assert_eq!(url_key(url).unwrap(), key, "{}", url);
+        }
+        assert_eq!(key, "example.com/post/1");
+    }
+
+    #[test]
+    fn meaningful_differences_are_kept() {
+        assert_ne!(
+            url_key("https://example.com/Post/1"),
+            url_key("https://example.com/post/1")
+        );
+        assert_eq!(
+            url_key("https://example.com/item?id=42&utm_campaign=x").as_deref(),
+            Some("example.com/item?id=42")
+        );
+        assert_ne!(
+            url_key("https://example.com/item?id=42"),
+            url_key("https://example.com/item?id=43")
+        );
+        assert_eq!(
+            url_key("https://example.com/").as_deref(),
+            Some("example.com")
+        );
+    }
+
+    #[test]
+    fn urls_without_host_have_no_key() {
+        assert_eq!(url_key(""), None);
+        assert_eq!(url_key("https:///path"), None);
+    }
+}
@@ -0,0 +1,203 @@
+use serde::Deserialize;
+
+pub const ENTITIES_PROMPT: &str = "Extract structured metadata from the following finance article. Reply with only a JSON object of the form {\"entities\": [\"...\"], \"tickers\": [\"...\"], \"event_type\": \"...\"}, where entities are the companies and organizations mentioned, tickers are their stock ticker symbols, and event_type is one of earnings, m&a, regulatory or other. Do not add any other text.";
+
+// 设置了 TAG_MIN_CONFIDENCE 时追加到提示词后，让模型给每个股票代码附上置信度
+pub const CONFIDENCE_PROMPT: &str = "Write each ticker as an object of the form {\"symbol\": \"...\", \"confidence\": 0.0}, where confidence between 0 and 1 is how sure you are that the article is about that company.";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Entities {
+    pub entities: Vec<String>,
+    pub tickers: Vec<Ticker>,
+    pub event_type: String,
+}
+
+// 股票代码可以是字符串，也可以是带置信度的对象
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Ticker {
+    Symbol(String),
+    Scored {
+        symbol: String,
+        #[serde(default)]
+        confidence: Option<f32>,
+    },
+}
+
+impl Ticker {
+    pub fn symbol(&self) -> &str {
+        match self {
+            Ticker::Symbol(symbol) | Ticker::Scored { symbol, .. } => symbol,
+        }
+    }
+
+    pub fn confidence(&self) -> Option<f32> {
+        match self {
+            Ticker::Symbol(_) => None,
+            Ticker::Scored { confidence, .. } => *confidence,
+        }
+    }
+}
+
+impl Entities {
+    // 清理空白项和不合法的代码，统一为大写且去掉 `$` 前缀
+    pub fn normalized(self) -> Entities {
+        let mut tickers: Vec<Ticker> = Vec::new();
+        for ticker in self.tickers {
+            let symbol = ticker
+                .symbol()
+                .trim()
+                .trim_start_matches('$')
+                .to_uppercase();
+            let valid = !symbol.is_empty()
+                && symbol.len() <= 12
+                && symbol
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == ':');
+            if valid && !tickers.iter().any(|t| t.symbol() == symbol) {
+                tickers.push(Ticker::Scored {
+                    symbol,
+                    confidence: ticker.confidence(),
+                });
+            }
+        }
+
+        let mut entities: Vec<String> = Vec::new();
+        for entity in self.entities {
+            let entity = entity.trim().to_string();
+            if !entity.is_empty() && !entities.contains(&entity) {
+                entities.push(entity);
+            }
+        }
+
+        let event_type = match self.event_type.trim().to_lowercase().as_str() {
+            "earnings" => "earnings",
+            "m&a" | "m_and_a" | "merger" | "acquisition" => "m&a",
+            "regulatory" => "regulatory",
+            _ => "",
+        }
+        .to_string();
+
+        Entities {
+            entities,
+            tickers,
+            event_type,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty() && self.tickers.is_empty() && self.event_type.is_empty()
+    }
+
+    // 渲染为摘要下方的一行，例如 `🏢 Apple、Microsoft | 📈 AAPL、MSFT | 🏷️ earnings`
+    pub fn render(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if !self.entities.is_empty() {
+            parts.push(format!("🏢 {}", self.entities.join("、")));
+        }
+        if !self.tickers.is_empty() {
+            let symbols: Vec<&str> = self.tickers.iter().map(Ticker::symbol).collect();
+            parts.push(format!("📈 {}", symbols.join("、")));
+        }
+        if !self.event_type.is_empty() {
+            parts.push(format!("🏷️ {}", self.event_type));
+        }
+
+        (!parts.is_empty()).then(|| parts.join(" | "))
+    }
+}
+
+// 写入标签的股票代码，模型给出的置信度低于阈值时丢弃，没有给出置信度时保留
+pub fn tag_symbols(tickers: &[Ticker], min_confidence: Option<f32>) -> Vec<&str> {
+    tickers
+        .iter()
+        .filter(|ticker| match (min_confidence, ticker.confidence()) {
+            (Some(min), Some(confidence)) => confidence >= min,
+            _ => true,
+        })
+        .map(Ticker::symbol)
+        .collect()
+}
+
+// 把股票代码合并进已有标签，忽略大小写去重
+pub fn merge_tags(tags: &mut Vec<String>, tickers: &[&str]) {
+    for ticker in tickers {
+        if !tags.iter().any(|tag| tag.eq_ignore_ascii_case(ticker)) {
+            tags.push(ticker.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(raw: &str) -> Entities {
+        crate::structured::parse_json_object::<Entities>(raw)
+            .unwrap()
+            .normalized()
+    }
+
+    #[test]
+    fn normalizes_model_output() {
+        let entities = parse(
+            r#"Sure! ```json
+            {"entities": [" Apple ", "Apple", "", "Microsoft"],
+             "tickers": ["$aapl", "AAPL", "msft", "", "not a ticker", "BRK.B"],
+             "event_type": " Merger "}
+            ```"#,
+        );
+        assert_eq!(entities.entities, vec!["Apple", "Microsoft"]);
+        assert_eq!(
+            entities
+                .tickers
+                .iter()
+                .map(Ticker::symbol)
+                .collect::<Vec<_>>(),
+            vec!["AAPL", "MSFT", "BRK.B"]
+        );
+        assert_eq!(entities.event_type, "m&a");
+        assert_eq!(
+            entities.render().as_deref(),
+            Some("🏢 Apple、Microsoft | 📈 AAPL、MSFT、BRK.B | 🏷️ m&a")
+        );
+    }
+
+    #[test]
+    fn unknown_event_types_and_missing_fields_are_empty() {
+        let entities = parse(r#"{"event_type": "gossip"}"#);
+        assert!(entities.is_empty());
+        assert_eq!(entities.render(), None);
+        assert!(crate::structured::parse_json_object::<Entities>("no json here").is_none());
+    }
+
+    #[test]
+    fn merges_tickers_into_tags_case_insensitively() {
+        let mut tags = vec!["aapl".to_string(), "tech".to_string()];
+        merge_tags(&mut tags, &["AAPL", "MSFT", "MSFT"]);
+        assert_eq!(tags, vec!["aapl", "tech", "MSFT"]);
+    }
+
+    #[test]
+    fn drops_low_confidence_tickers() {
+        let entities = parse(
+            r#"{"tickers": [
+                {"symbol": "aapl", "confidence": 0.9},
+                {"symbol": "MSFT", "confidence": 0.2},
+                {"symbol": "GOOG"},
+                "TSLA"
+            ]}"#,
+        );
+        assert_eq!(
+            tag_symbols(&entities.tickers, Some(0.5)),
+            vec!["AAPL", "GOOG", "TSLA"]
+        );
+        // 没有阈值时保留所有股票代码
+        assert_eq!(tag_symbols(&entities.tickers, None).len(), 4);
+        assert_eq!(
+            tag_symbols(&entities.tickers, Some(0.9)),
+            vec!["AAPL", "GOOG", "TSLA"]
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use crate::html::is_cjk;
+use std::collections::HashMap;
+
+const STOPWORDS: &[&str] = &[
+    "the", "and", "for", "that", "with", "this", "are", "was", "were", "from", "have", "has",
+    "had", "not", "but", "its", "they", "their", "you", "your", "our", "will", "would", "can",
+    "could", "been", "also", "than", "then", "there", "which", "what", "when", "who", "into",
+];
+
+// 按中英文句末标点切分句子，英文句点后需要跟空白才算句末，避免切开小数和缩写
+pub fn split_sentences(text: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    let mut current = String::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        current.push(c);
+        let end = match c {
+            '。' | '！' | '？' | '\n' => true,
+            '!' | '?' | '.' => chars.peek().is_none_or(|next| next.is_whitespace()),
+            _ => false,
+        };
+        if end {
+            push_sentence(&mut sentences, &current);
+            current.clear();
+        }
+    }
+    push_sentence(&mut sentences, &current);
+
+    sentences
+}
+
+fn push_sentence(sentences: &mut Vec<String>, sentence: &str) {
+    let sentence = sentence.trim();
+    if !sentence.is_empty() {
+        sentences.push(sentence.to_string());
+    }
+}
+
+// 英文按单词，中日韩文字按相邻两个字组成的词计算
+fn tokens(sentence: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut previous_cjk: Option<char> = None;
+
+    for c in sentence.chars() {
+        if is_cjk(c) && c.is_alphanumeric() {
+            push_word(&mut tokens, &mut word);
+            if let Some(previous) = previous_cjk {
+                tokens.push(format!("{}{}", previous, c));
+            }
+            previous_cjk = Some(c);
+        } else if c.is_alphanumeric() {
+            word.extend(c.to_lowercase());
+            previous_cjk = None;
+        } else {
+            push_word(&mut tokens, &mut word);
+            previous_cjk = None;
+        }
+    }
+    push_word(&mut tokens, &mut word);
+
+    tokens
+}
+
+fn push_word(tokens: &mut Vec<String>, word: &mut String) {
+    if word.chars().count() > 2 && !STOPWORDS.contains(&word.as_str()) {
+        tokens.push(word.clone());
+    }
+    word.clear();
+}
+
+// 基于词频的抽取式摘要：按句中词的平均词频打分，取分数最高的句子直到长度上限，再按原文顺序输出
+pub fn summarize(text: &str, max_chars: usize) -> String {
+    let sentences = split_sentences(text);
+    let sentence_tokens: Vec<Vec<String>> = sentences.iter().map(|s| tokens(s)).collect();
+
+    let mut frequencies: HashMap<&str, usize> = HashMap::new();
+    for token in sentence_tokens.iter().flatten() {
+        *frequencies.entry(token.as_str()).or_default() += 1;
+    }
+
+    let mut scored: Vec<(usize, f32)> = sentence_tokens
+        .iter()
+        .enumerate()
+        .filter(|(_, tokens)| !tokens.is_empty())
+        .map(|(i, tokens)| {
+            let total: usize = tokens.iter().map(|t| frequencies[t.as_str()]).sum();
+            (i, total as f32 / tokens.len() as f32)
+        })
+        .collect();
+    // 分数相同时靠前的句子优先，保证结果稳定
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let mut picked = Vec::new();
+    let mut length = 0;
+    for (i, _) in scored {
+        let len = sentences[i].chars().count();
+        // 放不下时停止，只有第一句就超长时才继续找更短的句子
+        if length + len > max_chars {
+            if picked.is_empty() {
+                continue;
+            }
+            break;
+        }
+        length += len;
+        picked.push(i);
+    }
+    picked.sort_unstable();
+
+    let mut summary = String::new();
+    for i in picked {
+        let sentence = &sentences[i];
+        // 英文句子之间补一个空格，中文句子直接相连
+        if !summary.is_empty() && !sentence.starts_with(is_cjk) && !summary.ends_with(is_cjk) {
+            summary.push(' ');
+        }
+        summary.push_str(sentence);
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_chinese_and_english_sentences() {
+        assert_eq!(
+            split_sentences("第一句。第二句！Version 1.5 is out. Is it? Yes\n最后"),
+            vec![
+                "第一句。",
+                "第二句！",
+                "Version 1.5 is out.",
+                "Is it?",
+                "Yes",
+                "最后"
+            ]
+        );
+        assert!(split_sentences("  \n ").is_empty());
+    }
+
+    #[test]
+    fn tokenizes_words_and_cjk_bigrams() {
+        assert_eq!(
+            tokens("The Rust compiler is fast"),
+            vec!["rust", "compiler", "fast"]
+        );
+        assert_eq!(tokens("模型摘要"), vec!["模型", "型摘", "摘要"]);
+    }
+
+    #[test]
+    fn picks_the_most_representative_sentences_in_order() {
+        let text = "Rust compiler releases are frequent. The weather was nice. \
+                    The Rust compiler team shipped a faster compiler. Lunch was pasta.";
+        assert_eq!(
+            summarize(text, 90),
+            "Rust compiler releases are frequent. The Rust compiler team shipped a faster compiler."
+        );
+    }
+
+    #[test]
+    fn stays_within_the_length_limit() {
+        let text = "这是一段很长很长很长很长很长的开头句子。模型摘要很好。模型摘要稳定。";
+        let summary = summarize(text, 14);
+        assert!(summary.chars().count() <= 14);
+        assert_eq!(summary, "模型摘要很好。模型摘要稳定。");
+        assert_eq!(summarize("", 100), "");
+    }
+}
@@ -0,0 +1,157 @@
+use crate::shared_state::Shared;
+use std::collections::HashMap;
+
+// 主模型失败后依次尝试的备用模型，可以限制同时发给它的请求数
+#[derive(Debug, Clone, PartialEq)]
+pub struct FallbackModel {
+    pub model: String,
+    pub max_concurrency: Option<usize>,
+}
+
+// 格式为逗号分隔的 `model[:并发数]`，例如 `gpt-4o-mini:2,@cf/meta/llama-3.1-8b-instruct`
+// 只有冒号后面是数字时才视为并发数，`llama3:8b` 这类模型名保持原样
+pub fn parse_chain(raw: &str) -> Vec<FallbackModel> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| match item.rsplit_once(':') {
+            Some((model, cap)) if !model.is_empty() => match cap.trim().parse::<usize>() {
+                Ok(cap) => FallbackModel {
+                    model: model.trim().to_string(),
+                    max_concurrency: Some(cap.max(1)),
+                },
+                Err(_) => FallbackModel {
+                    model: item.to_string(),
+                    max_concurrency: None,
+                },
+            },
+            _ => FallbackModel {
+                model: item.to_string(),
+                max_concurrency: None,
+            },
+        })
+        .collect()
+}
+
+// 主模型之后依次尝试的模型，主模型出现在列表中时只用于设置并发上限，不重复请求
+pub fn attempt_order<'a>(primary: &'a str, chain: &'a [FallbackModel]) -> Vec<&'a str> {
+    std::iter::once(primary)
+        .chain(
+            chain
+                .iter()
+                .map(|fallback| fallback.model.as_str())
+                .filter(|model| *model != primary),
+        )
+        .collect()
+}
+
+// 列表中给模型设置的并发上限，主模型和备用模型都适用
+pub fn max_concurrency(chain: &[FallbackModel], model: &str) -> Option<usize> {
+    chain
+        .iter()
+        .find(|fallback| fallback.model == model)
+        .and_then(|fallback| fallback.max_concurrency)
+}
+
+// 按模型统计正在进行的请求数，同一个 isolate 内的任务共享
+#[derive(Default)]
+pub struct ModelLimiter {
+    active: Shared<HashMap<String, usize>>,
+}
+
+impl ModelLimiter {
+    // 达到上限时返回 None，由调用方等待后重试
+    pub fn try_acquire(&self, model: &str, limit: Option<usize>) -> Option<ModelPermit<'_>> {
+        let acquired = self.active.with(|active| {
+            let count = active.entry(model.to_string()).or_insert(0);
+            if limit.is_some_and(|limit| *count >= limit) {
+                return false;
+            }
+            *count += 1;
+            true
+        });
+        acquired.then(|| ModelPermit {
+            limiter: self,
+            model: model.to_string(),
+        })
+    }
+}
+
+// 持有期间占用一个名额，drop 时释放
+pub struct ModelPermit<'a> {
+    limiter: &'a ModelLimiter,
+    model: String,
+}
+
+impl Drop for ModelPermit<'_> {
+    fn drop(&mut self) {
+        self.limiter.active.with(|active| {
+            if let Some(count) = active.get_mut(&self.model) {
+                *count = count.saturating_sub(1);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(model: &str, max_concurrency: Option<usize>) -> FallbackModel {
+        FallbackModel {
+            model: model.to_string(),
+            max_concurrency,
+        }
+    }
+
+    #[test]
+    fn parses_models_with_optional_caps() {
+        assert_eq!(
+            parse_chain(" gpt-4o-mini:2, @cf/meta/llama-3.1-8b-instruct ,, llama3:8b ,x:0"),
+            vec![
+                model("gpt-4o-mini", Some(2)),
+                model("@cf/meta/llama-3.1-8b-instruct", None),
+                model("llama3:8b", None),
+                model("x", Some(1)),
+            ]
+        );
+        assert!(parse_chain("").is_empty());
+    }
+
+    #[test]
+    fn primary_is_tried_first_and_only_once() {
+        let chain = parse_chain("gpt-4o:4,gpt-4o-mini:2,llama");
+        assert_eq!(
+            attempt_order("gpt-4o", &chain),
+            vec!["gpt-4o", "gpt-4o-mini", "llama"]
+        );
+        assert_eq!(
+            attempt_order("other", &chain),
+            vec!["other", "gpt-4o", "gpt-4o-mini", "llama"]
+        );
+        assert_eq!(attempt_order("gpt-4o", &[]), vec!["gpt-4o"]);
+    }
+
+    #[test]
+    fn caps_apply_to_any_listed_model() {
+        let chain = parse_chain("gpt-4o:4,gpt-4o-mini");
+        assert_eq!(max_concurrency(&chain, "gpt-4o"), Some(4));
+        assert_eq!(max_concurrency(&chain, "gpt-4o-mini"), None);
+        assert_eq!(max_concurrency(&chain, "unlisted"), None);
+    }
+
+    #[test]
+    fn limiter_enforces_cap_per_model_and_releases_on_drop() {
+        let limiter = ModelLimiter::default();
+        let first = limiter.try_acquire("a", Some(2));
+        let second = limiter.try_acquire("a", Some(2));
+        assert!(first.is_some() && second.is_some());
+        assert!(limiter.try_acquire("a", Some(2)).is_none());
+        // 其他模型不受影响
+        assert!(limiter.try_acquire("b", Some(1)).is_some());
+
+        drop(first);
+        assert!(limiter.try_acquire("a", Some(2)).is_some());
+        assert!(limiter.try_acquire("a", None).is_some());
+    }
+}
@@ -0,0 +1,330 @@
+use crate::html::escape;
+use crate::report::RunReport;
+use chrono::{DateTime, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+pub const FEED_TITLE: &str = "Miniflux AI 摘要";
+pub const LOG_FEED_TITLE: &str = "miniflux-ai logs";
+
+// 导出的一条摘要
+#[derive(Debug, Clone)]
+pub struct FeedItem {
+    pub id: u64,
+    pub title: String,
+    pub url: String,
+    pub summary: String,
+    pub generated_at: u64,
+}
+
+impl FeedItem {
+    // 旧缓存中没有标题
+    fn title(&self) -> String {
+        if self.title.trim().is_empty() {
+            format!("Entry {}", self.id)
+        } else {
+            self.title.clone()
+        }
+    }
+}
+
+fn rfc3339(ms: u64) -> String {
+    DateTime::from_timestamp_millis(ms as i64)
+        .unwrap_or_default()
+        .to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+// XML 1.0 不允许出现制表、换行以外的控制字符
+fn xml_text(text: &str) -> String {
+    let text: String = text
+        .chars()
+        .filter(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r'))
+        .collect();
+    escape(&text).replace('\'', "&apos;")
+}
+
+// Atom 1.0，条目按传入顺序输出，更新时间取最新一条摘要的时间
+pub fn render_atom(feed_url: &str, items: &[FeedItem]) -> String {
+    let updated = items.iter().map(|i| i.generated_at).max().unwrap_or(0);
+    let mut xml = atom_header(feed_url, FEED_TITLE, updated);
+    for item in items {
+        xml.push_str(&format!(
+            "<entry>\n<id>urn:miniflux-ai:entry:{}</id>\n<title>{}</title>\n<updated>{}</updated>\n<author><name>miniflux-ai</name></author>\n",
+            item.id,
+            xml_text(&item.title()),
+            rfc3339(item.generated_at)
+        ));
+        if !item.url.is_empty() {
+            xml.push_str(&format!(
+                "<link rel=\"alternate\" href=\"{}\"/>\n",
+                xml_text(&item.url)
+            ));
+        }
+        xml.push_str(&format!(
+            "<content type=\"text\">{}</content>\n</entry>\n",
+            xml_text(&item.summary)
+        ));
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+fn atom_header(feed_url: &str, title: &str, updated: u64) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">\n<id>{}</id>\n<title>{}</title>\n<updated>{}</updated>\n<link rel=\"self\" href=\"{}\"/>\n",
+        xml_text(feed_url),
+        xml_text(title),
+        rfc3339(updated),
+        xml_text(feed_url)
+    )
+}
+
+// 一次定时任务的结果，报告中的计数原样保存，渲染时再转成表格
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunLog {
+    pub summary: String,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub report: Value,
+}
+
+impl RunLog {
+    pub fn from_report(report: &RunReport) -> Self {
+        RunLog {
+            summary: report.to_string(),
+            error: None,
+            report: serde_json::to_value(report).unwrap_or_default(),
+        }
+    }
+
+    // 查询文章失败时没有报告
+    pub fn failed(error: String) -> Self {
+        RunLog {
+            summary: "Failed to fetch entries from Miniflux".to_string(),
+            error: Some(error),
+            report: Value::Null,
+        }
+    }
+
+    fn count(&self, field: &str) -> u64 {
+        self.report.get(field).and_then(Value::as_u64).unwrap_or(0)
+    }
+
+    fn title(&self, started_at: u64) -> String {
+        let time = rfc3339(started_at);
+        if self.error.is_some() {
+            return format!("{}: failed to fetch entries", time);
+        }
+        format!(
+            "{}: {} summarized, {} failed",
+            time,
+            self.count("summarized"),
+            self.count("failed")
+        )
+    }
+
+    // 每篇文章的步骤太长，不放进日志
+    fn html(&self) -> String {
+        let mut html = format!("<p>{}</p>", escape(&self.summary));
+        if let Some(error) = &self.error {
+            html.push_str(&format!("<p><strong>Error:</strong> {}</p>", escape(error)));
+        }
+        let Some(fields) = self.report.as_object() else {
+            return html;
+        };
+        let mut rows = String::new();
+        let mut notes = String::new();
+        for (name, value) in fields {
+            match value {
+                Value::Number(n) => rows.push_str(&row(name, &n.to_string())),
+                Value::Object(counts) if name != "steps" => {
+                    for (key, n) in counts {
+                        rows.push_str(&row(&format!("{}: {}", name, key), &n.to_string()));
+                    }
+                }
+                Value::Array(items) if name == "notes" => {
+                    for note in items.iter().filter_map(Value::as_str) {
+                        notes.push_str(&format!("<li>{}</li>", escape(note)));
+                    }
+                }
+                _ => {}
+            }
+        }
+        if !rows.is_empty() {
+            html.push_str(&format!("<table>{}</table>", rows));
+        }
+        if !notes.is_empty() {
+            html.push_str(&format!("<ul>{}</ul>", notes));
+        }
+        html
+    }
+}
+
+fn row(name: &str, value: &str) -> String {
+    format!(
+        "<tr><th>{}</th><td>{}</td></tr>",
+        escape(name),
+        escape(value)
+    )
+}
+
+// 运行日志的 Atom 订阅源，条目 ID 取运行开始的时间，重复读取时保持不变
+pub fn render_log_atom(feed_url: &str, runs: &[(u64, RunLog)]) -> String {
+    let updated = runs
+        .iter()
+        .map(|(started_at, _)| *started_at)
+        .max()
+        .unwrap_or(0);
+    let mut xml = atom_header(feed_url, LOG_FEED_TITLE, updated);
+    for (started_at, log) in runs {
+        xml.push_str(&format!(
+            "<entry>\n<id>urn:miniflux-ai:run:{}</id>\n<title>{}</title>\n<updated>{}</updated>\n<author><name>miniflux-ai</name></author>\n<content type=\"html\">{}</content>\n</entry>\n",
+            started_at,
+            xml_text(&log.title(*started_at)),
+            rfc3339(*started_at),
+            xml_text(&log.html())
+        ));
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+// JSON Feed 1.1，必填字段为 version、title 和每个条目的 id
+pub fn render_json_feed(feed_url: &str, items: &[FeedItem]) -> Value {
+    let items: Vec<Value> = items
+        .iter()
+        .map(|item| {
+            let mut value = json!({
+                "id": item.id.to_string(),
+                "title": item.title(),
+                "content_text": item.summary,
+                "date_published": rfc3339(item.generated_at),
+            });
+            if !item.url.is_empty() {
+                value["url"] = json!(item.url);
+            }
+            value
+        })
+        .collect();
+
+    json!({
+        "version": "https://jsonfeed.org/version/1.1",
+        "title": FEED_TITLE,
+        "feed_url": feed_url,
+        "items": items,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEED_URL: &str = "https://ai.example.com/feed.atom";
+
+    fn item(id: u64, title: &str, url: &str, generated_at: u64) -> FeedItem {
+        FeedItem {
+            id,
+            title: title.to_string(),
+            url: url.to_string(),
+            summary: "要点 <b> & 'quote'\u{1}".to_string(),
+            generated_at,
+        }
+    }
+
+    #[test]
+    fn renders_atom_with_escaped_text() {
+        let items = [
+            item(
+                2,
+                "A & B",
+                "https://example.com/?a=1&b=2",
+                1_700_000_000_000,
+            ),
+            item(1, " ", "", 1_600_000_000_000),
+        ];
+        let xml = render_atom(FEED_URL, &items);
+        assert!(xml.contains("<updated>2023-11-14T22:13:20Z</updated>\n<link rel=\"self\""));
+        assert!(xml.contains("<id>urn:miniflux-ai:entry:2</id>\n<title>A &amp; B</title>"));
+        assert!(xml.contains("<link rel=\"alternate\" href=\"https://example.com/?a=1&amp;b=2\"/>"));
+        assert!(
+            xml.contains("<content type=\"text\">要点 &lt;b&gt; &amp; &apos;quote&apos;</content>")
+        );
+        // 没有标题时用文章 ID，没有地址时不输出链接
+        assert!(xml.contains("<title>Entry 1</title>"));
+        assert_eq!(xml.matches("rel=\"alternate\"").count(), 1);
+        assert!(xml.ends_with("</feed>\n"));
+    }
+
+    #[test]
+    fn empty_atom_feed_is_valid() {
+        let xml = render_atom(FEED_URL, &[]);
+        assert!(xml.contains("<updated>1970-01-01T00:00:00Z</updated>"));
+        assert!(!xml.contains("<entry>"));
+    }
+
+    #[test]
+    fn renders_json_feed_items() {
+        let feed = render_json_feed(
+            FEED_URL,
+            &[
+                item(2, "Title", "https://example.com/2", 0),
+                item(1, "", "", 0),
+            ],
+        );
+        assert_eq!(feed["version"], "https://jsonfeed.org/version/1.1");
+        assert_eq!(feed["feed_url"], FEED_URL);
+        assert_eq!(feed["items"][0]["id"], "2");
+        assert_eq!(feed["items"][0]["url"], "https://example.com/2");
+        assert_eq!(feed["items"][0]["date_published"], "1970-01-01T00:00:00Z");
+        assert_eq!(feed["items"][1]["title"], "Entry 1");
+        assert!(feed["items"][1].get("url").is_none());
+    }
+
+    fn run_log() -> RunLog {
+        let mut report = RunReport::default();
+        report.record(&Ok::<_, String>(crate::report::EntryOutcome::Updated));
+        report.record(&Err::<crate::report::EntryOutcome, _>(
+            "timeout".to_string(),
+        ));
+        report.record_skip(crate::report::SkipReason::TooShort);
+        RunLog::from_report(&report)
+    }
+
+    // 条目 ID 只取决于运行开始时间，重复读取和追加新运行时不变
+    #[test]
+    fn run_log_ids_are_stable() {
+        let first = render_log_atom(FEED_URL, &[(1_700_000_000_000, run_log())]);
+        let later = render_log_atom(
+            FEED_URL,
+            &[
+                (1_700_000_600_000, run_log()),
+                (1_700_000_000_000, run_log()),
+            ],
+        );
+        assert_eq!(first.matches("<entry>").count(), 1);
+        assert!(first.contains("<id>urn:miniflux-ai:run:1700000000000</id>"));
+        assert!(later.contains("<id>urn:miniflux-ai:run:1700000000000</id>"));
+        assert!(later.contains("<id>urn:miniflux-ai:run:1700000600000</id>"));
+        assert!(later.contains("<updated>2023-11-14T22:23:20Z</updated>\n<link rel=\"self\""));
+        assert!(first.contains("<title>2023-11-14T22:13:20Z: 0 summarized, 1 failed</title>"));
+    }
+
+    #[test]
+    fn run_log_content_is_escaped_twice() {
+        let log = RunLog::failed("bad <html> & \"quotes\"".to_string());
+        let xml = render_log_atom(FEED_URL, &[(0, log)]);
+        assert!(xml.contains("<title>1970-01-01T00:00:00Z: failed to fetch entries</title>"));
+        // HTML 内容先按 HTML 转义，再作为 XML 文本转义
+        assert!(xml.contains("bad &amp;lt;html&amp;gt; &amp;amp; &amp;quot;quotes&amp;quot;"));
+        assert!(!xml.contains("<html>"));
+    }
+
+    #[test]
+    fn run_log_lists_counts() {
+        let html = run_log().html();
+        assert!(html.contains("<tr><th>failed</th><td>1</td></tr>"));
+        assert!(html.contains("<tr><th>skipped: too_short</th><td>1</td></tr>"));
+        assert!(!html.contains("steps"));
+    }
+}
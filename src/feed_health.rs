@@ -0,0 +1,182 @@
+use serde::{Deserialize, Serialize};
+
+// 每个订阅源的连续失败计数，达到阈值后在冷却期内停止处理该订阅源
+// 连续产出空摘要或无效摘要的订阅源会被一直跳过，直到通过 EMPTY_FEED_RESET 重置
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeedHealth {
+    pub streak: u32,
+    pub disabled_until: Option<u64>,
+    #[serde(default)]
+    pub empty_streak: u32,
+    #[serde(default)]
+    pub empty_skipped: bool,
+}
+
+impl FeedHealth {
+    pub fn is_disabled(&self, now: u64) -> bool {
+        self.disabled_until.is_some_and(|until| now < until)
+    }
+
+    pub fn record_success(&mut self) {
+        self.streak = 0;
+        self.disabled_until = None;
+    }
+
+    // Returns true when this failure is the one that disables the feed
+    pub fn record_failure(&mut self, now: u64, threshold: u32, cooldown_ms: u64) -> bool {
+        if self.is_disabled(now) {
+            return false;
+        }
+
+        self.streak += 1;
+        if threshold > 0 && self.streak >= threshold {
+            self.streak = 0;
+            self.disabled_until = Some(now + cooldown_ms);
+            return true;
+        }
+
+        false
+    }
+
+    pub fn record_summary(&mut self) {
+        self.empty_streak = 0;
+    }
+
+    // Returns true when this empty summary is the one that starts skipping the feed
+    pub fn record_empty(&mut self, threshold: u32) -> bool {
+        self.empty_streak += 1;
+        if threshold > 0 && self.empty_streak >= threshold && !self.empty_skipped {
+            self.empty_skipped = true;
+            return true;
+        }
+
+        false
+    }
+
+    pub fn reset_empty(&mut self) {
+        self.empty_streak = 0;
+        self.empty_skipped = false;
+    }
+}
+
+// 归一化 site_url：去掉首尾空白和末尾斜杠，协议和主机名转为小写
+pub fn normalize_site_url(site_url: &str) -> String {
+    let trimmed = site_url.trim().trim_end_matches('/');
+
+    match trimmed.find("://") {
+        Some(scheme_end) => {
+            let host_start = scheme_end + 3;
+            let host_end = trimmed[host_start..]
+                .find('/')
+                .map_or(trimmed.len(), |i| host_start + i);
+            format!(
+                "{}{}",
+                trimmed[..host_end].to_lowercase(),
+                &trimmed[host_end..]
+            )
+        }
+        None => trimmed.to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COOLDOWN: u64 = 1000;
+
+    #[test]
+    fn failure_streak_disables_feed_at_threshold() {
+        let mut health = FeedHealth::default();
+        assert!(!health.record_failure(0, 3, COOLDOWN));
+        assert!(!health.record_failure(1, 3, COOLDOWN));
+        assert!(!health.is_disabled(1));
+        assert!(health.record_failure(2, 3, COOLDOWN));
+        assert!(health.is_disabled(2));
+        assert_eq!(health.disabled_until, Some(2 + COOLDOWN));
+        assert_eq!(health.streak, 0);
+    }
+
+    #[test]
+    fn zero_threshold_never_disables() {
+        let mut health = FeedHealth::default();
+        for now in 0..10 {
+            assert!(!health.record_failure(now, 0, COOLDOWN));
+        }
+        assert!(!health.is_disabled(10));
+    }
+
+    #[test]
+    fn failures_during_cooldown_are_ignored() {
+        let mut health = FeedHealth::default();
+        health.record_failure(0, 1, COOLDOWN);
+        assert!(!health.record_failure(10, 1, COOLDOWN));
+        assert_eq!(health.streak, 0);
+        assert_eq!(health.disabled_until, Some(COOLDOWN));
+    }
+
+    #[test]
+    fn success_resets_streak_and_cooldown() {
+        let mut health = FeedHealth::default();
+        health.record_failure(0, 3, COOLDOWN);
+        health.record_failure(1, 3, COOLDOWN);
+        health.record_success();
+        assert_eq!(health.streak, 0);
+        assert!(!health.record_failure(2, 3, COOLDOWN));
+        assert!(!health.record_failure(3, 3, COOLDOWN));
+
+        health.record_failure(4, 3, COOLDOWN);
+        assert!(health.is_disabled(5));
+        health.record_success();
+        assert!(!health.is_disabled(5));
+    }
+
+    #[test]
+    fn cooldown_expires() {
+        let mut health = FeedHealth::default();
+        health.record_failure(100, 1, COOLDOWN);
+        assert!(health.is_disabled(100 + COOLDOWN - 1));
+        assert!(!health.is_disabled(100 + COOLDOWN));
+        // 冷却结束后重新开始计数
+        assert!(health.record_failure(100 + COOLDOWN, 1, COOLDOWN));
+    }
+
+    #[test]
+    fn empty_streak_starts_skipping_once() {
+        let mut health = FeedHealth::default();
+        assert!(!health.record_empty(2));
+        assert!(health.record_empty(2));
+        assert!(health.empty_skipped);
+        assert!(!health.record_empty(2));
+
+        health.reset_empty();
+        assert!(!health.empty_skipped);
+        assert_eq!(health.empty_streak, 0);
+    }
+
+    #[test]
+    fn summary_resets_empty_streak() {
+        let mut health = FeedHealth::default();
+        health.record_empty(2);
+        health.record_summary();
+        assert!(!health.record_empty(2));
+    }
+
+    #[test]
+    fn normalizes_site_url() {
+        assert_eq!(
+            normalize_site_url("  HTTPS://Example.COM/Blog/  "),
+            "https://example.com/Blog"
+        );
+        assert_eq!(
+            normalize_site_url("https://example.com/"),
+            "https://example.com"
+        );
+        assert_eq!(normalize_site_url("Example.com/"), "example.com");
+        assert_eq!(
+            normalize_site_url("http://Host.example"),
+            "http://host.example"
+        );
+        assert_eq!(normalize_site_url(""), "");
+    }
+}
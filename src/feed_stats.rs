@@ -0,0 +1,207 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+// 统计最多保留的天数
+pub const MAX_DAYS: u64 = 30;
+// 每页返回的订阅源数
+pub const PAGE_SIZE: usize = 200;
+
+// 单个订阅源一天内的累计值，平均值在查询时计算
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DailyStats {
+    pub entries: u32,
+    pub summarized: u32,
+    pub failed: u32,
+    pub refused: u32,
+    pub summary_chars: u64,
+    pub latency_ms: u64,
+    // 最近一篇文章的分类，用于解析当前生效的设置
+    pub category: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Summarized { chars: usize },
+    Refused,
+    Failed,
+    Other,
+}
+
+impl DailyStats {
+    pub fn record(&mut self, outcome: Outcome, latency_ms: u64) {
+        self.entries += 1;
+        self.latency_ms += latency_ms;
+        match outcome {
+            Outcome::Summarized { chars } => {
+                self.summarized += 1;
+                self.summary_chars += chars as u64;
+            }
+            Outcome::Refused => self.refused += 1,
+            Outcome::Failed => self.failed += 1,
+            Outcome::Other => {}
+        }
+    }
+}
+
+// 键为 `{天数}|{site_url}`，天数从 Unix 纪元开始计算
+pub fn day_key(site_url: &str, now: u64) -> String {
+    format!("{}|{}", now / DAY_MS, site_url)
+}
+
+fn parse_key(key: &str) -> Option<(u64, &str)> {
+    let (day, site_url) = key.split_once('|')?;
+    Some((day.parse().ok()?, site_url))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FeedStats {
+    pub site_url: String,
+    #[serde(skip)]
+    pub category: String,
+    // 由调用方按当前设置填写
+    pub settings_hash: Option<String>,
+    pub entries: u32,
+    pub summarized: u32,
+    pub failed: u32,
+    pub refused: u32,
+    pub avg_summary_chars: Option<u64>,
+    pub avg_latency_ms: Option<u64>,
+}
+
+// 合并最近 `days` 天（含今天）的记录，按文章数从多到少排列
+pub fn fold<'a>(
+    records: impl IntoIterator<Item = (&'a str, &'a DailyStats)>,
+    now: u64,
+    days: u64,
+) -> Vec<FeedStats> {
+    let today = now / DAY_MS;
+    let first_day = today.saturating_sub(days.saturating_sub(1));
+    let mut totals: HashMap<&str, (DailyStats, u64)> = HashMap::new();
+    for (key, stats) in records {
+        let Some((day, site_url)) = parse_key(key) else {
+            continue;
+        };
+        if day < first_day || day > today {
+            continue;
+        }
+        let (total, latest) = totals.entry(site_url).or_default();
+        total.entries += stats.entries;
+        total.summarized += stats.summarized;
+        total.failed += stats.failed;
+        total.refused += stats.refused;
+        total.summary_chars += stats.summary_chars;
+        total.latency_ms += stats.latency_ms;
+        if day >= *latest {
+            *latest = day;
+            total.category = stats.category.clone();
+        }
+    }
+
+    let mut feeds: Vec<FeedStats> = totals
+        .into_iter()
+        .map(|(site_url, (total, _))| FeedStats {
+            site_url: site_url.to_string(),
+            category: total.category,
+            settings_hash: None,
+            entries: total.entries,
+            summarized: total.summarized,
+            failed: total.failed,
+            refused: total.refused,
+            avg_summary_chars: (total.summarized > 0)
+                .then(|| total.summary_chars / total.summarized as u64),
+            avg_latency_ms: (total.entries > 0).then(|| total.latency_ms / total.entries as u64),
+        })
+        .collect();
+    feeds.sort_by(|a, b| b.entries.cmp(&a.entries).then(a.site_url.cmp(&b.site_url)));
+    feeds
+}
+
+// 页码从 1 开始，超出范围时返回空页
+pub fn page<T>(items: Vec<T>, page: usize) -> Vec<T> {
+    items
+        .into_iter()
+        .skip(page.saturating_sub(1) * PAGE_SIZE)
+        .take(PAGE_SIZE)
+        .collect()
+}
+
+pub fn page_count(total: usize) -> usize {
+    total.div_ceil(PAGE_SIZE).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(outcomes: &[Outcome], category: &str) -> DailyStats {
+        let mut stats = DailyStats {
+            category: category.to_string(),
+            ..Default::default()
+        };
+        for outcome in outcomes {
+            stats.record(*outcome, 100);
+        }
+        stats
+    }
+
+    #[test]
+    fn records_outcomes() {
+        let stats = stats(
+            &[
+                Outcome::Summarized { chars: 120 },
+                Outcome::Refused,
+                Outcome::Failed,
+                Outcome::Other,
+            ],
+            "",
+        );
+        assert_eq!(
+            (stats.entries, stats.summarized, stats.refused, stats.failed),
+            (4, 1, 1, 1)
+        );
+        assert_eq!((stats.summary_chars, stats.latency_ms), (120, 400));
+    }
+
+    // 只合并窗口内的天数，分类取最近一天的记录
+    #[test]
+    fn folds_recent_days_per_feed() {
+        let now = 100 * DAY_MS + 5;
+        let a_today = stats(&[Outcome::Summarized { chars: 100 }], "News");
+        let a_old = stats(
+            &[Outcome::Summarized { chars: 300 }, Outcome::Failed],
+            "Tech",
+        );
+        let a_expired = stats(&[Outcome::Failed; 5], "Old");
+        let b = stats(&[Outcome::Other], "Blog");
+        let keys = [
+            day_key("https://a.com", now),
+            day_key("https://a.com", now - 2 * DAY_MS),
+            day_key("https://a.com", now - 7 * DAY_MS),
+            day_key("https://b.com|x", now),
+            "garbage".to_string(),
+        ];
+        let records = [&a_today, &a_old, &a_expired, &b, &b];
+        let feeds = fold(keys.iter().map(String::as_str).zip(records), now, 7);
+        assert_eq!(feeds.len(), 2);
+        let a = &feeds[0];
+        assert_eq!(a.site_url, "https://a.com");
+        assert_eq!(a.category, "News");
+        assert_eq!((a.entries, a.summarized, a.failed), (3, 2, 1));
+        assert_eq!(a.avg_summary_chars, Some(200));
+        assert_eq!(a.avg_latency_ms, Some(100));
+        assert_eq!(feeds[1].site_url, "https://b.com|x");
+        assert_eq!(feeds[1].avg_summary_chars, None);
+    }
+
+    #[test]
+    fn pages_start_at_one() {
+        let items: Vec<usize> = (0..PAGE_SIZE + 1).collect();
+        assert_eq!(page(items.clone(), 0).len(), PAGE_SIZE);
+        assert_eq!(page(items.clone(), 2), vec![PAGE_SIZE]);
+        assert!(page(items, 3).is_empty());
+        assert_eq!(page_count(0), 1);
+        assert_eq!(page_count(PAGE_SIZE + 1), 2);
+    }
+}
@@ -0,0 +1,266 @@
+use crate::feed_health::normalize_site_url;
+#[cfg(feature = "regex-filters")]
+use regex::{RegexSet, RegexSetBuilder};
+
+// 订阅源匹配规则，支持 `*` 通配符，按归一化后的 site_url 比较
+// 构造时预先拆成字符，匹配时不再重复处理规则本身
+#[derive(Debug, Clone)]
+pub struct FeedPattern {
+    pattern: Vec<char>,
+}
+
+impl FeedPattern {
+    pub fn new(pattern: &str) -> Self {
+        FeedPattern {
+            pattern: normalize_site_url(pattern).chars().collect(),
+        }
+    }
+
+    pub fn matches(&self, site_url: &str) -> bool {
+        self.matches_normalized(&normalize_site_url(site_url))
+    }
+
+    // site_url 已经归一化过时直接匹配
+    pub fn matches_normalized(&self, site_url: &str) -> bool {
+        glob_match(&self.pattern, site_url)
+    }
+
+    // 非通配符字符越多的规则越具体
+    pub fn specificity(&self) -> usize {
+        self.pattern.iter().filter(|&&c| c != '*').count()
+    }
+}
+
+pub fn parse_patterns(raw: &str) -> Vec<FeedPattern> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(FeedPattern::new)
+        .collect()
+}
+
+fn glob_match(pattern: &[char], text: &str) -> bool {
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PriorityStrategy {
+    NewestFirst,
+    OldestFirst,
+    FeedPriority,
+}
+
+impl std::str::FromStr for PriorityStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "newest_first" => Ok(PriorityStrategy::NewestFirst),
+            "oldest_first" => Ok(PriorityStrategy::OldestFirst),
+            "feed_priority" => Ok(PriorityStrategy::FeedPriority),
+            other => Err(format!("Unknown priority strategy: {}", other)),
+        }
+    }
+}
+
+// 编译后的正则集合大小上限，regex crate 本身保证线性时间匹配，不会出现灾难性回溯
+#[cfg(feature = "regex-filters")]
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+// 按标题和正文开头跳过不需要摘要的文章，普通规则为不区分大小写的子串，`re:` 前缀为正则
+#[derive(Debug, Default)]
+pub struct ContentFilter {
+    substrings: Vec<String>,
+    #[cfg(feature = "regex-filters")]
+    regexes: Option<RegexSet>,
+}
+
+impl ContentFilter {
+    pub fn new(patterns: &[String]) -> Result<Self, String> {
+        let mut substrings = Vec::new();
+        let mut regexes = Vec::new();
+        for pattern in patterns.iter().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            match pattern.strip_prefix("re:") {
+                Some(regex) => regexes.push(regex),
+                None => substrings.push(pattern.to_lowercase()),
+            }
+        }
+
+        #[cfg(not(feature = "regex-filters"))]
+        if let Some(regex) = regexes.first() {
+            return Err(format!(
+                "re:{} needs the regex-filters feature, which is not enabled in this build",
+                regex
+            ));
+        }
+        #[cfg(feature = "regex-filters")]
+        let regexes = if regexes.is_empty() {
+            None
+        } else {
+            Some(
+                RegexSetBuilder::new(regexes)
+                    .case_insensitive(true)
+                    .size_limit(REGEX_SIZE_LIMIT)
+                    .build()
+                    .map_err(|e| e.to_string())?,
+            )
+        };
+
+        Ok(ContentFilter {
+            substrings,
+            #[cfg(feature = "regex-filters")]
+            regexes,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.substrings.is_empty() && !self.has_regexes()
+    }
+
+    #[cfg(feature = "regex-filters")]
+    fn has_regexes(&self) -> bool {
+        self.regexes.is_some()
+    }
+
+    #[cfg(not(feature = "regex-filters"))]
+    fn has_regexes(&self) -> bool {
+        false
+    }
+
+    #[cfg(feature = "regex-filters")]
+    fn regex_matches(&self, haystack: &str) -> bool {
+        self.regexes.as_ref().is_some_and(|r| r.is_match(haystack))
+    }
+
+    #[cfg(not(feature = "regex-filters"))]
+    fn regex_matches(&self, _haystack: &str) -> bool {
+        false
+    }
+
+    pub fn matches(&self, title: &str, text: &str) -> bool {
+        [title, text].iter().any(|haystack| {
+            let lower = haystack.to_lowercase();
+            self.substrings.iter().any(|s| lower.contains(s.as_str()))
+                || self.regex_matches(haystack)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns_match_normalized_site_urls() {
+        let pattern = FeedPattern::new("https://Example.com/blog/");
+        assert!(pattern.matches("https://example.com/blog"));
+        assert!(pattern.matches(" HTTPS://EXAMPLE.COM/blog/ "));
+        assert!(!pattern.matches("https://example.com/Blog"));
+        assert!(!pattern.matches("https://example.com/blog/post"));
+    }
+
+    #[test]
+    fn wildcards_match_any_run_of_characters() {
+        let pattern = FeedPattern::new("https://*.example.com/*");
+        assert!(pattern.matches("https://news.example.com/feed"));
+        assert!(pattern.matches("https://a.b.example.com/x"));
+        assert!(!pattern.matches("https://example.com/feed"));
+        assert!(FeedPattern::new("*").matches("anything"));
+        assert!(FeedPattern::new("a*b*c").matches("aXbYbZc"));
+        assert!(!FeedPattern::new("a*b*c").matches("aXbYbZ"));
+        assert!(FeedPattern::new("*中文*").matches("https://例子.cn/中文/"));
+    }
+
+    #[test]
+    fn specificity_counts_literal_characters() {
+        assert_eq!(FeedPattern::new("*").specificity(), 0);
+        assert!(
+            FeedPattern::new("https://news.example.com").specificity()
+                > FeedPattern::new("https://*.example.com").specificity()
+        );
+    }
+
+    #[test]
+    fn parses_comma_separated_patterns() {
+        let patterns = parse_patterns(" https://a.com/ ,, *.b.com ,");
+        assert_eq!(patterns.len(), 2);
+        assert!(patterns[0].matches("https://a.com"));
+        assert!(patterns[1].matches("news.b.com"));
+        assert!(parse_patterns("").is_empty());
+    }
+
+    #[test]
+    fn parses_priority_strategies() {
+        assert_eq!(
+            " oldest_first ".parse::<PriorityStrategy>(),
+            Ok(PriorityStrategy::OldestFirst)
+        );
+        assert_eq!(
+            "feed_priority".parse::<PriorityStrategy>(),
+            Ok(PriorityStrategy::FeedPriority)
+        );
+        assert!("random".parse::<PriorityStrategy>().is_err());
+    }
+
+    fn filter(patterns: &[&str]) -> ContentFilter {
+        let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+        ContentFilter::new(&patterns).unwrap()
+    }
+
+    #[test]
+    fn substrings_match_title_or_text_case_insensitively() {
+        let filter = filter(&["Sponsored", " 广告 ", ""]);
+        assert!(!filter.is_empty());
+        assert!(filter.matches("SPONSORED: new phone", ""));
+        assert!(filter.matches("", "本文为广告内容"));
+        assert!(!filter.matches("Release notes", "nothing to see"));
+    }
+
+    #[test]
+    fn blank_patterns_give_an_empty_filter() {
+        let filter = filter(&["", "  "]);
+        assert!(filter.is_empty());
+        assert!(!filter.matches("anything", "at all"));
+    }
+
+    #[cfg(feature = "regex-filters")]
+    #[test]
+    fn regex_patterns_match_case_insensitively() {
+        let filter = filter(&["re:^weekly (digest|roundup)", r"re:\bv\d+\.\d+\b"]);
+        assert!(filter.matches("Weekly Roundup #12", ""));
+        assert!(filter.matches("", "Released v1.2 today"));
+        assert!(!filter.matches("The weekly digest", "no version"));
+    }
+
+    #[cfg(feature = "regex-filters")]
+    #[test]
+    fn invalid_regex_is_rejected() {
+        assert!(ContentFilter::new(&["re:(".to_string()]).is_err());
+    }
+
+    #[cfg(not(feature = "regex-filters"))]
+    #[test]
+    fn regex_patterns_need_the_feature() {
+        assert!(ContentFilter::new(&["re:^weekly".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,23 @@
+// 转发出去的 Webhook 带上这个请求头，收到带有它的请求时不再转发，避免两个 Worker 互相转发
+pub const FORWARDED_HEADER: &str = "X-Miniflux-AI-Forwarded";
+
+// 只转发 Miniflux 直接发来的请求
+pub fn should_forward(target: Option<&str>, forwarded: Option<&str>) -> bool {
+    target.is_some_and(|target| !target.trim().is_empty()) && forwarded.is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forwards_only_direct_webhooks_with_a_target() {
+        assert!(should_forward(Some("https://other.workers.dev/"), None));
+        assert!(!should_forward(
+            Some("https://other.workers.dev/"),
+            Some("1")
+        ));
+        assert!(!should_forward(None, None));
+        assert!(!should_forward(Some("  "), None));
+    }
+}
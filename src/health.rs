@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+
+const HOUR_MS: u64 = 60 * 60 * 1000;
+
+// 最近一次成功写回摘要的文章，用于判断整条链路是否正常
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LastSuccess {
+    pub at: u64,
+    pub entry_id: u64,
+    pub site_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HealthPolicy {
+    pub max_age_hours: u64,
+    // 首次部署后还没有成功记录时，在这段时间内不报告异常
+    pub grace_hours: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HealthStatus {
+    pub healthy: bool,
+    // 从未成功过时为 "never"
+    pub last_success: serde_json::Value,
+    pub age_seconds: Option<u64>,
+}
+
+// `first_seen` 是第一次检查健康状态的时间，没有成功记录时用来计算宽限期
+pub fn check(
+    policy: HealthPolicy,
+    now: u64,
+    first_seen: u64,
+    last_success: Option<&LastSuccess>,
+) -> HealthStatus {
+    match last_success {
+        Some(last) => {
+            let age = now.saturating_sub(last.at);
+            HealthStatus {
+                healthy: age <= policy.max_age_hours * HOUR_MS,
+                last_success: serde_json::to_value(last).unwrap_or_default(),
+                age_seconds: Some(age / 1000),
+            }
+        }
+        None => HealthStatus {
+            healthy: now.saturating_sub(first_seen) <= policy.grace_hours * HOUR_MS,
+            last_success: serde_json::Value::from("never"),
+            age_seconds: None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: HealthPolicy = HealthPolicy {
+        max_age_hours: 6,
+        grace_hours: 24,
+    };
+
+    fn success(at: u64) -> LastSuccess {
+        LastSuccess {
+            at,
+            entry_id: 42,
+            site_url: Some("https://example.com".into()),
+        }
+    }
+
+    #[test]
+    fn recent_success_is_healthy() {
+        let status = check(POLICY, 10 * HOUR_MS, 0, Some(&success(5 * HOUR_MS)));
+        assert!(status.healthy);
+        assert_eq!(status.age_seconds, Some(5 * 60 * 60));
+        assert_eq!(status.last_success["entry_id"], 42);
+    }
+
+    #[test]
+    fn stale_success_is_unhealthy() {
+        let last = success(HOUR_MS);
+        assert!(check(POLICY, 7 * HOUR_MS, 0, Some(&last)).healthy);
+        assert!(!check(POLICY, 7 * HOUR_MS + 1, 0, Some(&last)).healthy);
+    }
+
+    // 还没有成功记录时在宽限期内视为正常
+    #[test]
+    fn never_succeeded_uses_grace_period() {
+        let first_seen = 100 * HOUR_MS;
+        let status = check(POLICY, first_seen + 24 * HOUR_MS, first_seen, None);
+        assert!(status.healthy);
+        assert_eq!(status.last_success, "never");
+        assert_eq!(status.age_seconds, None);
+        assert!(!check(POLICY, first_seen + 25 * HOUR_MS, first_seen, None).healthy);
+    }
+
+    // 时钟回拨时不会溢出
+    #[test]
+    fn clock_skew_does_not_underflow() {
+        let status = check(POLICY, HOUR_MS, 0, Some(&success(2 * HOUR_MS)));
+        assert!(status.healthy);
+        assert_eq!(status.age_seconds, Some(0));
+    }
+}
@@ -0,0 +1,914 @@
+// 去除 HTML 标签并解码常见实体，得到适合发送给模型的纯文本
+pub fn extract_text(html: &str) -> String {
+    extract_text_with_alts(html).0
+}
+
+// 同 extract_text，另外收集图片的 alt 文本
+pub fn extract_text_with_alts(html: &str) -> (String, Vec<String>) {
+    let mut text = String::with_capacity(html.len());
+    let mut alts: Vec<String> = Vec::new();
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let Some(end) = rest.find('>') else {
+            rest = "";
+            break;
+        };
+        let raw_tag = &rest[1..end];
+        let closing = raw_tag.starts_with('/');
+        let tag = raw_tag.trim_start_matches('/').to_ascii_lowercase();
+        rest = &rest[end + 1..];
+
+        if tag.starts_with("img") {
+            if let Some(alt) = attribute(raw_tag, "alt") {
+                let alt = collapse_whitespace(&decode_entities(alt));
+                if !alt.is_empty() && !alts.contains(&alt) {
+                    alts.push(alt);
+                }
+            }
+        }
+
+        // 跳过脚本和样式的内容，结束标签不再触发跳过
+        for skipped in ["script", "style"] {
+            if !closing && tag.starts_with(skipped) && !tag.ends_with('/') {
+                let closing = format!("</{}", skipped);
+                rest = match rest.to_ascii_lowercase().find(&closing) {
+                    Some(i) => &rest[i..],
+                    None => "",
+                };
+            }
+        }
+        text.push(' ');
+    }
+    text.push_str(rest);
+
+    (collapse_whitespace(&decode_entities(&text)), alts)
+}
+
+const BLOCK_TAGS: &[&str] = &[
+    "p",
+    "div",
+    "br",
+    "li",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "blockquote",
+    "pre",
+    "tr",
+    "section",
+    "article",
+    "figcaption",
+];
+
+// 按块级标签拆分出段落纯文本，用于逐段翻译
+pub fn paragraphs(html: &str) -> Vec<String> {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        // 源码中的换行只是空白，段落只按块级标签划分
+        text.push_str(&rest[..start].replace('\n', " "));
+        rest = &rest[start..];
+
+        let Some(end) = rest.find('>') else {
+            rest = "";
+            break;
+        };
+        let closing = rest[1..].starts_with('/');
+        let tag = rest[1..end].trim_start_matches('/').to_ascii_lowercase();
+        rest = &rest[end + 1..];
+
+        for skipped in ["script", "style"] {
+            if !closing && tag.starts_with(skipped) && !tag.ends_with('/') {
+                let closing = format!("</{}", skipped);
+                rest = match rest.to_ascii_lowercase().find(&closing) {
+                    Some(i) => &rest[i..],
+                    None => "",
+                };
+            }
+        }
+        let name = tag
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default();
+        text.push(if BLOCK_TAGS.contains(&name) {
+            '\n'
+        } else {
+            ' '
+        });
+    }
+    text.push_str(&rest.replace('\n', " "));
+
+    text.split('\n')
+        .map(|p| collapse_whitespace(&decode_entities(p)))
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+// 引用和链接文字占正文的比例，用于识别以摘录、链接为主的聚合类文章
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AggregationStats {
+    pub quoted_ratio: f32,
+    pub link_density: f32,
+    pub links: Vec<Link>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    pub url: String,
+    pub text: String,
+}
+
+// 按非空白字符计算比例，嵌套的引用和链接只算一次
+pub fn aggregation_stats(html: &str) -> AggregationStats {
+    let (mut total, mut quoted, mut linked) = (0usize, 0usize, 0usize);
+    let mut quote_depth = 0usize;
+    let mut link: Option<Link> = None;
+    let mut links = Vec::new();
+    let mut rest = html;
+
+    let mut count = |text: &str, quote_depth: usize, link: &mut Option<Link>| {
+        let text = decode_entities(text);
+        let chars = text.chars().filter(|c| !c.is_whitespace()).count();
+        total += chars;
+        if quote_depth > 0 {
+            quoted += chars;
+        }
+        if let Some(link) = link {
+            linked += chars;
+            link.text.push_str(&text);
+        }
+    };
+
+    while let Some(start) = rest.find('<') {
+        count(&rest[..start], quote_depth, &mut link);
+        rest = &rest[start..];
+        let Some(end) = rest.find('>') else {
+            rest = "";
+            break;
+        };
+        let raw_tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        let closing = raw_tag.starts_with('/');
+        let tag = raw_tag.trim_start_matches('/').to_ascii_lowercase();
+        let name = tag
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default();
+        match (name, closing) {
+            ("blockquote", false) => quote_depth += 1,
+            ("blockquote", true) => quote_depth = quote_depth.saturating_sub(1),
+            ("a", false) => {
+                link = attribute(raw_tag, "href").map(|url| Link {
+                    url: decode_entities(url),
+                    text: String::new(),
+                });
+            }
+            ("a", true) => {
+                if let Some(mut done) = link.take() {
+                    done.text = collapse_whitespace(&done.text);
+                    if !done.text.is_empty() && !links.iter().any(|l: &Link| l.url == done.url) {
+                        links.push(done);
+                    }
+                }
+            }
+            ("script" | "style", false) => {
+                let closing = format!("</{}", name);
+                rest = match rest.to_ascii_lowercase().find(&closing) {
+                    Some(i) => &rest[i..],
+                    None => "",
+                };
+            }
+            _ => {}
+        }
+    }
+    count(rest, quote_depth, &mut link);
+
+    if total == 0 {
+        return AggregationStats::default();
+    }
+    AggregationStats {
+        quoted_ratio: quoted as f32 / total as f32,
+        link_density: linked as f32 / total as f32,
+        links,
+    }
+}
+
+// 文章中的 h2、h3 标题，用于标注摘要要点出自哪一节
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heading {
+    pub level: u8,
+    pub text: String,
+}
+
+// 忽略 figure 中的标题，嵌套的标题合并到外层，重复的标题只保留第一个
+pub fn outline(html: &str) -> Vec<Heading> {
+    let mut headings: Vec<Heading> = Vec::new();
+    let mut figure_depth = 0usize;
+    // 正在读取的标题及其嵌套层数
+    let mut current: Option<(Heading, usize)> = None;
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        // 标签两侧按空白处理，后面会合并多余的空白
+        if let Some((heading, _)) = &mut current {
+            heading.text.push_str(&rest[..start]);
+            heading.text.push(' ');
+        }
+        rest = &rest[start..];
+        let Some(end) = rest.find('>') else {
+            break;
+        };
+        let raw_tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        let closing = raw_tag.starts_with('/');
+        let tag = raw_tag.trim_start_matches('/').to_ascii_lowercase();
+        let name = tag
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default();
+        let level = match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => name.as_bytes()[1] - b'0',
+            _ => 0,
+        };
+        match (name, closing) {
+            ("figure", false) => figure_depth += 1,
+            ("figure", true) => figure_depth = figure_depth.saturating_sub(1),
+            ("script" | "style", false) => {
+                let closing = format!("</{}", name);
+                rest = match rest.to_ascii_lowercase().find(&closing) {
+                    Some(i) => &rest[i..],
+                    None => "",
+                };
+            }
+            _ if level == 0 => {}
+            (_, false) => match &mut current {
+                Some((_, depth)) => *depth += 1,
+                None => {
+                    current = Some((
+                        Heading {
+                            level,
+                            text: String::new(),
+                        },
+                        1,
+                    ))
+                }
+            },
+            (_, true) => {
+                let done = match &mut current {
+                    Some((_, depth)) if *depth > 1 => {
+                        *depth -= 1;
+                        false
+                    }
+                    Some(_) => true,
+                    None => false,
+                };
+                if done {
+                    let (mut heading, _) = current.take().unwrap();
+                    heading.text = collapse_whitespace(&decode_entities(&heading.text));
+                    if (2..=3).contains(&heading.level)
+                        && figure_depth == 0
+                        && !heading.text.is_empty()
+                        && !headings.iter().any(|h| h.text == heading.text)
+                    {
+                        headings.push(heading);
+                    }
+                }
+            }
+        }
+    }
+
+    headings
+}
+
+// 读取标签中的属性值，支持单引号、双引号和不带引号的写法
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let lower = tag.to_ascii_lowercase();
+    let pattern = format!("{}=", name);
+    let mut from = 0;
+
+    while let Some(i) = lower[from..].find(&pattern) {
+        let start = from + i;
+        from = start + pattern.len();
+        if !lower[..start].ends_with(char::is_whitespace) {
+            continue;
+        }
+
+        let value = &tag[from..];
+        return match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].split(quote).next(),
+            // 不带引号的值可以包含 `/`，只去掉紧贴在标签结尾的自闭合斜杠
+            _ => value.split(char::is_whitespace).next().map(|v| {
+                if v.len() == value.len() {
+                    v.strip_suffix('/').unwrap_or(v)
+                } else {
+                    v
+                }
+            }),
+        };
+    }
+
+    None
+}
+
+pub fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| decode_entity(&rest[1..end + 1]).map(|c| (c, end + 2)));
+
+        match entity {
+            Some((c, len)) => {
+                decoded.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+
+    decoded
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => {
+            let code = name.strip_prefix('#')?;
+            let value = match code.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => code.parse().ok()?,
+            };
+            char::from_u32(value)
+        }
+    }
+}
+
+pub fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3000..=0x303F // CJK 标点
+        | 0x3040..=0x30FF // 假名
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xAC00..=0xD7AF // 韩文
+        | 0xF900..=0xFAFF
+        | 0xFF00..=0xFFEF // 全角字符
+    )
+}
+
+// 截断超长的连续非空白字符（base64、压缩后的 JSON 等），CJK 文本本身没有空格，只按其中的非 CJK 片段计算长度
+pub fn truncate_long_runs(text: &str, max_run: usize) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut run_len = 0;
+
+    for c in text.chars() {
+        if c.is_whitespace() || is_cjk(c) {
+            run_len = 0;
+            result.push(c);
+            continue;
+        }
+
+        run_len += 1;
+        if run_len <= max_run {
+            result.push(c);
+        } else if run_len == max_run + 1 {
+            result.push('…');
+        }
+    }
+
+    result
+}
+
+// 发送给模型前的文本规范化：去标签、解码实体、截断超长片段，可选附加图片描述
+pub fn prepare_input(html: &str, max_run: usize, include_alt_text: bool) -> String {
+    let (mut text, alts) = extract_text_with_alts(html);
+    if include_alt_text && !alts.is_empty() {
+        text = format!("{}\n\nImage descriptions: {}", text, alts.join("; "));
+    }
+    truncate_long_runs(&text, max_run)
+}
+
+// 纯文本订阅源的正文只去掉首尾空白并截断超长片段，不按 HTML 处理
+pub fn prepare_plain_input(text: &str, max_run: usize) -> String {
+    truncate_long_runs(text.trim(), max_run)
+}
+
+// 正文格式，auto 时按内容判断
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ContentFormat {
+    Auto,
+    Html,
+    Plain,
+}
+
+impl std::str::FromStr for ContentFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "auto" => Ok(ContentFormat::Auto),
+            "html" => Ok(ContentFormat::Html),
+            "plain" => Ok(ContentFormat::Plain),
+            other => Err(format!("Unknown content format: {}", other)),
+        }
+    }
+}
+
+const COMMON_TAGS: &[&str] = &[
+    "a",
+    "article",
+    "b",
+    "blockquote",
+    "br",
+    "code",
+    "div",
+    "em",
+    "figure",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "i",
+    "iframe",
+    "img",
+    "li",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "span",
+    "strong",
+    "table",
+    "ul",
+    "video",
+];
+
+// 出现常见的 HTML 标签才视为 HTML，正文里偶尔出现的 `<`、`>` 不算
+pub fn is_html(content: &str) -> bool {
+    let mut rest = content;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let tag = rest.strip_prefix('/').unwrap_or(rest);
+        let name: String = tag
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        let closed = tag[name.len()..]
+            .chars()
+            .next()
+            .is_some_and(|c| c == '>' || c == '/' || c.is_whitespace());
+        if closed && COMMON_TAGS.contains(&name.as_str()) {
+            return true;
+        }
+    }
+    false
+}
+
+// 纯文本转为 HTML，空行分段，单个换行保留为 `<br>`
+pub fn plain_to_html(text: &str) -> String {
+    text.replace("\r\n", "\n")
+        .split("\n\n")
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(|p| format!("<p>{}</p>", escape(p).replace('\n', "<br>")))
+        .collect()
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// 文章中的图片、视频和嵌入内容数量
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MediaStats {
+    pub images: usize,
+    pub videos: usize,
+    pub embed_hosts: Vec<String>,
+}
+
+const VIDEO_HOSTS: &[&str] = &[
+    "youtube.com",
+    "youtu.be",
+    "youtube-nocookie.com",
+    "vimeo.com",
+];
+
+// 正文中 `<link rel="canonical">` 指向的原始地址，转载的文章通常带有这个标签
+pub fn canonical_url(html: &str) -> Option<&str> {
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let end = rest.find('>')?;
+        let tag = &rest[..end];
+        rest = &rest[end + 1..];
+
+        let is_link = tag
+            .get(..4)
+            .is_some_and(|name| name.eq_ignore_ascii_case("link"))
+            && tag[4..].starts_with(char::is_whitespace);
+        if is_link
+            && attribute(tag, "rel").is_some_and(|rel| {
+                rel.split_whitespace()
+                    .any(|r| r.eq_ignore_ascii_case("canonical"))
+            })
+        {
+            if let Some(href) = attribute(tag, "href").filter(|h| !h.trim().is_empty()) {
+                return Some(href.trim());
+            }
+        }
+    }
+    None
+}
+
+pub fn count_media(html: &str) -> MediaStats {
+    let mut stats = MediaStats::default();
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let Some(end) = rest.find('>') else {
+            break;
+        };
+        let tag = &rest[..end];
+        rest = &rest[end + 1..];
+
+        let name: String = tag
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        match name.as_str() {
+            "img" if !is_tracking_pixel(tag) => stats.images += 1,
+            "video" => stats.videos += 1,
+            "iframe" => {
+                let Some(host) = attribute(tag, "src").and_then(url_host) else {
+                    continue;
+                };
+                let host = host.trim_start_matches("www.").to_string();
+                if VIDEO_HOSTS
+                    .iter()
+                    .any(|v| host == *v || host.ends_with(&format!(".{}", v)))
+                {
+                    stats.videos += 1;
+                }
+                if !stats.embed_hosts.contains(&host) {
+                    stats.embed_hosts.push(host);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    stats
+}
+
+// 宽或高为 0、1 像素，或者隐藏的图片通常是统计用的跟踪像素
+fn is_tracking_pixel(tag: &str) -> bool {
+    let tiny = |value: &str| {
+        let value = value.trim().trim_end_matches("px");
+        value == "0" || value == "1"
+    };
+    if ["width", "height"]
+        .iter()
+        .any(|name| attribute(tag, name).is_some_and(tiny))
+    {
+        return true;
+    }
+
+    attribute(tag, "style").is_some_and(|style| {
+        let style: String = style
+            .to_ascii_lowercase()
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        style.contains("display:none")
+            || style
+                .split(';')
+                .filter_map(|rule| rule.split_once(':'))
+                .any(|(name, value)| (name == "width" || name == "height") && tiny(value))
+    })
+}
+
+fn url_host(url: &str) -> Option<&str> {
+    let rest = url.split_once("//")?.1;
+    let host = rest.split(['/', '?', '#']).next()?;
+    let host = host.rsplit('@').next()?.split(':').next()?;
+    (!host.is_empty()).then_some(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_images_videos_and_embeds() {
+        let html = r#"<p>intro</p><IMG src="a.png"><img src="b.png" alt="b">
+            <video src="c.mp4"></video>
+            <iframe src="https://www.youtube.com/embed/x"></iframe>
+            <iframe src="https://player.vimeo.com/video/1"></iframe>
+            <iframe src="https://codepen.io/pen/1"></iframe>
+            <iframe src="https://www.youtube.com/embed/y"></iframe>
+            <iframe></iframe>"#;
+        assert_eq!(
+            count_media(html),
+            MediaStats {
+                images: 2,
+                videos: 4,
+                embed_hosts: vec![
+                    "youtube.com".to_string(),
+                    "player.vimeo.com".to_string(),
+                    "codepen.io".to_string(),
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn tracking_pixels_are_not_images() {
+        let html = r#"<img src="t.gif" width="1" height="1"><img src="t.gif" width="0px">
+            <img src="t.gif" style="display: none"><img src="t.gif" style="width:1px; height:1px">
+            <img src="photo.jpg" width="640">"#;
+        assert_eq!(count_media(html).images, 1);
+    }
+
+    #[test]
+    fn unterminated_tags_are_ignored() {
+        assert_eq!(
+            count_media("<img src=\"a.png\"><img src="),
+            MediaStats {
+                images: 1,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn extracts_text_without_tags_scripts_or_styles() {
+        let html = r#"<p>Hello<b>world</b></p><script>var x = "<p>";</script>
+            <STYLE>p { color: red }</STYLE><div>中文&nbsp;内容</div>"#;
+        assert_eq!(extract_text(html), "Hello world 中文 内容");
+        assert_eq!(extract_text("plain text"), "plain text");
+        assert_eq!(extract_text("broken <b"), "broken");
+    }
+
+    #[test]
+    fn decodes_named_and_numeric_entities() {
+        assert_eq!(
+            decode_entities("a &amp; b &lt;c&gt; &quot;d&quot; &apos;e&apos;"),
+            "a & b <c> \"d\" 'e'"
+        );
+        assert_eq!(decode_entities("&#20013;&#x6587;&#X41;"), "中文A");
+        // 未知或不完整的实体原样保留
+        assert_eq!(
+            decode_entities("AT&T &unknown; &#xZZ; & end"),
+            "AT&T &unknown; &#xZZ; & end"
+        );
+        assert_eq!(decode_entities("&amp;amp;"), "&amp;");
+    }
+
+    #[test]
+    fn reads_quoted_and_unquoted_attributes() {
+        assert_eq!(attribute(r#"a href="x y" title='t'"#, "href"), Some("x y"));
+        assert_eq!(attribute(r#"a href="x" title='t'"#, "title"), Some("t"));
+        assert_eq!(attribute("img src=a/b.png/", "src"), Some("a/b.png"));
+        assert_eq!(attribute("img SRC=a.png alt=x", "src"), Some("a.png"));
+        // data-src 不是 src
+        assert_eq!(attribute(r#"img data-src="a.png""#, "src"), None);
+        assert_eq!(attribute("img", "src"), None);
+    }
+
+    #[test]
+    fn collapses_whitespace() {
+        assert_eq!(collapse_whitespace("  a \n\t b  "), "a b");
+        assert_eq!(collapse_whitespace(" \n "), "");
+    }
+
+    #[test]
+    fn truncates_long_base64_runs() {
+        let base64 = "QUJD".repeat(100);
+        let text = format!("before {} after", base64);
+        assert_eq!(truncate_long_runs(&text, 8), "before QUJDQUJD… after");
+        // 刚好达到上限的片段不截断
+        assert_eq!(truncate_long_runs("abcdefgh ij", 8), "abcdefgh ij");
+    }
+
+    // CJK 文本没有空格，不能整段当作一个超长片段截断
+    #[test]
+    fn cjk_text_is_not_truncated() {
+        let chinese = "这是一段很长的中文内容没有任何空格".repeat(20);
+        assert_eq!(truncate_long_runs(&chinese, 8), chinese);
+        assert_eq!(
+            truncate_long_runs("全角ＡＢＣＤＥＦＧＨＩＪ和日本語のテキスト", 4),
+            "全角ＡＢＣＤＥＦＧＨＩＪ和日本語のテキスト"
+        );
+    }
+
+    // 夹在 CJK 文本中的长片段仍会截断
+    #[test]
+    fn long_runs_inside_cjk_text_are_truncated() {
+        let text = format!("图片{}结束", "A".repeat(50));
+        assert_eq!(truncate_long_runs(&text, 4), "图片AAAA…结束");
+    }
+
+    #[test]
+    fn prepare_input_extracts_and_truncates() {
+        let html = format!(
+            "<p>Intro</p><img src=\"x.png\" alt=\"A &amp; B\"><p>data:{}</p>",
+            "x".repeat(30)
+        );
+        assert_eq!(prepare_input(&html, 16, false), "Intro data:xxxxxxxxxxx…");
+    }
+
+    #[test]
+    fn collects_unique_image_alts() {
+        let html = r#"<p>Chart</p><img src="a.png" alt="Revenue &amp; profit"><IMG ALT='  Revenue   &amp; profit '>
+            <img src="b.png" alt=""><img src="c.png" alt="第二张图">"#;
+        assert_eq!(
+            extract_text_with_alts(html),
+            (
+                "Chart".to_string(),
+                vec!["Revenue & profit".to_string(), "第二张图".to_string()]
+            )
+        );
+    }
+
+    #[test]
+    fn alt_text_is_appended_only_when_enabled() {
+        let html = r#"<p>Intro</p><img src="x.png" alt="A &amp; B">"#;
+        assert_eq!(prepare_input(html, 200, false), "Intro");
+        assert_eq!(
+            prepare_input(html, 200, true),
+            "Intro\n\nImage descriptions: A & B"
+        );
+        assert_eq!(prepare_input("<p>Intro</p>", 200, true), "Intro");
+    }
+
+    #[test]
+    fn finds_canonical_link() {
+        let html = r#"<p>Reposted</p><link rel="stylesheet" href="a.css">
+            <LINK REL="Canonical" href=" https://origin.example.com/post ">"#;
+        assert_eq!(canonical_url(html), Some("https://origin.example.com/post"));
+        assert_eq!(canonical_url(r#"<link rel="canonical" href="">"#), None);
+        assert_eq!(canonical_url(r#"<linked rel="canonical" href="x">"#), None);
+        assert_eq!(canonical_url("<p>no link</p>"), None);
+    }
+
+    #[test]
+    fn detects_html_by_common_tags() {
+        assert!(is_html("<p>Hello</p>"));
+        assert!(is_html("line<br/>break"));
+        assert!(is_html("<IMG src=\"a.png\">"));
+        assert!(is_html("text </div>"));
+        // 正文里的比较符号和未知标签不算 HTML
+        assert!(!is_html("if a < b && c > d"));
+        assert!(!is_html("Vec<String> and <T>"));
+        assert!(!is_html("<preview> coming soon"));
+        assert!(!is_html("plain text"));
+    }
+
+    #[test]
+    fn converts_plain_text_to_paragraphs() {
+        assert_eq!(
+            plain_to_html("First line\nsecond line\r\n\r\n\n\nA < B & C"),
+            "<p>First line<br>second line</p><p>A &lt; B &amp; C</p>"
+        );
+        assert_eq!(plain_to_html("  \n\n  "), "");
+    }
+
+    #[test]
+    fn plain_input_keeps_markup_like_text() {
+        assert_eq!(prepare_plain_input("  a <b> c  ", 100), "a <b> c");
+        assert!(prepare_plain_input(&"x".repeat(500), 100).len() < 500);
+    }
+
+    #[test]
+    fn parses_content_format() {
+        assert_eq!("auto".parse(), Ok(ContentFormat::Auto));
+        assert_eq!(" html ".parse(), Ok(ContentFormat::Html));
+        assert_eq!("plain".parse(), Ok(ContentFormat::Plain));
+        assert!("markdown".parse::<ContentFormat>().is_err());
+    }
+
+    #[test]
+    fn splits_paragraphs_on_block_tags() {
+        assert_eq!(
+            paragraphs(
+                "<h2>Title</h2><p>First <b>bold</b>\n text.</p><ul><li>One</li><li>Two &amp; three</li></ul>"
+            ),
+            vec!["Title", "First bold text.", "One", "Two & three"]
+        );
+    }
+
+    #[test]
+    fn paragraphs_skip_scripts_and_empty_blocks() {
+        assert_eq!(
+            paragraphs("<p>Before</p><script>var a = 1;</script><p> </p><div>After</div>"),
+            vec!["Before", "After"]
+        );
+        assert_eq!(paragraphs("plain text"), vec!["plain text"]);
+    }
+
+    #[test]
+    fn measures_quotes_and_links() {
+        let stats = aggregation_stats(
+            "<p>Mine</p><blockquote>Quoted <blockquote>deep</blockquote></blockquote>\
+             <a href=\"https://a.com/x?a=1&amp;b=2\">Link</a>",
+        );
+        // Mine + Quoted + deep + Link 共 18 个非空白字符
+        assert_eq!(stats.quoted_ratio, 10.0 / 18.0);
+        assert_eq!(stats.link_density, 4.0 / 18.0);
+        assert_eq!(
+            stats.links,
+            vec![Link {
+                url: "https://a.com/x?a=1&b=2".into(),
+                text: "Link".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn links_are_unique_and_need_text() {
+        let stats = aggregation_stats(
+            "<a href=\"/a\">One</a> <a href=\"/a\">Again</a> <a href=\"/b\"><img src=x></a> <a name=c>anchor</a>",
+        );
+        assert_eq!(stats.links.len(), 1);
+        assert_eq!(stats.links[0].text, "One");
+        // 没有 href 的锚点不算链接
+        assert_eq!(stats.link_density, 8.0 / 14.0);
+    }
+
+    #[test]
+    fn empty_content_has_no_aggregation() {
+        assert_eq!(aggregation_stats(""), AggregationStats::default());
+        assert_eq!(
+            aggregation_stats("<script>var a = '<a href=x>b</a>';</script>"),
+            AggregationStats::default()
+        );
+    }
+
+    #[test]
+    fn outlines_h2_and_h3_headings() {
+        let headings = outline(
+            "<h1>Title</h1><h2 id=a>Intro &amp; <em>goals</em></h2><p>x</p>\
+             <h3>Details</h3><h4>Minor</h4><h2>Intro &amp; goals</h2><h2> </h2>",
+        );
+        assert_eq!(
+            headings,
+            vec![
+                Heading {
+                    level: 2,
+                    text: "Intro & goals".into(),
+                },
+                Heading {
+                    level: 3,
+                    text: "Details".into(),
+                },
+            ]
+        );
+    }
+
+    // figure 中的标题和脚本中的标签不算小节
+    #[test]
+    fn outline_skips_figures_and_scripts() {
+        let headings = outline(
+            "<figure><h2>Chart</h2></figure><script>'<h2>x</h2>'</script><h2>Real <h3>nested</h3></h2>",
+        );
+        assert_eq!(headings.len(), 1);
+        assert_eq!(headings[0].text, "Real nested");
+    }
+}
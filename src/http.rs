@@ -0,0 +1,501 @@
+use crate::shared_state::Shared;
+#[cfg(feature = "compression")]
+use flate2::{write::GzEncoder, Compression};
+#[cfg(feature = "compression")]
+use reqwest::header::CONTENT_ENCODING;
+use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT};
+#[cfg(feature = "compression")]
+use reqwest::StatusCode;
+use reqwest::{IntoUrl, Method, RequestBuilder, Response};
+use std::cell::OnceCell;
+#[cfg(feature = "compression")]
+use std::io::Write;
+use std::rc::Rc;
+use worker::async_trait::async_trait;
+
+pub type HttpResult = Result<Response, Box<dyn std::error::Error>>;
+
+// 所有出站请求都通过 HttpExec 执行，预算、记录等功能以包装层的方式叠加
+#[async_trait(?Send)]
+pub trait HttpExec {
+    async fn execute(&self, request: RequestBuilder) -> HttpResult;
+}
+
+#[async_trait(?Send)]
+impl<E: HttpExec + ?Sized> HttpExec for Rc<E> {
+    async fn execute(&self, request: RequestBuilder) -> HttpResult {
+        (**self).execute(request).await
+    }
+}
+
+pub struct ReqwestExec;
+
+#[async_trait(?Send)]
+impl HttpExec for ReqwestExec {
+    async fn execute(&self, request: RequestBuilder) -> HttpResult {
+        Ok(request.send().await?)
+    }
+}
+
+// Workers 限制每次调用的子请求数量，超出预算后直接拒绝，避免运行到一半被平台中断
+pub struct Budgeted<E> {
+    inner: E,
+    limit: Option<usize>,
+    used: Shared<usize>,
+}
+
+impl<E> Budgeted<E> {
+    pub fn new(inner: E, limit: Option<usize>) -> Self {
+        Budgeted {
+            inner,
+            limit,
+            used: Shared::new(0),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl<E: HttpExec> HttpExec for Budgeted<E> {
+    async fn execute(&self, request: RequestBuilder) -> HttpResult {
+        let reserved = self.used.try_update(|used| {
+            self.limit
+                .is_none_or(|limit| *used < limit)
+                .then_some(used + 1)
+        });
+        if !reserved {
+            return Err("Subrequest budget exhausted".into());
+        }
+        self.inner.execute(request).await
+    }
+}
+
+#[cfg(feature = "compression")]
+pub fn gzip(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+// 请求体超过阈值时 gzip 压缩，服务端不接受压缩请求（415/400）时自动改为不压缩重试
+#[cfg(feature = "compression")]
+pub struct Compressed<E> {
+    inner: E,
+    threshold: usize,
+}
+
+#[cfg(feature = "compression")]
+impl<E> Compressed<E> {
+    pub fn new(inner: E, threshold: usize) -> Self {
+        Compressed { inner, threshold }
+    }
+}
+
+#[cfg(feature = "compression")]
+#[async_trait(?Send)]
+impl<E: HttpExec> HttpExec for Compressed<E> {
+    async fn execute(&self, request: RequestBuilder) -> HttpResult {
+        let body = request
+            .try_clone()
+            .and_then(|r| r.build().ok())
+            .and_then(|r| r.body().and_then(|b| b.as_bytes()).map(<[u8]>::to_vec))
+            .filter(|body| body.len() >= self.threshold);
+        let (Some(body), Some(uncompressed)) = (body, request.try_clone()) else {
+            return self.inner.execute(request).await;
+        };
+
+        let compressed = request.header(CONTENT_ENCODING, "gzip").body(gzip(&body)?);
+        let response = self.inner.execute(compressed).await?;
+        if matches!(
+            response.status(),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE | StatusCode::BAD_REQUEST
+        ) {
+            return self.inner.execute(uncompressed).await;
+        }
+        Ok(response)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub url: String,
+    pub status: Option<u16>,
+}
+
+// 记录每个出站请求的方法、地址和状态码
+pub struct Recording<E> {
+    inner: E,
+    records: Shared<Vec<RecordedRequest>>,
+}
+
+impl<E> Recording<E> {
+    pub fn new(inner: E) -> Self {
+        Recording {
+            inner,
+            records: Shared::new(Vec::new()),
+        }
+    }
+
+    pub fn records(&self) -> Vec<RecordedRequest> {
+        self.records.get()
+    }
+}
+
+#[async_trait(?Send)]
+impl<E: HttpExec> HttpExec for Recording<E> {
+    async fn execute(&self, request: RequestBuilder) -> HttpResult {
+        let (method, url) = request
+            .try_clone()
+            .and_then(|r| r.build().ok())
+            .map(|r| (r.method().to_string(), r.url().to_string()))
+            .unwrap_or_default();
+
+        let result = self.inner.execute(request).await;
+        let record = RecordedRequest {
+            method,
+            url,
+            status: result.as_ref().ok().map(|r| r.status().as_u16()),
+        };
+        self.records.with(|records| records.push(record));
+        result
+    }
+}
+
+// 默认请求头在创建请求时加上，执行层看到的就是实际发送的请求，实际发送交给共享的 HttpExec
+// 客户端在第一次发起请求时才创建，缩短冷启动时间
+#[derive(Clone)]
+pub struct HttpClient {
+    headers: HeaderMap,
+    client: OnceCell<reqwest::Client>,
+    exec: Rc<dyn HttpExec>,
+}
+
+impl HttpClient {
+    // User-Agent 无效时不发送
+    pub fn new(user_agent: &str, mut headers: HeaderMap, exec: Rc<dyn HttpExec>) -> Self {
+        if let Ok(user_agent) = HeaderValue::from_str(user_agent) {
+            headers.insert(USER_AGENT, user_agent);
+        }
+        HttpClient {
+            headers,
+            client: OnceCell::new(),
+            exec,
+        }
+    }
+
+    fn client(&self) -> &reqwest::Client {
+        self.client.get_or_init(reqwest::Client::new)
+    }
+
+    fn request<U: IntoUrl>(&self, method: Method, url: U) -> RequestBuilder {
+        self.client()
+            .request(method, url)
+            .headers(self.headers.clone())
+    }
+
+    pub fn get<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::GET, url)
+    }
+
+    pub fn post<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::POST, url)
+    }
+
+    pub fn put<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::PUT, url)
+    }
+
+    pub async fn send(&self, request: RequestBuilder) -> HttpResult {
+        self.exec.execute(request).await
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg(test)]
+pub struct MockRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+// 测试用的响应，状态码 0 表示请求失败
+#[derive(Debug, Clone)]
+#[cfg(test)]
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    // 按分块传输发送，响应没有 Content-Length
+    pub chunked: bool,
+}
+
+#[cfg(test)]
+impl MockResponse {
+    pub fn status(status: u16) -> Self {
+        MockResponse {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+            chunked: false,
+        }
+    }
+
+    pub fn json(body: serde_json::Value) -> Self {
+        MockResponse::status(200)
+            .header("Content-Type", "application/json")
+            .body(body.to_string())
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    pub fn chunked(mut self) -> Self {
+        self.chunked = true;
+        self
+    }
+}
+
+// 测试用的执行层，记录收到的请求，由 handler 按请求决定返回的响应
+#[cfg(test)]
+pub struct MockExec {
+    handler: Box<dyn Fn(&MockRequest) -> MockResponse>,
+    requests: Shared<Vec<MockRequest>>,
+}
+
+#[cfg(test)]
+impl MockExec {
+    // 按顺序返回预设的状态码，用完后返回 200
+    pub fn new(statuses: &[u16]) -> Self {
+        let statuses: Shared<std::collections::VecDeque<u16>> =
+            Shared::new(statuses.iter().copied().collect());
+        MockExec::with_handler(move |_| {
+            MockResponse::status(
+                statuses
+                    .with(|statuses| statuses.pop_front())
+                    .unwrap_or(200),
+            )
+        })
+    }
+
+    pub fn with_handler(handler: impl Fn(&MockRequest) -> MockResponse + 'static) -> Self {
+        MockExec {
+            handler: Box::new(handler),
+            requests: Shared::new(Vec::new()),
+        }
+    }
+
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.get()
+    }
+}
+
+#[cfg(test)]
+#[async_trait(?Send)]
+impl HttpExec for MockExec {
+    async fn execute(&self, request: RequestBuilder) -> HttpResult {
+        use reqwest::ResponseBuilderExt;
+
+        let request = request.build()?;
+        let mock = MockRequest {
+            method: request.method().to_string(),
+            url: request.url().to_string(),
+            headers: request.headers().clone(),
+            body: request
+                .body()
+                .and_then(|b| b.as_bytes())
+                .map(<[u8]>::to_vec)
+                .unwrap_or_default(),
+        };
+        let reply = (self.handler)(&mock);
+        self.requests.with(|requests| requests.push(mock));
+        // 和真实请求一样在等待响应时让出执行权，并发的任务会交错执行
+        crate::shared_state::yield_now().await;
+        if reply.status == 0 {
+            return Err("connection failed".into());
+        }
+        let mut response = http02::Response::builder()
+            .status(reply.status)
+            .url(request.url().clone());
+        for (name, value) in &reply.headers {
+            response = response.header(name.as_str(), value.as_str());
+        }
+        let body = if reply.chunked {
+            let chunk: Result<_, std::io::Error> = Ok(reply.body);
+            reqwest::Body::wrap_stream(futures::stream::once(async { chunk }))
+        } else {
+            reqwest::Body::from(reply.body)
+        };
+        Ok(Response::from(response.body(body)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    fn post(body: &str) -> RequestBuilder {
+        reqwest::Client::new()
+            .post("https://example.com/v1/chat/completions")
+            .body(body.to_string())
+    }
+
+    #[test]
+    fn mock_returns_statuses_in_order() {
+        let mock = MockExec::new(&[404, 0]);
+        let first = block_on(mock.execute(post("a"))).unwrap();
+        assert_eq!(first.status(), 404);
+        assert!(block_on(mock.execute(post("b"))).is_err());
+        assert_eq!(block_on(mock.execute(post("c"))).unwrap().status(), 200);
+        let bodies: Vec<_> = mock.requests().into_iter().map(|r| r.body).collect();
+        assert_eq!(bodies, vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]);
+    }
+
+    #[test]
+    fn mock_captures_method_url_and_headers() {
+        let mock = MockExec::new(&[]);
+        let request = reqwest::Client::new()
+            .put("https://example.com/v1/entries/1")
+            .header("X-Test", "1");
+        block_on(mock.execute(request)).unwrap();
+        let request = &mock.requests()[0];
+        assert_eq!(request.method, "PUT");
+        assert_eq!(request.url, "https://example.com/v1/entries/1");
+        assert_eq!(request.headers["x-test"], "1");
+    }
+
+    #[test]
+    fn budget_rejects_requests_over_the_limit() {
+        let budgeted = Budgeted::new(Rc::new(MockExec::new(&[])), Some(2));
+        assert!(block_on(budgeted.execute(post("1"))).is_ok());
+        assert!(block_on(budgeted.execute(post("2"))).is_ok());
+        let error = block_on(budgeted.execute(post("3"))).unwrap_err();
+        assert_eq!(error.to_string(), "Subrequest budget exhausted");
+        assert_eq!(budgeted.inner.requests().len(), 2);
+    }
+
+    #[test]
+    fn budget_counts_failed_requests() {
+        let budgeted = Budgeted::new(MockExec::new(&[0]), Some(1));
+        assert!(block_on(budgeted.execute(post("1"))).is_err());
+        assert_eq!(
+            block_on(budgeted.execute(post("2")))
+                .unwrap_err()
+                .to_string(),
+            "Subrequest budget exhausted"
+        );
+    }
+
+    #[test]
+    fn unlimited_budget_passes_everything_through() {
+        let budgeted = Budgeted::new(MockExec::new(&[]), None);
+        for _ in 0..100 {
+            assert!(block_on(budgeted.execute(post(""))).is_ok());
+        }
+    }
+
+    #[test]
+    fn recording_keeps_method_url_and_status() {
+        let recording = Recording::new(MockExec::new(&[201, 0]));
+        let get = reqwest::Client::new().get("https://example.com/v1/entries?status=unread");
+        block_on(recording.execute(get)).unwrap();
+        assert!(block_on(recording.execute(post("x"))).is_err());
+
+        let records = recording.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].method, "GET");
+        assert_eq!(
+            records[0].url,
+            "https://example.com/v1/entries?status=unread"
+        );
+        assert_eq!(records[0].status, Some(201));
+        assert_eq!(records[1].method, "POST");
+        assert_eq!(records[1].status, None);
+    }
+
+    #[cfg(feature = "compression")]
+    fn gunzip(data: &[u8]) -> Vec<u8> {
+        use std::io::Read;
+        let mut out = Vec::new();
+        flate2::read::GzDecoder::new(data)
+            .read_to_end(&mut out)
+            .unwrap();
+        out
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn small_bodies_are_sent_uncompressed() {
+        let compressed = Compressed::new(MockExec::new(&[]), 100);
+        block_on(compressed.execute(post("short"))).unwrap();
+        let requests = compressed.inner.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].headers.get(CONTENT_ENCODING).is_none());
+        assert_eq!(requests[0].body, b"short");
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn large_bodies_are_gzipped() {
+        let body = "x".repeat(1000);
+        let compressed = Compressed::new(MockExec::new(&[]), 100);
+        let response = block_on(compressed.execute(post(&body))).unwrap();
+        assert_eq!(response.status(), 200);
+        let requests = compressed.inner.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].headers[CONTENT_ENCODING], "gzip");
+        assert!(requests[0].body.len() < body.len());
+        assert_eq!(gunzip(&requests[0].body), body.as_bytes());
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn retries_uncompressed_on_415_and_400() {
+        for status in [415, 400] {
+            let body = "y".repeat(1000);
+            let compressed = Compressed::new(MockExec::new(&[status]), 100);
+            let response = block_on(compressed.execute(post(&body))).unwrap();
+            assert_eq!(response.status(), 200);
+            let requests = compressed.inner.requests();
+            assert_eq!(requests.len(), 2);
+            assert_eq!(requests[0].headers[CONTENT_ENCODING], "gzip");
+            assert!(requests[1].headers.get(CONTENT_ENCODING).is_none());
+            assert_eq!(requests[1].body, body.as_bytes());
+        }
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn other_errors_are_not_retried() {
+        let compressed = Compressed::new(MockExec::new(&[500]), 100);
+        let response = block_on(compressed.execute(post(&"z".repeat(1000)))).unwrap();
+        assert_eq!(response.status(), 500);
+        assert_eq!(compressed.inner.requests().len(), 1);
+    }
+
+    // 和 build_config 中的顺序一致：压缩层在最外侧，不压缩重试也计入子请求预算并被记录
+    #[cfg(feature = "compression")]
+    #[test]
+    fn uncompressed_retry_counts_against_the_budget() {
+        let exec = Compressed::new(
+            Budgeted::new(Recording::new(MockExec::new(&[415])), Some(2)),
+            100,
+        );
+        let response = block_on(exec.execute(post(&"w".repeat(1000)))).unwrap();
+        assert_eq!(response.status(), 200);
+        let records = exec.inner.inner.records();
+        assert_eq!(
+            records.iter().map(|r| r.status).collect::<Vec<_>>(),
+            vec![Some(415), Some(200)]
+        );
+        assert!(block_on(exec.execute(post("again"))).is_err());
+    }
+}
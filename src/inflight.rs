@@ -0,0 +1,119 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// 同一个 isolate 内正在处理的 Webhook 数量
+static INFLIGHT_WEBHOOKS: AtomicUsize = AtomicUsize::new(0);
+
+// 持有期间占用一个名额，drop 时释放
+pub struct InflightGuard;
+
+impl InflightGuard {
+    pub fn try_acquire(limit: usize) -> Option<InflightGuard> {
+        INFLIGHT_WEBHOOKS
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |current| {
+                (current < limit).then_some(current + 1)
+            })
+            .ok()
+            .map(|_| InflightGuard)
+    }
+}
+
+// 当前占用的名额数，只反映处理本次请求的 isolate
+pub fn inflight_webhooks() -> usize {
+    INFLIGHT_WEBHOOKS.load(Ordering::Acquire)
+}
+
+impl Drop for InflightGuard {
+    fn drop(&mut self) {
+        INFLIGHT_WEBHOOKS.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+// 同一个 isolate 内正在进行的模型请求数，所有 Webhook 和定时任务共享
+static MODEL_CONNECTIONS: AtomicUsize = AtomicUsize::new(0);
+
+pub struct ConnectionGuard;
+
+impl ConnectionGuard {
+    pub fn try_acquire(limit: usize) -> Option<ConnectionGuard> {
+        MODEL_CONNECTIONS
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |current| {
+                (current < limit).then_some(current + 1)
+            })
+            .ok()
+            .map(|_| ConnectionGuard)
+    }
+}
+
+pub fn model_connections() -> usize {
+    MODEL_CONNECTIONS.load(Ordering::Acquire)
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        MODEL_CONNECTIONS.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared_state::{yield_now, Shared};
+    use futures::executor::block_on;
+    use futures::{stream, StreamExt};
+    use std::sync::Mutex;
+
+    // 模型连接数是进程内的全局计数，测试线程并行时需要串行访问
+    static CONNECTIONS: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn webhook_guards_are_counted_until_dropped() {
+        let first = InflightGuard::try_acquire(2).unwrap();
+        let second = InflightGuard::try_acquire(2).unwrap();
+        assert_eq!(inflight_webhooks(), 2);
+        assert!(InflightGuard::try_acquire(2).is_none());
+        drop(first);
+        assert_eq!(inflight_webhooks(), 1);
+        drop(second);
+        assert_eq!(inflight_webhooks(), 0);
+    }
+
+    #[test]
+    fn connection_guards_are_counted_until_dropped() {
+        let _lock = CONNECTIONS.lock().unwrap();
+        let guard = ConnectionGuard::try_acquire(1).unwrap();
+        assert_eq!(model_connections(), 1);
+        assert!(ConnectionGuard::try_acquire(1).is_none());
+        drop(guard);
+        assert_eq!(model_connections(), 0);
+    }
+
+    // 和 acquire_connection 一样拿不到名额时让出执行权重试，同时持有的连接数不超过上限
+    #[test]
+    fn contending_tasks_never_exceed_the_limit() {
+        let _lock = CONNECTIONS.lock().unwrap();
+        let peak = Shared::new(0);
+        let finished = block_on(
+            stream::iter(0..10)
+                .map(|_| {
+                    let peak = &peak;
+                    async move {
+                        let guard = loop {
+                            match ConnectionGuard::try_acquire(3) {
+                                Some(guard) => break guard,
+                                None => yield_now().await,
+                            }
+                        };
+                        peak.with(|peak| *peak = (*peak).max(model_connections()));
+                        yield_now().await;
+                        yield_now().await;
+                        drop(guard);
+                    }
+                })
+                .buffer_unordered(10)
+                .count(),
+        );
+        assert_eq!(finished, 10);
+        assert_eq!(peak.get(), 3);
+        assert_eq!(model_connections(), 0);
+    }
+}
@@ -0,0 +1,279 @@
+use crate::platform::console_warn;
+use crate::shared_state::Shared;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use worker::kv::KvStore;
+
+// 打包格式的版本号，格式变化时递增，读到旧版本的文档直接丢弃
+pub const PACKED_VERSION: u32 = 1;
+
+// 单个文档最多保留的键数量，超出时丢弃最旧的，避免超过 KV 值的大小限制
+pub const MAX_PACKED_ENTRIES: usize = 500;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackedValue {
+    pub value: serde_json::Value,
+    pub updated_at: u64,
+}
+
+// 多个键打包在一个 KV 值中，每个键带有更新时间
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackedMap {
+    pub version: u32,
+    pub entries: HashMap<String, PackedValue>,
+}
+
+impl Default for PackedMap {
+    fn default() -> Self {
+        PackedMap {
+            version: PACKED_VERSION,
+            entries: HashMap::new(),
+        }
+    }
+}
+
+impl PackedMap {
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let packed = self.entries.get(key)?;
+        serde_json::from_value(packed.value.clone()).ok()
+    }
+
+    pub fn insert(&mut self, key: String, value: serde_json::Value, now: u64) {
+        self.entries.insert(
+            key,
+            PackedValue {
+                value,
+                updated_at: now,
+            },
+        );
+    }
+
+    // 合并另一个文档，同一个键保留更新时间较新的值
+    pub fn merge(&mut self, other: PackedMap) {
+        for (key, value) in other.entries {
+            match self.entries.get(&key) {
+                Some(existing) if existing.updated_at > value.updated_at => {}
+                _ => {
+                    self.entries.insert(key, value);
+                }
+            }
+        }
+    }
+
+    // 去掉过期的键，并在超出数量上限时丢弃最旧的键
+    pub fn prune(&mut self, now: u64, max_age_ms: u64) {
+        self.entries
+            .retain(|_, v| now.saturating_sub(v.updated_at) < max_age_ms);
+
+        if self.entries.len() > MAX_PACKED_ENTRIES {
+            let mut updated: Vec<u64> = self.entries.values().map(|v| v.updated_at).collect();
+            updated.sort_unstable_by(|a, b| b.cmp(a));
+            let cutoff = updated[MAX_PACKED_ENTRIES - 1];
+            self.entries.retain(|_, v| v.updated_at >= cutoff);
+        }
+    }
+}
+
+// 保存在同一个 KV 值中的一组键，以及这些键的保留时间
+#[derive(Debug, Clone, Copy)]
+pub struct PackedDoc {
+    pub key: &'static str,
+    pub max_age_ms: u64,
+}
+
+// 运行期间的读写都在内存中进行，读取时每个文档只 get 一次，结束时每个文档只 put 一次
+pub struct KvBatch {
+    kv: KvStore,
+    loaded: Shared<HashMap<&'static str, PackedMap>>,
+    pending: Shared<HashMap<&'static str, (PackedDoc, PackedMap)>>,
+}
+
+impl KvBatch {
+    pub fn new(kv: KvStore) -> Self {
+        KvBatch {
+            kv,
+            loaded: Shared::new(HashMap::new()),
+            pending: Shared::new(HashMap::new()),
+        }
+    }
+
+    async fn fetch(&self, doc: PackedDoc) -> PackedMap {
+        match self.kv.get(doc.key).json::<PackedMap>().await {
+            Ok(Some(packed)) if packed.version == PACKED_VERSION => packed,
+            Ok(Some(packed)) => {
+                console_warn!(
+                    "Discarding {} with unsupported version {}",
+                    doc.key,
+                    packed.version
+                );
+                PackedMap::default()
+            }
+            Ok(None) => PackedMap::default(),
+            Err(e) => {
+                console_warn!("Failed to load {} from KV: {:?}", doc.key, e);
+                PackedMap::default()
+            }
+        }
+    }
+
+    // 每个文档只读取一次，并发任务同时读取时以先写入的为准
+    async fn load(&self, doc: PackedDoc) {
+        if self.loaded.with(|loaded| loaded.contains_key(doc.key)) {
+            return;
+        }
+        let packed = self.fetch(doc).await;
+        self.loaded.with(|loaded| {
+            loaded.entry(doc.key).or_insert(packed);
+        });
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, doc: PackedDoc, key: &str) -> Option<T> {
+        let pending = self
+            .pending
+            .with(|pending| pending.get(doc.key).and_then(|(_, packed)| packed.get(key)));
+        if pending.is_some() {
+            return pending;
+        }
+
+        self.load(doc).await;
+        self.loaded
+            .with(|loaded| loaded.get(doc.key).and_then(|packed| packed.get(key)))
+    }
+
+    // 文档中的全部键，按更新时间从新到旧排列，无法解析的值直接跳过
+    pub async fn entries<T: DeserializeOwned>(&self, doc: PackedDoc) -> Vec<(String, T, u64)> {
+        self.load(doc).await;
+        let mut entries: Vec<(String, T, u64)> = self.loaded.with(|loaded| {
+            loaded
+                .get(doc.key)
+                .map(|packed| {
+                    packed
+                        .entries
+                        .iter()
+                        .filter_map(|(key, v)| {
+                            let value = serde_json::from_value(v.value.clone()).ok()?;
+                            Some((key.clone(), value, v.updated_at))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default()
+        });
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.2));
+        entries
+    }
+
+    pub fn put<T: Serialize>(&self, doc: PackedDoc, key: String, value: &T, now: u64) {
+        match serde_json::to_value(value) {
+            Ok(value) => self.pending.with(|pending| {
+                pending
+                    .entry(doc.key)
+                    .or_insert_with(|| (doc, PackedMap::default()))
+                    .1
+                    .insert(key, value, now)
+            }),
+            Err(e) => console_warn!("Failed to serialize {} for {}: {}", key, doc.key, e),
+        }
+    }
+
+    // 重新读取当前文档再合并，避免覆盖其他调用在本次运行期间写入的键
+    pub async fn flush(&self, now: u64) {
+        let pending: Vec<(PackedDoc, PackedMap)> = self
+            .pending
+            .with(|pending| pending.drain().map(|(_, v)| v).collect());
+
+        for (doc, changes) in pending {
+            let mut packed = self.fetch(doc).await;
+            packed.merge(changes);
+            packed.prune(now, doc.max_age_ms);
+
+            let result = match self.kv.put(doc.key, &packed) {
+                Ok(put) => put.execute().await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(()) => {
+                    self.loaded.with(|loaded| loaded.insert(doc.key, packed));
+                }
+                Err(e) => console_warn!("Failed to save {} to KV: {:?}", doc.key, e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn packed(entries: &[(&str, u64)]) -> PackedMap {
+        let mut map = PackedMap::default();
+        for (key, updated_at) in entries {
+            map.insert(key.to_string(), json!(updated_at), *updated_at);
+        }
+        map
+    }
+
+    fn keys(map: &PackedMap) -> Vec<&str> {
+        let mut keys: Vec<&str> = map.entries.keys().map(String::as_str).collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    #[test]
+    fn get_deserializes_stored_values() {
+        let mut map = PackedMap::default();
+        map.insert("a".to_string(), json!({"count": 3}), 1);
+        assert_eq!(map.get::<serde_json::Value>("a"), Some(json!({"count": 3})));
+        assert_eq!(map.get::<u32>("a"), None);
+        assert_eq!(map.get::<u32>("missing"), None);
+    }
+
+    #[test]
+    fn merge_keeps_the_newer_value_per_key() {
+        let mut stored = packed(&[("a", 5), ("b", 1)]);
+        stored.merge(packed(&[("a", 3), ("b", 2), ("c", 4)]));
+        assert_eq!(stored.get::<u64>("a"), Some(5));
+        assert_eq!(stored.get::<u64>("b"), Some(2));
+        assert_eq!(stored.get::<u64>("c"), Some(4));
+    }
+
+    // 更新时间相同时以本次运行写入的值为准
+    #[test]
+    fn merge_prefers_incoming_on_ties() {
+        let mut stored = PackedMap::default();
+        stored.insert("a".to_string(), json!("old"), 1);
+        let mut incoming = PackedMap::default();
+        incoming.insert("a".to_string(), json!("new"), 1);
+        stored.merge(incoming);
+        assert_eq!(stored.get::<String>("a").as_deref(), Some("new"));
+    }
+
+    #[test]
+    fn prune_drops_expired_keys() {
+        // 刚好达到保留时间的键也算过期
+        let mut map = packed(&[("old", 0), ("expired", 10), ("kept", 11), ("fresh", 95)]);
+        map.prune(100, 90);
+        assert_eq!(keys(&map), vec!["fresh", "kept"]);
+    }
+
+    #[test]
+    fn prune_keeps_the_newest_entries_over_the_cap() {
+        let mut map = PackedMap::default();
+        for i in 0..MAX_PACKED_ENTRIES as u64 + 10 {
+            map.insert(i.to_string(), json!(i), i);
+        }
+        map.prune(MAX_PACKED_ENTRIES as u64 + 10, u64::MAX);
+        assert_eq!(map.entries.len(), MAX_PACKED_ENTRIES);
+        assert!(map.entries.values().all(|v| v.updated_at >= 10));
+    }
+
+    #[test]
+    fn documents_round_trip_through_json() {
+        let map = packed(&[("a", 1)]);
+        let decoded: PackedMap =
+            serde_json::from_str(&serde_json::to_string(&map).unwrap()).unwrap();
+        assert_eq!(decoded.version, PACKED_VERSION);
+        assert_eq!(decoded.get::<u64>("a"), Some(1));
+    }
+}
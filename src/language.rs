@@ -0,0 +1,188 @@
+// 基于字符集的轻量语言检测，返回检测结果和置信度
+#[derive(Debug, Clone, PartialEq)]
+pub struct Detection {
+    pub language: Option<&'static str>,
+    pub confidence: f32,
+}
+
+const ENGLISH_STOPWORDS: &[&str] = &[
+    "the", "and", "of", "to", "in", "is", "that", "for", "it", "with", "on", "as", "was", "are",
+];
+
+// 只统计前 N 个字符，长文章不需要全部扫描
+const SAMPLE_CHARS: usize = 4000;
+
+// 从右向左书写的语言，支持英文名称和 ISO 639-1 代码
+const RTL_LANGUAGES: &[&str] = &[
+    "arabic", "hebrew", "persian", "farsi", "urdu", "yiddish", "pashto", "ar", "he", "fa", "ur",
+    "yi", "ps",
+];
+
+pub fn is_rtl(language: &str) -> bool {
+    let language = language.trim().to_lowercase();
+    let primary = language.split(['-', '_']).next().unwrap_or_default();
+    RTL_LANGUAGES.contains(&language.as_str()) || RTL_LANGUAGES.contains(&primary)
+}
+
+fn is_rtl_char(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x0590..=0x08FF | 0xFB1D..=0xFDFF | 0xFE70..=0xFEFF | 0x10800..=0x10FFF
+    )
+}
+
+// 希伯来文、阿拉伯文等从右向左书写的字母占字母总数的三成以上
+pub fn is_rtl_text(text: &str) -> bool {
+    let (mut rtl, mut letters) = (0, 0);
+    for c in text
+        .chars()
+        .take(SAMPLE_CHARS)
+        .filter(|c| c.is_alphabetic())
+    {
+        letters += 1;
+        if is_rtl_char(c) {
+            rtl += 1;
+        }
+    }
+    letters > 0 && rtl * 10 >= letters * 3
+}
+
+pub fn detect(text: &str) -> Detection {
+    let (mut han, mut kana, mut hangul, mut cyrillic, mut arabic, mut hebrew, mut latin) =
+        (0, 0, 0, 0, 0, 0, 0);
+
+    for c in text.chars().take(SAMPLE_CHARS) {
+        match c as u32 {
+            0x4E00..=0x9FFF | 0x3400..=0x4DBF => han += 1,
+            0x3040..=0x30FF => kana += 1,
+            0xAC00..=0xD7AF | 0x1100..=0x11FF => hangul += 1,
+            0x0400..=0x04FF => cyrillic += 1,
+            0x0600..=0x06FF | 0x0750..=0x077F => arabic += 1,
+            0x0590..=0x05FF => hebrew += 1,
+            _ if c.is_ascii_alphabetic() || ('\u{00C0}'..='\u{024F}').contains(&c) => latin += 1,
+            _ => {}
+        }
+    }
+
+    let total = (han + kana + hangul + cyrillic + arabic + hebrew + latin) as f32;
+    if total == 0.0 {
+        return Detection {
+            language: None,
+            confidence: 0.0,
+        };
+    }
+
+    // 日文混合汉字和假名，假名占一定比例即可判断为日文
+    let cjk_language = if kana > 0 && kana * 5 >= han {
+        "Japanese"
+    } else {
+        "Chinese"
+    };
+    let candidates = [
+        (cjk_language, han + kana),
+        ("Korean", hangul),
+        ("Russian", cyrillic),
+        ("Arabic", arabic),
+        ("Hebrew", hebrew),
+    ];
+    let (language, count) = candidates
+        .iter()
+        .max_by_key(|(_, count)| *count)
+        .copied()
+        .unwrap_or(("", 0));
+
+    if count as f32 >= latin as f32 {
+        return Detection {
+            language: Some(language),
+            confidence: count as f32 / total,
+        };
+    }
+
+    // 拉丁字母无法区分具体语言，只有英文常用词足够多时才认为是英文
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .take(SAMPLE_CHARS / 5)
+        .map(str::to_lowercase)
+        .collect();
+    let stopwords = words
+        .iter()
+        .filter(|w| ENGLISH_STOPWORDS.contains(&w.as_str()))
+        .count();
+    let stopword_ratio = if words.is_empty() {
+        0.0
+    } else {
+        stopwords as f32 / words.len() as f32
+    };
+
+    Detection {
+        language: Some("English"),
+        confidence: (latin as f32 / total) * (stopword_ratio / 0.15).min(1.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_cjk_languages_with_high_confidence() {
+        let chinese = detect("这是一个测试文章，主要介绍语言检测。");
+        assert_eq!(chinese.language, Some("Chinese"));
+        assert!(chinese.confidence > 0.9);
+        assert_eq!(
+            detect("これはテストの記事です。").language,
+            Some("Japanese")
+        );
+        assert_eq!(detect("이것은 테스트 기사입니다").language, Some("Korean"));
+        assert_eq!(detect("Это тестовая статья").language, Some("Russian"));
+    }
+
+    #[test]
+    fn english_needs_enough_stopwords() {
+        let english = detect("The cat sat on the mat and it was happy with the result.");
+        assert_eq!(english.language, Some("English"));
+        assert!(english.confidence >= 0.9);
+
+        // 拉丁字母但不是英文，置信度低，由调用方回退到 DEFAULT_LANGUAGE
+        let german = detect("Kubernetes Cluster Verwaltung leicht gemacht");
+        assert_eq!(german.language, Some("English"));
+        assert!(german.confidence < 0.5);
+    }
+
+    #[test]
+    fn text_without_letters_is_undetected() {
+        assert_eq!(
+            detect("1234 !!! ---"),
+            Detection {
+                language: None,
+                confidence: 0.0,
+            }
+        );
+        assert_eq!(detect("").language, None);
+    }
+
+    #[test]
+    fn recognizes_rtl_language_names_and_codes() {
+        for language in ["Arabic", " hebrew ", "fa", "ur-PK", "he_IL", "AR"] {
+            assert!(is_rtl(language), "{}", language);
+        }
+        for language in ["English", "zh-CN", "Chinese", "", "arab"] {
+            assert!(!is_rtl(language), "{}", language);
+        }
+    }
+
+    #[test]
+    fn rtl_text_needs_thirty_percent_rtl_letters() {
+        assert!(is_rtl_text("مرحبا بالعالم"));
+        assert!(is_rtl_text("שלום עולם"));
+        // 数字和标点不算字母
+        assert!(is_rtl_text("123 !!! مرحبا"));
+        // 夹带少量英文术语的阿拉伯文仍是从右向左
+        assert!(is_rtl_text("استخدام Rust في الخوادم"));
+        assert!(!is_rtl_text("An English article quoting مرحبا once"));
+        assert!(!is_rtl_text("纯中文内容"));
+        assert!(!is_rtl_text("123"));
+        assert!(!is_rtl_text(""));
+    }
+}
@@ -0,0 +1,83 @@
+// 按阅读时间换算字数时使用的阅读速度
+const WORDS_PER_SECOND: f32 = 2.5;
+
+// 摘要长度上限，按字数设置或由阅读时间换算而来
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SummaryLength {
+    pub words: usize,
+    pub seconds: Option<u32>,
+}
+
+impl SummaryLength {
+    pub fn from_seconds(seconds: u32) -> SummaryLength {
+        SummaryLength {
+            words: ((seconds as f32 * WORDS_PER_SECOND).round() as usize).max(1),
+            seconds: Some(seconds),
+        }
+    }
+
+    // 追加到系统提示词末尾
+    pub fn prompt(&self) -> String {
+        let limit = format!(
+            "Keep the summary to about {} words, counting each Chinese, Japanese or Korean character as one word.",
+            self.words
+        );
+        match self.seconds {
+            Some(seconds) => format!(
+                "{} It should take about {} seconds to read.",
+                limit, seconds
+            ),
+            None => limit,
+        }
+    }
+}
+
+// SUMMARY_MAX_WORDS 和 SUMMARY_READING_SECONDS 只能设置一个
+pub fn resolve(
+    max_words: Option<usize>,
+    reading_seconds: Option<u32>,
+) -> Result<Option<SummaryLength>, String> {
+    match (max_words, reading_seconds) {
+        (Some(_), Some(_)) => {
+            Err("SUMMARY_MAX_WORDS and SUMMARY_READING_SECONDS cannot both be set".to_string())
+        }
+        (Some(0), None) | (None, Some(0)) => Err("summary length must be positive".to_string()),
+        (Some(words), None) => Ok(Some(SummaryLength {
+            words,
+            seconds: None,
+        })),
+        (None, Some(seconds)) => Ok(Some(SummaryLength::from_seconds(seconds))),
+        (None, None) => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_one_of_words_or_seconds() {
+        assert_eq!(resolve(None, None), Ok(None));
+        assert_eq!(
+            resolve(Some(120), None),
+            Ok(Some(SummaryLength {
+                words: 120,
+                seconds: None,
+            }))
+        );
+        assert_eq!(resolve(None, Some(30)).unwrap().unwrap().words, 75);
+        assert!(resolve(Some(120), Some(30)).is_err());
+        assert!(resolve(Some(0), None).is_err());
+        assert!(resolve(None, Some(0)).is_err());
+    }
+
+    #[test]
+    fn prompt_mentions_reading_time_only_when_set() {
+        let words = resolve(Some(100), None).unwrap().unwrap().prompt();
+        assert!(words.contains("about 100 words"));
+        assert!(!words.contains("seconds"));
+        let seconds = SummaryLength::from_seconds(20).prompt();
+        assert!(seconds.contains("about 50 words"));
+        assert!(seconds.ends_with("about 20 seconds to read."));
+    }
+}
@@ -1,22 +1,168 @@
-use base64::{engine::general_purpose::STANDARD, Engine as _};
+mod alert;
+mod auth;
+mod auto_read;
+mod budget;
+mod canary;
+mod category;
+mod chunk;
+mod citations;
+mod comments;
+mod context;
+mod deadletter;
+mod debug;
+mod dedup;
+mod entities;
+mod extractive;
+mod fallback;
+mod feed;
+mod feed_health;
+mod feed_stats;
+mod filter;
+mod forward;
+mod health;
+mod html;
+mod http;
+mod inflight;
+mod kv_batch;
+mod language;
+mod length;
+mod llm_cache;
+mod notify;
+mod output;
+mod overlay;
+mod paywall;
+mod phrases;
+mod platform;
+mod prompts;
+mod queue;
+mod report;
+mod rewrite;
+mod sampling;
+mod sanitize;
+mod settings;
+mod shared_state;
+pub mod signature;
+mod sse;
+mod sticky;
+mod structured;
+mod summarize;
+mod template;
+mod tiers;
+mod title_hint;
+mod workers_ai;
+
+pub use signature::{sign, verify};
+
+use alert::AlertState;
+use budget::TokenBudget;
+use canary::{Arm, ArmStats, Attempt, Canary};
+use category::Category;
+use chrono::{DateTime, Utc};
+use context::FeedContext;
+use deadletter::{DeadLetter, DeadLetterLog};
+use debug::{Capturer, DebugRecord};
+use entities::Entities;
+use fallback::{FallbackModel, ModelLimiter, ModelPermit};
+use feed_health::{normalize_site_url, FeedHealth};
+use feed_stats::DailyStats;
+use filter::{ContentFilter, FeedPattern, PriorityStrategy};
 use futures::{stream, StreamExt};
-use hmac::{Hmac, Mac};
-use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
-use serde::{Deserialize, Serialize};
-use sha2::Sha256;
-use std::collections::HashSet;
-use worker::{event, Context, Env, Method, Request, Response, ScheduleContext, ScheduledEvent};
+use health::{HealthPolicy, LastSuccess};
+use html::{AggregationStats, ContentFormat, Link};
+#[cfg(feature = "compression")]
+use http::Compressed;
+use http::{Budgeted, HttpClient, HttpExec, Recording, ReqwestExec};
+use inflight::{ConnectionGuard, InflightGuard};
+use kv_batch::{KvBatch, PackedDoc};
+use length::SummaryLength;
+use llm_cache::LlmCache;
+use notify::Notifier;
+use output::RefusalDetector;
+use overlay::ConfigOverlay;
+use paywall::PaywallDetector;
+use phrases::PhraseFilter;
+use platform::{console_debug, console_error, console_log, console_warn, Date, Delay};
+use queue::{Disposition, DlqEntry, QueueMessage};
+use report::{EntryOutcome, Progress, RunReport, SkipReason};
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, ACCEPT_LANGUAGE, AUTHORIZATION, CONTENT_TYPE, LOCATION,
+};
+use reqwest::{RequestBuilder, StatusCode};
+use rewrite::SummaryRewriter;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use settings::{AggregationPolicy, FeatureOverrides, Features, FeedOverrides, FeedSettings, Step};
+use sha2::{Digest as _, Sha256};
+use shared_state::Shared;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::Duration;
+use sticky::CachedSummary;
+use template::{
+    BlockStyle, TextDirection, EXTRACT_LABEL, FAILURE_LABEL, PROCESSED_TAG, SCORE_TAG_PREFIX,
+    SUMMARY_LABEL,
+};
+use tiers::LengthTier;
+use title_hint::HintKind;
+use worker::{
+    event, kv::KvStore, Context, Env, Method, Request, Response, ScheduleContext, ScheduledEvent,
+};
+use workers_ai::WorkersAi;
 
-#[derive(Debug, Deserialize)]
+// 安全模式下输出每篇文章的详细处理日志
+macro_rules! verbose_log {
+    ($config:expr, $($arg:tt)*) => {
+        if $config.safe_mode {
+            console_log!($($arg)*);
+        }
+    };
+}
+
+#[derive(Debug, Clone, Deserialize)]
 struct Feed {
+    #[serde(default)]
+    id: u64,
     site_url: String,
+    // 较新的 Miniflux 在文章中也带订阅源简介，没有时按 ID 查询
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    category: Option<FeedCategory>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct FeedCategory {
+    title: String,
 }
 
 #[derive(Debug, Deserialize)]
 struct Entry {
     id: u64,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    url: String,
     content: String,
+    #[serde(default)]
+    comments_url: String,
+    #[serde(default)]
+    published_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    hash: String,
+    #[serde(default)]
+    changed_at: Option<DateTime<Utc>>,
+    // Miniflux 按正文估算的阅读分钟数，写回正文时会重新计算
+    #[serde(default)]
+    reading_time: u32,
     feed: Option<Feed>,
+    // 读取时正文前带有之前运行写入的不可用提示
+    #[serde(skip)]
+    has_notice: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -31,65 +177,361 @@ struct WebhookPayload {
     entries: Vec<Entry>,
 }
 
-#[derive(Serialize)]
+// 完整解析失败时只读取事件类型，未知事件仍然按忽略处理
+#[derive(Debug, Deserialize)]
+struct WebhookEvent {
+    event_type: String,
+}
+
+#[derive(Debug, Serialize)]
+struct PayloadError {
+    error: String,
+    path: String,
+    line: usize,
+    column: usize,
+}
+
+fn parse_webhook_payload(payload: &str) -> Result<WebhookPayload, PayloadError> {
+    let deserializer = &mut serde_json::Deserializer::from_str(payload);
+    serde_path_to_error::deserialize(deserializer).map_err(|e| PayloadError {
+        path: e.path().to_string(),
+        line: e.inner().line(),
+        column: e.inner().column(),
+        error: e.inner().to_string(),
+    })
+}
+
+enum WebhookBody {
+    Parsed(WebhookPayload),
+    Ignored,
+    Invalid(PayloadError),
+}
+
+// 请求体无效时返回的状态码，响应体是 PayloadError
+const INVALID_PAYLOAD_STATUS: u16 = 422;
+
+// 无法完整解析时只要事件类型不是 new_entries 就照常忽略，否则带上出错的位置拒绝
+fn read_webhook_payload(payload: &str) -> WebhookBody {
+    match parse_webhook_payload(payload) {
+        Ok(webhook_payload) => WebhookBody::Parsed(webhook_payload),
+        Err(e) => match serde_json::from_str::<WebhookEvent>(payload) {
+            Ok(event) if event.event_type != "new_entries" => WebhookBody::Ignored,
+            _ => WebhookBody::Invalid(e),
+        },
+    }
+}
+
+#[derive(Serialize, Default)]
 struct UpdateRequest {
-    content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reading_time: Option<u32>,
 }
 
+// 每次查询的文章数上限
+const MAX_PAGE_SIZE: usize = 100;
+
 async fn get_entries(
-    base_url: &str,
-    username: &str,
-    password: &str,
+    miniflux: &Miniflux,
+    search: Option<&str>,
+    page_size: usize,
+    statuses: &[String],
+    category_id: Option<u64>,
 ) -> Result<ApiResponse, Box<dyn std::error::Error>> {
-    // 创建 HTTP 客户端
-    let client = reqwest::Client::new();
+    // 发送 GET 请求，设置了搜索词时只查询匹配的文章，设置了分类时只查询该分类
+    let url = match category_id {
+        Some(id) => format!("{}/v1/categories/{}/entries", miniflux.url, id),
+        None => format!("{}/v1/entries", miniflux.url),
+    };
+    let mut request = miniflux
+        .client
+        .get(url)
+        .query(&[("limit", &page_size.to_string())]);
+    for status in statuses {
+        request = request.query(&[("status", status)]);
+    }
+    if let Some(search) = search {
+        request = request.query(&[("search", search)]);
+    }
+    let request = miniflux.authorize(request);
+    let response = miniflux.send(request).await?;
+    read_entries(response).await
+}
+
+#[derive(Deserialize)]
+struct MinifluxCategory {
+    id: u64,
+}
 
-    // 使用 Basic Auth 进行身份验证
-    let auth = format!(
-        "Basic {}",
-        STANDARD.encode(format!("{}:{}", username, password))
+async fn get_categories(
+    miniflux: &Miniflux,
+) -> Result<Vec<MinifluxCategory>, Box<dyn std::error::Error>> {
+    let request = miniflux.authorize(
+        miniflux
+            .client
+            .get(format!("{}/v1/categories", miniflux.url)),
     );
+    let response = miniflux.send(request).await?;
+    read_json(response.error_for_status()?).await
+}
 
-    // 发送 GET 请求
-    let response = client
-        .get(&format!("{}/v1/entries?status=unread&limit=100", base_url))
-        .header(AUTHORIZATION, auth)
-        .header(CONTENT_TYPE, "application/json")
-        .send()
-        .await?
-        .json::<ApiResponse>()
-        .await?;
+// 定时任务查询文章的方式，默认一次查询全部分类
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FetchStrategy {
+    Global,
+    PerCategory,
+}
+
+impl FromStr for FetchStrategy {
+    type Err = String;
 
-    Ok(response)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "global" => Ok(FetchStrategy::Global),
+            "per-category" | "per_category" => Ok(FetchStrategy::PerCategory),
+            other => Err(format!("Unknown fetch strategy: {}", other)),
+        }
+    }
 }
 
-async fn update_entry(
-    base_url: &str,
-    username: &str,
-    password: &str,
-    id: u64,
-    content: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let client = reqwest::Client::new();
+// 响应被截断时按 TRUNCATED_RETRY_PAGE_SIZE 重新查询一次，其他错误不重试
+async fn get_entries_retrying(
+    config: &Config,
+    category_id: Option<u64>,
+) -> Result<ApiResponse, Box<dyn std::error::Error>> {
+    let fetch = |page_size| {
+        get_entries(
+            &config.miniflux,
+            config.search_query.as_deref(),
+            page_size,
+            &config.entry_statuses,
+            category_id,
+        )
+    };
+    match fetch(config.page_size).await {
+        Err(e) if e.is::<TruncatedJson>() && config.truncated_retry_page_size > 0 => {
+            console_warn!(
+                "{}, retrying with {} entries per page",
+                e,
+                config.truncated_retry_page_size
+            );
+            fetch(config.truncated_retry_page_size).await
+        }
+        result => result,
+    }
+}
 
-    let auth = format!(
-        "Basic {}",
-        STANDARD.encode(format!("{}:{}", username, password))
-    );
+// CATEGORY_ID 优先于 FETCH_STRATEGY，逐个分类查询时合并后只保留最新的 page_size 篇，和全局查询的数量一致
+async fn fetch_entries(config: &Config) -> Result<ApiResponse, Box<dyn std::error::Error>> {
+    let fetch = |category_id| get_entries_retrying(config, category_id);
+    if config.category_id.is_some() || config.fetch_strategy == FetchStrategy::Global {
+        return fetch(config.category_id).await;
+    }
 
-    let url = format!("{}/v1/entries/{}", base_url, id);
-    let update_request = UpdateRequest {
-        content: content.to_string(),
+    let mut entries: Vec<Entry> = Vec::new();
+    for category in get_categories(&config.miniflux).await? {
+        entries.extend(fetch(Some(category.id)).await?.entries);
+    }
+    entries.sort_by(newest_first);
+    entries.dedup_by_key(|entry| entry.id);
+    entries.truncate(config.page_size);
+    Ok(ApiResponse { entries })
+}
+
+// 条件请求返回 304 或响应体为空时没有需要处理的文章，不当作解析错误
+// 分块传输的响应没有 Content-Length，读取后再判断是否为空
+async fn read_entries(
+    response: reqwest::Response,
+) -> Result<ApiResponse, Box<dyn std::error::Error>> {
+    let no_entries = ApiResponse {
+        entries: Vec::new(),
     };
+    if response.status() == StatusCode::NOT_MODIFIED || response.status() == StatusCode::NO_CONTENT
+    {
+        return Ok(no_entries);
+    }
+    let body = read_body(response.error_for_status()?).await?;
+    if body.text.trim().is_empty() {
+        return Ok(no_entries);
+    }
+    parse_body(body)
+}
 
-    client
-        .put(&url)
-        .header(AUTHORIZATION, auth)
-        .header(CONTENT_TYPE, "application/json")
-        .json(&update_request) // 将请求体序列化为 JSON
-        .send()
-        .await?
-        .error_for_status()?;
+// 最近的文章，不限已读状态，用于查找旧设置生成的摘要
+async fn get_recent_entries(
+    miniflux: &Miniflux,
+    limit: usize,
+    offset: usize,
+) -> Result<ApiResponse, Box<dyn std::error::Error>> {
+    let request = miniflux.authorize(
+        miniflux
+            .client
+            .get(format!("{}/v1/entries", miniflux.url))
+            .query(&[
+                ("order", "published_at"),
+                ("direction", "desc"),
+                ("limit", &limit.to_string()),
+                ("offset", &offset.to_string()),
+            ]),
+    );
+    let response = miniflux.send(request).await?;
+    read_entries(response).await
+}
+
+// Miniflux 返回 404，文章已被删除
+#[derive(Debug)]
+struct EntryGone(u64);
+
+impl std::fmt::Display for EntryGone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Entry {} no longer exists", self.0)
+    }
+}
+
+impl std::error::Error for EntryGone {}
+
+async fn get_entry(miniflux: &Miniflux, id: u64) -> Result<Entry, Box<dyn std::error::Error>> {
+    let request = miniflux.authorize(
+        miniflux
+            .client
+            .get(format!("{}/v1/entries/{}", miniflux.url, id)),
+    );
+    let response = miniflux.send(request).await?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Err(EntryGone(id).into());
+    }
+
+    read_json(response.error_for_status()?).await
+}
+
+// 用于检查认证是否有效，返回当前登录的用户
+async fn get_me(miniflux: &Miniflux) -> Result<MinifluxUser, Box<dyn std::error::Error>> {
+    let request = miniflux.authorize(miniflux.client.get(format!("{}/v1/me", miniflux.url)));
+    let response = miniflux.send(request).await?;
+    read_json(response.error_for_status()?).await
+}
+
+#[derive(Deserialize)]
+struct MinifluxFeed {
+    #[serde(default)]
+    description: String,
+}
+
+async fn get_feed(
+    miniflux: &Miniflux,
+    feed_id: u64,
+) -> Result<MinifluxFeed, Box<dyn std::error::Error>> {
+    let request = miniflux.authorize(
+        miniflux
+            .client
+            .get(format!("{}/v1/feeds/{}", miniflux.url, feed_id)),
+    );
+    let response = miniflux.send(request).await?;
+    read_json(response.error_for_status()?).await
+}
+
+// 按 ID 查询文章的结果，已删除的文章和请求失败分开记录
+enum EntryLookup {
+    Found(Box<Entry>),
+    Gone,
+    Failed(String),
+}
+
+// 先按 entry_ids 批量查询，不支持该过滤条件的 Miniflux 会忽略它，所以只采用返回结果中请求过的文章
+// 没有查到的再逐篇并发查询，并发数受 concurrency 限制
+async fn get_entries_by_ids(
+    miniflux: &Miniflux,
+    ids: &[u64],
+    concurrency: usize,
+) -> HashMap<u64, EntryLookup> {
+    let unique: BTreeSet<u64> = ids.iter().copied().collect();
+    let mut lookups = HashMap::new();
+    let batches: Vec<u64> = unique.iter().copied().collect();
+    for batch in batches.chunks(MAX_PAGE_SIZE) {
+        match get_entries_batch(miniflux, batch).await {
+            Ok(response) => {
+                for entry in response.entries {
+                    if unique.contains(&entry.id) {
+                        lookups.insert(entry.id, EntryLookup::Found(Box::new(entry)));
+                    }
+                }
+            }
+            Err(e) => console_warn!("Batch entry lookup failed, fetching one by one: {}", e),
+        }
+    }
+
+    let missing: Vec<u64> = unique
+        .into_iter()
+        .filter(|id| !lookups.contains_key(id))
+        .collect();
+    let fetched: Vec<(u64, EntryLookup)> = stream::iter(missing)
+        .map(|id| async move {
+            let lookup = match get_entry(miniflux, id).await {
+                Ok(entry) => EntryLookup::Found(Box::new(entry)),
+                Err(e) if e.is::<EntryGone>() => EntryLookup::Gone,
+                Err(e) => EntryLookup::Failed(e.to_string()),
+            };
+            (id, lookup)
+        })
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await;
+    lookups.extend(fetched);
+    lookups
+}
+
+async fn get_entries_batch(
+    miniflux: &Miniflux,
+    ids: &[u64],
+) -> Result<ApiResponse, Box<dyn std::error::Error>> {
+    let ids: Vec<String> = ids.iter().map(u64::to_string).collect();
+    let request = miniflux.authorize(
+        miniflux
+            .client
+            .get(format!("{}/v1/entries", miniflux.url))
+            .query(&[
+                ("entry_ids", ids.join(",")),
+                ("limit", ids.len().to_string()),
+            ]),
+    );
+    let response = miniflux.send(request).await?;
+    read_entries(response).await
+}
+
+// 批量修改文章状态，Miniflux 成功时返回 204
+async fn set_entries_status(
+    miniflux: &Miniflux,
+    ids: &[u64],
+    status: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let request = miniflux
+        .authorize(miniflux.client.put(format!("{}/v1/entries", miniflux.url)))
+        .json(&serde_json::json!({ "entry_ids": ids, "status": status }));
+    miniflux.send(request).await?.error_for_status()?;
+    Ok(())
+}
+
+async fn update_entry(
+    miniflux: &Miniflux,
+    id: u64,
+    update_request: &UpdateRequest,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let url = format!("{}/v1/entries/{}", miniflux.url, id);
+
+    let request = miniflux
+        .authorize(miniflux.client.put(url))
+        .json(update_request); // 将请求体序列化为 JSON
+                               // 204 或空响应体都算成功，不解析响应体
+    let response = miniflux.send(request).await?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Err(EntryGone(id).into());
+    }
+    response.error_for_status()?;
 
     Ok(())
 }
@@ -98,9 +540,11 @@ async fn update_entry(
 struct ChatCompletionRequest {
     model: String,
     messages: Vec<Message>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 struct Message {
     role: String,
     content: String,
@@ -113,32 +557,66 @@ struct ChatCompletionChoice {
 
 #[derive(Deserialize)]
 struct ChatCompletionResponse {
+    #[serde(default)]
     choices: Vec<ChatCompletionChoice>,
+    // 部分代理出错时仍返回 200，错误信息放在响应体里
+    error: Option<serde_json::Value>,
 }
 
 async fn request_openai_chat_completion(
+    client: &HttpClient,
     base_url: &str,
     api_key: &str,
     model: &str,
+    content_path: Option<&str>,
+    stream: bool,
     messages: Vec<Message>,
 ) -> Result<String, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::new();
     let request_body = ChatCompletionRequest {
         model: model.to_string(),
         messages,
+        stream,
     };
 
-    let response = client
-        .post(&format!("{}/v1/chat/completions", base_url))
+    let request = client
+        .post(format!("{}/v1/chat/completions", base_url))
         .header(AUTHORIZATION, format!("Bearer {}", api_key))
         .header(CONTENT_TYPE, "application/json")
-        .json(&request_body)
-        .send()
-        .await?;
+        .json(&request_body);
+    let response = client.send(request).await?;
+
+    // 流式响应逐块累积 delta，不需要缓存整个响应体
+    if stream && response.status().is_success() {
+        let mut parser = sse::SseParser::default();
+        let mut chunks = response.bytes_stream();
+        while let Some(chunk) = chunks.next().await {
+            parser.feed(&chunk?)?;
+        }
+        return Ok(parser.finish()?);
+    }
 
     if response.status().is_success() {
-        let completion_response: ChatCompletionResponse = response.json().await?;
-        Ok(completion_response.choices[0].message.content.clone())
+        let body: serde_json::Value = response.json().await?;
+        let completion_response: Option<ChatCompletionResponse> =
+            serde_json::from_value(body.clone()).ok();
+        if let Some(error) = completion_response.as_ref().and_then(|r| r.error.as_ref()) {
+            let message = error
+                .get("message")
+                .and_then(|m| m.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| error.to_string());
+            return Err(format!("OpenAI error: {}", message).into());
+        }
+        if let Some(choice) = completion_response.and_then(|r| r.choices.into_iter().next()) {
+            return Ok(choice.message.content);
+        }
+
+        // 非标准的兼容接口，按配置的路径读取内容
+        match content_path.and_then(|path| structured::value_at_path(&body, path)) {
+            Some(serde_json::Value::String(content)) => Ok(content.clone()),
+            Some(other) => Err(format!("OpenAI error: content is not a string: {}", other).into()),
+            None => Err("OpenAI error: no choices in response".into()),
+        }
     } else {
         let error_message = response.text().await?;
         Err(format!("Error: {:?}", error_message).into())
@@ -146,183 +624,5879 @@ async fn request_openai_chat_completion(
 }
 
 struct Miniflux {
+    client: HttpClient,
     url: String,
     username: String,
     password: String,
+    // 设置后改用 X-Auth-Token 认证
+    api_token: Option<String>,
 }
 
-struct OpenAi {
-    url: String,
-    token: String,
-    model: String,
+#[derive(Deserialize)]
+struct MinifluxUser {
+    username: String,
 }
 
-struct Config {
-    miniflux: Miniflux,
-    openai: OpenAi,
-    whitelist: HashSet<String>,
-}
+impl Miniflux {
+    // 重定向通常是 http/https 配置错误，跟随后只会拿到登录页，直接报错
+    async fn send(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<reqwest::Response, Box<dyn std::error::Error>> {
+        // 用户名无法用于 Basic 认证时不发出请求，避免只看到 401
+        if self.api_token.is_none() {
+            auth::check_username(&self.username)?;
+        }
+        let response = self.client.send(request).await?;
+        if response.status() == StatusCode::UNAUTHORIZED {
+            let credentials = if self.api_token.is_some() {
+                "MINIFLUX_API_TOKEN"
+            } else {
+                "MINIFLUX_USERNAME and MINIFLUX_PASSWORD"
+            };
+            return Err(format!(
+                "Miniflux rejected the credentials (401), check {}",
+                credentials
+            )
+            .into());
+        }
+        // 304 不是跳转，由调用方当作没有新文章处理
+        if response.status().is_redirection() && response.status() != StatusCode::NOT_MODIFIED {
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+            return Err(format!(
+                "Miniflux responded with redirect {} to {:?}, check the scheme and host of MINIFLUX_URL",
+                response.status(),
+                location
+            )
+            .into());
+        }
+        let origin = reqwest::Url::parse(&self.url).map(|url| url.origin());
+        if origin.is_ok_and(|origin| origin != response.url().origin()) {
+            return Err(format!(
+                "Miniflux request was redirected to {}, check the scheme and host of MINIFLUX_URL",
+                response.url()
+            )
+            .into());
+        }
+        Ok(response)
+    }
 
-async fn generate_and_update_entry(
-    config: &Config,
-    entry: Entry,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let content: &str = &entry.content;
-    // Check if the content should be summarized and if the site is whitelisted
-    if content.starts_with("<pre")
-        || entry
-            .feed
-            .as_ref()
-            .map_or(false, |feed| !config.whitelist.contains(&feed.site_url))
-    {
-        return Ok(());
+    // 所有 Miniflux API 请求都通过这里加上认证头，定时任务、Webhook 和管理接口保持一致
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        let request = match &self.api_token {
+            Some(token) => request.header(X_AUTH_TOKEN, token),
+            // 用户名无效时在 send 中报错
+            None => match auth::basic_auth(&self.username, &self.password) {
+                Ok(value) => request.header(AUTHORIZATION, value),
+                Err(_) => request,
+            },
+        };
+        request.header(CONTENT_TYPE, "application/json")
     }
+}
 
-    let messages = vec![
-        Message {
-            role: "system".to_string(),
-            content: "Please summarize the content of the article under 150 words in Chinese. Do not add any additional Character、markdown language to the result text. 请用不超过150个汉字概括文章内容。结果文本中不要添加任何额外的字符、Markdown语言。".to_string(),
-        },
-        Message {
-            role: "user".to_string(),
-            content: format!(
-                "The following is the input content:\n---\n {}",
-                content,
-            ),
-        },
-    ];
+const X_AUTH_TOKEN: &str = "X-Auth-Token";
 
-    // Generate summary
-    if let Ok(summary) = request_openai_chat_completion(
-        &config.openai.url,
-        &config.openai.token,
-        &config.openai.model,
-        messages,
-    )
-    .await
-    {
-        if !summary.trim().is_empty() {
-            let updated_content = format!(
-                "<pre style=\"white-space: pre-wrap;\"><code>\n💡AI 摘要：\n{}</code></pre><hr><br />{}",
-                summary, content
-            );
+// 反向代理后的 Miniflux 可能返回 HTML 维护页等非 JSON 内容，给出明确的错误而不是 serde 错误
+async fn read_json<T: DeserializeOwned>(
+    response: reqwest::Response,
+) -> Result<T, Box<dyn std::error::Error>> {
+    parse_body(read_body(response).await?)
+}
 
-            // Update the entry
-            update_entry(
-                &config.miniflux.url,
-                &config.miniflux.username,
-                &config.miniflux.password,
-                entry.id,
-                &updated_content,
-            )
-            .await?;
+struct ResponseBody {
+    status: StatusCode,
+    is_json: bool,
+    text: String,
+}
+
+async fn read_body(
+    response: reqwest::Response,
+) -> Result<ResponseBody, Box<dyn std::error::Error>> {
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("json"));
+    let status = response.status();
+    let bytes = match response.bytes().await {
+        Ok(bytes) => bytes,
+        Err(e) if e.is_body() => return Err(TruncatedJson(e.to_string()).into()),
+        Err(e) => return Err(e.into()),
+    };
+    // 个别订阅源的内容带有无效字节，替换掉而不是让整批文章解析失败
+    let body = String::from_utf8_lossy(&bytes);
+    if matches!(body, Cow::Owned(_)) {
+        console_warn!("Miniflux response contained invalid UTF-8, replaced with U+FFFD");
+    }
+    let text = match sanitize::replace_lone_surrogates(&body) {
+        Cow::Owned(replaced) => {
+            console_warn!("Miniflux response contained unpaired surrogates, replaced with U+FFFD");
+            replaced
         }
+        Cow::Borrowed(_) => body.into_owned(),
+    };
+    Ok(ResponseBody {
+        status,
+        is_json,
+        text,
+    })
+}
+
+fn parse_body<T: DeserializeOwned>(body: ResponseBody) -> Result<T, Box<dyn std::error::Error>> {
+    match serde_json::from_str(&body.text) {
+        Ok(value) => Ok(value),
+        Err(e) if e.is_eof() => Err(TruncatedJson(e.to_string()).into()),
+        Err(e) if body.is_json => Err(format!("Invalid JSON response from Miniflux: {}", e).into()),
+        Err(_) => Err(format!(
+            "Unexpected response from Miniflux ({}, is MINIFLUX_URL pointing at the API?): {:?}",
+            body.status,
+            truncate_chars(body.text.trim(), 200)
+        )
+        .into()),
     }
+}
 
-    Ok(())
+// 响应体在传输中途被截断，通常是文章太多导致超时，减少每页数量重试可能成功
+#[derive(Debug)]
+struct TruncatedJson(String);
+
+impl std::fmt::Display for TruncatedJson {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Truncated JSON response from Miniflux: {}", self.0)
+    }
 }
 
-fn build_config(env: &Env) -> Config {
-    Config {
-        whitelist: env
-            .var("WHITELIST_URL")
-            .unwrap()
-            .to_string()
-            .split(',')
-            .map(|s| s.to_string())
-            .collect(),
-        openai: OpenAi {
-            url: env.var("OPENAI_URL").unwrap().to_string(),
-            token: env.var("OPENAI_TOKEN").unwrap().to_string(),
-            model: env.var("OPENAI_MODEL").unwrap().to_string(),
-        },
-        miniflux: Miniflux {
-            url: env.var("MINIFLUX_URL").unwrap().to_string(),
-            username: env.var("MINIFLUX_USERNAME").unwrap().to_string(),
-            password: env.var("MINIFLUX_PASSWORD").unwrap().to_string(),
-        },
+impl std::error::Error for TruncatedJson {}
+
+struct CanaryConfig {
+    settings: Canary,
+    provider: Option<Provider>,
+}
+
+// CANARY 无效时只记录错误，所有文章照常使用原设置
+fn parse_canary(env: &dyn EnvVars) -> Option<CanaryConfig> {
+    let raw = env_var(env, "CANARY")?;
+    let parsed = canary::parse(&raw).and_then(|settings| {
+        let provider = settings
+            .provider
+            .as_deref()
+            .map(Provider::from_str)
+            .transpose()?;
+        Ok(CanaryConfig { settings, provider })
+    });
+    parsed
+        .map_err(|e| console_error!("Ignoring invalid CANARY: {}", e))
+        .ok()
+}
+
+// 模型调用方式，默认使用 OpenAI 兼容接口
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Provider {
+    OpenAi,
+    WorkersAi,
+}
+
+// 模型名称和请求发往的服务商。CANARY 切换服务商时按文章所在的分组决定，
+// 对照组的文章即使用了同名模型也发给默认服务商
+#[derive(Debug, Clone, Copy)]
+struct ModelRoute<'a> {
+    name: &'a str,
+    provider: Provider,
+}
+
+impl FromStr for Provider {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "openai" => Ok(Provider::OpenAi),
+            "workers-ai" => Ok(Provider::WorkersAi),
+            other => Err(format!("Unknown provider: {}", other)),
+        }
     }
 }
 
-#[event(scheduled)]
-async fn scheduled(_event: ScheduledEvent, env: Env, _ctx: ScheduleContext) {
-    let config = build_config(&env);
+impl Provider {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Provider::OpenAi => "openai",
+            Provider::WorkersAi => "workers-ai",
+        }
+    }
+}
 
-    // 查询未读文章
-    let entries = get_entries(
-        &config.miniflux.url,
-        &config.miniflux.username,
-        &config.miniflux.password,
-    )
-    .await
-    .unwrap();
+struct OpenAi {
+    client: HttpClient,
+    url: String,
+    token: String,
+    content_path: Option<String>,
+    stream: bool,
+}
 
-    // 生成摘要并更新的并发任务
-    let max_concurrent_tasks = 5;
+struct Config {
+    miniflux: Miniflux,
+    openai: OpenAi,
+    provider: Provider,
+    workers_ai: Option<WorkersAi>,
+    http_client: HttpClient,
+    http_recorder: Rc<Recording<Rc<dyn HttpExec>>>,
+    whitelist: HashSet<String>,
+    kv: Option<KvStore>,
+    kv_batch: Option<KvBatch>,
+    overlay: OverlayState,
+    feed_error_streak: u32,
+    feed_cooldown_ms: u64,
+    deadletter_threshold: u32,
+    feed_defaults: FeedSettings,
+    feed_overrides: HashMap<String, FeedOverrides>,
+    feed_features: Vec<(FeedPattern, FeatureOverrides)>,
+    max_entries_per_run: Option<usize>,
+    priority_strategy: PriorityStrategy,
+    feed_priority: Vec<FeedPattern>,
+    summary_target: SummaryTarget,
+    block_style: BlockStyle,
+    max_concurrency: usize,
+    safe_mode: bool,
+    digest_webhook: Option<DigestWebhook>,
+    summary_language: Option<String>,
+    summary_dir: TextDirection,
+    default_language: String,
+    max_token_chars: usize,
+    max_inflight_webhooks: usize,
+    // 未设置时不限制
+    max_model_connections: Option<usize>,
+    article_accept_language: Option<String>,
+    verify_before_update: bool,
+    write_delay_ms: u64,
+    verify_after_update: bool,
+    title_hint: Option<HintKind>,
+    title_hint_separator: String,
+    search_query: Option<String>,
+    alert: AlertConfig,
+    skip_content: ContentFilter,
+    summary_replace: SummaryRewriter,
+    banned_phrases: PhraseFilter,
+    normalize_typography: bool,
+    context_hints_max_tokens: usize,
+    feed_description_context: bool,
+    // 把每次定时任务的结果写入 KV，供 /logfeed.xml 输出
+    log_feed: bool,
+    // 本次运行中查询过的订阅源简介，查询失败也记录，避免重复请求
+    feed_descriptions: Shared<HashMap<u64, Option<String>>>,
+    // 低于该置信度的股票代码不写入标签，未设置时全部写入
+    tag_min_confidence: Option<f32>,
+    section_citations: bool,
+    token_budget: TokenBudget,
+    // 不限额，只统计全文翻译用掉的 token
+    translation_tokens: TokenBudget,
+    length_tiers: Vec<LengthTier>,
+    summary_length: Option<SummaryLength>,
+    // 本次运行中各档位摘要的文章数
+    tier_counts: Shared<BTreeMap<String, usize>>,
+    canary: Option<CanaryConfig>,
+    // 本次运行中每篇文章的分组、结果、耗时和是否改用了原设置
+    canary_records: Shared<Vec<(Arm, Attempt, u64, bool)>>,
+    include_alt_text: bool,
+    debug_capture: Option<DebugCaptureConfig>,
+    stop_at_processed: bool,
+    // 管理接口明确指定的文章，不按状态、白名单、抽样等规则跳过
+    explicit_entries: bool,
+    fallback_extractive: bool,
+    fallback_models: Vec<FallbackModel>,
+    health: HealthPolicy,
+    preserve_reading_time: bool,
+    queue_max_deliveries: u32,
+    page_size: usize,
+    // 整次运行模型都不可用时写在失败文章前的提示
+    unavailable_notice: Option<String>,
+    // 只处理该 Miniflux 分类中的文章
+    category_id: Option<u64>,
+    fetch_strategy: FetchStrategy,
+    // 响应被截断时重试使用的每页文章数，0 表示不重试
+    truncated_retry_page_size: usize,
+    // 每完成多少篇输出一次进度日志，0 表示不输出
+    heartbeat_every: usize,
+    // 查询和处理的文章状态，不在其中的文章不会被修改
+    entry_statuses: Vec<String>,
+    auto_read_skipped: Vec<SkipReason>,
+    // 未配置 LLM_CACHE_TTL_SECONDS 或设置了 BYPASS_LLM_CACHE 时为 None
+    llm_cache: Option<LlmCache>,
+    model_limiter: ModelLimiter,
+    media_template: Option<String>,
+    dry_run: bool,
+    min_content_chars: usize,
+    // 引用文字或链接文字占比超过其一即视为聚合类文章
+    aggregation_quote_ratio: f32,
+    aggregation_link_density: f32,
+    empty_feed_streak: u32,
+    empty_feed_reset: Vec<FeedPattern>,
+    // 为空时不分类，所有文章都摘要
+    summarize_categories: Vec<Category>,
+    // 分类名称（小写）到模型的映射
+    category_models: HashMap<String, String>,
+    category_styles: HashMap<String, String>,
+    // 按归一化后的 site_url 和分类缓存解析好的订阅源设置
+    settings_memo: Shared<HashMap<(String, String), FeedSettings>>,
+    content_format: ContentFormat,
+    refusal: RefusalDetector,
+    paywall: PaywallDetector,
+    paywall_notice: String,
+    dedup_urls: bool,
+    // 试用时只处理按 id 抽样的一部分文章
+    sample_rate: f64,
+    sample_salt: String,
+    retry_on_refusal: bool,
+}
 
-    // Create a stream to process tasks with concurrency limit
-    let _: Vec<_> = stream::iter(entries.entries)
-        .map(|entry| {
-            let config = &config;
-            async move { generate_and_update_entry(config, entry).await }
-        })
-        .buffer_unordered(max_concurrent_tasks)
-        .collect()
-        .await;
+struct DebugCaptureConfig {
+    // 写入前从记录中替换掉的密钥
+    secrets: Vec<String>,
+    sample_percent: u64,
 }
 
-// 验证 Miniflux 的 Webhook 请求签名
-fn validate_signature(secret: &str, payload: &str, signature: &str) -> bool {
-    let mut mac =
-        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC can take key of any size");
-    mac.update(payload.as_bytes());
-    let result = mac.finalize();
-    let computed_signature = hex::encode(result.into_bytes());
-    computed_signature == signature
+struct AlertConfig {
+    notifiers: Vec<Notifier>,
+    failure_ratio: f32,
+    window_ms: u64,
 }
 
-#[event(fetch)]
-async fn main(mut req: Request, env: Env, _: Context) -> worker::Result<Response> {
-    // 检查请求方法
-    if req.method() != Method::Post {
-        return Response::error("Method Not Allowed", 405);
+impl Config {
+    // 白名单和 site_url 都归一化后比较，忽略末尾斜杠和主机名大小写的差异
+    fn is_whitelisted(&self, site_url: &str) -> bool {
+        self.whitelist.contains(&normalize_site_url(site_url))
     }
 
-    // 提取请求体和签名
-    let payload = req.text().await?;
-    let signature = req.headers().get("X-Miniflux-Signature")?.unwrap();
+    // 同一订阅源的设置在一次调用内只解析一次，并发处理的文章共享同一份缓存
+    fn feed_settings(&self, entry: &Entry) -> FeedSettings {
+        let Some(feed) = &entry.feed else {
+            return self.feed_defaults.clone();
+        };
 
-    let secret = env.var("MINIFLUX_WEBHOOK_SECRET").unwrap().to_string();
+        let key = (
+            normalize_site_url(&feed.site_url),
+            feed.category
+                .as_ref()
+                .map(|c| c.title.trim().to_lowercase())
+                .unwrap_or_default(),
+        );
+        if let Some(settings) = self.settings_memo.with(|memo| memo.get(&key).cloned()) {
+            return settings;
+        }
+        let settings = self.resolve_feed_settings(&key.0, &key.1);
+        self.settings_memo
+            .with(|memo| memo.insert(key, settings.clone()));
+        settings
+    }
 
-    // 验证签名
-    if !validate_signature(&secret, &payload, &signature) {
-        return Response::error("Invalid signature", 401);
-    };
+    // 优先级：全局默认值 < CATEGORY_MODELS / CATEGORY_STYLES < FEED_SETTINGS < FEED_FEATURES（越具体的规则越靠后应用）
+    fn resolve_feed_settings(&self, site_url: &str, category: &str) -> FeedSettings {
+        let mut settings = self.feed_defaults.clone();
+        if let Some(model) = self.category_models.get(category) {
+            settings = settings.with_model(model);
+        }
+        if let Some(style) = self.category_styles.get(category) {
+            settings = settings.with_style(style);
+        }
+        if let Some(overrides) = self.feed_overrides.get(site_url) {
+            settings = settings.with_overrides(overrides);
+        }
+        for (pattern, features) in &self.feed_features {
+            if pattern.matches_normalized(site_url) {
+                settings = settings.with_features(features);
+            }
+        }
+        settings
+    }
+}
 
-    // 解析请求体
-    let webhook_payload: WebhookPayload = serde_json::from_str(&payload)?;
+const MAX_COMMENTS_PAGE_BYTES: usize = 1024 * 1024;
+const MAX_COMMENTS: usize = 30;
+const MAX_COMMENTS_CHARS: usize = 6000;
 
-    if webhook_payload.event_type != "new_entries" {
-        return Response::ok("Ignored non-new_entries event");
-    };
+fn truncate_chars(text: &str, max_chars: usize) -> &str {
+    match text.char_indices().nth(max_chars) {
+        Some((i, _)) => &text[..i],
+        None => text,
+    }
+}
 
-    let config = build_config(&env);
+async fn fetch_page(
+    client: &HttpClient,
+    request: RequestBuilder,
+    max_bytes: usize,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let response = client.send(request).await?.error_for_status()?;
 
-    if !config.whitelist.contains(&webhook_payload.feed.site_url) {
-        return Response::ok("Ignored non-whitelist feed");
-    };
+    if response
+        .content_length()
+        .is_some_and(|len| len > max_bytes as u64)
+    {
+        return Err(format!("Page too large: {}", response.url()).into());
+    }
 
-    // 处理每个新文章的生成和更新，限制并发为 5 个任务
-    let max_concurrent_tasks = 5;
+    let body = response.text().await?;
+    Ok(truncate_chars(&body, max_bytes).to_string())
+}
 
-    let _: Vec<_> = stream::iter(webhook_payload.entries)
-        .map(|entry| {
-            let config = &config;
-            async move { generate_and_update_entry(config, entry).await }
-        })
-        .buffer_unordered(max_concurrent_tasks)
-        .collect()
-        .await;
+const MAX_ARTICLE_BYTES: usize = 2 * 1024 * 1024;
+
+// 抓取原文页面，用于 Feed 只提供摘要或截断正文的情况
+async fn fetch_article(config: &Config, url: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let client = &config.http_client;
+    let mut request = client.get(url);
+    if let Some(accept_language) = &config.article_accept_language {
+        request = request.header(ACCEPT_LANGUAGE, accept_language);
+    }
 
-    Response::ok("Webhook handled")
+    fetch_page(client, request, MAX_ARTICLE_BYTES).await
+}
+
+// 所有模型调用都经过这里，开启 DEBUG_CAPTURE 时记录请求和原始响应
+async fn chat_completion(
+    config: &Config,
+    capture: &Capturer,
+    model: ModelRoute<'_>,
+    messages: Vec<Message>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let rendered: Vec<(String, String)> = messages
+        .iter()
+        .map(|m| (m.role.clone(), m.content.clone()))
+        .collect();
+    let mut result = Err("No model configured".into());
+    let mut failed: Option<&str> = None;
+    for candidate in fallback::attempt_order(model.name, &config.fallback_models) {
+        if let (Some(failed), Err(e)) = (failed, &result) {
+            console_warn!(
+                "Model {} failed, falling back to {}: {}",
+                failed,
+                candidate,
+                e
+            );
+        }
+        // 备用模型按 FALLBACK_MODELS 配置，总是发给默认的服务商
+        let provider = if candidate == model.name {
+            model.provider
+        } else {
+            config.provider
+        };
+        let _permit = acquire_model_slot(config, candidate).await;
+        result = request_model(config, candidate, provider, messages.clone()).await;
+        if result.is_ok() {
+            break;
+        }
+        failed = Some(candidate);
+    }
+
+    capture.record(
+        rendered.iter().map(|(r, c)| (r.as_str(), c.as_str())),
+        result.as_deref().map_err(|e| e.to_string()),
+    );
+    result
+}
+
+// 所有模型请求都经过这里，配置了 LLM_CACHE_TTL_SECONDS 时先查 Cache API
+async fn request_model(
+    config: &Config,
+    model: &str,
+    provider: Provider,
+    messages: Vec<Message>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let Some(cache) = config.llm_cache else {
+        return request_provider(config, model, provider, messages).await;
+    };
+
+    let body = serde_json::to_string(&messages)?;
+    let key = llm_cache::cache_key(llm_cache::KeyParts {
+        provider: provider.as_str(),
+        url: &config.openai.url,
+        model,
+        content_path: config.openai.content_path.as_deref(),
+        stream: config.openai.stream,
+        body: &body,
+    });
+    llm_cache::get_or_generate(
+        &llm_cache::EdgeCache,
+        cache,
+        &key,
+        request_provider(config, model, provider, messages),
+    )
+    .await
+}
+
+async fn request_provider(
+    config: &Config,
+    model: &str,
+    provider: Provider,
+    messages: Vec<Message>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let _connection = acquire_connection(config).await;
+    match provider {
+        Provider::OpenAi => {
+            request_openai_chat_completion(
+                &config.openai.client,
+                &config.openai.url,
+                &config.openai.token,
+                model,
+                config.openai.content_path.as_deref(),
+                config.openai.stream,
+                messages,
+            )
+            .await
+        }
+        Provider::WorkersAi => {
+            let Some(ai) = &config.workers_ai else {
+                return Err("PROVIDER=workers-ai needs an AI binding named AI".into());
+            };
+            Ok(ai.run(model, serde_json::to_value(&messages)?).await?)
+        }
+    }
+}
+
+// 模型的并发达到 FALLBACK_MODELS 中的上限时等待其他任务释放名额
+async fn acquire_model_slot<'a>(config: &'a Config, model: &str) -> ModelPermit<'a> {
+    let limit = fallback::max_concurrency(&config.fallback_models, model);
+    loop {
+        if let Some(permit) = config.model_limiter.try_acquire(model, limit) {
+            return permit;
+        }
+        Delay::from(Duration::from_millis(MODEL_SLOT_POLL_MS)).await;
+    }
+}
+
+// MAX_MODEL_CONNECTIONS 限制同一个 isolate 内所有调用同时发出的模型请求数
+async fn acquire_connection(config: &Config) -> Option<ConnectionGuard> {
+    let limit = config.max_model_connections?;
+    loop {
+        if let Some(guard) = ConnectionGuard::try_acquire(limit) {
+            return Some(guard);
+        }
+        Delay::from(Duration::from_millis(MODEL_SLOT_POLL_MS)).await;
+    }
+}
+
+const MODEL_SLOT_POLL_MS: u64 = 50;
+
+// 要求模型返回 JSON 对象并解析为指定结构
+async fn request_structured<T: DeserializeOwned>(
+    config: &Config,
+    capture: &Capturer,
+    model: ModelRoute<'_>,
+    messages: Vec<Message>,
+) -> Result<T, Box<dyn std::error::Error>> {
+    let raw = chat_completion(config, capture, model, messages).await?;
+
+    structured::parse_json_object(&raw)
+        .ok_or_else(|| format!("Invalid structured output: {:?}", truncate_chars(&raw, 200)).into())
+}
+
+async fn extract_entities(
+    config: &Config,
+    capture: &Capturer,
+    model: ModelRoute<'_>,
+    content: &str,
+) -> Result<Entities, Box<dyn std::error::Error>> {
+    let messages = vec![
+        Message {
+            role: "system".to_string(),
+            content: match config.tag_min_confidence {
+                Some(_) => format!(
+                    "{}\n{}",
+                    entities::ENTITIES_PROMPT,
+                    entities::CONFIDENCE_PROMPT
+                ),
+                None => entities::ENTITIES_PROMPT.to_string(),
+            },
+        },
+        Message {
+            role: "user".to_string(),
+            content: format!("The following is the input content:\n---\n {}", content),
+        },
+    ];
+
+    let entities = request_structured::<Entities>(config, capture, model, messages)
+        .await?
+        .normalized();
+    if entities.is_empty() {
+        return Err("Empty entity extraction".into());
+    }
+    Ok(entities)
+}
+
+// 抓取讨论页并单独生成评论要点，失败不影响文章摘要
+async fn summarize_comments(
+    config: &Config,
+    capture: &Capturer,
+    model: ModelRoute<'_>,
+    comments_url: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let client = &config.http_client;
+    let page = fetch_page(client, client.get(comments_url), MAX_COMMENTS_PAGE_BYTES).await?;
+    let comments = comments::extract_comments(&page, MAX_COMMENTS);
+    if comments.is_empty() {
+        return Err(format!("No comments found at {}", comments_url).into());
+    }
+
+    let messages = vec![
+        Message {
+            role: "system".to_string(),
+            content: "Please summarize the main viewpoints of the following discussion comments under 100 words in Chinese. Do not add any additional Character、markdown language to the result text. 请用不超过100个汉字概括评论中的主要观点。结果文本中不要添加任何额外的字符、Markdown语言。".to_string(),
+        },
+        Message {
+            role: "user".to_string(),
+            content: format!(
+                "The following are the comments:\n---\n {}",
+                truncate_chars(&comments.join("\n---\n"), MAX_COMMENTS_CHARS),
+            ),
+        },
+    ];
+
+    chat_completion(config, capture, model, messages).await
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SummaryTarget {
+    Content,
+    Tags,
+}
+
+impl FromStr for SummaryTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "content" => Ok(SummaryTarget::Content),
+            "tags" => Ok(SummaryTarget::Tags),
+            other => Err(format!("Unknown summary target: {}", other)),
+        }
+    }
+}
+
+// 影响摘要结果的设置的短哈希，写入摘要块标记，用于找出旧设置生成的摘要
+fn settings_hash(config: &Config, settings: &FeedSettings) -> String {
+    settings_hash_with_style(config, settings, config.block_style)
+}
+
+fn settings_hash_with_style(config: &Config, settings: &FeedSettings, style: BlockStyle) -> String {
+    let prompt = prompts::resolve(&settings.prompt)
+        .map(|prompt| prompt.system)
+        .unwrap_or_else(|_| settings.prompt.clone());
+    let mut parts = vec![
+        prompt,
+        settings.model.clone(),
+        format!("{:?}", style),
+        config.media_template.clone().unwrap_or_default(),
+        config.summary_language.clone().unwrap_or_default(),
+    ];
+    // 只在设置了长度时加入，避免已有摘要的哈希全部改变
+    if let Some(length) = &config.summary_length {
+        parts.push(length.prompt());
+    }
+    if config.section_citations {
+        parts.push(citations::CITATIONS_PROMPT.to_string());
+    }
+    if let Some(style) = &settings.style {
+        parts.push(style.clone());
+    }
+
+    let digest = Sha256::digest(parts.join("\0").as_bytes());
+    hex::encode(&digest[..3])
+}
+
+// 根据 SUMMARY_TARGET 把摘要写入正文，或者作为标签写入以保持正文不变
+fn summary_update(
+    config: &Config,
+    entry: &Entry,
+    settings: &FeedSettings,
+    label: &str,
+    text: &str,
+) -> UpdateRequest {
+    match config.summary_target {
+        SummaryTarget::Content => UpdateRequest {
+            content: Some(template::prepend_block(
+                &template::render_block(
+                    config.block_style,
+                    label,
+                    text,
+                    &settings_hash(config, settings),
+                    summary_dir(config, entry, text),
+                ),
+                &original_html(config, &entry.content),
+            )),
+            // 保留原文的阅读时间，避免把摘要块也算进去
+            reading_time: (config.preserve_reading_time && entry.reading_time > 0)
+                .then_some(entry.reading_time),
+            ..Default::default()
+        },
+        SummaryTarget::Tags => UpdateRequest {
+            tags: Some(template::summary_tags(
+                &entry.tags,
+                label,
+                &html::collapse_whitespace(text),
+            )),
+            ..Default::default()
+        },
+    }
+}
+
+// 开启标题提示或小节出处时模型输出 JSON，只取其中的摘要
+fn summary_part(config: &Config, raw: &str) -> String {
+    if let Some(parsed) = config
+        .title_hint
+        .and_then(|_| structured::parse_json_object::<title_hint::SummaryWithHint>(raw))
+    {
+        return parsed.summary;
+    }
+    config
+        .section_citations
+        .then(|| structured::parse_json_object::<citations::Takeaways>(raw))
+        .flatten()
+        .map(|parsed| parsed.plain())
+        .unwrap_or_else(|| raw.to_string())
+}
+
+// SECTION_CITATIONS 只在没有标题提示时生效，两者都要求模型输出不同结构的 JSON
+fn citation_outline(config: &Config, source: &str) -> Vec<html::Heading> {
+    if !config.section_citations || config.title_hint.is_some() || is_plain_text(config, source) {
+        return Vec::new();
+    }
+    html::outline(source)
+}
+
+// 只规范化发送给模型的文本，写回的正文保持原样
+fn model_input(config: &Config, source: &str) -> String {
+    let source = &sanitize::strip_controls(source);
+    if is_plain_text(config, source) {
+        html::prepare_plain_input(source, config.max_token_chars)
+    } else {
+        html::prepare_input(source, config.max_token_chars, config.include_alt_text)
+    }
+}
+
+// 按处理后的字数选择 LENGTH_TIERS 中的预设，FEED_SETTINGS 中指定的提示词优先
+fn tiered_settings<'a>(
+    config: &'a Config,
+    settings: &FeedSettings,
+    input: &str,
+) -> (FeedSettings, Option<&'a LengthTier>) {
+    if settings.prompt_overridden {
+        return (settings.clone(), None);
+    }
+    match tiers::select(&config.length_tiers, tiers::word_count(input)) {
+        Some(tier) => (
+            FeedSettings {
+                prompt: tier.prompt(),
+                ..settings.clone()
+            },
+            Some(tier),
+        ),
+        None => (settings.clone(), None),
+    }
+}
+
+fn is_plain_text(config: &Config, content: &str) -> bool {
+    match config.content_format {
+        ContentFormat::Auto => !html::is_html(content),
+        ContentFormat::Html => false,
+        ContentFormat::Plain => true,
+    }
+}
+
+// 纯文本正文在摘要块后面按 HTML 段落写回，否则 Miniflux 显示时换行会丢失
+fn original_html(config: &Config, content: &str) -> String {
+    if is_plain_text(config, content) {
+        html::plain_to_html(content)
+    } else {
+        content.to_string()
+    }
+}
+
+fn entry_text(config: &Config, content: &str) -> String {
+    if is_plain_text(config, content) {
+        content.to_string()
+    } else {
+        html::extract_text(content)
+    }
+}
+
+// 只检查正文开头，足够识别固定格式的帖子
+const SKIP_CONTENT_PREFIX_CHARS: usize = 1024;
+
+fn matches_skip_content(config: &Config, entry: &Entry) -> bool {
+    if config.skip_content.is_empty() {
+        return false;
+    }
+    let text = entry_text(config, &entry.content);
+    config.skip_content.matches(
+        &entry.title,
+        truncate_chars(&text, SKIP_CONTENT_PREFIX_CHARS),
+    )
+}
+
+fn is_processed(entry: &Entry) -> bool {
+    template::has_summary_block(&entry.content)
+        || entry.tags.iter().any(|tag| template::is_processed_tag(tag))
+}
+
+// 返回文章不需要处理的原因，需要处理时返回 None
+fn skip_reason(
+    config: &Config,
+    entry: &Entry,
+    health: &HashMap<String, FeedHealth>,
+    now: u64,
+) -> Option<SkipReason> {
+    if is_processed(entry) {
+        return Some(SkipReason::AlreadyProcessed);
+    }
+    if !has_allowed_status(&config.entry_statuses, &entry.status) {
+        return Some(SkipReason::Status);
+    }
+
+    if let Some(feed) = &entry.feed {
+        if !config.is_whitelisted(&feed.site_url) {
+            return Some(SkipReason::NotWhitelisted);
+        }
+        let h = health.get(&normalize_site_url(&feed.site_url));
+        if h.is_some_and(|h| h.is_disabled(now)) {
+            return Some(SkipReason::FeedDisabled);
+        }
+        if h.is_some_and(|h| h.empty_skipped) {
+            return Some(SkipReason::EmptyFeed);
+        }
+    }
+
+    let settings = config.feed_settings(entry);
+    if !settings.features.any() {
+        return Some(SkipReason::NoSteps);
+    }
+    if config.min_content_chars > 0
+        && entry_text(config, &entry.content).chars().count() < config.min_content_chars
+    {
+        return Some(SkipReason::TooShort);
+    }
+    if matches_skip_content(config, entry) {
+        return Some(SkipReason::ContentPattern);
+    }
+    if settings.aggregation == AggregationPolicy::Skip
+        && is_aggregation(config, &aggregation_stats(config, &entry.content))
+    {
+        return Some(SkipReason::Aggregation);
+    }
+    if !sampling::is_included(entry.id, &config.sample_salt, config.sample_rate) {
+        return Some(SkipReason::SampledOut);
+    }
+
+    None
+}
+
+fn aggregation_stats(config: &Config, content: &str) -> AggregationStats {
+    html::aggregation_stats(&original_html(config, content))
+}
+
+fn is_aggregation(config: &Config, stats: &AggregationStats) -> bool {
+    stats.quoted_ratio > config.aggregation_quote_ratio
+        || stats.link_density > config.aggregation_link_density
+}
+
+fn newest_first(a: &Entry, b: &Entry) -> std::cmp::Ordering {
+    b.published_at.cmp(&a.published_at).then(b.id.cmp(&a.id))
+}
+
+// 按配置的策略排序待处理文章，同一优先级下以文章 id 保证顺序稳定
+fn prioritize_entries(config: &Config, entries: &mut [Entry]) {
+    let feed_rank = |entry: &Entry| {
+        entry
+            .feed
+            .as_ref()
+            .and_then(|feed| {
+                config
+                    .feed_priority
+                    .iter()
+                    .position(|pattern| pattern.matches(&feed.site_url))
+            })
+            .unwrap_or(config.feed_priority.len())
+    };
+    entries.sort_by(|a, b| match config.priority_strategy {
+        PriorityStrategy::NewestFirst => newest_first(a, b),
+        PriorityStrategy::OldestFirst => a.published_at.cmp(&b.published_at).then(a.id.cmp(&b.id)),
+        PriorityStrategy::FeedPriority => feed_rank(a)
+            .cmp(&feed_rank(b))
+            .then_with(|| newest_first(a, b)),
+    });
+}
+
+const LANGUAGE_CONFIDENCE_THRESHOLD: f32 = 0.6;
+
+// SUMMARY_LANGUAGE=auto 时使用原文的语言，检测不确定时回退到 DEFAULT_LANGUAGE
+fn summary_language(config: &Config, text: &str) -> Option<String> {
+    let language = config.summary_language.as_deref()?;
+    if !language.eq_ignore_ascii_case("auto") {
+        return Some(language.to_string());
+    }
+
+    let detection = language::detect(text);
+    match detection.language {
+        Some(detected) if detection.confidence >= LANGUAGE_CONFIDENCE_THRESHOLD => {
+            Some(detected.to_string())
+        }
+        _ => Some(config.default_language.clone()),
+    }
+}
+
+// 摘要块的 dir 属性：没有指定 SUMMARY_DIR 时按 SUMMARY_LANGUAGE 判断，自动识别语言时按摘要内容判断
+// 摘要不是从右向左的语言但原文是时用 `auto`，摘要中引用的原文片段也能正确显示
+fn summary_dir(config: &Config, entry: &Entry, text: &str) -> Option<&'static str> {
+    let rtl = match config.summary_dir {
+        TextDirection::Rtl => true,
+        TextDirection::Ltr => return None,
+        TextDirection::Auto => match config.summary_language.as_deref() {
+            Some(language) if !language.eq_ignore_ascii_case("auto") => language::is_rtl(language),
+            _ => language::is_rtl_text(text),
+        },
+    };
+    if rtl {
+        Some("rtl")
+    } else if language::is_rtl_text(&entry_text(config, &entry.content)) {
+        Some("auto")
+    } else {
+        None
+    }
+}
+
+fn build_system_prompt(system: &str, language: Option<&str>) -> String {
+    match language {
+        Some(language) => format!(
+            "{}\nRegardless of any language mentioned above, write the result text in {}.",
+            system, language
+        ),
+        None => system.to_string(),
+    }
+}
+
+// 自定义提示词没有长度要求时抽取式摘要使用的长度
+const DEFAULT_EXTRACT_CHARS: usize = 300;
+
+// 把标题翻译为摘要使用的语言，已经是目标语言时不做处理
+async fn translate_title(
+    config: &Config,
+    capture: &Capturer,
+    model: ModelRoute<'_>,
+    title: &str,
+    language: &str,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let detection = language::detect(title);
+    if detection.confidence >= LANGUAGE_CONFIDENCE_THRESHOLD
+        && detection
+            .language
+            .is_some_and(|detected| detected.eq_ignore_ascii_case(language))
+    {
+        return Ok(None);
+    }
+
+    let messages = vec![
+        Message {
+            role: "system".to_string(),
+            content: prompts::TRANSLATE_TITLE_PROMPT.replace("{language}", language),
+        },
+        Message {
+            role: "user".to_string(),
+            content: title.to_string(),
+        },
+    ];
+
+    let translated = chat_completion(config, capture, model, messages).await?;
+    let translated = translated.trim().trim_matches(['"', '“', '”']).trim();
+    if translated.is_empty() || translated == title {
+        return Ok(None);
+    }
+    Ok(Some(html::collapse_whitespace(translated)))
+}
+
+#[derive(Deserialize)]
+struct TranslatedParagraphs {
+    paragraphs: Vec<String>,
+}
+
+// 每次翻译请求最多包含的字符数，留出模型输出翻译结果的空间
+const TRANSLATE_CHUNK_CHARS: usize = 6000;
+
+// 按分块逐段翻译，段落数对不上时整篇放弃，避免译文和原文错位
+async fn translate_body(
+    config: &Config,
+    capture: &Capturer,
+    model: ModelRoute<'_>,
+    paragraphs: &[String],
+    language: &str,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let system = prompts::TRANSLATE_BODY_PROMPT.replace("{language}", language);
+    let mut translated = Vec::with_capacity(paragraphs.len());
+    for range in chunk::pack(paragraphs, TRANSLATE_CHUNK_CHARS) {
+        let input = serde_json::to_string(&paragraphs[range.clone()])?;
+        // 译文和原文长度相近，按输入的两倍估算
+        let tokens = budget::estimate_tokens(&system) + budget::estimate_tokens(&input) * 2;
+        if !config.token_budget.try_reserve(tokens) {
+            return Err("Translation stopped by MAX_TOKENS_PER_RUN".into());
+        }
+        config.translation_tokens.try_reserve(tokens);
+
+        let messages = vec![
+            Message {
+                role: "system".to_string(),
+                content: system.clone(),
+            },
+            Message {
+                role: "user".to_string(),
+                content: input,
+            },
+        ];
+        let result =
+            request_structured::<TranslatedParagraphs>(config, capture, model, messages).await?;
+        if result.paragraphs.len() != range.len() {
+            return Err(format!(
+                "Translation returned {} paragraphs for {}",
+                result.paragraphs.len(),
+                range.len()
+            )
+            .into());
+        }
+        translated.extend(result.paragraphs);
+    }
+    Ok(translated)
+}
+
+#[derive(Deserialize)]
+struct LinkItems {
+    items: Vec<LinkItem>,
+}
+
+#[derive(Deserialize)]
+struct LinkItem {
+    title: String,
+    #[serde(default)]
+    description: String,
+}
+
+const MAX_LINK_ITEMS: usize = 30;
+
+// 聚合类文章的链接列表，每行一个条目
+async fn summarize_links(
+    config: &Config,
+    capture: &Capturer,
+    model: ModelRoute<'_>,
+    links: &[Link],
+    content: &str,
+    language: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let links: Vec<_> = links
+        .iter()
+        .take(MAX_LINK_ITEMS)
+        .map(|link| serde_json::json!({ "url": link.url, "text": link.text }))
+        .collect();
+    let messages = vec![
+        Message {
+            role: "system".to_string(),
+            content: prompts::LINKS_PROMPT.replace("{language}", language),
+        },
+        Message {
+            role: "user".to_string(),
+            content: format!("{}\n---\n {}", serde_json::to_string(&links)?, content),
+        },
+    ];
+
+    let result = request_structured::<LinkItems>(config, capture, model, messages).await?;
+    Ok(result
+        .items
+        .iter()
+        .filter(|item| !item.title.trim().is_empty())
+        .map(|item| match item.description.trim() {
+            "" => format!("• {}", item.title.trim()),
+            description => format!("• {} — {}", item.title.trim(), description),
+        })
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+#[derive(Deserialize)]
+struct Classification {
+    category: String,
+}
+
+// 判断文章类别，模型给出未知类别时归为 other
+async fn classify(
+    config: &Config,
+    capture: &Capturer,
+    model: ModelRoute<'_>,
+    content: &str,
+) -> Result<Category, Box<dyn std::error::Error>> {
+    let messages = vec![
+        Message {
+            role: "system".to_string(),
+            content: prompts::CLASSIFY_PROMPT.to_string(),
+        },
+        Message {
+            role: "user".to_string(),
+            content: format!("The following is the input content:\n---\n {}", content),
+        },
+    ];
+
+    let classification =
+        request_structured::<Classification>(config, capture, model, messages).await?;
+    Ok(classification.category.parse().unwrap_or(Category::Other))
+}
+
+#[derive(Deserialize)]
+struct Score {
+    score: u8,
+}
+
+async fn score_entry(
+    config: &Config,
+    capture: &Capturer,
+    model: ModelRoute<'_>,
+    content: &str,
+) -> Result<u8, Box<dyn std::error::Error>> {
+    let messages = vec![
+        Message {
+            role: "system".to_string(),
+            content: prompts::SCORE_PROMPT.to_string(),
+        },
+        Message {
+            role: "user".to_string(),
+            content: format!("The following is the input content:\n---\n {}", content),
+        },
+    ];
+
+    let score = request_structured::<Score>(config, capture, model, messages).await?;
+    Ok(score.score.clamp(1, 10))
+}
+
+// 设置了 CANARY 时按文章 id 分组，金丝雀组使用覆盖后的设置，失败时改用原设置重新处理
+async fn generate_and_update_entry(
+    config: &Config,
+    capture: &Capturer,
+    entry: &Entry,
+    steps: &mut Vec<Step>,
+) -> Result<EntryOutcome, Box<dyn std::error::Error>> {
+    let settings = config.feed_settings(entry);
+    let Some(canary) = config
+        .canary
+        .as_ref()
+        .filter(|_| settings.features.summarize)
+    else {
+        return generate_with_settings(config, capture, entry, settings, config.provider, steps)
+            .await;
+    };
+
+    let arm = canary.settings.arm(entry.id);
+    let started = Date::now().as_millis();
+    let result = match arm {
+        Arm::Canary => {
+            let canary_settings = canary.settings.apply(&settings);
+            let provider = canary.provider.unwrap_or(config.provider);
+            generate_with_settings(config, capture, entry, canary_settings, provider, steps).await
+        }
+        Arm::Control => {
+            generate_with_settings(
+                config,
+                capture,
+                entry,
+                settings.clone(),
+                config.provider,
+                steps,
+            )
+            .await
+        }
+    };
+    let latency_ms = Date::now().as_millis().saturating_sub(started);
+    let attempt = match &result {
+        Ok(EntryOutcome::Deferred) => return result,
+        Ok(EntryOutcome::Summarized(_)) => Attempt::Succeeded,
+        Ok(EntryOutcome::Refused) => Attempt::Refused,
+        Ok(_) => Attempt::Other,
+        Err(e) if e.is::<output::Rejected>() => Attempt::Rejected,
+        Err(_) => Attempt::Failed,
+    };
+    let fell_back = arm == Arm::Canary && attempt.is_failure();
+    config
+        .canary_records
+        .with(|records| records.push((arm, attempt, latency_ms, fell_back)));
+    if !fell_back {
+        return result;
+    }
+
+    console_warn!(
+        "Entry {}: canary settings failed ({}), retrying with control settings",
+        entry.id,
+        match &result {
+            Err(e) => e.to_string(),
+            Ok(outcome) => format!("{:?}", outcome),
+        }
+    );
+    steps.clear();
+    generate_with_settings(config, capture, entry, settings, config.provider, steps).await
+}
+
+// 按订阅源启用的步骤依次处理，最后把各步骤的输出合并为一次更新
+async fn generate_with_settings(
+    config: &Config,
+    capture: &Capturer,
+    entry: &Entry,
+    settings: FeedSettings,
+    provider: Provider,
+    steps: &mut Vec<Step>,
+) -> Result<EntryOutcome, Box<dyn std::error::Error>> {
+    let features = settings.features;
+    let model = ModelRoute {
+        name: settings.model.as_str(),
+        provider,
+    };
+
+    // 摘要被 Miniflux 覆盖时直接恢复缓存的摘要，不再调用模型
+    if settings.sticky_summary && features.summarize {
+        if let Some(cached) = cached_summary(config, entry).await {
+            verbose_log!(config, "Entry {}: re-applying cached summary", entry.id);
+            let update = summary_update(config, entry, &settings, SUMMARY_LABEL, &cached.summary);
+            if !write_update(config, entry, &update).await? {
+                return Ok(EntryOutcome::Gone);
+            }
+            return Ok(EntryOutcome::Summarized(cached.summary));
+        }
+    }
+
+    let mut source = entry.content.clone();
+    if settings.fetch_full_content && !entry.url.is_empty() {
+        match fetch_article(config, &entry.url).await {
+            Ok(article) => source = article,
+            Err(e) => console_warn!("Failed to fetch article of entry {}: {}", entry.id, e),
+        }
+    }
+
+    let content = model_input(config, &source);
+    if content.contains(char::REPLACEMENT_CHARACTER) {
+        console_warn!(
+            "Entry {}: content contains invalid UTF-8 replaced with U+FFFD",
+            entry.id
+        );
+    }
+
+    // 付费墙截断的正文摘要没有意义，写入提示作为摘要块，之后不再重试
+    if features.summarize && config.paywall.is_stub(&content) {
+        verbose_log!(config, "Entry {}: paywall stub, writing notice", entry.id);
+        let update = summary_update(config, entry, &settings, "", &config.paywall_notice);
+        if !write_update(config, entry, &update).await? {
+            return Ok(EntryOutcome::Gone);
+        }
+        return Ok(EntryOutcome::Paywalled);
+    }
+    let (settings, tier) = tiered_settings(config, &settings, &content);
+    let prompt = prompts::resolve(&settings.prompt)?;
+    let language = summary_language(config, &content);
+    let mut system = build_system_prompt(&prompt.system, language.as_deref());
+    if let Some(length) = &config.summary_length {
+        system = format!("{}\n{}", system, length.prompt());
+    }
+    if let Some(style) = &settings.style {
+        system = format!(
+            "{}\n{}",
+            system,
+            prompts::STYLE_PROMPT.replace("{style}", style)
+        );
+    }
+    if let Some(kind) = config.title_hint {
+        system = format!("{}\n{}", system, kind.prompt());
+    }
+    // 没有小标题的文章不标注出处
+    let outline = citation_outline(config, &source);
+    if !outline.is_empty() {
+        system = format!("{}\n{}", system, citations::prompt(&outline));
+    }
+    if config.feed_description_context && features.summarize {
+        if let Some(description) = feed_description(config, entry).await {
+            system = format!("{}\n{}", system, description);
+        }
+    }
+    if settings.feed_context && features.summarize {
+        if let Some(recap) = feed_recap(config, entry).await {
+            system = format!("{}\n\n{}", recap, system);
+        }
+    }
+    // 只放在发给模型的用户消息中，不会写入摘要块
+    let mut hints = None;
+    if settings.context_hints > 0 && features.summarize {
+        hints = feed_hints(config, entry).await;
+    }
+
+    // 预算不足时不再发起新的摘要请求，留到下次运行
+    let mut tokens = budget::estimate_tokens(&content);
+    if features.summarize {
+        tokens += budget::estimate_tokens(&system);
+        tokens += hints.as_deref().map_or(0, budget::estimate_tokens);
+    }
+    if !config.summarize_categories.is_empty() {
+        tokens += budget::estimate_tokens(prompts::CLASSIFY_PROMPT);
+    }
+    if !config.token_budget.try_reserve(tokens) {
+        verbose_log!(config, "Entry {}: deferred by MAX_TOKENS_PER_RUN", entry.id);
+        return Ok(EntryOutcome::Deferred);
+    }
+
+    // 只摘要 SUMMARIZE_CATEGORIES 中的类别，分类失败时照常摘要
+    let mut summarize = features.summarize;
+    if summarize && !config.summarize_categories.is_empty() {
+        steps.push(Step::Classify);
+        match classify(config, capture, model, &content).await {
+            Ok(category) if !config.summarize_categories.contains(&category) => {
+                verbose_log!(
+                    config,
+                    "Entry {}: category {} not in SUMMARIZE_CATEGORIES",
+                    entry.id,
+                    category.as_str()
+                );
+                summarize = false;
+            }
+            Ok(_) => {}
+            Err(e) => console_warn!("Failed to classify entry {}: {}", entry.id, e),
+        }
+    }
+
+    // 聚合类文章按 AGGREGATION_POLICY 只列出链接条目，没有链接时照常摘要
+    let links = if summarize && settings.aggregation == AggregationPolicy::LinksOnly {
+        let stats = aggregation_stats(config, &source);
+        is_aggregation(config, &stats)
+            .then_some(stats.links)
+            .filter(|links| !links.is_empty())
+    } else {
+        None
+    };
+
+    let mut summary = None;
+    let mut hint = String::new();
+    if let Some(links) = links {
+        steps.push(Step::Summarize);
+        let target = language
+            .clone()
+            .unwrap_or_else(|| config.default_language.clone());
+        let text = summarize_links(config, capture, model, &links, &content, &target).await?;
+        if text.is_empty() {
+            verbose_log!(config, "Entry {}: no link items, not updated", entry.id);
+            return Ok(EntryOutcome::Empty);
+        }
+        verbose_log!(config, "Entry {}: listed {} links", entry.id, links.len());
+        summary = Some(text);
+    } else if summarize {
+        steps.push(Step::Summarize);
+        if let Some(tier) = tier {
+            config
+                .tier_counts
+                .with(|counts| *counts.entry(tier.label()).or_default() += 1);
+        }
+        let messages = vec![
+            Message {
+                role: "system".to_string(),
+                content: system,
+            },
+            Message {
+                role: "user".to_string(),
+                content: match &hints {
+                    Some(hints) => format!(
+                        "{}\n\nThe following is the input content:\n---\n {}",
+                        hints, content
+                    ),
+                    None => format!("The following is the input content:\n---\n {}", content),
+                },
+            },
+        ];
+
+        // Generate summary
+        let mut raw = match chat_completion(config, capture, model, messages.clone()).await {
+            Ok(raw) => raw,
+            Err(e) if config.fallback_extractive => {
+                // 模型不可用时退回到抽取式摘要，单独标注，跳过其他依赖模型的步骤
+                let text = extractive::summarize(
+                    &content,
+                    prompt.max_chars.unwrap_or(DEFAULT_EXTRACT_CHARS),
+                );
+                if text.is_empty() {
+                    return Err(e);
+                }
+                console_warn!(
+                    "Entry {}: using extractive summary, model request failed: {}",
+                    entry.id,
+                    e
+                );
+                let update = summary_update(config, entry, &settings, EXTRACT_LABEL, &text);
+                if !write_update(config, entry, &update).await? {
+                    return Ok(EntryOutcome::Gone);
+                }
+                return Ok(EntryOutcome::Summarized(text));
+            }
+            Err(e) => return Err(e),
+        };
+        // 模型拒答时不把拒答内容写进文章，可选用更中性的提示词重试一次
+        if config.refusal.is_refusal(&raw) && config.retry_on_refusal {
+            verbose_log!(config, "Entry {}: model refused, retrying", entry.id);
+            let mut messages = messages.clone();
+            messages[0].content =
+                format!("{}\n{}", messages[0].content, prompts::REFUSAL_RETRY_PROMPT);
+            raw = chat_completion(config, capture, model, messages).await?;
+        }
+        // 摘要几乎都是套话时附上要求重试一次，重试结果是拒答时保留原来的输出
+        if !config.refusal.is_refusal(&raw)
+            && config.banned_phrases.is_filler(&summary_part(config, &raw))
+        {
+            verbose_log!(
+                config,
+                "Entry {}: summary is mostly filler, retrying",
+                entry.id
+            );
+            let mut messages = messages;
+            messages[0].content = format!(
+                "{}\n{}",
+                messages[0].content,
+                prompts::BANNED_PHRASES_RETRY_PROMPT
+                    .replace("{phrases}", &config.banned_phrases.examples())
+            );
+            let retried = chat_completion(config, capture, model, messages).await?;
+            if !config.refusal.is_refusal(&retried) {
+                raw = retried;
+            }
+        }
+        if config.refusal.is_refusal(&raw) {
+            console_warn!(
+                "Entry {}: model refused to summarize: {}",
+                entry.id,
+                truncate_chars(&raw, 200)
+            );
+            return Ok(EntryOutcome::Refused);
+        }
+        // 长度只按摘要文字计算，不算标题提示和小节出处
+        output::validate_summary(&summary_part(config, &raw), prompt.max_chars)?;
+        // 模型没有按 JSON 返回时把整段输出当作摘要，只是不加标题提示
+        let text = if config.title_hint.is_some() {
+            match structured::parse_json_object::<title_hint::SummaryWithHint>(&raw) {
+                Some(parsed) => {
+                    hint = parsed.hint;
+                    parsed.summary
+                }
+                None => {
+                    console_warn!("Entry {}: no title hint in model output", entry.id);
+                    raw
+                }
+            }
+        } else if !outline.is_empty() {
+            match structured::parse_json_object::<citations::Takeaways>(&raw) {
+                Some(parsed) => citations::render(&parsed, &outline),
+                None => {
+                    console_warn!("Entry {}: no takeaways in model output", entry.id);
+                    raw
+                }
+            }
+        } else {
+            raw
+        };
+        verbose_log!(
+            config,
+            "Entry {}: summary generated ({} chars)",
+            entry.id,
+            text.chars().count()
+        );
+        let text = config.summary_replace.apply(text.trim());
+        let mut text = config.banned_phrases.strip_leading(&text);
+        if config.normalize_typography {
+            text = output::normalize_typography(&text);
+        }
+        let text = text.trim();
+        if text.is_empty() {
+            verbose_log!(config, "Entry {}: empty summary, not updated", entry.id);
+            return Ok(EntryOutcome::Empty);
+        }
+
+        let mut text = text.to_string();
+        if settings.summarize_comments && !entry.comments_url.is_empty() {
+            match summarize_comments(config, capture, model, &entry.comments_url).await {
+                Ok(discussion) if !discussion.trim().is_empty() => {
+                    text = format!("{}\n\n💬 讨论要点：\n{}", text, discussion);
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    console_warn!("Failed to summarize comments of entry {}: {}", entry.id, e)
+                }
+            }
+        }
+        summary = Some(text);
+    }
+
+    // 实体只提取一次，同时用于摘要下方的实体行和标签
+    let mut entities = None;
+    if (summary.is_some() && settings.extract_entities) || features.tags {
+        if features.tags {
+            steps.push(Step::Tags);
+        }
+        match extract_entities(config, capture, model, &content).await {
+            Ok(extracted) => entities = Some(extracted),
+            Err(e) => console_warn!("Failed to extract entities of entry {}: {}", entry.id, e),
+        }
+    }
+
+    let mut update = UpdateRequest::default();
+    if let Some(text) = &mut summary {
+        // 提取失败或结果为空时只保留摘要
+        if settings.extract_entities {
+            if let Some(line) = entities.as_ref().and_then(Entities::render) {
+                *text = format!("{}\n{}", text, line);
+            }
+        }
+        if let Some(template) = &config.media_template {
+            if let Some(line) = template::render_media_line(template, &html::count_media(&source)) {
+                *text = format!("{}\n{}", text, line);
+            }
+        }
+        update = summary_update(config, entry, &settings, SUMMARY_LABEL, text);
+    }
+    // 全文翻译放在摘要块后面，没有写入正文的摘要时不翻译
+    if settings.translate_body && update.content.is_some() {
+        steps.push(Step::TranslateBody);
+        let target = language
+            .clone()
+            .unwrap_or_else(|| config.default_language.clone());
+        let paragraphs = html::paragraphs(&original_html(config, &source));
+        match translate_body(config, capture, model, &paragraphs, &target).await {
+            Ok(translated) if !translated.is_empty() => {
+                if let Some(content) = update
+                    .content
+                    .as_deref()
+                    .and_then(|content| template::with_translation(content, &translated))
+                {
+                    update.content = Some(content);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => console_warn!("Failed to translate body of entry {}: {}", entry.id, e),
+        }
+    }
+    if let Some(entities) = entities.filter(|_| features.tags) {
+        let symbols = entities::tag_symbols(&entities.tickers, config.tag_min_confidence);
+        if symbols.len() < entities.tickers.len() {
+            verbose_log!(
+                config,
+                "Entry {}: dropped {} tags below TAG_MIN_CONFIDENCE",
+                entry.id,
+                entities.tickers.len() - symbols.len()
+            );
+        }
+        if !symbols.is_empty() {
+            entities::merge_tags(
+                update.tags.get_or_insert_with(|| entry.tags.clone()),
+                &symbols,
+            );
+        }
+    }
+
+    let mut title = entry.title.clone();
+    if features.translate_title {
+        steps.push(Step::TranslateTitle);
+        let target = language
+            .clone()
+            .unwrap_or_else(|| config.default_language.clone());
+        match translate_title(config, capture, model, &entry.title, &target).await {
+            Ok(Some(translated)) => title = translated,
+            Ok(None) => {}
+            Err(e) => console_warn!("Failed to translate title of entry {}: {}", entry.id, e),
+        }
+    }
+    if let Some(kind) = config.title_hint {
+        if let Some(hinted) =
+            title_hint::append_hint(&title, &config.title_hint_separator, &hint, kind)
+        {
+            title = hinted;
+        }
+    }
+    if title != entry.title {
+        update.title = Some(title);
+    }
+
+    if features.score {
+        steps.push(Step::Score);
+        match score_entry(config, capture, model, &content).await {
+            Ok(score) => {
+                let tags = update.tags.get_or_insert_with(|| entry.tags.clone());
+                tags.retain(|tag| !tag.starts_with(SCORE_TAG_PREFIX));
+                tags.push(format!("{}{}", SCORE_TAG_PREFIX, score));
+            }
+            Err(e) => console_warn!("Failed to score entry {}: {}", entry.id, e),
+        }
+    }
+
+    // 没有写入摘要时打上标记，下次运行不再重复处理
+    if summary.is_none() {
+        let tags = update.tags.get_or_insert_with(|| entry.tags.clone());
+        if !tags.iter().any(|tag| tag == PROCESSED_TAG) {
+            tags.push(PROCESSED_TAG.to_string());
+        }
+    }
+
+    if !write_update(config, entry, &update).await? {
+        return Ok(EntryOutcome::Gone);
+    }
+
+    Ok(match summary {
+        Some(summary) => {
+            if let Some(kv_batch) = &config.kv_batch {
+                let cached = CachedSummary {
+                    hash: entry.hash.clone(),
+                    summary: summary.clone(),
+                    title: entry.title.clone(),
+                    url: entry.url.clone(),
+                };
+                let now = Date::now().as_millis();
+                kv_batch.put(SUMMARY_DOC, entry.id.to_string(), &cached, now);
+                if let (true, Some(feed)) = (settings.feed_context, &entry.feed) {
+                    let context = FeedContext {
+                        entry_id: entry.id,
+                        summary: summary.clone(),
+                    };
+                    kv_batch.put(
+                        FEED_CONTEXT_DOC,
+                        normalize_site_url(&feed.site_url),
+                        &context,
+                        now,
+                    );
+                }
+                if let (true, Some(feed)) = (settings.context_hints > 0, &entry.feed) {
+                    let site_url = normalize_site_url(&feed.site_url);
+                    let mut hints = kv_batch
+                        .get::<Vec<FeedContext>>(FEED_HINTS_DOC, &site_url)
+                        .await
+                        .unwrap_or_default();
+                    context::push_hint(
+                        &mut hints,
+                        FeedContext {
+                            entry_id: entry.id,
+                            summary: summary.clone(),
+                        },
+                        settings.context_hints,
+                    );
+                    kv_batch.put(FEED_HINTS_DOC, site_url, &hints, now);
+                }
+                if let Some(key) = config.dedup_urls.then(|| entry_url_key(entry)).flatten() {
+                    kv_batch.put(CANONICAL_DOC, key, &entry.id, now);
+                }
+            }
+            EntryOutcome::Summarized(summary)
+        }
+        None => EntryOutcome::Updated,
+    })
+}
+
+// 写回文章，文章已被删除或移除时返回 false
+async fn write_update(
+    config: &Config,
+    entry: &Entry,
+    update: &UpdateRequest,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    // 生成摘要期间文章可能已被删除或移除，写回前确认一下
+    if config.verify_before_update {
+        match get_entry(&config.miniflux, entry.id).await {
+            Ok(current) if current.status == "removed" => return Ok(false),
+            Ok(_) => {}
+            Err(e) if e.is::<EntryGone>() => return Ok(false),
+            Err(e) => return Err(e),
+        }
+    }
+
+    // 部分 Miniflux 部署会在写入时和自身的内容清理产生竞争，等待一段时间再写
+    if config.write_delay_ms > 0 {
+        Delay::from(Duration::from_millis(config.write_delay_ms)).await;
+    }
+
+    // Update the entry
+    match update_entry(&config.miniflux, entry.id, update).await {
+        Err(e) if e.is::<EntryGone>() => return Ok(false),
+        result => result?,
+    }
+    verbose_log!(config, "Entry {}: updated", entry.id);
+
+    // 重新读取文章，确认摘要标记没有被 Miniflux 清理掉
+    if config.verify_after_update {
+        match get_entry(&config.miniflux, entry.id).await {
+            Ok(current) if !is_processed(&current) => {
+                return Err(format!(
+                    "Summary marker of entry {} was stripped after the update",
+                    entry.id
+                )
+                .into())
+            }
+            Ok(_) => {}
+            Err(e) if e.is::<EntryGone>() => return Ok(false),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(true)
+}
+
+const FEED_HEALTH_KEY: &str = "feed_health";
+const DEADLETTER_KEY: &str = "deadletter";
+
+async fn kv_get_json<T: DeserializeOwned + Default>(kv: &KvStore, key: &str) -> T {
+    match kv.get(key).json().await {
+        Ok(value) => value.unwrap_or_default(),
+        Err(e) => {
+            console_warn!("Failed to load {} from KV: {:?}", key, e);
+            T::default()
+        }
+    }
+}
+
+async fn kv_put_json<T: Serialize>(kv: &KvStore, key: &str, value: &T) {
+    let result = match kv.put(key, value) {
+        Ok(put) => put.execute().await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        console_warn!("Failed to save {} to KV: {:?}", key, e);
+    }
+}
+
+// 摘要或标签已经写回 Miniflux
+// 按订阅源和日期累计，供 /admin/feeds/stats 查询
+const FEED_STATS_DOC: PackedDoc = PackedDoc {
+    key: "feed_stats",
+    max_age_ms: feed_stats::MAX_DAYS * feed_stats::DAY_MS,
+};
+
+// 把其他样式的摘要块按当前 PLAINTEXT_SUMMARY 重新渲染，原文部分原样保留，不调用模型
+async fn migrate_block(config: &Config, entry: &Entry) -> Result<bool, Box<dyn std::error::Error>> {
+    let Some(block) = template::parse_block(&entry.content) else {
+        return Ok(false);
+    };
+    if block.style == config.block_style {
+        return Ok(false);
+    }
+
+    // 缓存中保存的是写入时的完整摘要，比从旧的摘要块中解析更可靠
+    let mut text = block.text;
+    if block.label == SUMMARY_LABEL {
+        if let Some(kv_batch) = &config.kv_batch {
+            if let Some(cached) = kv_batch
+                .get::<CachedSummary>(SUMMARY_DOC, &entry.id.to_string())
+                .await
+            {
+                text = cached.summary;
+            }
+        }
+    }
+
+    // 旧哈希只因样式不同而变化时换成当前哈希，否则保留，/admin/resummarize 仍能发现过期摘要
+    let settings = config.feed_settings(entry);
+    let hash = match template::parse_marker(&entry.content).and_then(|marker| marker.hash) {
+        Some(hash) if hash != settings_hash_with_style(config, &settings, block.style) => {
+            hash.to_string()
+        }
+        _ => settings_hash(config, &settings),
+    };
+    let rendered = template::render_block(config.block_style, block.label, &text, &hash, block.dir);
+    let update = UpdateRequest {
+        content: Some(format!("{}{}", rendered, block.rest)),
+        reading_time: (config.preserve_reading_time && entry.reading_time > 0)
+            .then_some(entry.reading_time),
+        ..Default::default()
+    };
+    write_update(config, entry, &update).await
+}
+
+// 单篇文章的处理结果、执行的步骤和耗时（毫秒）
+type EntryResult = (
+    Entry,
+    Result<EntryOutcome, Box<dyn std::error::Error>>,
+    Vec<Step>,
+    u64,
+);
+
+// 金丝雀组和对照组每天的结果，保留天数和订阅源统计相同
+const CANARY_STATS_DOC: PackedDoc = PackedDoc {
+    key: "canary_stats",
+    max_age_ms: feed_stats::MAX_DAYS * feed_stats::DAY_MS,
+};
+
+async fn record_canary_stats(config: &Config, kv_batch: &KvBatch, now: u64) {
+    let records = config.canary_records.with(std::mem::take);
+    let mut daily: HashMap<String, ArmStats> = HashMap::new();
+    for (arm, attempt, latency_ms, fell_back) in records {
+        let key = canary::day_key(arm, now);
+        let stats = match daily.get_mut(&key) {
+            Some(stats) => stats,
+            None => {
+                let stats = kv_batch
+                    .get(CANARY_STATS_DOC, &key)
+                    .await
+                    .unwrap_or_default();
+                daily.entry(key).or_insert(stats)
+            }
+        };
+        stats.record(attempt, latency_ms, fell_back);
+    }
+    for (key, stats) in daily {
+        kv_batch.put(CANARY_STATS_DOC, key, &stats, now);
+    }
+}
+
+async fn record_feed_stats(kv_batch: &KvBatch, results: &[EntryResult], now: u64) {
+    let mut daily: HashMap<String, DailyStats> = HashMap::new();
+    for (entry, result, _, latency_ms) in results {
+        let Some(feed) = &entry.feed else {
+            continue;
+        };
+        let outcome = match result {
+            Ok(EntryOutcome::Summarized(summary)) => feed_stats::Outcome::Summarized {
+                chars: summary.chars().count(),
+            },
+            Ok(EntryOutcome::Refused) => feed_stats::Outcome::Refused,
+            // 延后处理的文章下次运行还会再统计一次
+            Ok(EntryOutcome::Deferred) => continue,
+            Ok(_) => feed_stats::Outcome::Other,
+            Err(_) => feed_stats::Outcome::Failed,
+        };
+        let key = feed_stats::day_key(&normalize_site_url(&feed.site_url), now);
+        let stats = match daily.get_mut(&key) {
+            Some(stats) => stats,
+            None => {
+                let stats = kv_batch.get(FEED_STATS_DOC, &key).await.unwrap_or_default();
+                daily.entry(key).or_insert(stats)
+            }
+        };
+        stats.record(outcome, *latency_ms);
+        if let Some(category) = &feed.category {
+            stats.category = category.title.clone();
+        }
+    }
+    for (key, stats) in daily {
+        kv_batch.put(FEED_STATS_DOC, key, &stats, now);
+    }
+}
+
+fn is_success(result: &Result<EntryOutcome, Box<dyn std::error::Error>>) -> bool {
+    matches!(
+        result,
+        Ok(EntryOutcome::Summarized(_)) | Ok(EntryOutcome::Updated)
+    )
+}
+
+// 每次运行只记录最后一篇成功的文章，避免逐篇写入 KV
+async fn record_last_success(kv: &KvStore, entry: &Entry, now: u64) {
+    let last = LastSuccess {
+        at: now,
+        entry_id: entry.id,
+        site_url: entry.feed.as_ref().map(|feed| feed.site_url.clone()),
+    };
+    kv_put_json(kv, LAST_SUCCESS_KEY, &last).await;
+}
+
+// 之前运行写入的不可用提示不属于原文，处理前去掉
+fn strip_unavailable_notice(entry: &mut Entry) {
+    if let Some(original) = template::strip_notice(&entry.content) {
+        entry.content = original.to_string();
+        entry.has_notice = true;
+    }
+}
+
+// 只写标签时不改写正文，已带提示的文章也不重复写入
+fn needs_unavailable_notice(target: SummaryTarget, entry: &Entry, failed: bool) -> bool {
+    failed && target == SummaryTarget::Content && !entry.has_notice
+}
+
+// 写入不可用提示，文章仍按未处理对待，模型恢复后会正常摘要
+async fn write_unavailable_notices(config: &Config, notice: &str, results: &[EntryResult]) {
+    for (entry, result, _, _) in results {
+        if !needs_unavailable_notice(config.summary_target, entry, result.is_err()) {
+            continue;
+        }
+        let update = UpdateRequest {
+            content: Some(template::with_notice(
+                notice,
+                &original_html(config, &entry.content),
+            )),
+            ..Default::default()
+        };
+        if let Err(e) = write_update(config, entry, &update).await {
+            console_warn!(
+                "Failed to write unavailable notice to entry {}: {}",
+                entry.id,
+                e
+            );
+        }
+    }
+}
+
+// 多次失败后写入失败提示，避免下次运行继续尝试
+async fn mark_entry_failed(
+    config: &Config,
+    entry: &Entry,
+) -> Result<(), Box<dyn std::error::Error>> {
+    update_entry(
+        &config.miniflux,
+        entry.id,
+        &summary_update(
+            config,
+            entry,
+            &config.feed_settings(entry),
+            FAILURE_LABEL,
+            "多次尝试后仍无法生成摘要",
+        ),
+    )
+    .await
+}
+
+#[derive(Serialize)]
+struct DigestEntry {
+    id: u64,
+    title: String,
+    url: String,
+    summary: String,
+}
+
+#[derive(Serialize)]
+struct Digest {
+    entries: Vec<DigestEntry>,
+}
+
+struct DigestWebhook {
+    url: String,
+    secret: Option<String>,
+}
+
+const DIGEST_SIGNATURE_HEADER: &str = "X-Miniflux-AI-Signature";
+
+// 把本次生成的摘要推送到外部 Webhook，配置了密钥时和 Miniflux 一样附带签名
+async fn send_digest(
+    config: &Config,
+    digest_webhook: &DigestWebhook,
+    digest: &Digest,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let payload = serde_json::to_string(digest)?;
+
+    let mut request = config
+        .http_client
+        .post(&digest_webhook.url)
+        .header(CONTENT_TYPE, "application/json");
+    if let Some(secret) = &digest_webhook.secret {
+        request = request.header(DIGEST_SIGNATURE_HEADER, sign(secret, &payload));
+    }
+
+    config
+        .http_client
+        .send(request.body(payload))
+        .await?
+        .error_for_status()?;
+
+    Ok(())
+}
+
+// 调试记录打包在同一个 KV 值中，只短期保留
+const DEBUG_DOC: PackedDoc = PackedDoc {
+    key: "debug",
+    max_age_ms: 24 * 60 * 60 * 1000,
+};
+const LAST_RUN_KEY: &str = "last_run";
+const LAST_SUCCESS_KEY: &str = "last_success";
+// 第一次检查健康状态的时间，还没有成功记录时用来计算宽限期
+const HEALTH_FIRST_SEEN_KEY: &str = "health_first_seen";
+// 查找旧设置生成的摘要时扫描的最近文章数
+const STALE_SCAN_LIMIT: usize = 100;
+// 已写入的摘要，用于发现被覆盖的文章并在 STICKY_SUMMARY 开启时直接恢复
+const SUMMARY_DOC: PackedDoc = PackedDoc {
+    key: "summaries",
+    max_age_ms: 14 * 24 * 60 * 60 * 1000,
+};
+
+// 已摘要文章的规范地址到文章 ID 的映射，用于跨订阅源、跨运行去重
+const CANONICAL_DOC: PackedDoc = PackedDoc {
+    key: "canonical_urls",
+    max_age_ms: 30 * 24 * 60 * 60 * 1000,
+};
+
+const READING_TIME_NOTE: &str =
+    "Miniflux recomputes reading_time from the rewritten content, so it includes the summary block";
+
+// 队列消息失败投递的次数，超过 QUEUE_MAX_DELIVERIES 后移入 `dlq:{entry_id}`
+const QUEUE_ATTEMPTS_DOC: PackedDoc = PackedDoc {
+    key: "queue_attempts",
+    max_age_ms: 7 * 24 * 60 * 60 * 1000,
+};
+
+// 每个订阅源最近一篇文章的摘要，FEED_CONTEXT 开启时作为回顾加入提示词
+const FEED_CONTEXT_DOC: PackedDoc = PackedDoc {
+    key: "feed_context",
+    max_age_ms: 30 * 24 * 60 * 60 * 1000,
+};
+
+async fn feed_recap(config: &Config, entry: &Entry) -> Option<String> {
+    let kv_batch = config.kv_batch.as_ref()?;
+    let site_url = normalize_site_url(&entry.feed.as_ref()?.site_url);
+    let context = kv_batch
+        .get::<FeedContext>(FEED_CONTEXT_DOC, &site_url)
+        .await?;
+    context::recap(&context, entry.id)
+}
+
+// 文章自带简介时直接使用，否则每个订阅源每次运行最多查询一次
+async fn feed_description(config: &Config, entry: &Entry) -> Option<String> {
+    let feed = entry.feed.as_ref()?;
+    if !feed.description.trim().is_empty() {
+        return context::describe(&feed.description);
+    }
+    if feed.id == 0 {
+        return None;
+    }
+    if let Some(cached) = config
+        .feed_descriptions
+        .with(|descriptions| descriptions.get(&feed.id).cloned())
+    {
+        return cached;
+    }
+    let description = match get_feed(&config.miniflux, feed.id).await {
+        Ok(fetched) => context::describe(&fetched.description),
+        Err(e) => {
+            console_warn!("Failed to fetch feed {}: {}", feed.id, e);
+            None
+        }
+    };
+    config
+        .feed_descriptions
+        .with(|descriptions| descriptions.insert(feed.id, description.clone()));
+    description
+}
+
+// 每个订阅源最近几篇文章的摘要，CONTEXT_HINTS 开启时作为背景加入用户消息
+const FEED_HINTS_DOC: PackedDoc = PackedDoc {
+    key: "feed_hints",
+    max_age_ms: 30 * 24 * 60 * 60 * 1000,
+};
+
+async fn feed_hints(config: &Config, entry: &Entry) -> Option<String> {
+    let kv_batch = config.kv_batch.as_ref()?;
+    let site_url = normalize_site_url(&entry.feed.as_ref()?.site_url);
+    let hints = kv_batch
+        .get::<Vec<FeedContext>>(FEED_HINTS_DOC, &site_url)
+        .await?;
+    context::hints_block(&hints, entry.id, config.context_hints_max_tokens)
+}
+
+// 优先使用正文中的 canonical 链接，没有时使用文章地址
+fn entry_url_key(entry: &Entry) -> Option<String> {
+    let url = html::canonical_url(&entry.content).unwrap_or(&entry.url);
+    dedup::url_key(url)
+}
+
+async fn cached_summary(config: &Config, entry: &Entry) -> Option<CachedSummary> {
+    let kv_batch = config.kv_batch.as_ref()?;
+    kv_batch
+        .get::<CachedSummary>(SUMMARY_DOC, &entry.id.to_string())
+        .await
+        .filter(|cached| sticky::is_reverted(cached, &entry.hash))
+}
+
+// 失败的文章和按比例抽样的成功文章写入 KV，可通过 /admin/debug/{id} 查看
+fn store_debug_capture(
+    config: &Config,
+    capture: Capturer,
+    entry: &Entry,
+    result: &Result<EntryOutcome, Box<dyn std::error::Error>>,
+) {
+    let (Some(debug_capture), Some(kv_batch)) = (&config.debug_capture, &config.kv_batch) else {
+        return;
+    };
+    let error = result.as_ref().err().map(|e| e.to_string());
+    if error.is_none() && !debug::is_sampled(entry.id, debug_capture.sample_percent) {
+        return;
+    }
+    let now = Date::now().as_millis();
+    if let Some(record) = capture.into_record(entry.id, error.as_deref(), now) {
+        kv_batch.put(DEBUG_DOC, entry.id.to_string(), &record, now);
+    }
+}
+
+const ALERT_STATE_KEY: &str = "alert_state";
+
+// 通过所有配置的渠道发送告警，依赖 KV 记录上次发送时间来限流
+async fn send_alert(config: &Config, message: &str) {
+    if config.alert.notifiers.is_empty() {
+        return;
+    }
+    let Some(kv) = &config.kv else {
+        console_warn!("Alerts require the KV binding, not sending: {}", message);
+        return;
+    };
+
+    let now = Date::now().as_millis();
+    let mut state: AlertState = kv_get_json(kv, ALERT_STATE_KEY).await;
+    if !state.can_send(now, config.alert.window_ms) {
+        console_log!("Alert suppressed by rate limit: {}", message);
+        return;
+    }
+
+    for notifier in &config.alert.notifiers {
+        if let Err(e) = notifier.send(&config.http_client, message).await {
+            console_warn!("Failed to send alert via {}: {}", notifier.name(), e);
+        }
+    }
+    state.last_sent = Some(now);
+    kv_put_json(kv, ALERT_STATE_KEY, &state).await;
+}
+
+// 试运行时每篇文章都输出跳过原因，正常运行时只在安全模式下输出
+fn log_skip(config: &Config, entry: &Entry, reason: SkipReason) {
+    if config.dry_run {
+        console_log!("Entry {}: would skip, {}", entry.id, reason.description());
+    } else {
+        verbose_log!(
+            config,
+            "Entry {}: skipped, {}",
+            entry.id,
+            reason.description()
+        );
+    }
+}
+
+// 连续多次运行都失败的文章标记为失败并放入死信列表，拒答的文章保持原样，只记入死信列表
+async fn record_deadletter(
+    config: &Config,
+    deadletter: &mut DeadLetterLog,
+    entry: &Entry,
+    site_url: Option<String>,
+    error: String,
+    refused: bool,
+    now: u64,
+) {
+    if !deadletter.record_failure(entry.id, config.deadletter_threshold) {
+        return;
+    }
+    console_log!(
+        "Dead-lettered entry {} after {} consecutive failed runs",
+        entry.id,
+        config.deadletter_threshold
+    );
+    if !refused {
+        if let Err(e) = mark_entry_failed(config, entry).await {
+            console_warn!("Failed to mark entry {} as failed: {}", entry.id, e);
+        }
+    }
+    deadletter.push(DeadLetter {
+        entry_id: entry.id,
+        site_url,
+        error,
+        failed_at: now,
+        refused,
+    });
+}
+
+// 生成摘要并更新，跳过被自动禁用的订阅源，并记录每个订阅源的连续失败次数
+async fn process_entries(config: &Config, mut entries: Vec<Entry>) -> RunReport {
+    let now = Date::now().as_millis();
+    entries.iter_mut().for_each(strip_unavailable_notice);
+
+    // 订阅源严格按时间发布时，遇到第一篇已处理的文章即可认为更早的文章都已处理过
+    if config.stop_at_processed && !config.explicit_entries {
+        entries.sort_by(newest_first);
+        if let Some(i) = entries.iter().position(is_processed) {
+            verbose_log!(
+                config,
+                "Stopping at processed entry {}, skipping {} older entries",
+                entries[i].id,
+                entries.len() - i - 1
+            );
+            entries.truncate(i);
+        }
+    }
+    let mut health: HashMap<String, FeedHealth> = match &config.kv {
+        Some(kv) => kv_get_json(kv, FEED_HEALTH_KEY).await,
+        None => HashMap::new(),
+    };
+
+    let mut health_changed = false;
+    for (site_url, h) in health.iter_mut() {
+        if (h.empty_skipped || h.empty_streak > 0)
+            && config.empty_feed_reset.iter().any(|p| p.matches(site_url))
+        {
+            console_log!("Resetting empty summary streak of feed {}", site_url);
+            h.reset_empty();
+            health_changed = true;
+        }
+    }
+
+    let mut deadletter: DeadLetterLog = match &config.kv {
+        Some(kv) => kv_get_json(kv, DEADLETTER_KEY).await,
+        None => DeadLetterLog::default(),
+    };
+
+    let mut report = RunReport::default();
+    if !config.explicit_entries {
+        let skip_reason = |entry: &Entry| {
+            skip_reason(config, entry, &health, now).or_else(|| {
+                deadletter
+                    .is_refused(entry.id)
+                    .then_some(SkipReason::Refused)
+            })
+        };
+        entries.retain(|entry| match skip_reason(entry) {
+            Some(reason) => {
+                log_skip(config, entry, reason);
+                report.record_skip(reason);
+                if config.auto_read_skipped.contains(&reason) && entry.status != "read" {
+                    report.auto_read.push(entry.id);
+                }
+                false
+            }
+            None => true,
+        });
+    }
+
+    // 同一篇文章被多个订阅源转载时只摘要一次，本次运行内也只保留第一篇
+    if config.dedup_urls {
+        let mut seen = HashSet::new();
+        let mut unique = Vec::with_capacity(entries.len());
+        for entry in entries {
+            if let Some(key) = entry_url_key(&entry) {
+                let summarized_by = match &config.kv_batch {
+                    Some(kv_batch) => kv_batch.get::<u64>(CANONICAL_DOC, &key).await,
+                    None => None,
+                };
+                if summarized_by.is_some_and(|id| id != entry.id) || !seen.insert(key) {
+                    log_skip(config, &entry, SkipReason::DuplicateUrl);
+                    report.record_skip(SkipReason::DuplicateUrl);
+                    continue;
+                }
+            }
+            unique.push(entry);
+        }
+        entries = unique;
+    }
+
+    // 缓存中有摘要但文章又变回未处理状态，说明 Miniflux 刷新订阅源时覆盖了摘要
+    for entry in &entries {
+        if cached_summary(config, entry).await.is_some() {
+            verbose_log!(
+                config,
+                "Entry {}: summary was reverted (changed at {:?})",
+                entry.id,
+                entry.changed_at
+            );
+            let site_url = entry
+                .feed
+                .as_ref()
+                .map(|feed| normalize_site_url(&feed.site_url))
+                .unwrap_or_default();
+            report.record_reverted(site_url);
+        }
+    }
+
+    prioritize_entries(config, &mut entries);
+    if let Some(max_entries) = config.max_entries_per_run {
+        if entries.len() > max_entries {
+            console_log!(
+                "Deferring {} entries beyond MAX_ENTRIES_PER_RUN={}",
+                entries.len() - max_entries,
+                max_entries
+            );
+            entries.truncate(max_entries);
+        }
+    }
+
+    // 明确记录一次空运行，便于确认定时任务正常执行
+    if entries.is_empty() {
+        console_log!("No eligible entries, nothing to do");
+    }
+
+    if config.dry_run {
+        for entry in &entries {
+            console_log!("Entry {}: would summarize", entry.id);
+        }
+        console_log!("Dry run report: {}", report);
+        return report;
+    }
+
+    // 生成摘要并更新的并发任务，安全模式下严格按顺序逐篇处理
+    let max_concurrent_tasks = if config.safe_mode {
+        1
+    } else {
+        config.max_concurrency
+    };
+    verbose_log!(
+        config,
+        "Safe mode: processing {} entries sequentially",
+        entries.len()
+    );
+
+    let progress = Shared::new(Progress::new(
+        entries.len(),
+        config.heartbeat_every,
+        max_concurrent_tasks,
+    ));
+    // Create a stream to process tasks with concurrency limit
+    let results: Vec<EntryResult> = stream::iter(entries)
+        .map(|entry| async move {
+            verbose_log!(config, "Entry {}: start", entry.id);
+            let capture = Capturer::new(config.debug_capture.as_ref().map(|d| d.secrets.clone()));
+            let mut steps = Vec::new();
+            let started = Date::now().as_millis();
+            let result = generate_and_update_entry(config, &capture, &entry, &mut steps).await;
+            let latency_ms = Date::now().as_millis().saturating_sub(started);
+            match &result {
+                Ok(outcome) => verbose_log!(config, "Entry {}: done: {:?}", entry.id, outcome),
+                Err(e) => verbose_log!(config, "Entry {}: failed: {}", entry.id, e),
+            }
+            store_debug_capture(config, capture, &entry, &result);
+            (entry, result, steps, latency_ms)
+        })
+        .buffer_unordered(max_concurrent_tasks)
+        .inspect(|(_, result, _, _)| {
+            if let Some(line) = progress.with(|progress| progress.record(result)) {
+                console_log!("{}", line);
+            }
+        })
+        .collect()
+        .await;
+
+    let requests = config.http_recorder.records();
+    report.subrequests = requests.len();
+    report.estimated_tokens = config.token_budget.used();
+    report.translation_tokens = config.translation_tokens.used();
+    report.tiers = config.tier_counts.with(std::mem::take);
+    if report.summarized > 0
+        && config.summary_target == SummaryTarget::Content
+        && !config.preserve_reading_time
+    {
+        report.notes.push(READING_TIME_NOTE);
+    }
+    if config.sample_rate < 1.0 {
+        report.full_rate_tokens = Some(sampling::extrapolate(
+            report.estimated_tokens,
+            config.sample_rate,
+        ));
+    }
+    for (entry, result, steps, _) in &results {
+        report.record(result);
+        report.record_steps(entry.id, steps);
+        match result {
+            Ok(EntryOutcome::Deferred) => {
+                report.deferred_ids.insert(entry.id);
+            }
+            Err(e) => {
+                report.retry.insert(entry.id, e.to_string());
+            }
+            _ => {}
+        }
+    }
+    console_log!("Run report: {}", report);
+    if !report.steps.is_empty() {
+        console_log!("Steps per entry: {}", report.steps_line());
+    }
+    for request in &requests {
+        verbose_log!(
+            config,
+            "{} {} -> {:?}",
+            request.method,
+            request.url,
+            request.status
+        );
+    }
+
+    if alert::should_alert(&report, config.alert.failure_ratio) {
+        let errors: Vec<String> = results
+            .iter()
+            .filter_map(|(entry, result, _, _)| {
+                let e = result.as_ref().err()?;
+                Some(format!(
+                    "- entry {}: {}",
+                    entry.id,
+                    truncate_chars(&e.to_string(), 200)
+                ))
+            })
+            .take(3)
+            .collect();
+        let message = format!(
+            "miniflux-ai: {} entries failed in the last run\n{}\n{}",
+            report.failed,
+            report,
+            errors.join("\n")
+        );
+        send_alert(config, &message).await;
+    }
+
+    if let Some(digest_webhook) = &config.digest_webhook {
+        let digest = Digest {
+            entries: results
+                .iter()
+                .filter_map(|(entry, result, _, _)| match result {
+                    Ok(EntryOutcome::Summarized(summary)) => Some(DigestEntry {
+                        id: entry.id,
+                        title: entry.title.clone(),
+                        url: entry.url.clone(),
+                        summary: summary.clone(),
+                    }),
+                    _ => None,
+                })
+                .collect(),
+        };
+        if !digest.entries.is_empty() {
+            if let Err(e) = send_digest(config, digest_webhook, &digest).await {
+                console_warn!("Failed to send digest webhook: {}", e);
+            }
+        }
+    }
+
+    // 整次运行都失败时不计入死信和订阅源故障，避免服务恢复前把文章和订阅源都标记掉
+    let outage = report.is_outage();
+    if outage {
+        console_warn!(
+            "Model unavailable for the whole run: all {} processed entries failed",
+            report.failed
+        );
+        if let Some(notice) = &config.unavailable_notice {
+            write_unavailable_notices(config, notice, &results).await;
+        }
+    }
+
+    let Some(kv) = &config.kv else {
+        return report;
+    };
+
+    // 每次运行只写一次汇总文档，逐篇文章的数据在内存中累积后一起写入
+    kv_put_json(kv, LAST_RUN_KEY, &report).await;
+    if let Some((entry, _, _, _)) = results
+        .iter()
+        .rev()
+        .find(|(_, result, _, _)| is_success(result))
+    {
+        record_last_success(kv, entry, now).await;
+    }
+    if let Some(kv_batch) = &config.kv_batch {
+        record_feed_stats(kv_batch, &results, now).await;
+        record_canary_stats(config, kv_batch, now).await;
+        kv_batch.flush(now).await;
+    }
+
+    let mut deadletter_changed = false;
+
+    for (entry, result, _, _) in results {
+        let site_url = entry
+            .feed
+            .as_ref()
+            .map(|feed| normalize_site_url(&feed.site_url));
+
+        match result {
+            Ok(EntryOutcome::Deferred) => {}
+            // 拒答不算订阅源故障，但和失败一样计入死信，避免每次运行都重复请求
+            Ok(EntryOutcome::Refused) => {
+                deadletter_changed = true;
+                record_deadletter(
+                    config,
+                    &mut deadletter,
+                    &entry,
+                    site_url,
+                    "Model refused to summarize".to_string(),
+                    true,
+                    now,
+                )
+                .await;
+            }
+            Ok(outcome) => {
+                deadletter_changed |= deadletter.record_success(entry.id);
+
+                if let Some(site_url) = &site_url {
+                    let h = health.entry(site_url.clone()).or_default();
+                    let before = h.clone();
+                    if !h.is_disabled(now) {
+                        h.record_success();
+                    }
+                    let started_skipping = match outcome {
+                        EntryOutcome::Summarized(_) => {
+                            h.record_summary();
+                            false
+                        }
+                        EntryOutcome::Empty => h.record_empty(config.empty_feed_streak),
+                        _ => false,
+                    };
+                    if started_skipping {
+                        console_log!(
+                            "Skipping feed {} after {} empty summaries in a row",
+                            site_url,
+                            config.empty_feed_streak
+                        );
+                    }
+                    health_changed |= *h != before;
+                }
+            }
+            Err(e) if outage => {
+                console_warn!("Failed to process entry {}: {}", entry.id, e);
+            }
+            Err(e) => {
+                console_warn!("Failed to process entry {}: {}", entry.id, e);
+
+                if let (Some(site_url), true) = (&site_url, e.is::<output::Rejected>()) {
+                    let h = health.entry(site_url.clone()).or_default();
+                    if h.record_empty(config.empty_feed_streak) {
+                        console_log!(
+                            "Skipping feed {} after {} rejected summaries in a row",
+                            site_url,
+                            config.empty_feed_streak
+                        );
+                    }
+                }
+
+                deadletter_changed = true;
+                record_deadletter(
+                    config,
+                    &mut deadletter,
+                    &entry,
+                    site_url.clone(),
+                    e.to_string(),
+                    false,
+                    now,
+                )
+                .await;
+
+                if let Some(site_url) = site_url {
+                    let h = health.entry(site_url.clone()).or_default();
+                    if h.record_failure(now, config.feed_error_streak, config.feed_cooldown_ms) {
+                        console_log!(
+                            "Auto-disabled feed {} after {} consecutive failures",
+                            site_url,
+                            config.feed_error_streak
+                        );
+                    }
+                    health_changed = true;
+                }
+            }
+        }
+    }
+
+    if health_changed {
+        health.retain(|_, h| *h != FeedHealth::default());
+        kv_put_json(kv, FEED_HEALTH_KEY, &health).await;
+    }
+    if deadletter_changed {
+        kv_put_json(kv, DEADLETTER_KEY, &deadletter).await;
+    }
+    report
+}
+
+// 配置的读取来源，运行时是 Worker 的 Env，测试中换成固定的变量表
+trait EnvVars {
+    fn var(&self, name: &str) -> Option<String>;
+    fn secret(&self, name: &str) -> Option<String>;
+}
+
+impl EnvVars for Env {
+    fn var(&self, name: &str) -> Option<String> {
+        Env::var(self, name).ok().map(|v| v.to_string())
+    }
+
+    fn secret(&self, name: &str) -> Option<String> {
+        Env::secret(self, name).ok().map(|v| v.to_string())
+    }
+}
+
+// KV 中的配置覆盖层优先于环境变量
+fn env_var(env: &dyn EnvVars, name: &str) -> Option<String> {
+    overlay::lookup(name)
+        .or_else(|| env.var(name))
+        .filter(|v| !v.trim().is_empty())
+}
+
+// 敏感配置优先从加密的 secret 读取，未设置时回退到普通变量
+fn env_secret(env: &dyn EnvVars, name: &str) -> Option<String> {
+    env.secret(name)
+        .filter(|v| !v.trim().is_empty())
+        .or_else(|| env_var(env, name))
+}
+
+fn env_parse<T: FromStr>(env: &dyn EnvVars, name: &str, default: T) -> T {
+    env_var(env, name)
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+// FEED_SETTINGS 是以 site_url 为键的 JSON 对象，用于按订阅源覆盖设置
+fn parse_feed_overrides(env: &dyn EnvVars) -> HashMap<String, FeedOverrides> {
+    let Some(raw) = env_var(env, "FEED_SETTINGS") else {
+        return HashMap::new();
+    };
+
+    match serde_json::from_str::<HashMap<String, FeedOverrides>>(&raw) {
+        Ok(overrides) => overrides
+            .into_iter()
+            .map(|(site_url, o)| (normalize_site_url(&site_url), o))
+            .collect(),
+        Err(e) => {
+            console_warn!("Ignoring invalid FEED_SETTINGS: {}", e);
+            HashMap::new()
+        }
+    }
+}
+
+// FEED_FEATURES 的键是订阅源匹配规则，按具体程度排序，越具体的规则越后应用
+fn parse_feed_features(env: &dyn EnvVars) -> Vec<(FeedPattern, FeatureOverrides)> {
+    let Some(raw) = env_var(env, "FEED_FEATURES") else {
+        return Vec::new();
+    };
+
+    match serde_json::from_str::<HashMap<String, FeatureOverrides>>(&raw) {
+        Ok(features) => {
+            let mut rules: Vec<(String, FeatureOverrides)> = features.into_iter().collect();
+            rules.sort_by(|(a, _), (b, _)| a.cmp(b));
+            let mut rules: Vec<(FeedPattern, FeatureOverrides)> = rules
+                .into_iter()
+                .map(|(pattern, features)| (FeedPattern::new(&pattern), features))
+                .collect();
+            rules.sort_by_key(|(pattern, _)| pattern.specificity());
+            rules
+        }
+        Err(e) => {
+            console_warn!("Ignoring invalid FEED_FEATURES: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+fn default_prompt(env: &dyn EnvVars) -> String {
+    let name = env_var(env, "PROMPT_PRESET").unwrap_or(prompts::DEFAULT_PRESET.to_string());
+    match prompts::preset(&name) {
+        Ok(preset) => format!("preset:{}", preset.name),
+        Err(e) => {
+            console_warn!("{}, falling back to {}", e, prompts::DEFAULT_PRESET);
+            format!("preset:{}", prompts::DEFAULT_PRESET)
+        }
+    }
+}
+
+const DEFAULT_USER_AGENT: &str = concat!("miniflux-ai/", env!("CARGO_PKG_VERSION"));
+
+// MINIFLUX_EXTRA_HEADERS 是 JSON 对象，例如 Cloudflare Access 的服务令牌
+fn parse_extra_headers(env: &dyn EnvVars) -> HeaderMap {
+    let (headers, errors) =
+        extra_headers(&env_secret(env, "MINIFLUX_EXTRA_HEADERS").unwrap_or_default());
+    for error in errors {
+        console_warn!("{}", error);
+    }
+    headers
+}
+
+// 无效的 JSON 或请求头不会发送，连同原因一起返回
+fn extra_headers(raw: &str) -> (HeaderMap, Vec<String>) {
+    let mut headers = HeaderMap::new();
+    if raw.trim().is_empty() {
+        return (headers, Vec::new());
+    }
+
+    let extra = match serde_json::from_str::<HashMap<String, String>>(raw) {
+        Ok(extra) => extra,
+        Err(e) => {
+            return (
+                headers,
+                vec![format!("Ignoring invalid MINIFLUX_EXTRA_HEADERS: {}", e)],
+            )
+        }
+    };
+    let mut errors = Vec::new();
+    for (name, value) in extra {
+        match (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            (Ok(name), Ok(value)) => {
+                headers.insert(name, value);
+            }
+            _ => errors.push(format!(
+                "Ignoring invalid header in MINIFLUX_EXTRA_HEADERS: {}",
+                name
+            )),
+        }
+    }
+    errors.sort();
+    (headers, errors)
+}
+
+// 所有出站请求都带上 User-Agent，Miniflux 请求额外带上自定义请求头
+fn build_client(user_agent: &str, headers: HeaderMap, exec: &Rc<dyn HttpExec>) -> HttpClient {
+    HttpClient::new(user_agent, headers, exec.clone())
+}
+
+// Miniflux 的文章状态，removed 的文章始终不处理
+const ENTRY_STATUSES: &[&str] = &["unread", "read"];
+
+fn parse_entry_statuses(env: &dyn EnvVars) -> Vec<String> {
+    let (statuses, unknown) = entry_statuses(&env_var(env, "ENTRY_STATUSES").unwrap_or_default());
+    for item in unknown {
+        console_warn!("ENTRY_STATUSES: unknown status {}", item);
+    }
+    statuses
+}
+
+// 逗号分隔的状态列表，同时返回无法识别的项，全部无效时只处理未读文章
+fn entry_statuses(raw: &str) -> (Vec<String>, Vec<String>) {
+    let (mut statuses, mut unknown) = (Vec::new(), Vec::new());
+    for item in raw
+        .split(',')
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty())
+    {
+        if !ENTRY_STATUSES.contains(&item.as_str()) {
+            unknown.push(item);
+        } else if !statuses.contains(&item) {
+            statuses.push(item);
+        }
+    }
+    if statuses.is_empty() {
+        statuses.push("unread".to_string());
+    }
+    (statuses, unknown)
+}
+
+// 刷新订阅源后重新出现的已读文章等，webhook 中没有状态时不检查
+fn has_allowed_status(statuses: &[String], status: &str) -> bool {
+    status.is_empty() || statuses.iter().any(|s| s == status)
+}
+
+// 逗号分隔的跳过原因列表，默认为空，不标记任何文章
+fn parse_auto_read_skipped(env: &dyn EnvVars) -> Vec<SkipReason> {
+    let mut reasons = Vec::new();
+    for item in env_var(env, "AUTO_READ_SKIPPED")
+        .unwrap_or_default()
+        .split(',')
+        .filter(|s| !s.trim().is_empty())
+    {
+        match auto_read::reason(item) {
+            Ok(reason) if !reasons.contains(&reason) => reasons.push(reason),
+            Ok(_) => {}
+            Err(e) => console_warn!("AUTO_READ_SKIPPED: {}", e),
+        }
+    }
+    reasons
+}
+
+// 逗号分隔的类别列表，忽略无法识别的项
+fn parse_summarize_categories(env: &dyn EnvVars) -> Vec<Category> {
+    let mut categories = Vec::new();
+    for item in env_var(env, "SUMMARIZE_CATEGORIES")
+        .unwrap_or_default()
+        .split(',')
+        .filter(|s| !s.trim().is_empty())
+    {
+        match item.parse() {
+            Ok(category) if !categories.contains(&category) => categories.push(category),
+            Ok(_) => {}
+            Err(e) => console_warn!("SUMMARIZE_CATEGORIES: {}", e),
+        }
+    }
+    categories
+}
+
+// CATEGORY_MODELS 为分类名称到模型的 JSON 对象，分类名称不区分大小写
+fn parse_category_models(env: &dyn EnvVars) -> HashMap<String, String> {
+    let Some(raw) = env_var(env, "CATEGORY_MODELS") else {
+        return HashMap::new();
+    };
+    match serde_json::from_str::<HashMap<String, String>>(&raw) {
+        Ok(models) => models
+            .into_iter()
+            .filter(|(_, model)| !model.trim().is_empty())
+            .map(|(category, model)| (category.trim().to_lowercase(), model.trim().to_string()))
+            .collect(),
+        Err(e) => {
+            console_error!("Ignoring invalid CATEGORY_MODELS: {}", e);
+            HashMap::new()
+        }
+    }
+}
+
+// CATEGORY_STYLES 为分类名称到语气的 JSON 对象，例如 `{"News": "neutral", "Blogs": "casual"}`
+fn parse_category_styles(env: &dyn EnvVars) -> HashMap<String, String> {
+    let Some(raw) = env_var(env, "CATEGORY_STYLES") else {
+        return HashMap::new();
+    };
+    match serde_json::from_str::<HashMap<String, String>>(&raw) {
+        Ok(styles) => styles
+            .into_iter()
+            .filter(|(_, style)| !style.trim().is_empty())
+            .map(|(category, style)| (category.trim().to_lowercase(), style.trim().to_string()))
+            .collect(),
+        Err(e) => {
+            console_error!("Ignoring invalid CATEGORY_STYLES: {}", e);
+            HashMap::new()
+        }
+    }
+}
+
+// LENGTH_TIERS 为 JSON 数组，例如 `[{"max_words": 800, "preset": "concise_zh"}]`
+fn parse_length_tiers(env: &dyn EnvVars) -> Vec<LengthTier> {
+    let Some(raw) = env_var(env, "LENGTH_TIERS") else {
+        return Vec::new();
+    };
+    tiers::parse(&raw).unwrap_or_else(|e| {
+        console_error!("Ignoring invalid LENGTH_TIERS: {}", e);
+        Vec::new()
+    })
+}
+
+fn parse_summary_length(env: &dyn EnvVars) -> Option<SummaryLength> {
+    let number = |name| {
+        env_var(env, name).and_then(|v| {
+            v.trim()
+                .parse()
+                .map_err(|_| console_error!("Ignoring invalid {}: {}", name, v))
+                .ok()
+        })
+    };
+    length::resolve(
+        number("SUMMARY_MAX_WORDS").map(|words: u32| words as usize),
+        number("SUMMARY_READING_SECONDS"),
+    )
+    .unwrap_or_else(|e| {
+        console_error!("Ignoring summary length: {}", e);
+        None
+    })
+}
+
+// REFUSAL_PATTERNS 为 JSON 字符串数组，追加到内置的拒答开头中
+fn parse_refusal_patterns(env: &dyn EnvVars) -> Vec<String> {
+    let Some(raw) = env_var(env, "REFUSAL_PATTERNS") else {
+        return Vec::new();
+    };
+    serde_json::from_str(&raw).unwrap_or_else(|e| {
+        console_error!("Ignoring invalid REFUSAL_PATTERNS: {}", e);
+        Vec::new()
+    })
+}
+
+// PAYWALL_PHRASES 为 JSON 字符串数组，追加到内置的付费墙提示中
+fn parse_paywall_phrases(env: &dyn EnvVars) -> Vec<String> {
+    let Some(raw) = env_var(env, "PAYWALL_PHRASES") else {
+        return Vec::new();
+    };
+    serde_json::from_str(&raw).unwrap_or_else(|e| {
+        console_error!("Ignoring invalid PAYWALL_PHRASES: {}", e);
+        Vec::new()
+    })
+}
+
+// SUMMARY_REPLACE 为 JSON 字符串数组，正则在这里一次性编译
+fn parse_summary_replace(env: &dyn EnvVars) -> SummaryRewriter {
+    let Some(raw) = env_var(env, "SUMMARY_REPLACE") else {
+        return SummaryRewriter::default();
+    };
+    let rewriter = serde_json::from_str::<Vec<String>>(&raw)
+        .map_err(|e| e.to_string())
+        .and_then(|rules| SummaryRewriter::new(&rules));
+    match rewriter {
+        Ok(rewriter) => rewriter,
+        Err(e) => {
+            console_error!("Ignoring invalid SUMMARY_REPLACE: {}", e);
+            SummaryRewriter::default()
+        }
+    }
+}
+
+// BANNED_PHRASES 为 JSON 字符串数组，`re:` 前缀为正则
+fn parse_banned_phrases(env: &dyn EnvVars) -> PhraseFilter {
+    let Some(raw) = env_var(env, "BANNED_PHRASES") else {
+        return PhraseFilter::default();
+    };
+    let filter = serde_json::from_str::<Vec<String>>(&raw)
+        .map_err(|e| e.to_string())
+        .and_then(|phrases| PhraseFilter::new(&phrases));
+    match filter {
+        Ok(filter) => filter,
+        Err(e) => {
+            console_error!("Ignoring invalid BANNED_PHRASES: {}", e);
+            PhraseFilter::default()
+        }
+    }
+}
+
+// SKIP_CONTENT_PATTERNS 为 JSON 字符串数组，正则在这里一次性编译
+fn parse_skip_content(env: &dyn EnvVars) -> ContentFilter {
+    let Some(raw) = env_var(env, "SKIP_CONTENT_PATTERNS") else {
+        return ContentFilter::default();
+    };
+    let filter = serde_json::from_str::<Vec<String>>(&raw)
+        .map_err(|e| e.to_string())
+        .and_then(|patterns| ContentFilter::new(&patterns));
+    match filter {
+        Ok(filter) => filter,
+        Err(e) => {
+            console_error!("Ignoring invalid SKIP_CONTENT_PATTERNS: {}", e);
+            ContentFilter::default()
+        }
+    }
+}
+
+fn parse_notifiers(env: &dyn EnvVars) -> Vec<Notifier> {
+    #[allow(unused_mut)]
+    let mut notifiers = Vec::new();
+    #[cfg(feature = "notify-slack")]
+    if let Some(webhook_url) = env_secret(env, "ALERT_SLACK_WEBHOOK_URL") {
+        notifiers.push(Notifier::Slack { webhook_url });
+    }
+    #[cfg(not(feature = "notify-slack"))]
+    warn_disabled_notifier(env, "ALERT_SLACK_WEBHOOK_URL", "notify-slack");
+
+    #[cfg(feature = "notify-discord")]
+    if let Some(webhook_url) = env_secret(env, "ALERT_DISCORD_WEBHOOK_URL") {
+        notifiers.push(Notifier::Discord { webhook_url });
+    }
+    #[cfg(not(feature = "notify-discord"))]
+    warn_disabled_notifier(env, "ALERT_DISCORD_WEBHOOK_URL", "notify-discord");
+
+    #[cfg(feature = "notify-telegram")]
+    if let (Some(bot_token), Some(chat_id)) = (
+        env_secret(env, "ALERT_TELEGRAM_BOT_TOKEN"),
+        env_var(env, "ALERT_TELEGRAM_CHAT_ID"),
+    ) {
+        notifiers.push(Notifier::Telegram { bot_token, chat_id });
+    }
+    #[cfg(not(feature = "notify-telegram"))]
+    warn_disabled_notifier(env, "ALERT_TELEGRAM_BOT_TOKEN", "notify-telegram");
+    notifiers
+}
+
+// 配置了通知渠道但编译时没有启用对应功能
+#[cfg(not(all(
+    feature = "notify-slack",
+    feature = "notify-discord",
+    feature = "notify-telegram"
+)))]
+fn warn_disabled_notifier(env: &dyn EnvVars, name: &str, feature: &str) {
+    if env_secret(env, name).is_some() {
+        console_warn!("Ignoring {}, built without the {} feature", name, feature);
+    }
+}
+
+// KV 中保存的配置覆盖层，只包含可调整的设置，不含凭据
+const CONFIG_KEY: &str = "config";
+// 写入新的覆盖层前保存上一份，当前覆盖层无效时使用
+const CONFIG_ROLLBACK_KEY: &str = "config_rollback";
+
+// 本次运行使用的覆盖层，source 为 none、config 或 rollback
+#[derive(Debug, Serialize)]
+struct OverlayState {
+    source: &'static str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<String>,
+    #[serde(skip)]
+    overlay: ConfigOverlay,
+}
+
+impl Default for OverlayState {
+    fn default() -> Self {
+        OverlayState {
+            source: "none",
+            errors: Vec::new(),
+            overlay: ConfigOverlay::default(),
+        }
+    }
+}
+
+// 覆盖层无效或读取失败时记录错误，退回到上一份有效的覆盖层
+async fn load_overlay(env: &Env) -> OverlayState {
+    let mut state = OverlayState::default();
+    let Ok(kv) = env.kv("KV") else {
+        return state;
+    };
+    match kv.get(CONFIG_KEY).text().await {
+        Ok(None) => return state,
+        Ok(Some(raw)) => match overlay::parse(&raw) {
+            Ok(overlay) => {
+                state.source = "config";
+                state.overlay = overlay;
+                return state;
+            }
+            Err(errors) => state.errors = errors,
+        },
+        Err(e) => state.errors = vec![format!("failed to load: {:?}", e)],
+    }
+    console_error!(
+        "Ignoring invalid CONFIG overlay: {}",
+        state.errors.join("; ")
+    );
+
+    match kv.get(CONFIG_ROLLBACK_KEY).text().await {
+        Ok(Some(raw)) => match overlay::parse(&raw) {
+            Ok(overlay) => {
+                console_warn!("Using the previous CONFIG overlay");
+                state.source = "rollback";
+                state.overlay = overlay;
+            }
+            Err(errors) => {
+                console_error!("Ignoring invalid rollback overlay: {}", errors.join("; "))
+            }
+        },
+        Ok(None) => {}
+        Err(e) => console_warn!("Failed to load rollback overlay: {:?}", e),
+    }
+    state
+}
+
+async fn load_config(env: &Env) -> Config {
+    let state = load_overlay(env).await;
+    let mut config = overlay::with_overlay(&state.overlay, || build_config(env));
+    config.overlay = state;
+    config
+}
+
+fn build_config(env: &Env) -> Config {
+    config_from_vars(
+        env,
+        Rc::new(ReqwestExec),
+        env.kv("KV").ok(),
+        WorkersAi::from_env(env, "AI"),
+    )
+}
+
+// 绑定和实际发送请求的执行层由调用方传入，测试中不依赖 Worker 运行时
+fn config_from_vars(
+    env: &dyn EnvVars,
+    transport: Rc<dyn HttpExec>,
+    kv: Option<KvStore>,
+    workers_ai: Option<WorkersAi>,
+) -> Config {
+    let user_agent = env_var(env, "HTTP_USER_AGENT").unwrap_or(DEFAULT_USER_AGENT.to_string());
+    let username = env_var(env, "MINIFLUX_USERNAME").unwrap_or_default();
+    let api_token = env_secret(env, "MINIFLUX_API_TOKEN");
+    if api_token.is_none() {
+        if let Err(e) = auth::check_username(&username) {
+            console_error!("{}", e);
+        }
+    }
+    let http_recorder = Rc::new(Recording::new(transport));
+    let exec: Rc<dyn HttpExec> = Rc::new(Budgeted::new(
+        http_recorder.clone(),
+        env_var(env, "SUBREQUEST_BUDGET").and_then(|v| v.trim().parse().ok()),
+    ));
+
+    // 按客户端分别开启请求体压缩
+    let compress: Vec<String> = env_var(env, "COMPRESS_REQUESTS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|s| s.trim().to_lowercase())
+        .collect();
+    let compress_threshold = env_parse(env, "COMPRESS_THRESHOLD_BYTES", 8 * 1024);
+    #[cfg(feature = "compression")]
+    let client_exec = |name: &str| -> Rc<dyn HttpExec> {
+        if compress.iter().any(|c| c == name) {
+            Rc::new(Compressed::new(exec.clone(), compress_threshold))
+        } else {
+            exec.clone()
+        }
+    };
+    #[cfg(not(feature = "compression"))]
+    let client_exec = |name: &str| -> Rc<dyn HttpExec> {
+        if compress.iter().any(|c| c == name) {
+            console_warn!(
+                "Ignoring COMPRESS_REQUESTS for {}, built without the compression feature",
+                name
+            );
+        }
+        let _ = compress_threshold;
+        exec.clone()
+    };
+
+    // TITLE_SUFFIX 和 TITLE_HINT 共用同一次结构化请求，同时开启时使用 TITLE_SUFFIX
+    let title_hint = if env_parse(env, "TITLE_SUFFIX", false) {
+        Some(HintKind::Tldr)
+    } else {
+        env_parse(env, "TITLE_HINT", false).then_some(HintKind::Hook)
+    };
+
+    Config {
+        whitelist: env_var(env, "WHITELIST_URL")
+            .unwrap_or_default()
+            .split(',')
+            .filter(|s| !s.trim().is_empty())
+            .map(normalize_site_url)
+            .collect(),
+        http_client: build_client(&user_agent, HeaderMap::new(), &exec),
+        http_recorder,
+        openai: OpenAi {
+            client: build_client(&user_agent, HeaderMap::new(), &client_exec("openai")),
+            // 使用 Workers AI 时不需要
+            url: env_var(env, "OPENAI_URL").unwrap_or_default(),
+            token: env_secret(env, "OPENAI_TOKEN").unwrap_or_default(),
+            content_path: env_var(env, "RESPONSE_CONTENT_PATH"),
+            stream: env_parse(env, "STREAM", false),
+        },
+        provider: env_parse(env, "PROVIDER", Provider::OpenAi),
+        workers_ai,
+        miniflux: Miniflux {
+            client: build_client(
+                &user_agent,
+                parse_extra_headers(env),
+                &client_exec("miniflux"),
+            ),
+            url: env.var("MINIFLUX_URL").unwrap(),
+            username,
+            password: env_secret(env, "MINIFLUX_PASSWORD").unwrap_or_default(),
+            api_token,
+        },
+        kv_batch: kv.clone().map(KvBatch::new),
+        kv,
+        overlay: OverlayState::default(),
+        feed_error_streak: env_parse(env, "FEED_ERROR_STREAK", 10),
+        feed_cooldown_ms: env_parse(env, "FEED_COOLDOWN_HOURS", 24) * 60 * 60 * 1000,
+        deadletter_threshold: env_parse(env, "DEADLETTER_AFTER", 3),
+        feed_defaults: FeedSettings {
+            prompt: default_prompt(env),
+            prompt_overridden: false,
+            fetch_full_content: env_parse(env, "FETCH_FULL_CONTENT", false),
+            summarize_comments: env_parse(env, "SUMMARIZE_COMMENTS", false),
+            extract_entities: env_parse(env, "EXTRACT_ENTITIES", false),
+            sticky_summary: env_parse(env, "STICKY_SUMMARY", false),
+            feed_context: env_parse(env, "FEED_CONTEXT", false),
+            context_hints: env_parse(env, "CONTEXT_HINTS", 0),
+            translate_body: env_parse(env, "TRANSLATE_BODY", false),
+            aggregation: env_parse(
+                env,
+                "AGGREGATION_POLICY",
+                AggregationPolicy::SummarizeAnyway,
+            ),
+            model: env_var(env, "OPENAI_MODEL").unwrap(),
+            style: None,
+            features: Features {
+                summarize: env_parse(env, "SUMMARIZE", true),
+                tags: env_parse(env, "TICKER_TAGS", false),
+                translate_title: env_parse(env, "TRANSLATE_TITLE", false),
+                score: env_parse(env, "SCORE", false),
+            },
+        },
+        feed_overrides: parse_feed_overrides(env),
+        feed_features: parse_feed_features(env),
+        max_entries_per_run: env_var(env, "MAX_ENTRIES_PER_RUN")
+            .and_then(|v| v.trim().parse().ok()),
+        priority_strategy: env_parse(env, "PRIORITY_STRATEGY", PriorityStrategy::NewestFirst),
+        summary_target: env_parse(env, "SUMMARY_TARGET", SummaryTarget::Content),
+        block_style: if env_parse(env, "PLAINTEXT_SUMMARY", false) {
+            BlockStyle::Plaintext
+        } else {
+            BlockStyle::Pre
+        },
+        max_concurrency: env_parse(env, "MAX_CONCURRENCY", 5).max(1),
+        safe_mode: env_parse(env, "SAFE_MODE", false),
+        verify_before_update: env_parse(env, "VERIFY_BEFORE_UPDATE", false),
+        write_delay_ms: env_parse(env, "WRITE_DELAY_MS", 0),
+        verify_after_update: env_parse(env, "VERIFY_AFTER_UPDATE", false),
+        search_query: env_var(env, "SEARCH_QUERY"),
+        skip_content: parse_skip_content(env),
+        summary_replace: parse_summary_replace(env),
+        banned_phrases: parse_banned_phrases(env),
+        normalize_typography: env_parse(env, "NORMALIZE_TYPOGRAPHY", false),
+        context_hints_max_tokens: env_parse(env, "CONTEXT_HINTS_MAX_TOKENS", 500),
+        feed_description_context: env_parse(env, "FEED_DESCRIPTION_CONTEXT", false),
+        feed_descriptions: Shared::new(HashMap::new()),
+        log_feed: env_parse(env, "LOG_FEED", false),
+        tag_min_confidence: env_var(env, "TAG_MIN_CONFIDENCE")
+            .and_then(|v| v.trim().parse::<f32>().ok())
+            .map(|min| min.clamp(0.0, 1.0)),
+        section_citations: env_parse(env, "SECTION_CITATIONS", false),
+        include_alt_text: env_parse(env, "INCLUDE_ALT_TEXT", false),
+        stop_at_processed: env_parse(env, "STOP_AT_PROCESSED", false),
+        explicit_entries: false,
+        fallback_extractive: env_parse(env, "FALLBACK_EXTRACTIVE", false),
+        fallback_models: env_var(env, "FALLBACK_MODELS")
+            .map(|raw| fallback::parse_chain(&raw))
+            .unwrap_or_default(),
+        model_limiter: ModelLimiter::default(),
+        preserve_reading_time: env_parse(env, "PRESERVE_READING_TIME", false),
+        queue_max_deliveries: env_parse(env, "QUEUE_MAX_DELIVERIES", 5).max(1),
+        page_size: env_parse(env, "PAGE_SIZE", MAX_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE),
+        entry_statuses: parse_entry_statuses(env),
+        auto_read_skipped: parse_auto_read_skipped(env),
+        heartbeat_every: env_parse(env, "HEARTBEAT_EVERY", 10),
+        unavailable_notice: env_var(env, "UNAVAILABLE_NOTICE"),
+        category_id: env_var(env, "CATEGORY_ID").and_then(|v| v.trim().parse().ok()),
+        fetch_strategy: env_parse(env, "FETCH_STRATEGY", FetchStrategy::Global),
+        truncated_retry_page_size: env_var(env, "TRUNCATED_RETRY_PAGE_SIZE")
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(env_parse(env, "PAGE_SIZE", MAX_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE) / 2)
+            .min(MAX_PAGE_SIZE),
+        llm_cache: env_var(env, "LLM_CACHE_TTL_SECONDS")
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .filter(|&ttl| ttl > 0 && !env_parse(env, "BYPASS_LLM_CACHE", false))
+            .map(|ttl_seconds| LlmCache {
+                ttl_seconds,
+                max_bytes: env_parse(env, "LLM_CACHE_MAX_BYTES", 64 * 1024),
+            }),
+        health: HealthPolicy {
+            max_age_hours: env_parse(env, "HEALTH_MAX_AGE_HOURS", 24),
+            grace_hours: env_parse(env, "HEALTH_GRACE_HOURS", 24),
+        },
+        dry_run: env_parse(env, "DRY_RUN", false),
+        min_content_chars: env_parse(env, "MIN_CONTENT_CHARS", 0),
+        aggregation_quote_ratio: env_parse(env, "AGGREGATION_QUOTE_RATIO", 0.6),
+        aggregation_link_density: env_parse(env, "AGGREGATION_LINK_DENSITY", 0.5),
+        media_template: env_parse(env, "MEDIA_LINE", true).then(|| {
+            env_var(env, "MEDIA_LINE_TEMPLATE")
+                .unwrap_or(template::DEFAULT_MEDIA_TEMPLATE.to_string())
+        }),
+        empty_feed_streak: env_parse(env, "EMPTY_FEED_STREAK", 5),
+        summarize_categories: parse_summarize_categories(env),
+        category_models: parse_category_models(env),
+        category_styles: parse_category_styles(env),
+        settings_memo: Shared::new(HashMap::new()),
+        content_format: env_parse(env, "CONTENT_FORMAT", ContentFormat::Auto),
+        refusal: RefusalDetector::new(&parse_refusal_patterns(env)),
+        paywall: PaywallDetector::new(
+            env_parse(env, "PAYWALL_MAX_CHARS", 0),
+            &parse_paywall_phrases(env),
+        ),
+        paywall_notice: env_var(env, "PAYWALL_NOTICE")
+            .filter(|notice| !notice.trim().is_empty())
+            .unwrap_or(paywall::DEFAULT_NOTICE.to_string()),
+        retry_on_refusal: env_parse(env, "RETRY_ON_REFUSAL", false),
+        dedup_urls: env_parse(env, "DEDUP_BY_URL", false),
+        sample_rate: env_parse(env, "SAMPLE_RATE", 1.0_f64).clamp(0.0, 1.0),
+        sample_salt: env_var(env, "SAMPLE_SALT").unwrap_or_default(),
+        empty_feed_reset: filter::parse_patterns(
+            &env_var(env, "EMPTY_FEED_RESET").unwrap_or_default(),
+        ),
+        debug_capture: env_parse(env, "DEBUG_CAPTURE", false).then(|| DebugCaptureConfig {
+            secrets: [
+                "OPENAI_TOKEN",
+                "MINIFLUX_API_TOKEN",
+                "MINIFLUX_PASSWORD",
+                "MINIFLUX_WEBHOOK_SECRET",
+                "ADMIN_TOKEN",
+                "DIGEST_WEBHOOK_SECRET",
+            ]
+            .iter()
+            .filter_map(|name| env_secret(env, name))
+            .collect(),
+            sample_percent: env_parse(env, "DEBUG_CAPTURE_SAMPLE_PERCENT", 0).min(100),
+        }),
+        translation_tokens: TokenBudget::new(None),
+        length_tiers: parse_length_tiers(env),
+        summary_length: parse_summary_length(env),
+        tier_counts: Shared::new(BTreeMap::new()),
+        canary: parse_canary(env),
+        canary_records: Shared::new(Vec::new()),
+        token_budget: TokenBudget::new(
+            env_var(env, "MAX_TOKENS_PER_RUN").and_then(|v| v.trim().parse().ok()),
+        ),
+        alert: AlertConfig {
+            notifiers: parse_notifiers(env),
+            failure_ratio: env_parse(env, "ALERT_FAILURE_RATIO", 0.5),
+            window_ms: env_parse(env, "ALERT_WINDOW_HOURS", 6) * 60 * 60 * 1000,
+        },
+        title_hint,
+        title_hint_separator: env_var(env, "TITLE_HINT_SEPARATOR").unwrap_or(
+            title_hint
+                .unwrap_or(HintKind::Hook)
+                .default_separator()
+                .to_string(),
+        ),
+        article_accept_language: env_var(env, "ARTICLE_ACCEPT_LANGUAGE"),
+        max_inflight_webhooks: env_parse(env, "MAX_INFLIGHT_WEBHOOKS", 8),
+        max_model_connections: env_var(env, "MAX_MODEL_CONNECTIONS")
+            .and_then(|v| v.trim().parse().ok())
+            .map(|limit: usize| limit.max(1)),
+        max_token_chars: env_parse(env, "LONG_TOKEN_THRESHOLD", 200).max(1),
+        summary_language: env_var(env, "SUMMARY_LANGUAGE"),
+        summary_dir: env_parse(env, "SUMMARY_DIR", TextDirection::Auto),
+        default_language: env_var(env, "DEFAULT_LANGUAGE").unwrap_or("Chinese".to_string()),
+        digest_webhook: env_var(env, "DIGEST_WEBHOOK_URL").map(|url| DigestWebhook {
+            url,
+            secret: env_secret(env, "DIGEST_WEBHOOK_SECRET"),
+        }),
+        feed_priority: filter::parse_patterns(&env_var(env, "FEED_PRIORITY").unwrap_or_default()),
+    }
+}
+
+#[event(scheduled)]
+async fn scheduled(_event: ScheduledEvent, env: Env, _ctx: ScheduleContext) {
+    let config = load_config(&env).await;
+    let started_at = Date::now().as_millis();
+
+    // 查询未读文章，失败通常是配置或认证问题，需要告警
+    let entries = match fetch_entries(&config).await {
+        Ok(entries) => entries,
+        Err(e) => {
+            console_warn!("Failed to fetch entries from Miniflux: {}", e);
+            send_alert(
+                &config,
+                &format!("miniflux-ai: failed to fetch entries from Miniflux: {}", e),
+            )
+            .await;
+            record_run_log(&config, started_at, feed::RunLog::failed(e.to_string())).await;
+            return;
+        }
+    };
+
+    let report = process_entries(&config, entries.entries).await;
+    mark_skipped_read(&config, &report.auto_read).await;
+    record_run_log(&config, started_at, feed::RunLog::from_report(&report)).await;
+}
+
+// 每次定时任务的结果，LOG_FEED 开启时通过 /logfeed.xml 订阅
+const RUN_LOG_DOC: PackedDoc = PackedDoc {
+    key: "run_log",
+    max_age_ms: 14 * 24 * 60 * 60 * 1000,
+};
+
+async fn record_run_log(config: &Config, started_at: u64, log: feed::RunLog) {
+    let (true, Some(kv_batch)) = (config.log_feed, &config.kv_batch) else {
+        return;
+    };
+    kv_batch.put(RUN_LOG_DOC, started_at.to_string(), &log, started_at);
+    kv_batch.flush(Date::now().as_millis()).await;
+}
+
+// 分批标记为已读，某一批失败时只记录日志，下次运行会再次跳过并重试
+async fn mark_skipped_read(config: &Config, ids: &[u64]) {
+    if ids.is_empty() {
+        return;
+    }
+    if config.dry_run {
+        console_log!("Would mark {} skipped entries as read", ids.len());
+        return;
+    }
+    let mut marked = 0;
+    for chunk in ids.chunks(auto_read::MAX_IDS_PER_REQUEST) {
+        match set_entries_status(&config.miniflux, chunk, "read").await {
+            Ok(()) => marked += chunk.len(),
+            Err(e) => console_warn!(
+                "Failed to mark {} skipped entries as read: {}",
+                chunk.len(),
+                e
+            ),
+        }
+    }
+    console_log!("Marked {} skipped entries as read", marked);
+}
+
+// Cloudflare Queues 消费者，每条消息单独确认，失败的消息按队列配置的退避时间重新投递
+#[event(queue)]
+async fn queue(
+    batch: worker::MessageBatch<QueueMessage>,
+    env: Env,
+    _ctx: Context,
+) -> worker::Result<()> {
+    use worker::MessageExt as _;
+
+    let config = load_config(&env).await;
+    let messages = batch.messages()?;
+    let now = Date::now().as_millis();
+
+    let ids: Vec<u64> = messages.iter().map(|m| m.body().entry_id).collect();
+    let mut failed: HashMap<u64, String> = HashMap::new();
+    let mut entries = Vec::new();
+    for (id, lookup) in get_entries_by_ids(&config.miniflux, &ids, config.max_concurrency).await {
+        match lookup {
+            EntryLookup::Found(entry) => entries.push(*entry),
+            EntryLookup::Gone => {}
+            EntryLookup::Failed(e) => {
+                failed.insert(id, format!("Failed to fetch entry: {}", e));
+            }
+        }
+    }
+
+    // 和定时任务、Webhook 共用预算与并发限制
+    let report = process_entries(&config, entries).await;
+    failed.extend(report.retry);
+    let deferred = report.deferred_ids;
+
+    // 没有 KV 时无法统计投递次数，失败的消息交给队列自身的重试上限处理
+    let max_deliveries = match &config.kv {
+        Some(_) => config.queue_max_deliveries,
+        None => u32::MAX,
+    };
+    for message in &messages {
+        let id = message.body().entry_id;
+        let error = failed.get(&id);
+        let is_deferred = deferred.contains(&id);
+        let attempts = match (error, &config.kv_batch) {
+            (Some(_), Some(kv_batch)) if !is_deferred => {
+                kv_batch
+                    .get::<u32>(QUEUE_ATTEMPTS_DOC, &id.to_string())
+                    .await
+                    .unwrap_or(0)
+                    + 1
+            }
+            _ => 0,
+        };
+
+        match queue::disposition(error.is_some(), is_deferred, attempts, max_deliveries) {
+            Disposition::Ack => message.ack(),
+            Disposition::Defer => message.retry(),
+            Disposition::Retry => {
+                if let Some(kv_batch) = &config.kv_batch {
+                    kv_batch.put(QUEUE_ATTEMPTS_DOC, id.to_string(), &attempts, now);
+                }
+                message.retry();
+            }
+            Disposition::DeadLetter => {
+                console_warn!(
+                    "Entry {}: moved to the dead-letter list after {} deliveries",
+                    id,
+                    attempts
+                );
+                if let (Some(kv), Some(kv_batch)) = (&config.kv, &config.kv_batch) {
+                    let letter = DlqEntry {
+                        entry_id: id,
+                        attempts,
+                        error: error.cloned().unwrap_or_default(),
+                        failed_at: now,
+                    };
+                    kv_put_json(kv, &queue::dlq_key(id), &letter).await;
+                    kv_batch.put(QUEUE_ATTEMPTS_DOC, id.to_string(), &0u32, now);
+                }
+                message.ack();
+            }
+        }
+    }
+
+    if let Some(kv_batch) = &config.kv_batch {
+        kv_batch.flush(now).await;
+    }
+    Ok(())
+}
+
+fn is_admin_authorized(req: &Request, env: &Env) -> worker::Result<bool> {
+    let Some(token) = env_secret(env, "ADMIN_TOKEN") else {
+        return Ok(false);
+    };
+    let auth = req.headers().get("Authorization")?;
+    Ok(bearer_matches(auth.as_deref(), &token))
+}
+
+// 和 Webhook 签名一样使用常量时间比较，避免通过响应时间逐字节猜出令牌
+fn bearer_matches(authorization: Option<&str>, token: &str) -> bool {
+    authorization
+        .and_then(|auth| auth.strip_prefix("Bearer "))
+        .is_some_and(|given| signature::tokens_match(token, given))
+}
+
+fn query_token_matches(url: &reqwest::Url, token: &str) -> bool {
+    url.query_pairs()
+        .any(|(k, v)| k == "token" && signature::tokens_match(token, &v))
+}
+
+#[derive(Serialize)]
+struct DisabledFeed {
+    site_url: String,
+    disabled_until: u64,
+}
+
+async fn handle_admin(mut req: Request, env: Env) -> worker::Result<Response> {
+    if !is_admin_authorized(&req, &env)? {
+        return Response::error("Unauthorized", 401);
+    }
+
+    let mut config = load_config(&env).await;
+    let Some(kv) = config.kv.clone() else {
+        return Response::error("KV binding not configured", 501);
+    };
+    let kv = &kv;
+
+    match (req.method(), req.path().as_str()) {
+        (Method::Get, "/admin/disabled-feeds") => {
+            let now = Date::now().as_millis();
+            let health: HashMap<String, FeedHealth> = kv_get_json(kv, FEED_HEALTH_KEY).await;
+            let disabled: Vec<DisabledFeed> = health
+                .into_iter()
+                .filter(|(_, h)| h.is_disabled(now))
+                .map(|(site_url, h)| DisabledFeed {
+                    site_url,
+                    disabled_until: h.disabled_until.unwrap_or_default(),
+                })
+                .collect();
+            Response::from_json(&disabled)
+        }
+        (Method::Post, "/admin/disabled-feeds") => {
+            let mut health: HashMap<String, FeedHealth> = kv_get_json(kv, FEED_HEALTH_KEY).await;
+            health.retain(|_, h| h.disabled_until.is_none());
+            kv_put_json(kv, FEED_HEALTH_KEY, &health).await;
+            Response::ok("Disabled feeds cleared")
+        }
+        (Method::Get, "/admin/deadletter") => {
+            let deadletter: DeadLetterLog = kv_get_json(kv, DEADLETTER_KEY).await;
+            Response::from_json(&deadletter.entries)
+        }
+        (Method::Get, path) if path.starts_with("/admin/debug/") => {
+            let Ok(id) = path["/admin/debug/".len()..].parse::<u64>() else {
+                return Response::error("Invalid entry id", 400);
+            };
+            let kv_batch = KvBatch::new(kv.clone());
+            match kv_batch
+                .get::<DebugRecord>(DEBUG_DOC, &id.to_string())
+                .await
+            {
+                Some(record) => Response::from_json(&record),
+                None => Response::error("Not Found", 404),
+            }
+        }
+        (Method::Post, "/admin/resummarize") => {
+            let stale = req
+                .url()?
+                .query_pairs()
+                .any(|(k, v)| k == "stale" && v == "true");
+            if !stale {
+                return Response::error("Only ?stale=true is supported", 400);
+            }
+            let entries = match get_recent_entries(&config.miniflux, STALE_SCAN_LIMIT, 0).await {
+                Ok(response) => response.entries,
+                Err(e) => return Response::error(format!("Failed to fetch entries: {}", e), 502),
+            };
+
+            // 标记中的哈希和当前设置不同的摘要需要重新生成，没有哈希的旧摘要保持不变
+            let stale: Vec<Entry> = entries
+                .into_iter()
+                .filter_map(|mut entry| {
+                    let hash = template::parse_marker(&entry.content)?.hash?.to_string();
+                    let original = template::strip_summary_block(&entry.content)?.to_string();
+                    let (settings, _) = tiered_settings(
+                        &config,
+                        &config.feed_settings(&entry),
+                        &model_input(&config, &original),
+                    );
+                    if hash == settings_hash(&config, &settings) {
+                        return None;
+                    }
+                    entry.content = original;
+                    Some(entry)
+                })
+                .collect();
+            // 过期摘要可能属于已读文章或已不在白名单中的订阅源，都要重新生成
+            let count = stale.len();
+            config.explicit_entries = true;
+            let report = process_entries(&config, stale).await;
+            Response::from_json(&serde_json::json!({ "stale": count, "report": report }))
+        }
+        (Method::Get, "/admin/diag") => {
+            let now = Date::now().as_millis();
+            let run = diag_run(
+                config.health,
+                now,
+                kv.get(LAST_RUN_KEY).json().await,
+                kv.get(LAST_SUCCESS_KEY).json().await,
+                kv.get(HEALTH_FIRST_SEEN_KEY).json().await,
+            );
+            // 每项只读一次 KV，没有对应数据时为 null
+            let health: HashMap<String, FeedHealth> = kv_get_json(kv, FEED_HEALTH_KEY).await;
+            let deadletter: DeadLetterLog = kv_get_json(kv, DEADLETTER_KEY).await;
+            let retrying = match &config.kv_batch {
+                Some(kv_batch) => Some(
+                    kv_batch
+                        .entries::<u32>(QUEUE_ATTEMPTS_DOC)
+                        .await
+                        .iter()
+                        .filter(|(_, attempts, _)| *attempts > 0)
+                        .count(),
+                ),
+                None => None,
+            };
+            let dead_letters = kv
+                .list()
+                .prefix(queue::DLQ_PREFIX.to_string())
+                .execute()
+                .await
+                .ok()
+                .map(|list| list.keys.len());
+            let miniflux = match get_me(&config.miniflux).await {
+                Ok(user) => serde_json::json!({ "ok": true, "username": user.username }),
+                Err(e) => serde_json::json!({ "ok": false, "error": e.to_string() }),
+            };
+            Response::from_json(&serde_json::json!({
+                "last_run": run.last_run,
+                "health": run.health,
+                "miniflux": miniflux,
+                "config": config.overlay,
+                "budget": {
+                    "max_tokens_per_run": config.token_budget.limit(),
+                    "last_run_tokens": run.last_run_tokens,
+                },
+                "queue": {
+                    "retrying": retrying,
+                    "dead_letters": dead_letters,
+                },
+                // 并发限制的计数只在 isolate 内有效，反映的是处理本次请求的实例
+                "limits": {
+                    "webhooks": {
+                        "in_flight": inflight::inflight_webhooks(),
+                        "max": config.max_inflight_webhooks,
+                    },
+                    "model_connections": {
+                        "in_flight": inflight::model_connections(),
+                        "max": config.max_model_connections,
+                    },
+                },
+                "disabled_feeds": health.values().filter(|h| h.is_disabled(now)).count(),
+                "deadletter": deadletter.entries.len(),
+            }))
+        }
+        (Method::Get, "/admin/config") => {
+            // 只列出覆盖层允许的设置，凭据不会出现在结果中
+            let values: BTreeMap<&str, serde_json::Value> =
+                overlay::with_overlay(&config.overlay.overlay, || {
+                    overlay::keys()
+                        .filter_map(|name| {
+                            let value = env_var(&env, name)?;
+                            let source = if config.overlay.overlay.values.contains_key(name) {
+                                "overlay"
+                            } else {
+                                "env"
+                            };
+                            Some((
+                                name,
+                                serde_json::json!({ "value": value, "source": source }),
+                            ))
+                        })
+                        .collect()
+                });
+            Response::from_json(&serde_json::json!({
+                "overlay": config.overlay,
+                "values": values,
+            }))
+        }
+        (Method::Put, "/admin/config") => {
+            let raw = req.text().await?;
+            let overlay = match overlay::parse(&raw) {
+                Ok(overlay) => overlay,
+                Err(errors) => {
+                    return Ok(
+                        Response::from_json(&serde_json::json!({ "errors": errors }))?
+                            .with_status(422),
+                    )
+                }
+            };
+            // 当前覆盖层有效时才保存为回滚版本，避免覆盖掉上一份有效的配置
+            if let Some(previous) = kv.get(CONFIG_KEY).text().await? {
+                if overlay::parse(&previous).is_ok() {
+                    kv.put(CONFIG_ROLLBACK_KEY, previous)?.execute().await?;
+                }
+            }
+            kv.put(CONFIG_KEY, raw)?.execute().await?;
+            Response::from_json(
+                &serde_json::json!({ "keys": overlay.values.keys().collect::<Vec<_>>() }),
+            )
+        }
+        (Method::Get, "/admin/feeds/stats") => {
+            let url = req.url()?;
+            let param = |name: &str| {
+                url.query_pairs()
+                    .find(|(k, _)| k == name)
+                    .and_then(|(_, v)| v.parse::<u64>().ok())
+            };
+            let days = param("days").unwrap_or(7).clamp(1, feed_stats::MAX_DAYS);
+            let page = param("page").unwrap_or(1).max(1) as usize;
+            let Some(kv_batch) = &config.kv_batch else {
+                return Response::from_json(&Vec::<feed_stats::FeedStats>::new());
+            };
+            let records = kv_batch.entries::<DailyStats>(FEED_STATS_DOC).await;
+            let now = Date::now().as_millis();
+            let feeds = feed_stats::fold(
+                records.iter().map(|(key, stats, _)| (key.as_str(), stats)),
+                now,
+                days,
+            );
+            let total = feeds.len();
+            let mut feeds = feed_stats::page(feeds, page);
+            // 按当前配置解析每个订阅源的设置哈希，和摘要块中的标记对比可以发现过期摘要
+            for feed in &mut feeds {
+                let settings = config
+                    .resolve_feed_settings(&feed.site_url, &feed.category.trim().to_lowercase());
+                feed.settings_hash = Some(settings_hash(&config, &settings));
+            }
+            let mut response = Response::from_json(&feeds)?;
+            response
+                .headers_mut()
+                .set("X-Total-Count", &total.to_string())?;
+            response
+                .headers_mut()
+                .set("X-Page-Count", &feed_stats::page_count(total).to_string())?;
+            Ok(response)
+        }
+        (Method::Get, "/admin/canary-report") => {
+            let days = req
+                .url()?
+                .query_pairs()
+                .find(|(k, _)| k == "days")
+                .and_then(|(_, v)| v.parse::<u64>().ok())
+                .unwrap_or(7)
+                .clamp(1, feed_stats::MAX_DAYS);
+            let records = match &config.kv_batch {
+                Some(kv_batch) => kv_batch.entries::<ArmStats>(CANARY_STATS_DOC).await,
+                None => Vec::new(),
+            };
+            let arms = canary::report(
+                records.iter().map(|(key, stats, _)| (key.as_str(), stats)),
+                Date::now().as_millis(),
+                days,
+            );
+            Response::from_json(&serde_json::json!({
+                "canary": config.canary.as_ref().map(|canary| &canary.settings),
+                "days": days,
+                "arms": arms,
+            }))
+        }
+        (Method::Post, "/admin/migrate-wrapper") => {
+            if config.summary_target != SummaryTarget::Content {
+                return Response::error("Summaries are not written to the content", 400);
+            }
+            let url = req.url()?;
+            let param = |name: &str| {
+                url.query_pairs()
+                    .find(|(k, _)| k == name)
+                    .and_then(|(_, v)| v.parse::<usize>().ok())
+            };
+            let cursor = param("cursor").unwrap_or(0);
+            let limit = param("limit")
+                .unwrap_or(STALE_SCAN_LIMIT)
+                .clamp(1, MAX_PAGE_SIZE);
+            let entries = match get_recent_entries(&config.miniflux, limit, cursor).await {
+                Ok(response) => response.entries,
+                Err(e) => return Response::error(format!("Failed to fetch entries: {}", e), 502),
+            };
+
+            let scanned = entries.len();
+            let (mut migrated, mut failed) = (0, 0);
+            for entry in &entries {
+                match migrate_block(&config, entry).await {
+                    Ok(true) => migrated += 1,
+                    Ok(false) => {}
+                    Err(e) => {
+                        failed += 1;
+                        console_warn!("Failed to migrate entry {}: {}", entry.id, e);
+                    }
+                }
+            }
+            Response::from_json(&serde_json::json!({
+                "scanned": scanned,
+                "migrated": migrated,
+                "failed": failed,
+                "next_cursor": (scanned == limit).then_some(cursor + scanned),
+            }))
+        }
+        (Method::Get, "/admin/dlq") => {
+            let keys = kv
+                .list()
+                .prefix(queue::DLQ_PREFIX.to_string())
+                .execute()
+                .await?
+                .keys;
+            let mut letters = Vec::new();
+            for key in keys {
+                if let Some(letter) = kv.get(&key.name).json::<DlqEntry>().await? {
+                    letters.push(letter);
+                }
+            }
+            Response::from_json(&letters)
+        }
+        (Method::Post, path) if path.starts_with("/admin/dlq/") => {
+            let Some(Ok(id)) = path
+                .strip_prefix("/admin/dlq/")
+                .and_then(|rest| rest.strip_suffix("/reprocess"))
+                .map(str::parse::<u64>)
+            else {
+                return Response::error("Not Found", 404);
+            };
+            let key = queue::dlq_key(id);
+            if kv.get(&key).text().await?.is_none() {
+                return Response::error("Not Found", 404);
+            }
+            let entry = match get_entry(&config.miniflux, id).await {
+                Ok(entry) => entry,
+                Err(e) if e.is::<EntryGone>() => {
+                    kv.delete(&key).await?;
+                    return Response::error("Entry not found", 404);
+                }
+                Err(e) => return Response::error(format!("Failed to fetch entry: {}", e), 502),
+            };
+            kv.delete(&key).await?;
+            let report = process_entries(&config, vec![entry]).await;
+            Response::from_json(&report)
+        }
+        (Method::Get, "/admin/last-run") => {
+            match kv.get(LAST_RUN_KEY).json::<serde_json::Value>().await? {
+                Some(report) => Response::from_json(&report),
+                None => Response::error("Not Found", 404),
+            }
+        }
+        _ => Response::error("Not Found", 404),
+    }
+}
+
+const DEFAULT_WEBHOOK_SIGNATURE_HEADER: &str = "X-Miniflux-Signature";
+
+// 部分代理会改写请求头名称，可通过 WEBHOOK_SIGNATURE_HEADER 指定
+fn signature_header(env: &dyn EnvVars) -> String {
+    env_var(env, "WEBHOOK_SIGNATURE_HEADER").unwrap_or(DEFAULT_WEBHOOK_SIGNATURE_HEADER.to_string())
+}
+
+// 签名无效时返回对应的错误响应
+fn check_signature(req: &Request, env: &Env, payload: &str) -> worker::Result<Option<Response>> {
+    let headers = req.headers();
+    match signature_error(env, |name| headers.get(name).ok().flatten(), payload) {
+        Some(message) => Response::error(message, 401).map(Some),
+        None => Ok(None),
+    }
+}
+
+// 按名称读取请求头中的签名并验证，无效时返回错误信息
+fn signature_error(
+    env: &dyn EnvVars,
+    header: impl Fn(&str) -> Option<String>,
+    payload: &str,
+) -> Option<&'static str> {
+    let Some(signature) = header(&signature_header(env)) else {
+        return Some("Missing signature");
+    };
+
+    let secret = env_secret(env, "MINIFLUX_WEBHOOK_SECRET").unwrap();
+
+    // 验证签名
+    (!verify(&secret, payload, &signature)).then_some("Invalid signature")
+}
+
+// 手动触发单篇文章的摘要，不检查白名单，请求体 `{"entry_id": N}` 需要和 Webhook 一样签名
+async fn handle_summarize(mut req: Request, env: Env, id: &str) -> worker::Result<Response> {
+    if req.method() != Method::Post {
+        return Response::error("Method Not Allowed", 405);
+    }
+    let payload = req.text().await?;
+    if let Some(response) = check_signature(&req, &env, &payload)? {
+        return Ok(response);
+    }
+    let request = match summarize::parse_request(id, &payload) {
+        Ok(request) => request,
+        Err(e) => return Response::error(e, 400),
+    };
+    let id = request.entry_id;
+
+    let mut config = load_config(&env).await;
+    // 手动重新摘要时可以用 ?no_cache=1 跳过模型输出缓存
+    if req
+        .url()?
+        .query_pairs()
+        .any(|(k, v)| k == "no_cache" && v == "1")
+    {
+        config.llm_cache = None;
+    }
+
+    match summarize_entry(&config, id, request.force).await {
+        ManualSummary::NotFound => Response::error("Entry not found", 404),
+        ManualSummary::FetchFailed(e) => {
+            Response::error(format!("Failed to fetch entry: {}", e), 502)
+        }
+        ManualSummary::AlreadyProcessed => Response::ok("Entry already processed"),
+        ManualSummary::Finished(Ok(EntryOutcome::Summarized(summary))) => {
+            Response::from_json(&serde_json::json!({
+                "id": id,
+                "outcome": "summarized",
+                "summary": summary,
+            }))
+        }
+        ManualSummary::Finished(Ok(outcome)) => Response::from_json(&serde_json::json!({
+            "id": id,
+            "outcome": format!("{:?}", outcome).to_lowercase(),
+        })),
+        ManualSummary::Finished(Err(e)) => {
+            Response::error(format!("Failed to summarize entry: {}", e), 502)
+        }
+    }
+}
+
+// 手动摘要单篇文章的结果，由 handle_summarize 转换为响应
+enum ManualSummary {
+    NotFound,
+    FetchFailed(String),
+    AlreadyProcessed,
+    Finished(Result<EntryOutcome, Box<dyn std::error::Error>>),
+}
+
+// 读取并摘要单篇文章，force 时先去掉已有的摘要块和摘要标签再重新生成
+async fn summarize_entry(config: &Config, id: u64, force: bool) -> ManualSummary {
+    let mut entry = match get_entry(&config.miniflux, id).await {
+        Ok(entry) => entry,
+        Err(e) if e.is::<EntryGone>() => return ManualSummary::NotFound,
+        Err(e) => return ManualSummary::FetchFailed(e.to_string()),
+    };
+    strip_unavailable_notice(&mut entry);
+    if force {
+        if let Some(original) = template::strip_summary_block(&entry.content) {
+            entry.content = original.to_string();
+        }
+        entry.tags.retain(|tag| !template::is_processed_tag(tag));
+    }
+    if is_processed(&entry) {
+        return ManualSummary::AlreadyProcessed;
+    }
+
+    let capture = Capturer::new(config.debug_capture.as_ref().map(|d| d.secrets.clone()));
+    let mut steps = Vec::new();
+    let result = generate_and_update_entry(config, &capture, &entry, &mut steps).await;
+    store_debug_capture(config, capture, &entry, &result);
+    if let (Some(kv), true) = (&config.kv, is_success(&result)) {
+        record_last_success(kv, &entry, Date::now().as_millis()).await;
+    }
+    if let Some(kv_batch) = &config.kv_batch {
+        kv_batch.flush(Date::now().as_millis()).await;
+    }
+    ManualSummary::Finished(result)
+}
+
+// /admin/diag 中来自上次运行和健康检查记录的部分
+struct DiagRun {
+    last_run: Option<serde_json::Value>,
+    last_run_tokens: Option<serde_json::Value>,
+    health: health::HealthStatus,
+}
+
+// KV 中的值读取失败或格式不对时按没有记录处理，诊断接口仍然返回其他各项
+fn diag_run<E: std::fmt::Debug>(
+    policy: HealthPolicy,
+    now: u64,
+    last_run: Result<Option<serde_json::Value>, E>,
+    last_success: Result<Option<LastSuccess>, E>,
+    first_seen: Result<Option<u64>, E>,
+) -> DiagRun {
+    fn value<T, E: std::fmt::Debug>(key: &str, value: Result<Option<T>, E>) -> Option<T> {
+        value.unwrap_or_else(|e| {
+            console_warn!("Ignoring invalid {} in KV: {:?}", key, e);
+            None
+        })
+    }
+
+    let last_run = value(LAST_RUN_KEY, last_run);
+    let last_success = value(LAST_SUCCESS_KEY, last_success);
+    let first_seen = value(HEALTH_FIRST_SEEN_KEY, first_seen);
+    DiagRun {
+        last_run_tokens: last_run
+            .as_ref()
+            .and_then(|run| run.get("estimated_tokens"))
+            .cloned(),
+        last_run,
+        health: health::check(
+            policy,
+            now,
+            first_seen.unwrap_or(now),
+            last_success.as_ref(),
+        ),
+    }
+}
+
+// 供外部监控使用，超过 HEALTH_MAX_AGE_HOURS 没有成功写回摘要时返回 503
+async fn handle_healthz(env: Env) -> worker::Result<Response> {
+    let config = load_config(&env).await;
+    let Some(kv) = &config.kv else {
+        return Response::error("KV binding not configured", 501);
+    };
+
+    let now = Date::now().as_millis();
+    let first_seen = match kv.get(HEALTH_FIRST_SEEN_KEY).json::<u64>().await? {
+        Some(first_seen) => first_seen,
+        None => {
+            kv_put_json(kv, HEALTH_FIRST_SEEN_KEY, &now).await;
+            now
+        }
+    };
+    let last_success = kv.get(LAST_SUCCESS_KEY).json::<LastSuccess>().await?;
+    let status = health::check(config.health, now, first_seen, last_success.as_ref());
+    let code = if status.healthy { 200 } else { 503 };
+    Ok(Response::from_json(&status)?.with_status(code))
+}
+
+// 导出最近生成的摘要，只读取一次 KV，不调用模型
+async fn handle_feed(req: Request, env: Env) -> worker::Result<Response> {
+    if req.method() != Method::Get {
+        return Response::error("Method Not Allowed", 405);
+    }
+    let mut url = req.url()?;
+    // 阅读器通常不能设置请求头，令牌通过查询参数传递
+    if let Some(token) = env_secret(&env, "FEED_TOKEN") {
+        if !query_token_matches(&url, &token) {
+            return Response::error("Unauthorized", 401);
+        }
+    }
+    url.set_query(None);
+
+    let config = load_config(&env).await;
+    let Some(kv_batch) = &config.kv_batch else {
+        return Response::error("KV binding not configured", 501);
+    };
+    if req.path() == "/logfeed.xml" {
+        let runs: Vec<(u64, feed::RunLog)> = kv_batch
+            .entries::<feed::RunLog>(RUN_LOG_DOC)
+            .await
+            .into_iter()
+            .filter_map(|(started_at, log, _)| Some((started_at.parse().ok()?, log)))
+            .take(env_parse(&env, "FEED_ITEMS", 50))
+            .collect();
+        let mut response = Response::ok(feed::render_log_atom(url.as_str(), &runs))?;
+        response
+            .headers_mut()
+            .set("Content-Type", "application/atom+xml; charset=utf-8")?;
+        return Ok(response);
+    }
+    let items: Vec<feed::FeedItem> = kv_batch
+        .entries::<CachedSummary>(SUMMARY_DOC)
+        .await
+        .into_iter()
+        .filter_map(|(id, cached, generated_at)| {
+            Some(feed::FeedItem {
+                id: id.parse().ok()?,
+                title: cached.title,
+                url: cached.url,
+                summary: cached.summary,
+                generated_at,
+            })
+        })
+        .take(env_parse(&env, "FEED_ITEMS", 50))
+        .collect();
+
+    if req.path() == "/feed.json" {
+        let mut response = Response::from_json(&feed::render_json_feed(url.as_str(), &items))?;
+        response
+            .headers_mut()
+            .set("Content-Type", "application/feed+json; charset=utf-8")?;
+        Ok(response)
+    } else {
+        let mut response = Response::ok(feed::render_atom(url.as_str(), &items))?;
+        response
+            .headers_mut()
+            .set("Content-Type", "application/atom+xml; charset=utf-8")?;
+        Ok(response)
+    }
+}
+
+// 把签名通过的 Webhook 原样转发给 FORWARD_WEBHOOK_URL，在响应返回后执行，失败只记录日志
+// 和其他出站请求共用执行层，计入子请求预算
+fn forward_webhook(
+    req: &Request,
+    env: &Env,
+    config: &Config,
+    ctx: &Context,
+    payload: &str,
+) -> worker::Result<()> {
+    let target = env_var(env, "FORWARD_WEBHOOK_URL");
+    let forwarded = req.headers().get(forward::FORWARDED_HEADER)?;
+    if !forward::should_forward(target.as_deref(), forwarded.as_deref()) {
+        if target.is_some() && forwarded.is_some() {
+            console_log!("Not forwarding a webhook that was already forwarded");
+        }
+        return Ok(());
+    }
+    let target = target.unwrap();
+
+    let signature_header = signature_header(env);
+    let signature = req.headers().get(&signature_header)?.unwrap_or_default();
+    let client = config.http_client.clone();
+    let request = client
+        .post(&target)
+        .header(CONTENT_TYPE, "application/json")
+        .header(signature_header, signature)
+        .header(forward::FORWARDED_HEADER, "1")
+        .body(payload.to_string());
+    ctx.wait_until(async move {
+        match client.send(request).await {
+            Ok(response) if response.status().is_success() => {}
+            Ok(response) => console_warn!(
+                "Forwarding webhook to {} returned {}",
+                target,
+                response.status()
+            ),
+            Err(e) => console_warn!("Failed to forward webhook to {}: {}", target, e),
+        }
+    });
+    Ok(())
+}
+
+// Webhook 中每组订阅源设置相同的文章的处理结果
+#[derive(Serialize)]
+struct PartitionOutcome {
+    feeds: Vec<String>,
+    entries: Vec<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    report: Option<RunReport>,
+}
+
+// 按解析后的订阅源设置分组，保持文章原有顺序
+fn partition_entries(config: &Config, entries: Vec<Entry>) -> Vec<(FeedSettings, Vec<Entry>)> {
+    let mut partitions: Vec<(FeedSettings, Vec<Entry>)> = Vec::new();
+    for entry in entries {
+        let settings = config.feed_settings(&entry);
+        match partitions.iter_mut().find(|(s, _)| *s == settings) {
+            Some((_, group)) => group.push(entry),
+            None => partitions.push((settings, vec![entry])),
+        }
+    }
+    partitions
+}
+
+// 按订阅源设置分组分别处理，一组的配置错误不影响其他组
+async fn process_partitions(config: &Config, entries: Vec<Entry>) -> Vec<PartitionOutcome> {
+    let mut partitions = Vec::new();
+    for (settings, entries) in partition_entries(config, entries) {
+        let mut outcome = PartitionOutcome {
+            feeds: entries
+                .iter()
+                .filter_map(|entry| entry.feed.as_ref().map(|feed| feed.site_url.clone()))
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect(),
+            entries: entries.iter().map(|entry| entry.id).collect(),
+            error: None,
+            report: None,
+        };
+        match settings.validate() {
+            Ok(()) => outcome.report = Some(process_entries(config, entries).await),
+            Err(e) => {
+                console_error!(
+                    "Skipping {} entries of {}: {}",
+                    outcome.entries.len(),
+                    outcome.feeds.join(", "),
+                    e
+                );
+                outcome.error = Some(e);
+            }
+        }
+        partitions.push(outcome);
+    }
+
+    partitions
+}
+
+#[event(fetch)]
+async fn main(mut req: Request, env: Env, ctx: Context) -> worker::Result<Response> {
+    if req.path().starts_with("/admin/") {
+        return handle_admin(req, env).await;
+    }
+    if req.path() == "/healthz" {
+        return handle_healthz(env).await;
+    }
+    if matches!(
+        req.path().as_str(),
+        "/feed.xml" | "/feed.json" | "/logfeed.xml"
+    ) {
+        return handle_feed(req, env).await;
+    }
+    if let Some(id) = req.path().strip_prefix("/summarize/") {
+        let id = id.to_string();
+        return handle_summarize(req, env, &id).await;
+    }
+
+    // 检查请求方法
+    if req.method() != Method::Post {
+        return Response::error("Method Not Allowed", 405);
+    }
+
+    // 提取请求体和签名
+    let payload = req.text().await?;
+    if let Some(response) = check_signature(&req, &env, &payload)? {
+        return Ok(response);
+    }
+    let config = load_config(&env).await;
+    forward_webhook(&req, &env, &config, &ctx, &payload)?;
+
+    // 解析请求体，签名已通过校验，可以记录请求体方便排查
+    let webhook_payload = match read_webhook_payload(&payload) {
+        WebhookBody::Parsed(webhook_payload) => webhook_payload,
+        WebhookBody::Ignored => return Response::ok("Ignored non-new_entries event"),
+        WebhookBody::Invalid(e) => {
+            console_debug!("Invalid webhook payload: {}", truncate_chars(&payload, 500));
+            console_warn!("Invalid webhook payload at {}: {}", e.path, e.error);
+            return Ok(Response::from_json(&e)?.with_status(INVALID_PAYLOAD_STATUS));
+        }
+    };
+
+    if webhook_payload.event_type != "new_entries" {
+        return Response::ok("Ignored non-new_entries event");
+    };
+
+    if !config.is_whitelisted(&webhook_payload.feed.site_url) {
+        return Response::ok("Ignored non-whitelist feed");
+    };
+
+    // Webhook 中的文章不带 feed 信息，使用 payload 中的 feed 补全
+    let feed = webhook_payload.feed;
+    let entries = webhook_payload
+        .entries
+        .into_iter()
+        .map(|mut entry| {
+            entry.feed.get_or_insert_with(|| feed.clone());
+            entry
+        })
+        .collect();
+
+    // 同时处理的 Webhook 过多时返回 429，让 Miniflux 稍后重试
+    let Some(_inflight) = InflightGuard::try_acquire(config.max_inflight_webhooks) else {
+        return Response::error("Busy", 429);
+    };
+
+    let partitions = process_partitions(&config, entries).await;
+    Response::from_json(&serde_json::json!({ "partitions": partitions }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use http::{MockExec, MockRequest, MockResponse};
+    use reqwest::header::USER_AGENT;
+
+    fn entry(content: &str) -> Entry {
+        serde_json::from_value(serde_json::json!({ "id": 1, "content": content })).unwrap()
+    }
+
+    #[test]
+    fn unavailable_notice_is_stripped_and_remembered() {
+        let mut noticed = entry(&template::with_notice("AI unavailable", "<p>body</p>"));
+        strip_unavailable_notice(&mut noticed);
+        assert_eq!(noticed.content, "<p>body</p>");
+        assert!(noticed.has_notice);
+
+        let mut plain = entry("<p>body</p>");
+        strip_unavailable_notice(&mut plain);
+        assert_eq!(plain.content, "<p>body</p>");
+        assert!(!plain.has_notice);
+    }
+
+    #[test]
+    fn unavailable_notice_is_written_once_and_only_to_content() {
+        let mut noticed = entry(&template::with_notice("AI unavailable", "<p>body</p>"));
+        strip_unavailable_notice(&mut noticed);
+        let plain = entry("<p>body</p>");
+
+        assert!(needs_unavailable_notice(
+            SummaryTarget::Content,
+            &plain,
+            true
+        ));
+        assert!(!needs_unavailable_notice(
+            SummaryTarget::Content,
+            &plain,
+            false
+        ));
+        assert!(!needs_unavailable_notice(
+            SummaryTarget::Content,
+            &noticed,
+            true
+        ));
+        assert!(!needs_unavailable_notice(SummaryTarget::Tags, &plain, true));
+    }
+
+    #[test]
+    fn parses_entry_statuses() {
+        assert_eq!(
+            entry_statuses(" Read, unread ,read"),
+            (vec!["read".to_string(), "unread".to_string()], vec![])
+        );
+        // removed 和无法识别的状态不会被处理，全部无效时回退到未读
+        assert_eq!(
+            entry_statuses("removed,starred"),
+            (
+                vec!["unread".to_string()],
+                vec!["removed".to_string(), "starred".to_string()]
+            )
+        );
+        assert_eq!(entry_statuses("").0, vec!["unread".to_string()]);
+    }
+
+    #[test]
+    fn checks_entry_status() {
+        let statuses = entry_statuses("unread").0;
+        assert!(has_allowed_status(&statuses, "unread"));
+        assert!(!has_allowed_status(&statuses, "read"));
+        assert!(!has_allowed_status(&statuses, "removed"));
+        assert!(has_allowed_status(&statuses, ""));
+
+        // webhook 中的文章可能没有状态
+        assert_eq!(entry("").status, "");
+    }
+
+    fn json_response(content_type: &str, body: &str) -> reqwest::Response {
+        reqwest::Response::from(
+            http02::Response::builder()
+                .header(CONTENT_TYPE, content_type)
+                .body(body.to_string())
+                .unwrap(),
+        )
+    }
+
+    // 截断的响应单独识别，定时任务据此减少每页数量重试
+    #[test]
+    fn truncated_entries_response_is_retryable() {
+        let err = futures::executor::block_on(read_entries(json_response(
+            "application/json",
+            r#"{"total": 2, "entries": [{"id": 1, "content": "a"}, {"id"#,
+        )))
+        .err()
+        .unwrap();
+        assert!(err.is::<TruncatedJson>(), "{}", err);
+    }
+
+    #[test]
+    fn other_invalid_responses_are_not_retried() {
+        for (content_type, body) in [
+            ("application/json", r#"{"entries": 1}"#),
+            ("text/html", "<html>Maintenance</html>"),
+        ] {
+            let err = futures::executor::block_on(read_entries(json_response(content_type, body)))
+                .err()
+                .unwrap();
+            assert!(!err.is::<TruncatedJson>(), "{}", err);
+        }
+        let ok = futures::executor::block_on(read_entries(json_response(
+            "application/json",
+            r#"{"entries": [{"id": 1, "content": "a"}]}"#,
+        )))
+        .unwrap();
+        assert_eq!(ok.entries.len(), 1);
+    }
+
+    // 测试用的变量表，代替 Worker 的 Env
+    #[derive(Default, Clone)]
+    struct TestVars {
+        vars: HashMap<String, String>,
+        secrets: HashMap<String, String>,
+    }
+
+    impl TestVars {
+        fn with_var(mut self, name: &str, value: &str) -> Self {
+            self.vars.insert(name.to_string(), value.to_string());
+            self
+        }
+
+        fn with_secret(mut self, name: &str, value: &str) -> Self {
+            self.secrets.insert(name.to_string(), value.to_string());
+            self
+        }
+    }
+
+    impl EnvVars for TestVars {
+        fn var(&self, name: &str) -> Option<String> {
+            self.vars.get(name).cloned()
+        }
+
+        fn secret(&self, name: &str) -> Option<String> {
+            self.secrets.get(name).cloned()
+        }
+    }
+
+    #[test]
+    fn secret_takes_precedence_over_var() {
+        let env = TestVars::default()
+            .with_secret("OPENAI_TOKEN", "from-secret")
+            .with_var("OPENAI_TOKEN", "from-var");
+        assert_eq!(
+            env_secret(&env, "OPENAI_TOKEN").as_deref(),
+            Some("from-secret")
+        );
+    }
+
+    #[test]
+    fn secret_falls_back_to_var() {
+        let env = TestVars::default().with_var("OPENAI_TOKEN", "from-var");
+        assert_eq!(
+            env_secret(&env, "OPENAI_TOKEN").as_deref(),
+            Some("from-var")
+        );
+
+        // 空白的 secret 视为未设置
+        let env = env.with_secret("OPENAI_TOKEN", "  ");
+        assert_eq!(
+            env_secret(&env, "OPENAI_TOKEN").as_deref(),
+            Some("from-var")
+        );
+
+        let env = TestVars::default().with_var("OPENAI_TOKEN", " ");
+        assert_eq!(env_secret(&env, "OPENAI_TOKEN"), None);
+    }
+
+    #[test]
+    fn overlay_takes_precedence_over_env() {
+        let env = TestVars::default()
+            .with_var("PAGE_SIZE", "20")
+            .with_secret("OPENAI_TOKEN", "from-secret");
+        let overlay = ConfigOverlay {
+            values: [("PAGE_SIZE", "30"), ("OPENAI_TOKEN", "from-overlay")]
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        };
+        overlay::with_overlay(&overlay, || {
+            assert_eq!(env_parse(&env, "PAGE_SIZE", 0), 30);
+            // secret 仍然优先于覆盖层
+            assert_eq!(
+                env_secret(&env, "OPENAI_TOKEN").as_deref(),
+                Some("from-secret")
+            );
+        });
+        assert_eq!(env_parse(&env, "PAGE_SIZE", 0), 20);
+        assert_eq!(env_parse(&env, "MISSING", 7), 7);
+    }
+
+    // 构建配置必需的变量，其余使用默认值
+    fn test_vars() -> TestVars {
+        TestVars::default()
+            .with_var("MINIFLUX_URL", "https://miniflux.example.com")
+            .with_var("MINIFLUX_USERNAME", "admin")
+            .with_var("MINIFLUX_PASSWORD", "secret")
+            .with_var("OPENAI_URL", "https://llm.example.com")
+            .with_var("OPENAI_MODEL", "test-model")
+    }
+
+    fn test_config(env: &TestVars, exec: &Rc<MockExec>) -> Config {
+        config_from_vars(env, exec.clone(), None, None)
+    }
+
+    fn completion(content: &str) -> MockResponse {
+        MockResponse::json(serde_json::json!({
+            "choices": [{ "message": { "role": "assistant", "content": content } }]
+        }))
+    }
+
+    // Miniflux 请求回复当前用户，其余请求回复模型结果
+    fn me_or_completion() -> Rc<MockExec> {
+        Rc::new(MockExec::with_handler(|request| {
+            if request.url.ends_with("/v1/me") {
+                MockResponse::json(serde_json::json!({ "username": "admin" }))
+            } else {
+                completion("ok")
+            }
+        }))
+    }
+
+    fn send_miniflux_and_model_requests(config: &Config) {
+        block_on(get_me(&config.miniflux)).unwrap();
+        block_on(request_openai_chat_completion(
+            &config.openai.client,
+            &config.openai.url,
+            &config.openai.token,
+            "test-model",
+            None,
+            false,
+            Vec::new(),
+        ))
+        .unwrap();
+    }
+
+    #[test]
+    fn requests_carry_the_default_user_agent() {
+        let exec = me_or_completion();
+        let config = test_config(&test_vars(), &exec);
+        send_miniflux_and_model_requests(&config);
+
+        let expected = format!("miniflux-ai/{}", env!("CARGO_PKG_VERSION"));
+        let requests = exec.requests();
+        assert_eq!(requests.len(), 2);
+        for request in requests {
+            assert_eq!(request.headers[USER_AGENT], expected.as_str());
+        }
+    }
+
+    #[test]
+    fn user_agent_can_be_overridden() {
+        let exec = me_or_completion();
+        let config = test_config(
+            &test_vars().with_var("HTTP_USER_AGENT", "my-reader/2.0"),
+            &exec,
+        );
+        send_miniflux_and_model_requests(&config);
+
+        for request in exec.requests() {
+            assert_eq!(request.headers[USER_AGENT], "my-reader/2.0");
+        }
+    }
+
+    // 自定义请求头只发给 Miniflux，不会泄露给模型服务
+    #[test]
+    fn extra_headers_are_sent_to_miniflux_only() {
+        let exec = me_or_completion();
+        let env = test_vars().with_secret(
+            "MINIFLUX_EXTRA_HEADERS",
+            r#"{"CF-Access-Client-Id": "id", "CF-Access-Client-Secret": "token"}"#,
+        );
+        let config = test_config(&env, &exec);
+        send_miniflux_and_model_requests(&config);
+
+        let requests = exec.requests();
+        assert!(requests[0].url.ends_with("/v1/me"));
+        assert_eq!(requests[0].headers["cf-access-client-id"], "id");
+        assert_eq!(requests[0].headers["cf-access-client-secret"], "token");
+        assert!(requests[1].url.ends_with("/v1/chat/completions"));
+        assert!(requests[1].headers.get("cf-access-client-id").is_none());
+    }
+
+    #[test]
+    fn invalid_extra_headers_are_rejected() {
+        let (headers, errors) = extra_headers("not json");
+        assert!(headers.is_empty());
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("Ignoring invalid MINIFLUX_EXTRA_HEADERS"));
+
+        // 无效的名称或值只跳过对应的请求头
+        let (headers, errors) =
+            extra_headers(r#"{"Bad Header": "1", "X-Line": "a\nb", "X-Ok": "1"}"#);
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers["x-ok"], "1");
+        assert_eq!(
+            errors,
+            vec![
+                "Ignoring invalid header in MINIFLUX_EXTRA_HEADERS: Bad Header",
+                "Ignoring invalid header in MINIFLUX_EXTRA_HEADERS: X-Line",
+            ]
+        );
+
+        assert_eq!(extra_headers(""), (HeaderMap::new(), Vec::new()));
+    }
+
+    #[test]
+    fn page_size_defaults_and_is_clamped() {
+        let exec = Rc::new(MockExec::new(&[]));
+        let page_size = |value: Option<&str>| {
+            let env = match value {
+                Some(value) => test_vars().with_var("PAGE_SIZE", value),
+                None => test_vars(),
+            };
+            let config = test_config(&env, &exec);
+            (config.page_size, config.truncated_retry_page_size)
+        };
+        assert_eq!(page_size(None), (MAX_PAGE_SIZE, MAX_PAGE_SIZE / 2));
+        assert_eq!(page_size(Some("20")), (20, 10));
+        assert_eq!(page_size(Some("0")), (1, 0));
+        assert_eq!(page_size(Some("1000")), (MAX_PAGE_SIZE, MAX_PAGE_SIZE / 2));
+        // 无法解析时使用默认值
+        assert_eq!(page_size(Some("many")), (MAX_PAGE_SIZE, MAX_PAGE_SIZE / 2));
+    }
+
+    fn entries_response(entries: serde_json::Value) -> MockResponse {
+        MockResponse::json(serde_json::json!({ "total": 0, "entries": entries }))
+    }
+
+    fn query_pairs(url: &str) -> Vec<(String, String)> {
+        reqwest::Url::parse(url)
+            .unwrap()
+            .query_pairs()
+            .map(|(k, v)| (k.into_owned(), v.into_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn search_query_is_sent_url_encoded() {
+        let exec = Rc::new(MockExec::with_handler(|_| {
+            entries_response(serde_json::json!([]))
+        }));
+        let env = test_vars()
+            .with_var("SEARCH_QUERY", "rust & wasm/边缘?")
+            .with_var("PAGE_SIZE", "20");
+        block_on(fetch_entries(&test_config(&env, &exec))).unwrap();
+
+        let url = exec.requests()[0].url.clone();
+        assert!(
+            url.starts_with("https://miniflux.example.com/v1/entries?"),
+            "{}",
+            url
+        );
+        assert!(
+            url.contains("search=rust+%26+wasm%2F%E8%BE%B9%E7%BC%98%3F"),
+            "{}",
+            url
+        );
+        assert_eq!(
+            query_pairs(&url),
+            [
+                ("limit", "20"),
+                ("status", "unread"),
+                ("search", "rust & wasm/边缘?"),
+            ]
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+        );
+    }
+
+    #[test]
+    fn search_is_omitted_without_search_query() {
+        let exec = Rc::new(MockExec::with_handler(|_| {
+            entries_response(serde_json::json!([]))
+        }));
+        block_on(fetch_entries(&test_config(&test_vars(), &exec))).unwrap();
+        let url = exec.requests()[0].url.clone();
+        assert!(
+            query_pairs(&url).iter().all(|(k, _)| k != "search"),
+            "{}",
+            url
+        );
+    }
+
+    #[test]
+    fn category_id_fetches_from_the_category_endpoint() {
+        let exec = Rc::new(MockExec::with_handler(|_| {
+            entries_response(serde_json::json!([]))
+        }));
+        // CATEGORY_ID 优先于 FETCH_STRATEGY，不查询分类列表
+        let env = test_vars()
+            .with_var("CATEGORY_ID", "7")
+            .with_var("FETCH_STRATEGY", "per-category");
+        block_on(fetch_entries(&test_config(&env, &exec))).unwrap();
+
+        let requests = exec.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(
+            requests[0]
+                .url
+                .starts_with("https://miniflux.example.com/v1/categories/7/entries?"),
+            "{}",
+            requests[0].url
+        );
+    }
+
+    fn dated(id: u64, day: u32) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "content": "",
+            "published_at": format!("2024-01-{:02}T00:00:00Z", day),
+        })
+    }
+
+    fn entry_ids(response: &ApiResponse) -> Vec<u64> {
+        response.entries.iter().map(|e| e.id).collect()
+    }
+
+    // 逐个分类查询后合并，按发布时间从新到旧排序、去重，和全局查询一样只保留 page_size 篇
+    #[test]
+    fn per_category_fetch_merges_sorts_and_truncates() {
+        let categories = |request: &MockRequest| {
+            let path = reqwest::Url::parse(&request.url)
+                .unwrap()
+                .path()
+                .to_string();
+            match path.as_str() {
+                "/v1/categories" => {
+                    MockResponse::json(serde_json::json!([{ "id": 1 }, { "id": 2 }]))
+                }
+                "/v1/categories/1/entries" => {
+                    entries_response(serde_json::json!([dated(1, 1), dated(2, 3), dated(3, 5)]))
+                }
+                "/v1/categories/2/entries" => {
+                    entries_response(serde_json::json!([dated(3, 5), dated(4, 4), dated(5, 2)]))
+                }
+                _ => MockResponse::status(404),
+            }
+        };
+        let exec = Rc::new(MockExec::with_handler(categories));
+        let env = test_vars()
+            .with_var("FETCH_STRATEGY", "per-category")
+            .with_var("PAGE_SIZE", "3");
+        let per_category = block_on(fetch_entries(&test_config(&env, &exec))).unwrap();
+        assert_eq!(entry_ids(&per_category), vec![3, 4, 2]);
+        let requests = exec.requests();
+        assert_eq!(requests.len(), 3);
+        for request in &requests[1..] {
+            assert!(query_pairs(&request.url).contains(&("limit".to_string(), "3".to_string())));
+        }
+    }
+
+    fn fetch_with(reply: MockResponse) -> Result<ApiResponse, Box<dyn std::error::Error>> {
+        let exec = Rc::new(MockExec::with_handler(move |_| reply.clone()));
+        block_on(fetch_entries(&test_config(&test_vars(), &exec)))
+    }
+
+    #[test]
+    fn not_modified_and_no_content_mean_no_entries() {
+        for status in [304, 204] {
+            let response = fetch_with(MockResponse::status(status)).unwrap();
+            assert!(response.entries.is_empty(), "{}", status);
+        }
+    }
+
+    // 分块传输的空响应没有 Content-Length，也按没有文章处理
+    #[test]
+    fn empty_bodies_mean_no_entries() {
+        let sized = MockResponse::status(200).header("Content-Type", "application/json");
+        let chunked = sized.clone().chunked();
+        let whitespace = chunked.clone().body("\n");
+        for reply in [sized, chunked, whitespace] {
+            assert!(fetch_with(reply).unwrap().entries.is_empty());
+        }
+
+        // 有内容的分块响应照常解析
+        let reply = entries_response(serde_json::json!([dated(1, 1)])).chunked();
+        assert_eq!(entry_ids(&fetch_with(reply).unwrap()), vec![1]);
+        assert!(fetch_with(MockResponse::status(500)).is_err());
+    }
+
+    #[test]
+    fn update_succeeds_on_204_and_empty_200() {
+        for reply in [MockResponse::status(204), MockResponse::status(200)] {
+            let exec = Rc::new(MockExec::with_handler(move |_| reply.clone()));
+            let config = test_config(&test_vars(), &exec);
+            block_on(update_entry(&config.miniflux, 1, &UpdateRequest::default())).unwrap();
+        }
+    }
+
+    #[test]
+    fn html_page_is_reported_as_unexpected_response() {
+        let page = format!("<html>{}</html>", "维护中".repeat(100));
+        let reply = MockResponse::status(200)
+            .header("Content-Type", "text/html")
+            .body(page.clone());
+        let error = fetch_with(reply).unwrap_err().to_string();
+        assert!(
+            error.starts_with(
+                "Unexpected response from Miniflux (200 OK, is MINIFLUX_URL pointing at the API?)"
+            ),
+            "{}",
+            error
+        );
+        // 只带上前 200 个字符
+        let prefix: String = page.chars().take(200).collect();
+        assert!(error.ends_with(&format!("{:?}", prefix)), "{}", error);
+    }
+
+    #[test]
+    fn redirects_are_reported_with_the_location() {
+        let reply =
+            MockResponse::status(302).header("Location", "https://miniflux.example.com/login");
+        let error = fetch_with(reply).unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "Miniflux responded with redirect 302 Found to \"https://miniflux.example.com/login\", \
+             check the scheme and host of MINIFLUX_URL"
+        );
+    }
+
+    fn lookup_kind(lookup: Option<&EntryLookup>) -> &'static str {
+        match lookup {
+            Some(EntryLookup::Found(_)) => "found",
+            Some(EntryLookup::Gone) => "gone",
+            Some(EntryLookup::Failed(_)) => "failed",
+            None => "missing",
+        }
+    }
+
+    fn entry_path(request: &MockRequest) -> String {
+        reqwest::Url::parse(&request.url)
+            .unwrap()
+            .path()
+            .to_string()
+    }
+
+    #[test]
+    fn entries_by_ids_are_fetched_in_one_query() {
+        let exec = Rc::new(MockExec::with_handler(|_| {
+            entries_response(serde_json::json!([dated(1, 1), dated(2, 2), dated(3, 3)]))
+        }));
+        let config = test_config(&test_vars(), &exec);
+        let lookups = block_on(get_entries_by_ids(&config.miniflux, &[3, 1, 2, 1], 2));
+
+        for id in [1, 2, 3] {
+            assert_eq!(lookup_kind(lookups.get(&id)), "found");
+        }
+        let requests = exec.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(entry_path(&requests[0]), "/v1/entries");
+        assert_eq!(
+            query_pairs(&requests[0].url),
+            [("entry_ids", "1,2,3"), ("limit", "3")].map(|(k, v)| (k.to_string(), v.to_string()))
+        );
+    }
+
+    // 忽略 entry_ids 的 Miniflux 返回其他文章，没查到的逐篇查询
+    #[test]
+    fn entries_missing_from_the_query_are_fetched_one_by_one() {
+        let exec = Rc::new(MockExec::with_handler(|request| {
+            match entry_path(request).as_str() {
+                "/v1/entries" => entries_response(serde_json::json!([dated(2, 2), dated(7, 7)])),
+                "/v1/entries/1" => MockResponse::json(dated(1, 1)),
+                "/v1/entries/3" => MockResponse::status(404),
+                _ => MockResponse::status(500),
+            }
+        }));
+        let config = test_config(&test_vars(), &exec);
+        let lookups = block_on(get_entries_by_ids(&config.miniflux, &[1, 2, 3, 4], 2));
+
+        assert_eq!(lookups.len(), 4);
+        assert_eq!(lookup_kind(lookups.get(&1)), "found");
+        assert_eq!(lookup_kind(lookups.get(&2)), "found");
+        assert_eq!(lookup_kind(lookups.get(&3)), "gone");
+        assert_eq!(lookup_kind(lookups.get(&4)), "failed");
+        // 没有请求过的文章不会出现在结果中
+        assert_eq!(lookup_kind(lookups.get(&7)), "missing");
+
+        let mut paths: Vec<String> = exec.requests().iter().map(entry_path).collect();
+        paths[1..].sort();
+        assert_eq!(
+            paths,
+            [
+                "/v1/entries",
+                "/v1/entries/1",
+                "/v1/entries/3",
+                "/v1/entries/4"
+            ]
+        );
+    }
+
+    #[test]
+    fn failed_query_falls_back_to_single_entries() {
+        let exec = Rc::new(MockExec::with_handler(|request| {
+            match entry_path(request).as_str() {
+                "/v1/entries" => MockResponse::status(500),
+                "/v1/entries/1" => MockResponse::json(dated(1, 1)),
+                _ => MockResponse::json(dated(2, 2)),
+            }
+        }));
+        let config = test_config(&test_vars(), &exec);
+        let lookups = block_on(get_entries_by_ids(&config.miniflux, &[1, 2], 5));
+
+        assert_eq!(lookup_kind(lookups.get(&1)), "found");
+        assert_eq!(lookup_kind(lookups.get(&2)), "found");
+        assert_eq!(exec.requests().len(), 3);
+    }
+
+    fn complete_with(reply: MockResponse) -> Result<String, Box<dyn std::error::Error>> {
+        let exec = Rc::new(MockExec::with_handler(move |_| reply.clone()));
+        let config = test_config(&test_vars(), &exec);
+        block_on(request_openai_chat_completion(
+            &config.openai.client,
+            &config.openai.url,
+            &config.openai.token,
+            "test-model",
+            None,
+            false,
+            Vec::new(),
+        ))
+    }
+
+    // 部分代理出错时返回 200，错误信息在响应体的 error 中
+    #[test]
+    fn error_body_with_200_status_is_reported() {
+        let reply = MockResponse::json(serde_json::json!({
+            "error": { "message": "Rate limit reached for model", "type": "rate_limit" }
+        }));
+        assert_eq!(
+            complete_with(reply).unwrap_err().to_string(),
+            "OpenAI error: Rate limit reached for model"
+        );
+
+        // 没有 message 时带上整个 error
+        let reply = MockResponse::json(serde_json::json!({ "error": { "code": 503 } }));
+        assert_eq!(
+            complete_with(reply).unwrap_err().to_string(),
+            r#"OpenAI error: {"code":503}"#
+        );
+
+        assert_eq!(complete_with(completion("summary")).unwrap(), "summary");
+    }
+
+    fn invalid_payload(payload: &str) -> PayloadError {
+        match read_webhook_payload(payload) {
+            WebhookBody::Invalid(e) => e,
+            WebhookBody::Parsed(_) => panic!("parsed {}", payload),
+            WebhookBody::Ignored => panic!("ignored {}", payload),
+        }
+    }
+
+    #[test]
+    fn malformed_payload_is_rejected_with_its_location() {
+        let payload = r#"{
+  "event_type": "new_entries",
+  "feed": {"id": 1, "site_url": "https://example.com"},
+  "entries": [{"id": "seven", "content": ""}]
+}"#;
+        let e = invalid_payload(payload);
+        assert_eq!(INVALID_PAYLOAD_STATUS, 422);
+        assert_eq!(e.path, "entries[0].id");
+        assert_eq!((e.line, e.column), (4, 28));
+        assert!(
+            e.error.starts_with("invalid type: string \"seven\""),
+            "{}",
+            e.error
+        );
+        assert_eq!(
+            serde_json::to_value(&e).unwrap(),
+            serde_json::json!({
+                "error": e.error,
+                "path": "entries[0].id",
+                "line": 4,
+                "column": 28,
+            })
+        );
+
+        // 缺少字段时定位到所在的对象
+        let e = invalid_payload(r#"{"event_type": "new_entries", "entries": []}"#);
+        assert_eq!(e.path, ".");
+        assert_eq!(e.error, "missing field `feed` at line 1 column 44");
+        assert!(invalid_payload("not json").line > 0);
+    }
+
+    #[test]
+    fn other_events_are_ignored_even_when_malformed() {
+        assert!(matches!(
+            read_webhook_payload(r#"{"event_type": "save_entry", "entry": {}}"#),
+            WebhookBody::Ignored
+        ));
+        assert!(matches!(
+            read_webhook_payload(
+                r#"{"event_type": "new_entries", "feed": {"site_url": "https://example.com"}, "entries": []}"#
+            ),
+            WebhookBody::Parsed(_)
+        ));
+    }
+
+    fn signed_with(
+        headers: &[(&str, String)],
+        env: &TestVars,
+        payload: &str,
+    ) -> Option<&'static str> {
+        let headers: HeaderMap = headers
+            .iter()
+            .map(|(name, value)| {
+                (
+                    HeaderName::from_bytes(name.as_bytes()).unwrap(),
+                    HeaderValue::from_str(value).unwrap(),
+                )
+            })
+            .collect();
+        signature_error(
+            env,
+            |name| {
+                headers
+                    .get(name)
+                    .and_then(|v| v.to_str().ok())
+                    .map(str::to_string)
+            },
+            payload,
+        )
+    }
+
+    #[test]
+    fn signature_is_read_from_the_default_header() {
+        let env = test_vars().with_secret("MINIFLUX_WEBHOOK_SECRET", "s3cret");
+        let payload = r#"{"event_type":"new_entries"}"#;
+        let signature = sign("s3cret", payload);
+
+        assert_eq!(signature_header(&env), "X-Miniflux-Signature");
+        assert_eq!(
+            signed_with(
+                &[("X-Miniflux-Signature", signature.clone())],
+                &env,
+                payload
+            ),
+            None
+        );
+        assert_eq!(
+            signed_with(
+                &[("X-Miniflux-Signature", sign("other", payload))],
+                &env,
+                payload
+            ),
+            Some("Invalid signature")
+        );
+        assert_eq!(
+            signed_with(&[("X-Signature", signature)], &env, payload),
+            Some("Missing signature")
+        );
+    }
+
+    // 代理改写了请求头名称时只读取配置的请求头
+    #[test]
+    fn signature_header_is_configurable() {
+        let env = test_vars()
+            .with_secret("MINIFLUX_WEBHOOK_SECRET", "s3cret")
+            .with_var("WEBHOOK_SIGNATURE_HEADER", "X-Forwarded-Signature");
+        let payload = "{}";
+        let signature = sign("s3cret", payload);
+
+        assert_eq!(signature_header(&env), "X-Forwarded-Signature");
+        assert_eq!(
+            signed_with(
+                &[("x-forwarded-signature", signature.clone())],
+                &env,
+                payload
+            ),
+            None
+        );
+        assert_eq!(
+            signed_with(&[("X-Miniflux-Signature", signature)], &env, payload),
+            Some("Missing signature")
+        );
+    }
+
+    // 白名单和 FEED_SETTINGS 的键都忽略末尾斜杠、协议和主机名的大小写，路径区分大小写
+    #[test]
+    fn site_urls_match_ignoring_trailing_slash_and_host_case() {
+        let cases = [
+            ("https://example.com", "https://example.com/", true),
+            ("https://example.com/", "https://example.com", true),
+            ("https://example.com//", "https://example.com", true),
+            ("HTTPS://Example.COM", "https://example.com", true),
+            (
+                "https://example.com/blog/",
+                "https://EXAMPLE.com/blog",
+                true,
+            ),
+            (
+                " https://example.com/blog ",
+                "https://example.com/blog/",
+                true,
+            ),
+            (
+                "https://example.com/Blog",
+                "https://example.com/blog",
+                false,
+            ),
+            ("https://example.com", "http://example.com", false),
+            ("https://example.com", "https://example.com/blog", false),
+        ];
+        let exec = Rc::new(MockExec::new(&[]));
+        for (configured, site_url, expected) in cases {
+            let env = test_vars()
+                .with_var(
+                    "WHITELIST_URL",
+                    &format!("https://other.example,{}", configured),
+                )
+                .with_var(
+                    "FEED_SETTINGS",
+                    &serde_json::json!({ configured: { "prompt": "feed prompt" } }).to_string(),
+                );
+            let config = test_config(&env, &exec);
+            assert_eq!(
+                config.is_whitelisted(site_url),
+                expected,
+                "{} vs {}",
+                configured,
+                site_url
+            );
+
+            let entry: Entry = serde_json::from_value(serde_json::json!({
+                "id": 1,
+                "content": "",
+                "feed": { "site_url": site_url },
+            }))
+            .unwrap();
+            assert_eq!(
+                config.feed_settings(&entry).prompt == "feed prompt",
+                expected,
+                "{} vs {}",
+                configured,
+                site_url
+            );
+        }
+    }
+
+    fn entry_in(site_url: &str, category: Option<&str>) -> Entry {
+        let mut feed = serde_json::json!({ "site_url": site_url });
+        if let Some(category) = category {
+            feed["category"] = serde_json::json!({ "title": category });
+        }
+        serde_json::from_value(serde_json::json!({ "id": 1, "content": "", "feed": feed })).unwrap()
+    }
+
+    #[test]
+    fn parses_category_models() {
+        let env = test_vars().with_var(
+            "CATEGORY_MODELS",
+            r#"{" News ": " news-model ", "Tech": "tech-model", "Blogs": " "}"#,
+        );
+        let models = parse_category_models(&env);
+        assert_eq!(models.len(), 2);
+        assert_eq!(models["news"], "news-model");
+        assert_eq!(models["tech"], "tech-model");
+
+        let env = test_vars().with_var("CATEGORY_MODELS", r#"["news-model"]"#);
+        assert!(parse_category_models(&env).is_empty());
+        assert!(parse_category_models(&test_vars()).is_empty());
+    }
+
+    // 分类名称不区分大小写，没有分类或分类未配置时使用 OPENAI_MODEL
+    #[test]
+    fn model_is_resolved_per_entry_from_its_category() {
+        let exec = Rc::new(MockExec::new(&[]));
+        let env = test_vars().with_var(
+            "CATEGORY_MODELS",
+            r#"{"News": "news-model", "Tech": "tech-model"}"#,
+        );
+        let config = test_config(&env, &exec);
+        let model = |entry: Entry| config.feed_settings(&entry).model;
+
+        assert_eq!(
+            model(entry_in("https://a.example", Some("news"))),
+            "news-model"
+        );
+        assert_eq!(
+            model(entry_in("https://b.example", Some(" TECH "))),
+            "tech-model"
+        );
+        assert_eq!(
+            model(entry_in("https://c.example", Some("Sports"))),
+            "test-model"
+        );
+        assert_eq!(model(entry_in("https://d.example", None)), "test-model");
+        assert_eq!(model(entry("")), "test-model");
+    }
+
+    #[test]
+    fn parses_category_styles() {
+        let env = test_vars().with_var(
+            "CATEGORY_STYLES",
+            r#"{"News": " neutral ", " Blogs ": "casual", "Tech": ""}"#,
+        );
+        let styles = parse_category_styles(&env);
+        assert_eq!(styles.len(), 2);
+        assert_eq!(styles["news"], "neutral");
+        assert_eq!(styles["blogs"], "casual");
+
+        let env = test_vars().with_var("CATEGORY_STYLES", "neutral");
+        assert!(parse_category_styles(&env).is_empty());
+    }
+
+    // 分类的语气和模型分别生效，FEED_SETTINGS 中的提示词不影响语气
+    #[test]
+    fn style_is_resolved_per_entry_from_its_category() {
+        let exec = Rc::new(MockExec::new(&[]));
+        let env = test_vars()
+            .with_var(
+                "CATEGORY_STYLES",
+                r#"{"News": "neutral", "Blogs": "casual"}"#,
+            )
+            .with_var("CATEGORY_MODELS", r#"{"News": "news-model"}"#)
+            .with_var(
+                "FEED_SETTINGS",
+                r#"{"https://b.example": {"prompt": "preset:concise_zh"}}"#,
+            );
+        let config = test_config(&env, &exec);
+        let settings = |entry: Entry| {
+            let settings = config.feed_settings(&entry);
+            (settings.style, settings.model)
+        };
+
+        assert_eq!(
+            settings(entry_in("https://a.example", Some("NEWS"))),
+            (Some("neutral".to_string()), "news-model".to_string())
+        );
+        assert_eq!(
+            settings(entry_in("https://b.example", Some("blogs"))),
+            (Some("casual".to_string()), "test-model".to_string())
+        );
+        assert_eq!(
+            settings(entry_in("https://c.example", Some("Sports"))),
+            (None, "test-model".to_string())
+        );
+        assert_eq!(settings(entry("")), (None, "test-model".to_string()));
+    }
+
+    #[test]
+    fn memoized_feed_settings_match_fresh_resolution() {
+        let exec = Rc::new(MockExec::new(&[]));
+        let env = test_vars()
+            .with_var("CATEGORY_MODELS", r#"{"News": "news-model"}"#)
+            .with_var(
+                "FEED_SETTINGS",
+                r#"{"https://a.example": {"fetch_full_content": true}}"#,
+            )
+            .with_var(
+                "FEED_FEATURES",
+                r#"{"https://b.example": {"summarize": false}}"#,
+            );
+        let config = test_config(&env, &exec);
+        let entries = [
+            entry_in("https://a.example", Some("News")),
+            entry_in("https://a.example/", Some("news")),
+            entry_in("https://a.example", Some("Tech")),
+            entry_in("https://b.example", Some("News")),
+            entry_in("https://c.example", None),
+        ];
+
+        // 第一次解析并写入缓存，第二次从缓存读取，都和直接解析的结果一致
+        for _ in 0..2 {
+            for entry in &entries {
+                let feed = entry.feed.as_ref().unwrap();
+                let category = feed
+                    .category
+                    .as_ref()
+                    .map(|c| c.title.to_lowercase())
+                    .unwrap_or_default();
+                let fresh =
+                    config.resolve_feed_settings(&normalize_site_url(&feed.site_url), &category);
+                assert_eq!(config.feed_settings(entry), fresh, "{}", feed.site_url);
+            }
+        }
+        // 归一化后相同的订阅源和分类共用一项缓存
+        assert_eq!(config.settings_memo.with(|memo| memo.len()), 4);
+    }
+
+    // 同一订阅源的不同分类、同一分类的不同订阅源分别缓存，不会互相覆盖
+    #[test]
+    fn feed_settings_memo_keeps_feeds_apart() {
+        let exec = Rc::new(MockExec::new(&[]));
+        let env = test_vars()
+            .with_var("CATEGORY_MODELS", r#"{"News": "news-model"}"#)
+            .with_var(
+                "FEED_SETTINGS",
+                r#"{"https://a.example": {"fetch_full_content": true}}"#,
+            );
+        let config = test_config(&env, &exec);
+
+        let a_news = config.feed_settings(&entry_in("https://a.example", Some("News")));
+        let a_tech = config.feed_settings(&entry_in("https://a.example", Some("Tech")));
+        let b_news = config.feed_settings(&entry_in("https://b.example", Some("News")));
+        assert_eq!(
+            (a_news.model.as_str(), a_news.fetch_full_content),
+            ("news-model", true)
+        );
+        assert_eq!(
+            (a_tech.model.as_str(), a_tech.fetch_full_content),
+            ("test-model", true)
+        );
+        assert_eq!(
+            (b_news.model.as_str(), b_news.fetch_full_content),
+            ("news-model", false)
+        );
+        assert_eq!(
+            config.feed_settings(&entry_in("https://a.example", Some("News"))),
+            a_news
+        );
+    }
+
+    // 和 process_entries 一样按 EMPTY_FEED_STREAK 记录空摘要，达到阈值后才跳过该订阅源
+    #[test]
+    fn feeds_are_skipped_once_the_empty_streak_reaches_the_threshold() {
+        let exec = Rc::new(MockExec::new(&[]));
+        let env = test_vars()
+            .with_var("WHITELIST_URL", "https://a.example,https://b.example")
+            .with_var("EMPTY_FEED_STREAK", "3");
+        let config = test_config(&env, &exec);
+        let a = entry_in("https://a.example/", None);
+        let b = entry_in("https://b.example", None);
+
+        let mut health: HashMap<String, FeedHealth> = HashMap::new();
+        for empties in 1..=3 {
+            assert_eq!(skip_reason(&config, &a, &health, 0), None, "{}", empties);
+            let started = health
+                .entry(normalize_site_url("https://a.example"))
+                .or_default()
+                .record_empty(config.empty_feed_streak);
+            assert_eq!(started, empties == 3);
+        }
+        assert_eq!(
+            skip_reason(&config, &a, &health, 0),
+            Some(SkipReason::EmptyFeed)
+        );
+        assert_eq!(skip_reason(&config, &b, &health, 0), None);
+
+        // 成功的摘要重新开始计数
+        let mut health: HashMap<String, FeedHealth> = HashMap::new();
+        let h = health
+            .entry(normalize_site_url("https://a.example"))
+            .or_default();
+        h.record_empty(config.empty_feed_streak);
+        h.record_empty(config.empty_feed_streak);
+        h.record_summary();
+        h.record_empty(config.empty_feed_streak);
+        assert_eq!(skip_reason(&config, &a, &health, 0), None);
+    }
+
+    #[test]
+    fn zero_empty_streak_never_skips() {
+        let exec = Rc::new(MockExec::new(&[]));
+        let env = test_vars()
+            .with_var("WHITELIST_URL", "https://a.example")
+            .with_var("EMPTY_FEED_STREAK", "0");
+        let config = test_config(&env, &exec);
+        let a = entry_in("https://a.example", None);
+
+        let mut health: HashMap<String, FeedHealth> = HashMap::new();
+        let h = health
+            .entry(normalize_site_url("https://a.example"))
+            .or_default();
+        for _ in 0..100 {
+            assert!(!h.record_empty(config.empty_feed_streak));
+        }
+        assert_eq!(skip_reason(&config, &a, &health, 0), None);
+    }
+
+    const FEED_URL: &str = "https://feed.example";
+
+    fn feed_entry(id: u64, day: u32, content: &str) -> Entry {
+        let mut entry = dated(id, day);
+        entry["title"] = serde_json::json!(format!("Entry {}", id));
+        entry["content"] = serde_json::json!(content);
+        entry["feed"] = serde_json::json!({ "site_url": FEED_URL });
+        serde_json::from_value(entry).unwrap()
+    }
+
+    // Miniflux 写回成功，模型返回固定的摘要
+    fn summarizing_exec() -> Rc<MockExec> {
+        Rc::new(MockExec::with_handler(|request| {
+            if request.url.ends_with("/v1/chat/completions") {
+                completion("A short summary.")
+            } else if request.method == "PUT" {
+                MockResponse::status(204)
+            } else {
+                MockResponse::status(404)
+            }
+        }))
+    }
+
+    fn updated_ids(exec: &MockExec) -> Vec<u64> {
+        exec.requests()
+            .iter()
+            .filter(|r| r.method == "PUT")
+            .filter_map(|r| entry_path(r).strip_prefix("/v1/entries/")?.parse().ok())
+            .collect()
+    }
+
+    #[test]
+    fn stop_at_processed_keeps_only_entries_newer_than_the_first_processed() {
+        let processed = template::prepend_block(
+            &template::render_block(BlockStyle::Pre, SUMMARY_LABEL, "Old summary.", "", None),
+            "<p>done</p>",
+        );
+        let entries = || {
+            vec![
+                feed_entry(1, 1, "<p>oldest</p>"),
+                feed_entry(4, 4, "<p>newer</p>"),
+                feed_entry(3, 3, &processed),
+                feed_entry(5, 5, "<p>newest</p>"),
+                feed_entry(2, 2, "<p>older</p>"),
+            ]
+        };
+        let env = test_vars()
+            .with_var("WHITELIST_URL", FEED_URL)
+            .with_var("SAFE_MODE", "true");
+
+        let exec = summarizing_exec();
+        let config = test_config(&env.clone().with_var("STOP_AT_PROCESSED", "true"), &exec);
+        let report = block_on(process_entries(&config, entries()));
+        assert_eq!(report.summarized, 2);
+        assert_eq!(updated_ids(&exec), vec![5, 4]);
+
+        // 关闭时跳过已处理的文章，更早的文章照常处理
+        let exec = summarizing_exec();
+        let report = block_on(process_entries(&test_config(&env, &exec), entries()));
+        assert_eq!(report.summarized, 4);
+        let mut ids = updated_ids(&exec);
+        ids.sort();
+        assert_eq!(ids, vec![1, 2, 4, 5]);
+    }
+
+    fn request_log(exec: &MockExec) -> Vec<String> {
+        exec.requests()
+            .iter()
+            .map(|r| format!("{} {}", r.method, entry_path(r)))
+            .collect()
+    }
+
+    // 安全模式下逐篇处理，每篇文章的模型请求和写回完成后才开始下一篇
+    #[test]
+    fn safe_mode_processes_entries_one_at_a_time_in_order() {
+        let entries = || {
+            vec![
+                feed_entry(1, 1, "<p>one</p>"),
+                feed_entry(3, 3, "<p>three</p>"),
+                feed_entry(2, 2, "<p>two</p>"),
+            ]
+        };
+        let env = test_vars()
+            .with_var("WHITELIST_URL", FEED_URL)
+            .with_var("MAX_CONCURRENCY", "3");
+
+        let exec = summarizing_exec();
+        let config = test_config(&env.clone().with_var("SAFE_MODE", "true"), &exec);
+        block_on(process_entries(&config, entries()));
+        assert_eq!(
+            request_log(&exec),
+            [
+                "POST /v1/chat/completions",
+                "PUT /v1/entries/3",
+                "POST /v1/chat/completions",
+                "PUT /v1/entries/2",
+                "POST /v1/chat/completions",
+                "PUT /v1/entries/1",
+            ]
+        );
+
+        // 不开启时按 MAX_CONCURRENCY 并发，模型请求先全部发出
+        let exec = summarizing_exec();
+        block_on(process_entries(&test_config(&env, &exec), entries()));
+        assert_eq!(request_log(&exec)[..3], ["POST /v1/chat/completions"; 3]);
+    }
+
+    fn entry_of(id: u64, site_url: &str) -> Entry {
+        let mut entry = feed_entry(id, id as u32, &format!("<p>body {}</p>", id));
+        entry.feed.as_mut().unwrap().site_url = site_url.to_string();
+        entry
+    }
+
+    // 每组订阅源设置单独处理和汇报，一组配置错误或模型失败不影响其他组
+    #[test]
+    fn partitions_are_processed_and_reported_independently() {
+        let env = test_vars()
+            .with_var(
+                "WHITELIST_URL",
+                "https://a.example,https://b.example,https://bad.example",
+            )
+            .with_var(
+                "FEED_SETTINGS",
+                r#"{"https://b.example": {"prompt": "failing prompt"},
+                    "https://bad.example": {"prompt": "preset:nope"}}"#,
+            );
+        let exec = Rc::new(MockExec::with_handler(|request| {
+            if request.url.ends_with("/v1/chat/completions") {
+                if String::from_utf8_lossy(&request.body).contains("failing prompt") {
+                    MockResponse::status(500)
+                } else {
+                    completion("A short summary.")
+                }
+            } else if request.method == "PUT" {
+                MockResponse::status(204)
+            } else {
+                MockResponse::status(404)
+            }
+        }));
+        let config = test_config(&env, &exec);
+        let entries = vec![
+            entry_of(1, "https://a.example"),
+            entry_of(2, "https://bad.example"),
+            entry_of(3, "https://b.example"),
+            entry_of(4, "https://a.example"),
+        ];
+
+        let partitions = block_on(process_partitions(&config, entries));
+        let summary: Vec<_> = partitions
+            .iter()
+            .map(|p| {
+                (
+                    p.feeds.clone(),
+                    p.entries.clone(),
+                    p.error.is_some(),
+                    p.report.as_ref().map(|r| (r.summarized, r.failed)),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    vec!["https://a.example".to_string()],
+                    vec![1, 4],
+                    false,
+                    Some((2, 0))
+                ),
+                (vec!["https://bad.example".to_string()], vec![2], true, None),
+                (
+                    vec!["https://b.example".to_string()],
+                    vec![3],
+                    false,
+                    Some((0, 1))
+                ),
+            ]
+        );
+        let mut updated = updated_ids(&exec);
+        updated.sort();
+        assert_eq!(updated, vec![1, 4]);
+    }
+
+    fn summarized_body() -> String {
+        template::prepend_block(
+            &template::render_block(BlockStyle::Pre, SUMMARY_LABEL, "A summary.", "", None),
+            "<p>body</p>",
+        )
+    }
+
+    // 写回前按 WRITE_DELAY_MS 等待，写回后重新读取文章，返回的内容由 reread 决定
+    fn write_with_verify(reread: &str) -> (Result<bool, String>, Vec<String>) {
+        let reread = reread.to_string();
+        let env = test_vars()
+            .with_var("WRITE_DELAY_MS", "250")
+            .with_var("VERIFY_AFTER_UPDATE", "true");
+        let exec = Rc::new(MockExec::with_handler(move |request| {
+            let delayed = platform::delays().contains(&Duration::from_millis(250));
+            match request.method.as_str() {
+                "PUT" if delayed => MockResponse::status(204),
+                "GET" => {
+                    let mut entry = dated(1, 1);
+                    entry["content"] = serde_json::json!(&reread);
+                    MockResponse::json(entry)
+                }
+                _ => MockResponse::status(500),
+            }
+        }));
+        let config = test_config(&env, &exec);
+        let update = UpdateRequest {
+            content: Some(summarized_body()),
+            ..Default::default()
+        };
+
+        let result = block_on(write_update(
+            &config,
+            &feed_entry(1, 1, "<p>body</p>"),
+            &update,
+        ))
+        .map_err(|e| e.to_string());
+        (result, request_log(&exec))
+    }
+
+    #[test]
+    fn write_update_rereads_the_entry_after_the_delayed_write() {
+        let (result, log) = write_with_verify(&summarized_body());
+        assert_eq!(result, Ok(true));
+        assert_eq!(log, vec!["PUT /v1/entries/1", "GET /v1/entries/1"]);
+    }
+
+    // Miniflux 清理掉摘要标记时报错，交给重试处理
+    #[test]
+    fn write_update_detects_a_stripped_summary() {
+        let (result, log) = write_with_verify("<p>body</p>");
+        assert_eq!(
+            result,
+            Err("Summary marker of entry 1 was stripped after the update".to_string())
+        );
+        assert_eq!(log, vec!["PUT /v1/entries/1", "GET /v1/entries/1"]);
+    }
+
+    fn put_bodies(exec: &MockExec) -> Vec<serde_json::Value> {
+        exec.requests()
+            .iter()
+            .filter(|r| r.method == "PUT")
+            .map(|r| serde_json::from_slice(&r.body).unwrap())
+            .collect()
+    }
+
+    // PRESERVE_READING_TIME 开启时把原文的阅读时间随摘要一起写回，原文没有阅读时间时不写
+    #[test]
+    fn summary_update_preserves_reading_time() {
+        for (preserve, reading_time, expected) in
+            [("true", 7, Some(7)), ("true", 0, None), ("false", 7, None)]
+        {
+            let env = test_vars()
+                .with_var("WHITELIST_URL", FEED_URL)
+                .with_var("PRESERVE_READING_TIME", preserve);
+            let exec = summarizing_exec();
+            let config = test_config(&env, &exec);
+            let mut entry = feed_entry(1, 1, "<p>body</p>");
+            entry.reading_time = reading_time;
+
+            let report = block_on(process_entries(&config, vec![entry]));
+            assert_eq!(report.summarized, 1);
+            let bodies = put_bodies(&exec);
+            assert_eq!(bodies.len(), 1);
+            assert!(bodies[0]["content"].is_string());
+            assert_eq!(
+                bodies[0].get("reading_time").and_then(|v| v.as_u64()),
+                expected,
+                "{} {}",
+                preserve,
+                reading_time
+            );
+        }
+    }
+
+    // ARTICLE_ACCEPT_LANGUAGE 只在配置时随原文请求发送
+    #[test]
+    fn article_requests_send_the_configured_accept_language() {
+        for (configured, expected) in [
+            (Some("zh-CN,zh;q=0.9"), Some("zh-CN,zh;q=0.9")),
+            (None, None),
+        ] {
+            let env = match configured {
+                Some(value) => test_vars().with_var("ARTICLE_ACCEPT_LANGUAGE", value),
+                None => test_vars(),
+            };
+            let exec = Rc::new(MockExec::with_handler(|_| {
+                MockResponse::status(200).body("<p>article</p>")
+            }));
+            let config = test_config(&env, &exec);
+
+            let page = block_on(fetch_article(&config, "https://blog.example/post")).unwrap();
+            assert_eq!(page, "<p>article</p>");
+            let requests = exec.requests();
+            assert_eq!(requests.len(), 1);
+            assert_eq!(
+                requests[0]
+                    .headers
+                    .get(ACCEPT_LANGUAGE)
+                    .map(|v| v.to_str().unwrap()),
+                expected
+            );
+            assert!(requests[0].headers.contains_key(USER_AGENT));
+        }
+    }
+
+    // SUMMARY_TARGET=tags 时摘要作为标签写回，正文不变，带摘要标签的文章下次不再处理
+    #[test]
+    fn tag_target_writes_the_summary_as_a_tag() {
+        let env = test_vars()
+            .with_var("WHITELIST_URL", FEED_URL)
+            .with_var("SUMMARY_TARGET", "tags");
+        let exec = summarizing_exec();
+        let config = test_config(&env, &exec);
+        let mut entry = feed_entry(1, 1, "<p>body</p>");
+        entry.tags = vec!["rust".to_string()];
+
+        let report = block_on(process_entries(&config, vec![entry]));
+        assert_eq!(report.summarized, 1);
+        let bodies = put_bodies(&exec);
+        assert_eq!(
+            bodies,
+            vec![serde_json::json!({
+                "tags": ["rust", format!("{}A short summary.", SUMMARY_LABEL)],
+            })]
+        );
+
+        let mut entry = feed_entry(1, 1, "<p>body</p>");
+        entry.tags = serde_json::from_value(bodies[0]["tags"].clone()).unwrap();
+        let exec = summarizing_exec();
+        let config = test_config(&env, &exec);
+        let report = block_on(process_entries(&config, vec![entry]));
+        assert_eq!(report.summarized, 0);
+        assert!(exec.requests().is_empty());
+    }
+
+    // 签名按实际发送的请求体计算，接收方可以直接用原始字节验证
+    #[test]
+    fn digest_signature_matches_the_sent_body() {
+        let digest = Digest {
+            entries: vec![DigestEntry {
+                id: 1,
+                title: "标题 \"quoted\"".to_string(),
+                url: "https://blog.example/post".to_string(),
+                summary: "A short summary.".to_string(),
+            }],
+        };
+        for secret in [Some("digest-secret"), None] {
+            let exec = Rc::new(MockExec::new(&[204]));
+            let config = test_config(&test_vars(), &exec);
+            let webhook = DigestWebhook {
+                url: "https://hooks.example/digest".to_string(),
+                secret: secret.map(str::to_string),
+            };
+
+            block_on(send_digest(&config, &webhook, &digest)).unwrap();
+            let requests = exec.requests();
+            assert_eq!(requests.len(), 1);
+            let body = std::str::from_utf8(&requests[0].body).unwrap();
+            assert_eq!(
+                serde_json::from_str::<serde_json::Value>(body).unwrap()["entries"][0]["id"],
+                1
+            );
+            let header = requests[0]
+                .headers
+                .get(DIGEST_SIGNATURE_HEADER)
+                .map(|v| v.to_str().unwrap().to_string());
+            match secret {
+                Some(secret) => {
+                    assert_eq!(header, Some(sign(secret, body)));
+                    assert!(signature::verify(secret, body, &header.unwrap()));
+                }
+                None => assert_eq!(header, None),
+            }
+        }
+    }
+
+    // 对照组和金丝雀组使用同名模型时，只有金丝雀组的文章发给 CANARY 的服务商
+    #[test]
+    fn control_entries_sharing_the_canary_model_use_the_default_provider() {
+        let env = test_vars().with_var(
+            "CANARY",
+            r#"{"fraction": 0.5, "provider": "workers-ai", "model": "test-model"}"#,
+        );
+        let exec = summarizing_exec();
+        let config = test_config(&env, &exec);
+        let canary = &config.canary.as_ref().unwrap().settings;
+        let arms: Vec<Arm> = (1..=8).map(|id| canary.arm(id)).collect();
+        assert!(arms.contains(&Arm::Control) && arms.contains(&Arm::Canary));
+
+        for id in 1..=8 {
+            let entry = feed_entry(id, 1, "<p>body</p>");
+            let capture = Capturer::new(None);
+            let mut steps = Vec::new();
+            let outcome = block_on(generate_and_update_entry(
+                &config, &capture, &entry, &mut steps,
+            ))
+            .unwrap();
+            assert!(matches!(outcome, EntryOutcome::Summarized(_)), "{}", id);
+        }
+
+        // 测试中没有 Workers AI 绑定，金丝雀组失败后改用原设置，对照组直接成功
+        let records: Vec<(Arm, Attempt, bool)> = config.canary_records.with(|records| {
+            records
+                .iter()
+                .map(|(arm, attempt, _, fell_back)| (*arm, *attempt, *fell_back))
+                .collect()
+        });
+        let expected: Vec<(Arm, Attempt, bool)> = arms
+            .iter()
+            .map(|arm| match arm {
+                Arm::Control => (Arm::Control, Attempt::Succeeded, false),
+                Arm::Canary => (Arm::Canary, Attempt::Failed, true),
+            })
+            .collect();
+        assert_eq!(records, expected);
+        assert_eq!(updated_ids(&exec), (1..=8).collect::<Vec<u64>>());
+    }
+
+    // 达到 DEADLETTER_AFTER 后失败的文章写入失败提示，拒答的文章不修改，只在死信列表中标记
+    #[test]
+    fn refused_entries_are_dead_lettered_without_a_failure_notice() {
+        for refused in [false, true] {
+            let env = test_vars().with_var("DEADLETTER_AFTER", "2");
+            let exec = summarizing_exec();
+            let config = test_config(&env, &exec);
+            let entry = feed_entry(1, 1, "<p>body</p>");
+            let mut deadletter = DeadLetterLog::default();
+
+            for _ in 0..2 {
+                block_on(record_deadletter(
+                    &config,
+                    &mut deadletter,
+                    &entry,
+                    None,
+                    "failed".to_string(),
+                    refused,
+                    0,
+                ));
+            }
+            assert_eq!(deadletter.entries.len(), 1);
+            assert_eq!(deadletter.is_refused(1), refused);
+            let bodies = put_bodies(&exec);
+            if refused {
+                assert!(bodies.is_empty());
+            } else {
+                assert_eq!(bodies.len(), 1);
+                assert!(bodies[0]["content"]
+                    .as_str()
+                    .unwrap()
+                    .contains(FAILURE_LABEL));
+            }
+        }
+    }
+
+    // 已有摘要的文章只有 force 时才重新摘要，新的摘要块替换旧的
+    #[test]
+    fn manual_summaries_replace_an_existing_summary_only_when_forced() {
+        let processed = template::prepend_block(
+            &template::render_block(BlockStyle::Pre, SUMMARY_LABEL, "Old summary.", "", None),
+            "<p>body</p>",
+        );
+        for force in [false, true] {
+            let processed = processed.clone();
+            let exec = Rc::new(MockExec::with_handler(move |request| {
+                match (request.method.as_str(), entry_path(request).as_str()) {
+                    ("GET", "/v1/entries/1") => {
+                        let mut entry = dated(1, 1);
+                        entry["content"] = serde_json::json!(&processed);
+                        MockResponse::json(entry)
+                    }
+                    ("PUT", "/v1/entries/1") => MockResponse::status(204),
+                    (_, "/v1/chat/completions") => completion("A new summary."),
+                    _ => MockResponse::status(404),
+                }
+            }));
+            let config = test_config(&test_vars(), &exec);
+
+            let result = block_on(summarize_entry(&config, 1, force));
+            if !force {
+                assert!(matches!(result, ManualSummary::AlreadyProcessed));
+                assert_eq!(request_log(&exec), vec!["GET /v1/entries/1"]);
+                continue;
+            }
+            assert!(matches!(
+                result,
+                ManualSummary::Finished(Ok(EntryOutcome::Summarized(ref s))) if s == "A new summary."
+            ));
+            assert_eq!(
+                request_log(&exec),
+                vec![
+                    "GET /v1/entries/1",
+                    "POST /v1/chat/completions",
+                    "PUT /v1/entries/1"
+                ]
+            );
+            let content = put_bodies(&exec)[0]["content"]
+                .as_str()
+                .unwrap()
+                .to_string();
+            assert!(content.contains("A new summary."));
+            assert!(!content.contains("Old summary."));
+            assert_eq!(template::strip_summary_block(&content), Some("<p>body</p>"));
+        }
+    }
+
+    // KV 中没有记录或记录损坏时对应项为 null，健康检查按从未成功处理
+    #[test]
+    fn diag_degrades_missing_or_invalid_kv_values_to_null() {
+        const HOUR: u64 = 60 * 60 * 1000;
+        let policy = HealthPolicy {
+            max_age_hours: 6,
+            grace_hours: 24,
+        };
+        let now = 100 * HOUR;
+        fn bad<T>() -> Result<Option<T>, String> {
+            Err("invalid json".to_string())
+        }
+
+        let run = diag_run::<String>(policy, now, Ok(None), Ok(None), Ok(None));
+        assert_eq!((run.last_run, run.last_run_tokens), (None, None));
+        assert!(run.health.healthy);
+        assert_eq!(run.health.last_success, "never");
+
+        let run = diag_run(policy, now, bad(), bad(), bad());
+        assert_eq!((run.last_run, run.last_run_tokens), (None, None));
+        assert!(run.health.healthy);
+        assert_eq!(run.health.last_success, "never");
+
+        // 只有一项损坏时其他项照常返回
+        let last_run = serde_json::json!({ "summarized": 2, "estimated_tokens": 1200 });
+        let last_success = LastSuccess {
+            at: now - 2 * HOUR,
+            entry_id: 7,
+            site_url: None,
+        };
+        let run = diag_run(
+            policy,
+            now,
+            Ok(Some(last_run.clone())),
+            Ok(Some(last_success)),
+            bad(),
+        );
+        assert_eq!(run.last_run, Some(last_run));
+        assert_eq!(run.last_run_tokens, Some(serde_json::json!(1200)));
+        assert!(run.health.healthy);
+        assert_eq!(run.health.age_seconds, Some(2 * 60 * 60));
+
+        // 第一次检查的时间可读、没有成功记录时按宽限期判断
+        let run = diag_run(policy, now, bad(), Ok(None), Ok(Some(now - 48 * HOUR)));
+        assert_eq!(run.last_run, None);
+        assert!(!run.health.healthy);
+    }
+
+    #[test]
+    fn bearer_tokens_must_match_exactly() {
+        assert!(bearer_matches(Some("Bearer admin-token"), "admin-token"));
+        assert!(!bearer_matches(Some("Bearer admin-toke"), "admin-token"));
+        assert!(!bearer_matches(Some("Bearer admin-token2"), "admin-token"));
+        assert!(!bearer_matches(Some("bearer admin-token"), "admin-token"));
+        assert!(!bearer_matches(Some("admin-token"), "admin-token"));
+        assert!(!bearer_matches(Some("Bearer "), "admin-token"));
+        assert!(!bearer_matches(None, "admin-token"));
+    }
+
+    // 查询参数中的令牌按解码后的值比较，任意一个 token 参数匹配即可
+    #[test]
+    fn feed_tokens_are_read_from_the_query() {
+        let matches =
+            |url: &str| query_token_matches(&reqwest::Url::parse(url).unwrap(), "feed token");
+        assert!(matches("https://w.example/feed.xml?token=feed%20token"));
+        assert!(matches(
+            "https://w.example/feed.xml?token=x&token=feed+token"
+        ));
+        assert!(!matches("https://w.example/feed.xml?token=feed"));
+        assert!(!matches("https://w.example/feed.xml?other=feed%20token"));
+        assert!(!matches("https://w.example/feed.xml"));
+    }
 }
@@ -0,0 +1,249 @@
+use crate::platform::console_warn;
+use sha2::{Digest, Sha256};
+use std::future::Future;
+use worker::async_trait::async_trait;
+
+// 缓存键所在的虚拟地址，只用于 Cache API 索引，不会真的发出请求
+const CACHE_KEY_BASE: &str = "https://miniflux-ai.internal/llm-cache/";
+
+// 通过 Workers Cache API 缓存相同请求的模型输出，例如部分失败后重跑时不重复计费
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LlmCache {
+    pub ttl_seconds: u64,
+    pub max_bytes: usize,
+}
+
+impl LlmCache {
+    // 输出过大时不缓存，避免占用过多缓存空间
+    pub fn is_storable(&self, output: &str) -> bool {
+        !output.is_empty() && output.len() <= self.max_bytes
+    }
+
+    pub fn cache_control(&self) -> String {
+        format!("public, max-age={}", self.ttl_seconds)
+    }
+}
+
+// 参与缓存键的请求参数
+#[derive(Debug, Clone, Copy)]
+pub struct KeyParts<'a> {
+    pub provider: &'a str,
+    pub url: &'a str,
+    pub model: &'a str,
+    pub content_path: Option<&'a str>,
+    pub stream: bool,
+    pub body: &'a str,
+}
+
+// 所有影响输出的部分都参与哈希：服务商、接口地址、模型、输出路径、是否流式和完整的消息
+pub fn cache_key(parts: KeyParts) -> String {
+    let mut hasher = Sha256::new();
+    for part in [
+        parts.provider,
+        parts.url,
+        parts.model,
+        parts.content_path.unwrap_or_default(),
+        if parts.stream { "stream" } else { "" },
+        parts.body,
+    ] {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+    format!("{}{}", CACHE_KEY_BASE, hex::encode(hasher.finalize()))
+}
+
+// 模型输出的存储，线上使用 Workers Cache API，测试中可以换成内存实现
+#[async_trait(?Send)]
+pub trait OutputCache {
+    async fn get(&self, key: &str) -> Result<Option<String>, String>;
+    async fn put(&self, key: &str, output: &str, cache_control: &str) -> Result<(), String>;
+}
+
+pub struct EdgeCache;
+
+#[async_trait(?Send)]
+impl OutputCache for EdgeCache {
+    async fn get(&self, key: &str) -> Result<Option<String>, String> {
+        match worker::Cache::default().get(key, true).await {
+            Ok(Some(mut cached)) => cached.text().await.map(Some).map_err(|e| e.to_string()),
+            Ok(None) => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    async fn put(&self, key: &str, output: &str, cache_control: &str) -> Result<(), String> {
+        let mut response = worker::Response::ok(output).map_err(|e| e.to_string())?;
+        response
+            .headers_mut()
+            .set("Cache-Control", cache_control)
+            .map_err(|e| e.to_string())?;
+        worker::Cache::default()
+            .put(key, response)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+// 命中时直接返回缓存，否则生成后按 LlmCache 的限制写入，缓存出错只记录日志
+pub async fn get_or_generate<E>(
+    store: &dyn OutputCache,
+    policy: LlmCache,
+    key: &str,
+    generate: impl Future<Output = Result<String, E>>,
+) -> Result<String, E> {
+    match store.get(key).await {
+        Ok(Some(output)) => return Ok(output),
+        Ok(None) => {}
+        Err(e) => console_warn!("Failed to query model output cache: {}", e),
+    }
+
+    let output = generate.await?;
+    if policy.is_storable(&output) {
+        if let Err(e) = store.put(key, &output, &policy.cache_control()).await {
+            console_warn!("Failed to cache model output: {}", e);
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared_state::Shared;
+    use futures::executor::block_on;
+    use std::collections::HashMap;
+
+    const PARTS: KeyParts = KeyParts {
+        provider: "openai",
+        url: "https://api.openai.com/v1/chat/completions",
+        model: "gpt-4o-mini",
+        content_path: None,
+        stream: false,
+        body: r#"[{"role":"user","content":"hi"}]"#,
+    };
+
+    #[test]
+    fn key_is_stable_and_namespaced() {
+        let key = cache_key(PARTS);
+        assert_eq!(key, cache_key(PARTS));
+        assert!(key.starts_with(CACHE_KEY_BASE));
+        assert_eq!(key.len(), CACHE_KEY_BASE.len() + 64);
+    }
+
+    #[test]
+    fn every_part_changes_the_key() {
+        let key = cache_key(PARTS);
+        let variants = [
+            KeyParts {
+                provider: "workers-ai",
+                ..PARTS
+            },
+            KeyParts {
+                url: "https://example.com/v1/chat/completions",
+                ..PARTS
+            },
+            KeyParts {
+                model: "gpt-4o",
+                ..PARTS
+            },
+            KeyParts {
+                content_path: Some("choices.0.message.content"),
+                ..PARTS
+            },
+            KeyParts {
+                stream: true,
+                ..PARTS
+            },
+            KeyParts {
+                body: r#"[{"role":"user","content":"hello"}]"#,
+                ..PARTS
+            },
+        ];
+        for variant in variants {
+            assert_ne!(cache_key(variant), key, "{:?}", variant);
+        }
+    }
+
+    // 各部分之间有分隔符，拼接结果相同的不同参数不会冲突
+    #[test]
+    fn parts_are_separated() {
+        let a = KeyParts {
+            model: "ab",
+            body: "c",
+            content_path: None,
+            ..PARTS
+        };
+        let b = KeyParts {
+            model: "a",
+            body: "bc",
+            content_path: None,
+            ..PARTS
+        };
+        assert_ne!(cache_key(a), cache_key(b));
+    }
+
+    #[derive(Default)]
+    struct MemoryCache {
+        entries: Shared<HashMap<String, (String, String)>>,
+    }
+
+    #[async_trait(?Send)]
+    impl OutputCache for MemoryCache {
+        async fn get(&self, key: &str) -> Result<Option<String>, String> {
+            Ok(self
+                .entries
+                .with(|entries| entries.get(key).map(|(output, _)| output.clone())))
+        }
+
+        async fn put(&self, key: &str, output: &str, cache_control: &str) -> Result<(), String> {
+            self.entries.with(|entries| {
+                entries.insert(
+                    key.to_string(),
+                    (output.to_string(), cache_control.to_string()),
+                )
+            });
+            Ok(())
+        }
+    }
+
+    const POLICY: LlmCache = LlmCache {
+        ttl_seconds: 60,
+        max_bytes: 10,
+    };
+
+    #[test]
+    fn generates_once_then_hits() {
+        let store = MemoryCache::default();
+        let first: Result<_, String> = block_on(get_or_generate(&store, POLICY, "k", async {
+            Ok("out".into())
+        }));
+        assert_eq!(first.unwrap(), "out");
+        assert_eq!(
+            store.entries.get()["k"],
+            ("out".to_string(), "public, max-age=60".to_string())
+        );
+
+        let second: Result<_, String> = block_on(get_or_generate(&store, POLICY, "k", async {
+            panic!("should be served from the cache")
+        }));
+        assert_eq!(second.unwrap(), "out");
+    }
+
+    #[test]
+    fn skips_errors_and_oversized_outputs() {
+        let store = MemoryCache::default();
+        let failed: Result<String, _> = block_on(get_or_generate(&store, POLICY, "k", async {
+            Err("provider down")
+        }));
+        assert_eq!(failed, Err("provider down"));
+        let large: Result<_, String> = block_on(get_or_generate(&store, POLICY, "k", async {
+            Ok("x".repeat(11))
+        }));
+        assert_eq!(large.unwrap().len(), 11);
+        let empty: Result<_, String> = block_on(get_or_generate(&store, POLICY, "k", async {
+            Ok(String::new())
+        }));
+        assert_eq!(empty.unwrap(), "");
+        assert!(store.entries.get().is_empty());
+    }
+}
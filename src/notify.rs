@@ -0,0 +1,145 @@
+// 没有启用任何通知渠道时 Notifier 为空枚举，发送相关的代码都不会执行
+#![cfg_attr(
+    not(any(
+        feature = "notify-slack",
+        feature = "notify-discord",
+        feature = "notify-telegram"
+    )),
+    allow(unused, unreachable_code)
+)]
+
+use crate::http::HttpClient;
+use serde_json::json;
+
+// 告警等通知的发送渠道
+pub enum Notifier {
+    #[cfg(feature = "notify-slack")]
+    Slack { webhook_url: String },
+    #[cfg(feature = "notify-discord")]
+    Discord { webhook_url: String },
+    #[cfg(feature = "notify-telegram")]
+    Telegram { bot_token: String, chat_id: String },
+}
+
+impl Notifier {
+    pub fn name(&self) -> &'static str {
+        // 解引用后匹配，所有通知渠道都没有编译进来时也能通过编译
+        match *self {
+            #[cfg(feature = "notify-slack")]
+            Notifier::Slack { .. } => "slack",
+            #[cfg(feature = "notify-discord")]
+            Notifier::Discord { .. } => "discord",
+            #[cfg(feature = "notify-telegram")]
+            Notifier::Telegram { .. } => "telegram",
+        }
+    }
+
+    pub async fn send(
+        &self,
+        client: &HttpClient,
+        text: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let request = match *self {
+            #[cfg(feature = "notify-slack")]
+            Notifier::Slack { ref webhook_url } => {
+                client.post(webhook_url).json(&json!({ "text": text }))
+            }
+            #[cfg(feature = "notify-discord")]
+            Notifier::Discord { ref webhook_url } => {
+                client.post(webhook_url).json(&json!({ "content": text }))
+            }
+            #[cfg(feature = "notify-telegram")]
+            Notifier::Telegram {
+                ref bot_token,
+                ref chat_id,
+            } => client
+                .post(format!(
+                    "https://api.telegram.org/bot{}/sendMessage",
+                    bot_token
+                ))
+                .json(&json!({ "chat_id": chat_id, "text": text })),
+        };
+
+        client.send(request).await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::MockExec;
+    use futures::executor::block_on;
+    use reqwest::header::HeaderMap;
+    use std::rc::Rc;
+
+    fn send(notifier: &Notifier, statuses: &[u16]) -> (bool, Vec<(String, serde_json::Value)>) {
+        let mock = Rc::new(MockExec::new(statuses));
+        let client = HttpClient::new("miniflux-ai", HeaderMap::new(), mock.clone());
+        let ok = block_on(notifier.send(&client, "3 entries failed")).is_ok();
+        let requests = mock
+            .requests()
+            .into_iter()
+            .map(|r| (r.url, serde_json::from_slice(&r.body).unwrap()))
+            .collect();
+        (ok, requests)
+    }
+
+    #[cfg(feature = "notify-slack")]
+    #[test]
+    fn slack_posts_text() {
+        let notifier = Notifier::Slack {
+            webhook_url: "https://hooks.slack.com/services/x".to_string(),
+        };
+        assert_eq!(notifier.name(), "slack");
+        assert_eq!(
+            send(&notifier, &[]),
+            (
+                true,
+                vec![(
+                    "https://hooks.slack.com/services/x".to_string(),
+                    json!({ "text": "3 entries failed" })
+                )]
+            )
+        );
+    }
+
+    #[cfg(feature = "notify-discord")]
+    #[test]
+    fn discord_posts_content() {
+        let notifier = Notifier::Discord {
+            webhook_url: "https://discord.com/api/webhooks/x".to_string(),
+        };
+        let (ok, requests) = send(&notifier, &[]);
+        assert!(ok);
+        assert_eq!(requests[0].1, json!({ "content": "3 entries failed" }));
+    }
+
+    #[cfg(feature = "notify-telegram")]
+    #[test]
+    fn telegram_posts_to_the_bot_api() {
+        let notifier = Notifier::Telegram {
+            bot_token: "123:abc".to_string(),
+            chat_id: "42".to_string(),
+        };
+        let (ok, requests) = send(&notifier, &[]);
+        assert!(ok);
+        assert_eq!(
+            requests,
+            vec![(
+                "https://api.telegram.org/bot123:abc/sendMessage".to_string(),
+                json!({ "chat_id": "42", "text": "3 entries failed" })
+            )]
+        );
+    }
+
+    #[cfg(feature = "notify-slack")]
+    #[test]
+    fn error_statuses_are_failures() {
+        let notifier = Notifier::Slack {
+            webhook_url: "https://hooks.slack.com/services/x".to_string(),
+        };
+        assert!(!send(&notifier, &[500]).0);
+        assert!(!send(&notifier, &[0]).0);
+    }
+}
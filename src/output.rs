@@ -0,0 +1,181 @@
+// 超过预设长度的这个倍数才视为输出异常，给模型留出一定余量
+const LENGTH_TOLERANCE: usize = 2;
+
+// 摘要未通过校验，和请求失败区分开，用于统计持续产出无效摘要的订阅源
+#[derive(Debug)]
+pub struct Rejected(pub String);
+
+impl std::fmt::Display for Rejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Rejected {}
+
+// 校验模型输出的摘要
+pub fn validate_summary(summary: &str, max_chars: Option<usize>) -> Result<&str, Rejected> {
+    let summary = summary.trim();
+
+    if let Some(max_chars) = max_chars {
+        let len = summary.chars().count();
+        if len > max_chars * LENGTH_TOLERANCE {
+            return Err(Rejected(format!(
+                "Summary too long: {} chars, expected at most {}",
+                len, max_chars
+            )));
+        }
+    }
+
+    Ok(summary)
+}
+
+// 弯引号换成直引号，特殊空白换成普通空格，行内连续空白合并为一个，保留换行
+pub fn normalize_typography(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            let mut normalized = String::with_capacity(line.len());
+            for c in line.chars() {
+                let c = match c {
+                    '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' | '\u{2032}' => '\'',
+                    '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{201F}' | '\u{2033}' => '"',
+                    // 零宽字符直接去掉
+                    '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{FEFF}' => continue,
+                    '\t' | '\u{00A0}' | '\u{2000}'..='\u{200A}' | '\u{202F}' | '\u{205F}' => ' ',
+                    c => c,
+                };
+                if c == ' ' && normalized.ends_with(' ') {
+                    continue;
+                }
+                normalized.push(c);
+            }
+            normalized.trim_end().to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// 常见的拒答开头，只匹配输出的开头，正文中引用这些说法的正常摘要不受影响
+const REFUSAL_PREFIXES: &[&str] = &[
+    "i'm sorry",
+    "i am sorry",
+    "sorry, i",
+    "i apologize",
+    "i can't",
+    "i cannot",
+    "i can not",
+    "i won't",
+    "i will not",
+    "i'm unable",
+    "i am unable",
+    "i'm not able",
+    "i am not able",
+    "as an ai",
+    "unfortunately, i",
+    "抱歉",
+    "很抱歉",
+    "对不起",
+    "我无法",
+    "我不能",
+    "我不便",
+    "无法为您",
+    "作为一个ai",
+    "作为ai",
+    "作为人工智能",
+];
+
+// 模型拒绝摘要，不是请求失败，文章保持原样
+#[derive(Debug, Default)]
+pub struct RefusalDetector {
+    extra: Vec<String>,
+}
+
+impl RefusalDetector {
+    pub fn new(extra: &[String]) -> Self {
+        RefusalDetector {
+            extra: extra
+                .iter()
+                .map(|p| normalize(p))
+                .filter(|p| !p.is_empty())
+                .collect(),
+        }
+    }
+
+    pub fn is_refusal(&self, output: &str) -> bool {
+        let output = normalize(output);
+        REFUSAL_PREFIXES
+            .iter()
+            .copied()
+            .chain(self.extra.iter().map(String::as_str))
+            .any(|prefix| output.starts_with(prefix))
+    }
+}
+
+// 统一大小写和弯引号，去掉开头的引号和空白
+fn normalize(text: &str) -> String {
+    text.trim_start_matches(|c: char| c.is_whitespace() || matches!(c, '"' | '“' | '「'))
+        .trim_end()
+        .to_lowercase()
+        .replace(['’', '‘'], "'")
+        .replace('，', ",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summaries_within_tolerance_are_trimmed_and_accepted() {
+        assert_eq!(validate_summary("  要点  ", Some(2)).unwrap(), "要点");
+        // 最多允许预设长度的两倍，按字符而不是字节计算
+        assert!(validate_summary(&"字".repeat(4), Some(2)).is_ok());
+        assert!(validate_summary(&"x".repeat(10_000), None).is_ok());
+    }
+
+    #[test]
+    fn overlong_summaries_are_rejected() {
+        let err = validate_summary(&"字".repeat(5), Some(2)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Summary too long: 5 chars, expected at most 2"
+        );
+    }
+
+    #[test]
+    fn normalizes_quotes_and_spaces() {
+        assert_eq!(
+            normalize_typography(
+                "\u{201C}Hi\u{201D},\u{00A0}\u{00A0}it\u{2019}s\tme\u{200B}  \nnext\u{3000}line"
+            ),
+            "\"Hi\", it's me\nnext\u{3000}line"
+        );
+    }
+
+    // 保留换行和空行，缩进同样合并为一个空格，行尾空白去掉
+    #[test]
+    fn keeps_line_structure() {
+        assert_eq!(
+            normalize_typography("- a  \n\n  - b\u{2009}\u{2009}c"),
+            "- a\n\n - b c"
+        );
+        assert_eq!(normalize_typography("中文，没有变化。"), "中文，没有变化。");
+    }
+
+    #[test]
+    fn detects_refusals_at_the_start() {
+        let detector = RefusalDetector::default();
+        assert!(detector.is_refusal("I\u{2019}m sorry, but I can\u{2019}t help with that."));
+        assert!(detector.is_refusal("  \u{201C}As an AI language model, I..."));
+        assert!(detector.is_refusal("很抱歉，我无法总结这篇文章。"));
+        // 摘要正文中引用拒答说法不算拒答
+        assert!(!detector.is_refusal("The CEO said \"I'm sorry\" to customers."));
+        assert!(!detector.is_refusal("文章指出，厂商表示很抱歉。"));
+    }
+
+    #[test]
+    fn extra_patterns_are_normalized() {
+        let detector = RefusalDetector::new(&["  Je ne peux pas".to_string(), " ".to_string()]);
+        assert!(detector.is_refusal("je ne peux pas résumer"));
+        assert!(!detector.is_refusal("Le texte explique"));
+    }
+}
@@ -0,0 +1,263 @@
+use crate::filter::ContentFilter;
+use crate::phrases::PhraseFilter;
+use crate::rewrite::SummaryRewriter;
+use crate::settings::{FeatureOverrides, FeedOverrides};
+use serde::Serialize;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+
+// 覆盖层中值的类型，写入前按类型校验
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Text,
+    Bool,
+    Int,
+    Float,
+    Choice(&'static [&'static str]),
+    Preset,
+    LengthTiers,
+    FeedSettings,
+    FeedFeatures,
+    ModelMap,
+    StringList,
+    Patterns,
+    Replace,
+    Phrases,
+    Canary,
+}
+
+// 只允许调整提示词、过滤、按订阅源设置和预算，凭据和服务地址不能通过 KV 修改
+const KEYS: &[(&str, Kind)] = &[
+    ("OPENAI_MODEL", Kind::Text),
+    ("FALLBACK_MODELS", Kind::Text),
+    ("CATEGORY_MODELS", Kind::ModelMap),
+    ("CANARY", Kind::Canary),
+    ("CATEGORY_STYLES", Kind::ModelMap),
+    ("PROMPT_PRESET", Kind::Preset),
+    ("LENGTH_TIERS", Kind::LengthTiers),
+    ("SUMMARY_MAX_WORDS", Kind::Int),
+    ("SUMMARY_READING_SECONDS", Kind::Int),
+    ("SUMMARY_LANGUAGE", Kind::Text),
+    ("DEFAULT_LANGUAGE", Kind::Text),
+    ("SUMMARY_REPLACE", Kind::Replace),
+    ("BANNED_PHRASES", Kind::Phrases),
+    ("REFUSAL_PATTERNS", Kind::StringList),
+    ("RETRY_ON_REFUSAL", Kind::Bool),
+    ("PAYWALL_MAX_CHARS", Kind::Int),
+    ("PAYWALL_PHRASES", Kind::StringList),
+    ("PAYWALL_NOTICE", Kind::Text),
+    ("TITLE_HINT", Kind::Bool),
+    ("TITLE_SUFFIX", Kind::Bool),
+    ("NORMALIZE_TYPOGRAPHY", Kind::Bool),
+    ("SECTION_CITATIONS", Kind::Bool),
+    ("WHITELIST_URL", Kind::Text),
+    ("SEARCH_QUERY", Kind::Text),
+    ("CATEGORY_ID", Kind::Int),
+    (
+        "FETCH_STRATEGY",
+        Kind::Choice(&["global", "per-category", "per_category"]),
+    ),
+    ("ENTRY_STATUSES", Kind::Text),
+    ("AUTO_READ_SKIPPED", Kind::Text),
+    ("SKIP_CONTENT_PATTERNS", Kind::Patterns),
+    ("MIN_CONTENT_CHARS", Kind::Int),
+    ("SUMMARIZE_CATEGORIES", Kind::Text),
+    ("SAMPLE_RATE", Kind::Float),
+    ("DEDUP_BY_URL", Kind::Bool),
+    (
+        "AGGREGATION_POLICY",
+        Kind::Choice(&["skip", "summarize_anyway", "links_only"]),
+    ),
+    ("AGGREGATION_QUOTE_RATIO", Kind::Float),
+    ("AGGREGATION_LINK_DENSITY", Kind::Float),
+    (
+        "PRIORITY_STRATEGY",
+        Kind::Choice(&["newest_first", "oldest_first", "feed_priority"]),
+    ),
+    ("FEED_PRIORITY", Kind::Text),
+    ("FEED_SETTINGS", Kind::FeedSettings),
+    ("FEED_FEATURES", Kind::FeedFeatures),
+    ("SUMMARIZE", Kind::Bool),
+    ("TICKER_TAGS", Kind::Bool),
+    ("TAG_MIN_CONFIDENCE", Kind::Float),
+    ("TRANSLATE_TITLE", Kind::Bool),
+    ("TRANSLATE_BODY", Kind::Bool),
+    ("SCORE", Kind::Bool),
+    ("EXTRACT_ENTITIES", Kind::Bool),
+    ("FETCH_FULL_CONTENT", Kind::Bool),
+    ("SUMMARIZE_COMMENTS", Kind::Bool),
+    ("FEED_CONTEXT", Kind::Bool),
+    ("FEED_DESCRIPTION_CONTEXT", Kind::Bool),
+    ("CONTEXT_HINTS", Kind::Int),
+    ("CONTEXT_HINTS_MAX_TOKENS", Kind::Int),
+    ("STICKY_SUMMARY", Kind::Bool),
+    ("MAX_TOKENS_PER_RUN", Kind::Int),
+    ("MAX_ENTRIES_PER_RUN", Kind::Int),
+    ("MAX_CONCURRENCY", Kind::Int),
+    ("MAX_MODEL_CONNECTIONS", Kind::Int),
+    ("PAGE_SIZE", Kind::Int),
+    ("TRUNCATED_RETRY_PAGE_SIZE", Kind::Int),
+];
+
+pub fn keys() -> impl Iterator<Item = &'static str> {
+    KEYS.iter().map(|(name, _)| *name)
+}
+
+// KV 中 `CONFIG` 的内容，按环境变量名覆盖对应的值
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ConfigOverlay {
+    pub values: BTreeMap<String, String>,
+}
+
+// 返回所有校验错误，而不是遇到第一个就停止
+pub fn parse(raw: &str) -> Result<ConfigOverlay, Vec<String>> {
+    let object = match serde_json::from_str::<Value>(raw) {
+        Ok(Value::Object(object)) => object,
+        Ok(_) => return Err(vec!["overlay must be a JSON object".to_string()]),
+        Err(e) => return Err(vec![e.to_string()]),
+    };
+
+    let mut values = BTreeMap::new();
+    let mut errors = Vec::new();
+    for (name, value) in object {
+        let Some((_, kind)) = KEYS.iter().find(|(key, _)| *key == name) else {
+            errors.push(format!("{}: not allowed in the overlay", name));
+            continue;
+        };
+        // 布尔值、数字和 JSON 对象按环境变量中的写法转成字符串
+        let value = match value {
+            Value::String(s) => s,
+            Value::Null => {
+                errors.push(format!("{}: must not be null", name));
+                continue;
+            }
+            other => other.to_string(),
+        };
+        match validate(*kind, value.trim()) {
+            Ok(()) => {
+                values.insert(name, value);
+            }
+            Err(e) => errors.push(format!("{}: {}", name, e)),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(ConfigOverlay { values })
+    } else {
+        Err(errors)
+    }
+}
+
+fn validate(kind: Kind, value: &str) -> Result<(), String> {
+    // 空字符串等同于未设置
+    if value.is_empty() {
+        return Ok(());
+    }
+    match kind {
+        Kind::Text => Ok(()),
+        Kind::Bool => value.parse::<bool>().map(|_| ()).map_err(|e| e.to_string()),
+        Kind::Int => value.parse::<u64>().map(|_| ()).map_err(|e| e.to_string()),
+        Kind::Float => value.parse::<f64>().map(|_| ()).map_err(|e| e.to_string()),
+        Kind::Choice(choices) => {
+            if choices.contains(&value.to_lowercase().as_str()) {
+                Ok(())
+            } else {
+                Err(format!("expected one of {}", choices.join(", ")))
+            }
+        }
+        Kind::Preset => crate::prompts::preset(value).map(|_| ()),
+        Kind::LengthTiers => crate::tiers::parse(value).map(|_| ()),
+        Kind::FeedSettings => json::<HashMap<String, FeedOverrides>>(value).map(|_| ()),
+        Kind::FeedFeatures => json::<HashMap<String, FeatureOverrides>>(value).map(|_| ()),
+        Kind::ModelMap => json::<HashMap<String, String>>(value).map(|_| ()),
+        Kind::StringList => json::<Vec<String>>(value).map(|_| ()),
+        Kind::Patterns => ContentFilter::new(&json::<Vec<String>>(value)?).map(|_| ()),
+        Kind::Replace => SummaryRewriter::new(&json::<Vec<String>>(value)?).map(|_| ()),
+        Kind::Phrases => PhraseFilter::new(&json::<Vec<String>>(value)?).map(|_| ()),
+        Kind::Canary => crate::canary::parse(value).map(|_| ()),
+    }
+}
+
+fn json<T: serde::de::DeserializeOwned>(value: &str) -> Result<T, String> {
+    serde_json::from_str(value).map_err(|e| e.to_string())
+}
+
+thread_local! {
+    static ACTIVE: RefCell<Option<ConfigOverlay>> = const { RefCell::new(None) };
+}
+
+// 只在同步构建配置期间生效，不会和其他请求交错
+pub fn with_overlay<T>(overlay: &ConfigOverlay, f: impl FnOnce() -> T) -> T {
+    ACTIVE.with(|active| *active.borrow_mut() = Some(overlay.clone()));
+    let result = f();
+    ACTIVE.with(|active| *active.borrow_mut() = None);
+    result
+}
+
+pub fn lookup(name: &str) -> Option<String> {
+    ACTIVE.with(|active| {
+        active
+            .borrow()
+            .as_ref()
+            .and_then(|overlay| overlay.values.get(name).cloned())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_allowed_values_as_env_strings() {
+        let overlay = parse(
+            r#"{"OPENAI_MODEL": "gpt-4o", "SUMMARIZE": false, "SAMPLE_RATE": 0.5,
+                "FEED_SETTINGS": {"example.com": {}}, "PAGE_SIZE": ""}"#,
+        )
+        .unwrap();
+        assert_eq!(overlay.values["OPENAI_MODEL"], "gpt-4o");
+        assert_eq!(overlay.values["SUMMARIZE"], "false");
+        assert_eq!(overlay.values["SAMPLE_RATE"], "0.5");
+        assert_eq!(overlay.values["FEED_SETTINGS"], r#"{"example.com":{}}"#);
+        assert_eq!(overlay.values["PAGE_SIZE"], "");
+    }
+
+    // 一次返回所有错误，凭据等不在白名单中的键不能覆盖
+    #[test]
+    fn reports_every_invalid_key() {
+        let errors = parse(
+            r#"{"OPENAI_API_KEY": "sk", "SUMMARIZE": "yes", "PAGE_SIZE": -1,
+                "PROMPT_PRESET": "nope", "PRIORITY_STRATEGY": "random", "SEARCH_QUERY": null}"#,
+        )
+        .unwrap_err();
+        assert_eq!(errors.len(), 6, "{:?}", errors);
+        assert!(errors
+            .iter()
+            .any(|e| e == "OPENAI_API_KEY: not allowed in the overlay"));
+    }
+
+    #[test]
+    fn rejects_non_objects() {
+        assert!(parse("[]").is_err());
+        assert!(parse("not json").is_err());
+        assert_eq!(parse("{}"), Ok(ConfigOverlay::default()));
+        assert_eq!(
+            parse(r#"{"FETCH_STRATEGY": "Per-Category"}"#)
+                .unwrap()
+                .values
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn overlay_is_visible_only_inside_with_overlay() {
+        let overlay = parse(r#"{"OPENAI_MODEL": "gpt-4o"}"#).unwrap();
+        assert_eq!(
+            with_overlay(&overlay, || lookup("OPENAI_MODEL")),
+            Some("gpt-4o".to_string())
+        );
+        assert_eq!(with_overlay(&overlay, || lookup("SEARCH_QUERY")), None);
+        assert_eq!(lookup("OPENAI_MODEL"), None);
+    }
+}
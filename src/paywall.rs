@@ -0,0 +1,87 @@
+pub const DEFAULT_NOTICE: &str = "🔒 Paywalled — summary unavailable";
+
+// 常见付费墙提示，统一为小写
+const PAYWALL_PHRASES: &[&str] = &[
+    "subscribe to continue reading",
+    "subscribe to read",
+    "subscribers only",
+    "for subscribers only",
+    "this article is for subscribers",
+    "this content is for subscribers",
+    "already a subscriber",
+    "to continue reading",
+    "sign in to continue reading",
+    "log in to continue reading",
+    "become a member to read",
+    "unlock this article",
+    "订阅后阅读",
+    "订阅后继续阅读",
+    "付费内容",
+    "付费阅读",
+    "会员专享",
+    "登录后阅读",
+];
+
+// 正文很短且包含付费墙提示时视为付费墙截断的内容，max_chars 为 0 时不检测
+#[derive(Debug, Default)]
+pub struct PaywallDetector {
+    max_chars: usize,
+    extra: Vec<String>,
+}
+
+impl PaywallDetector {
+    pub fn new(max_chars: usize, extra: &[String]) -> Self {
+        PaywallDetector {
+            max_chars,
+            extra: extra
+                .iter()
+                .map(|p| p.trim().to_lowercase())
+                .filter(|p| !p.is_empty())
+                .collect(),
+        }
+    }
+
+    pub fn is_stub(&self, text: &str) -> bool {
+        if self.max_chars == 0 || text.chars().count() > self.max_chars {
+            return false;
+        }
+        let text = text.to_lowercase().replace('’', "'");
+        PAYWALL_PHRASES
+            .iter()
+            .copied()
+            .chain(self.extra.iter().map(String::as_str))
+            .any(|phrase| text.contains(phrase))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_text_with_paywall_phrase_is_a_stub() {
+        let detector = PaywallDetector::new(500, &[]);
+        assert!(detector.is_stub("The first paragraph. Subscribe to continue reading."));
+        assert!(detector.is_stub("开头一段。订阅后继续阅读"));
+        assert!(!detector.is_stub("A short news item without any paywall."));
+    }
+
+    // 长文中出现付费墙提示通常只是页脚，不算截断
+    #[test]
+    fn long_text_is_never_a_stub() {
+        let detector = PaywallDetector::new(100, &[]);
+        let text = format!("{} Already a subscriber?", "word ".repeat(40));
+        assert!(!detector.is_stub(&text));
+        assert!(!PaywallDetector::new(0, &[]).is_stub("Subscribers only"));
+    }
+
+    #[test]
+    fn extra_phrases_are_case_insensitive() {
+        let detector = PaywallDetector::new(
+            500,
+            &["  Réservé aux ABONNÉS ".to_string(), " ".to_string()],
+        );
+        assert!(detector.is_stub("Cet article est réservé aux abonnés."));
+        assert!(!detector.is_stub("Un article gratuit."));
+    }
+}
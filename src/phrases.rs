@@ -0,0 +1,252 @@
+#[cfg(feature = "regex-filters")]
+use regex::{Regex, RegexBuilder};
+
+#[cfg(feature = "regex-filters")]
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+// 去掉开头套话后紧跟的标点，避免摘要以逗号、冒号开头
+const SEPARATORS: &[char] = &[
+    ':', '：', ',', '，', '、', ';', '；', '-', '—', '–', '.', '。', '!', '！',
+];
+
+// BANNED_PHRASES 中的套话，普通规则为不区分大小写的字符串，`re:` 前缀为正则
+#[derive(Debug, Default)]
+pub struct PhraseFilter {
+    phrases: Vec<String>,
+    #[cfg(feature = "regex-filters")]
+    regexes: Vec<(Regex, Regex)>,
+}
+
+impl PhraseFilter {
+    pub fn new(patterns: &[String]) -> Result<Self, String> {
+        let mut phrases = Vec::new();
+        let mut regexes = Vec::new();
+        for pattern in patterns.iter().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            match pattern.strip_prefix("re:") {
+                Some(regex) => regexes.push(regex),
+                None => phrases.push(pattern.to_lowercase()),
+            }
+        }
+
+        #[cfg(not(feature = "regex-filters"))]
+        if let Some(regex) = regexes.first() {
+            return Err(format!(
+                "re:{} needs the regex-filters feature, which is not enabled in this build",
+                regex
+            ));
+        }
+
+        Ok(PhraseFilter {
+            phrases,
+            // 开头匹配和任意位置匹配各编译一份
+            #[cfg(feature = "regex-filters")]
+            regexes: regexes
+                .into_iter()
+                .map(|regex| Ok((build(&format!("^(?:{})", regex))?, build(regex)?)))
+                .collect::<Result<_, String>>()?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.phrases.is_empty() && !self.has_regexes()
+    }
+
+    // 反复去掉开头的套话和随后的标点，句中出现的不处理
+    pub fn strip_leading(&self, text: &str) -> String {
+        let mut rest = text.trim_start();
+        let mut stripped = false;
+        while let Some(after) = self.leading_match(rest) {
+            stripped = true;
+            rest = after.trim_start_matches(|c: char| c.is_whitespace() || SEPARATORS.contains(&c));
+        }
+        if !stripped {
+            return text.to_string();
+        }
+        capitalize_latin(rest)
+    }
+
+    fn leading_match<'a>(&self, text: &'a str) -> Option<&'a str> {
+        if let Some(rest) = self
+            .phrases
+            .iter()
+            .find_map(|phrase| strip_prefix_ignore_case(text, phrase))
+        {
+            return Some(rest);
+        }
+        self.leading_regex_match(text)
+    }
+
+    // 套话占摘要中文字一半以上时视为没有实际内容
+    pub fn is_filler(&self, text: &str) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        let total = count_letters(text);
+        if total == 0 {
+            return false;
+        }
+        let lower = text.to_lowercase();
+        let mut matched: usize = self
+            .phrases
+            .iter()
+            .map(|phrase| {
+                lower
+                    .match_indices(phrase.as_str())
+                    .map(|(_, m)| count_letters(m))
+                    .sum::<usize>()
+            })
+            .sum();
+        matched += self.regex_letters(text);
+        matched * 2 >= total
+    }
+
+    // 重试时追加到系统提示词中的示例，只列出普通规则
+    pub fn examples(&self) -> String {
+        self.phrases
+            .iter()
+            .map(|phrase| format!("\"{}\"", phrase))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    #[cfg(feature = "regex-filters")]
+    fn has_regexes(&self) -> bool {
+        !self.regexes.is_empty()
+    }
+
+    #[cfg(not(feature = "regex-filters"))]
+    fn has_regexes(&self) -> bool {
+        false
+    }
+
+    #[cfg(feature = "regex-filters")]
+    fn leading_regex_match<'a>(&self, text: &'a str) -> Option<&'a str> {
+        self.regexes.iter().find_map(|(leading, _)| {
+            leading
+                .find(text)
+                .filter(|m| !m.is_empty())
+                .map(|m| &text[m.end()..])
+        })
+    }
+
+    #[cfg(not(feature = "regex-filters"))]
+    fn leading_regex_match<'a>(&self, _text: &'a str) -> Option<&'a str> {
+        None
+    }
+
+    #[cfg(feature = "regex-filters")]
+    fn regex_letters(&self, text: &str) -> usize {
+        self.regexes
+            .iter()
+            .flat_map(|(_, anywhere)| anywhere.find_iter(text))
+            .map(|m| count_letters(m.as_str()))
+            .sum()
+    }
+
+    #[cfg(not(feature = "regex-filters"))]
+    fn regex_letters(&self, _text: &str) -> usize {
+        0
+    }
+}
+
+#[cfg(feature = "regex-filters")]
+fn build(pattern: &str) -> Result<Regex, String> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+// `phrase` 已经是小写，逐字符比较以免小写转换改变字节长度
+fn strip_prefix_ignore_case<'a>(text: &'a str, phrase: &str) -> Option<&'a str> {
+    let mut chars = text.char_indices();
+    for expected in phrase.chars() {
+        let (_, c) = chars.next()?;
+        if !c.to_lowercase().eq(expected.to_lowercase()) {
+            return None;
+        }
+    }
+    Some(chars.next().map_or("", |(i, _)| &text[i..]))
+}
+
+fn count_letters(text: &str) -> usize {
+    text.chars().filter(|c| c.is_alphanumeric()).count()
+}
+
+// 只处理拉丁字母，中文等没有大小写的文字保持原样
+fn capitalize_latin(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) if first.is_lowercase() && (first as u32) < 0x250 => {
+            first.to_uppercase().chain(chars).collect()
+        }
+        _ => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(patterns: &[&str]) -> PhraseFilter {
+        PhraseFilter::new(&patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>()).unwrap()
+    }
+
+    #[test]
+    fn strips_repeated_leading_phrases() {
+        let filter = filter(&["In summary", "本文介绍了", " "]);
+        assert_eq!(
+            filter.strip_leading("  IN SUMMARY, in summary: the bill passed."),
+            "The bill passed."
+        );
+        assert_eq!(filter.strip_leading("本文介绍了：新版发布"), "新版发布");
+        // 句中出现的套话不处理，没有匹配时原样返回
+        assert_eq!(
+            filter.strip_leading("  Votes, in summary, were close"),
+            "  Votes, in summary, were close"
+        );
+    }
+
+    #[test]
+    fn detects_filler_summaries() {
+        let filter = filter(&["this article discusses"]);
+        assert!(filter.is_filler("This article discusses things."));
+        assert!(
+            !filter.is_filler("This article discusses the new tax plan and its impact on renters")
+        );
+        assert!(!filter.is_filler("。。"));
+        assert!(!PhraseFilter::default().is_filler("anything"));
+        assert_eq!(filter.examples(), "\"this article discusses\"");
+    }
+
+    // 转小写后字节长度变化的字符不会切到字符中间
+    #[test]
+    fn case_folding_keeps_char_boundaries() {
+        // 开尔文符号 K 占 3 个字节，小写后是 1 个字节的 k
+        let filter = filter(&["ok"]);
+        assert_eq!(filter.strip_leading("O\u{212A}, fine"), "Fine");
+    }
+
+    #[cfg(feature = "regex-filters")]
+    #[test]
+    fn regex_phrases_match_only_at_the_start() {
+        let filter = filter(&[r"re:here(?:'s| is) (?:a|the) summary"]);
+        assert_eq!(
+            filter.strip_leading("Here is the summary — rates rose."),
+            "Rates rose."
+        );
+        assert_eq!(
+            filter.strip_leading("So here is a summary"),
+            "So here is a summary"
+        );
+        assert!(filter.is_filler("Here's a summary."));
+        assert!(PhraseFilter::new(&["re:(".to_string()]).is_err());
+    }
+
+    #[cfg(not(feature = "regex-filters"))]
+    #[test]
+    fn regex_phrases_need_the_regex_feature() {
+        assert!(PhraseFilter::new(&["re:a+".to_string()]).is_err());
+    }
+}
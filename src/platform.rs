@@ -0,0 +1,97 @@
+// Worker 运行时提供的日志、时间和定时器，原生运行的测试中换成标准库实现
+#[cfg(not(test))]
+pub use worker::{console_debug, console_error, console_log, console_warn, Date, Delay};
+
+#[cfg(test)]
+pub use native::{delays, Date, Delay};
+
+#[cfg(test)]
+macro_rules! console_log {
+    ($($t:tt)*) => {
+        eprintln!($($t)*)
+    };
+}
+
+#[cfg(test)]
+macro_rules! console_debug {
+    ($($t:tt)*) => {
+        eprintln!($($t)*)
+    };
+}
+
+#[cfg(test)]
+macro_rules! console_warn {
+    ($($t:tt)*) => {
+        eprintln!($($t)*)
+    };
+}
+
+#[cfg(test)]
+macro_rules! console_error {
+    ($($t:tt)*) => {
+        eprintln!($($t)*)
+    };
+}
+
+#[cfg(test)]
+pub(crate) use {console_debug, console_error, console_log, console_warn};
+
+#[cfg(test)]
+mod native {
+    use std::cell::RefCell;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    pub struct Date(u64);
+
+    impl Date {
+        pub fn now() -> Self {
+            Date(
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_millis() as u64)
+                    .unwrap_or_default(),
+            )
+        }
+
+        pub fn as_millis(&self) -> u64 {
+            self.0
+        }
+    }
+
+    thread_local! {
+        static DELAYS: RefCell<Vec<Duration>> = const { RefCell::new(Vec::new()) };
+    }
+
+    // 当前线程上创建过的 Delay 的时长，供测试检查等待发生的位置
+    pub fn delays() -> Vec<Duration> {
+        DELAYS.with(|delays| delays.borrow().clone())
+    }
+
+    // 不真正等待，只让出一次执行权，和 setTimeout 一样让其他任务先运行
+    pub struct Delay {
+        polled: bool,
+    }
+
+    impl From<Duration> for Delay {
+        fn from(duration: Duration) -> Self {
+            DELAYS.with(|delays| delays.borrow_mut().push(duration));
+            Delay { polled: false }
+        }
+    }
+
+    impl Future for Delay {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.polled {
+                return Poll::Ready(());
+            }
+            self.polled = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
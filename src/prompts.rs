@@ -0,0 +1,112 @@
+// 内置的系统提示词预设，新增预设只需要在 PRESETS 中追加一项
+pub struct Preset {
+    pub name: &'static str,
+    pub system: &'static str,
+    pub max_chars: usize,
+}
+
+pub const DEFAULT_PRESET: &str = "concise_zh";
+
+pub const TRANSLATE_TITLE_PROMPT: &str = "Translate the following article title into {language}. Respond with the translated title only, without quotes or any additional text.";
+
+pub const TRANSLATE_BODY_PROMPT: &str = "Translate each paragraph in the JSON array of the user message into {language}. Respond with a JSON object only, in the form {\"paragraphs\": [\"...\"]}, containing exactly one translated paragraph for each input paragraph, in the same order. Do not merge, split or omit paragraphs.";
+
+pub const SCORE_PROMPT: &str = "Rate how informative and worth reading the following article is on a scale from 1 (not worth reading) to 10 (must read). Respond with a JSON object only, in the form {\"score\": 7}.";
+
+// 聚合类文章只列出链接条目，用户消息是 `{"url", "text"}` 的 JSON 数组
+pub const LINKS_PROMPT: &str = "The user message is a JSON array of links taken from a newsletter or link roundup, each with its url and link text, followed by the article text. For each link that points to a distinct item, write a short title and a one-line description in {language} based on the article text. Respond with a JSON object only, in the form {\"items\": [{\"title\": \"...\", \"description\": \"...\"}]}, in the order the links appear. Omit navigation, sharing and subscription links.";
+
+// 摘要几乎都是 BANNED_PHRASES 中的套话时重试，追加到系统提示词
+pub const BANNED_PHRASES_RETRY_PROMPT: &str = "Do not use filler phrases such as {phrases}. Start directly with the key facts of the article.";
+
+// CATEGORY_STYLES 中配置的语气，追加到系统提示词
+pub const STYLE_PROMPT: &str = "Write the summary in a {style} tone and style.";
+
+// 模型拒答后重试时追加到系统提示词
+pub const REFUSAL_RETRY_PROMPT: &str = "This is a neutral summarization task for a personal news reader. Summarize what the article reports in a factual, neutral tone, without giving advice or endorsing its content. Do not refuse.";
+
+pub const CLASSIFY_PROMPT: &str = "Classify the following article into one of these categories: \"longform\" for in-depth articles, essays, analyses and long reads, \"news\" for short news reports and briefs, or \"other\" for anything else. Respond with a JSON object only, in the form {\"category\": \"news\"}.";
+
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "concise_zh",
+        system: "Please summarize the content of the article under 150 words in Chinese. Do not add any additional Character、markdown language to the result text. 请用不超过150个汉字概括文章内容。结果文本中不要添加任何额外的字符、Markdown语言。",
+        max_chars: 150,
+    },
+    Preset {
+        name: "detailed_zh",
+        system: "Please summarize the article under 400 words in Chinese, covering the background, the key points and the conclusion. Do not add any additional Character、markdown language to the result text. 请用不超过400个汉字概括文章的背景、要点和结论。结果文本中不要添加任何额外的字符、Markdown语言。",
+        max_chars: 400,
+    },
+    Preset {
+        name: "concise_en",
+        system: "Please summarize the content of the article under 100 words in English. Do not add any additional characters or markdown to the result text.",
+        max_chars: 700,
+    },
+    Preset {
+        name: "academic",
+        system: "Please summarize the article under 200 words in English in an academic register: state the research question, the methods, the findings and the limitations. Do not add any additional characters or markdown to the result text.",
+        max_chars: 1400,
+    },
+    Preset {
+        name: "eli5",
+        system: "Please explain the article under 100 words in simple English, as if to a curious ten-year-old, avoiding jargon. Do not add any additional characters or markdown to the result text.",
+        max_chars: 700,
+    },
+];
+
+pub struct Prompt {
+    pub system: String,
+    pub max_chars: Option<usize>,
+}
+
+pub fn preset(name: &str) -> Result<&'static Preset, String> {
+    PRESETS
+        .iter()
+        .find(|p| p.name == name.trim())
+        .ok_or_else(|| format!("Unknown prompt preset: {}", name.trim()))
+}
+
+// `preset:` 前缀表示内置预设，否则视为完整的提示词文本
+pub fn resolve(value: &str) -> Result<Prompt, String> {
+    match value.strip_prefix("preset:") {
+        Some(name) => preset(name).map(|p| Prompt {
+            system: p.system.to_string(),
+            max_chars: Some(p.max_chars),
+        }),
+        None => Ok(Prompt {
+            system: value.to_string(),
+            max_chars: None,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_presets_and_literal_prompts() {
+        let prompt = resolve("preset: concise_en ").unwrap();
+        assert_eq!(prompt.system, preset("concise_en").unwrap().system);
+        assert_eq!(prompt.max_chars, Some(700));
+
+        let prompt = resolve("Summarize in one sentence.").unwrap();
+        assert_eq!(prompt.system, "Summarize in one sentence.");
+        assert_eq!(prompt.max_chars, None);
+
+        assert!(resolve("preset:unknown").is_err());
+    }
+
+    #[test]
+    fn preset_names_are_unique_and_include_the_default() {
+        assert!(preset(DEFAULT_PRESET).is_ok());
+        for (i, a) in PRESETS.iter().enumerate() {
+            assert!(
+                PRESETS[i + 1..].iter().all(|b| b.name != a.name),
+                "{}",
+                a.name
+            );
+        }
+    }
+}
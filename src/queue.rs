@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+
+// 队列消息体，生产者只需要发送文章 ID，例如 `{"entry_id": 42}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueMessage {
+    pub entry_id: u64,
+}
+
+// 超过最大投递次数的消息，保存在 `dlq:{entry_id}` 中等待手动重新处理
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DlqEntry {
+    pub entry_id: u64,
+    pub attempts: u32,
+    pub error: String,
+    pub failed_at: u64,
+}
+
+pub const DLQ_PREFIX: &str = "dlq:";
+
+pub fn dlq_key(entry_id: u64) -> String {
+    format!("{}{}", DLQ_PREFIX, entry_id)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Disposition {
+    Ack,
+    // 交给队列按配置的退避时间重新投递
+    Retry,
+    // 超出 token 预算，重新投递但不计入失败次数
+    Defer,
+    DeadLetter,
+}
+
+// `attempts` 是包括本次在内的失败投递次数
+pub fn disposition(
+    failed: bool,
+    deferred: bool,
+    attempts: u32,
+    max_deliveries: u32,
+) -> Disposition {
+    if deferred {
+        Disposition::Defer
+    } else if !failed {
+        Disposition::Ack
+    } else if attempts >= max_deliveries {
+        Disposition::DeadLetter
+    } else {
+        Disposition::Retry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn acks_successful_messages() {
+        assert_eq!(disposition(false, false, 0, 5), Disposition::Ack);
+    }
+
+    #[test]
+    fn retries_failures_until_max_deliveries() {
+        assert_eq!(disposition(true, false, 1, 3), Disposition::Retry);
+        assert_eq!(disposition(true, false, 2, 3), Disposition::Retry);
+        assert_eq!(disposition(true, false, 3, 3), Disposition::DeadLetter);
+    }
+
+    // 预算不足的消息不论之前失败过几次都只是延后
+    #[test]
+    fn deferred_messages_never_dead_letter() {
+        assert_eq!(disposition(false, true, 0, 1), Disposition::Defer);
+        assert_eq!(disposition(false, true, 10, 3), Disposition::Defer);
+    }
+
+    #[test]
+    fn parses_message_and_builds_dlq_key() {
+        let message: QueueMessage = serde_json::from_str(r#"{"entry_id": 42}"#).unwrap();
+        assert_eq!(message.entry_id, 42);
+        assert_eq!(dlq_key(42), "dlq:42");
+    }
+}
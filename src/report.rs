@@ -0,0 +1,380 @@
+use crate::settings::Step;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+// 单篇文章的处理结果
+#[derive(Debug)]
+pub enum EntryOutcome {
+    Summarized(String),
+    // 没有生成摘要，但标签、标题等其他步骤有输出或已标记为处理过
+    Updated,
+    Empty,
+    // 文章在处理期间被删除或移除，不需要重试
+    Gone,
+    // 超出本次运行的 token 预算，留到下次运行
+    Deferred,
+    // 模型拒绝摘要，文章没有修改
+    Refused,
+    // 正文是付费墙截断的内容，写入提示代替摘要
+    Paywalled,
+}
+
+// 处理前就被跳过的原因
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SkipReason {
+    AlreadyProcessed,
+    Status,
+    NotWhitelisted,
+    FeedDisabled,
+    EmptyFeed,
+    NoSteps,
+    TooShort,
+    ContentPattern,
+    DuplicateUrl,
+    SampledOut,
+    Aggregation,
+    Refused,
+}
+
+impl SkipReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SkipReason::AlreadyProcessed => "already_processed",
+            SkipReason::Status => "status",
+            SkipReason::NotWhitelisted => "not_whitelisted",
+            SkipReason::FeedDisabled => "feed_disabled",
+            SkipReason::EmptyFeed => "empty_feed",
+            SkipReason::NoSteps => "no_steps",
+            SkipReason::TooShort => "too_short",
+            SkipReason::ContentPattern => "content_pattern",
+            SkipReason::DuplicateUrl => "duplicate_url",
+            SkipReason::SampledOut => "sampled_out",
+            SkipReason::Aggregation => "aggregation",
+            SkipReason::Refused => "refused",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            SkipReason::AlreadyProcessed => "already processed",
+            SkipReason::Status => "entry status not in ENTRY_STATUSES",
+            SkipReason::NotWhitelisted => "feed not in WHITELIST_URL",
+            SkipReason::FeedDisabled => "feed auto-disabled after repeated failures",
+            SkipReason::EmptyFeed => "feed keeps producing empty summaries",
+            SkipReason::NoSteps => "no steps enabled for the feed",
+            SkipReason::TooShort => "content shorter than MIN_CONTENT_CHARS",
+            SkipReason::ContentPattern => "matched SKIP_CONTENT_PATTERNS",
+            SkipReason::DuplicateUrl => "same article already summarized from another entry",
+            SkipReason::SampledOut => "not in the SAMPLE_RATE sample",
+            SkipReason::Aggregation => "mostly quotes or links, AGGREGATION_POLICY is skip",
+            SkipReason::Refused => "model kept refusing, dead-lettered",
+        }
+    }
+}
+
+const OUTAGE_MIN_FAILURES: usize = 3;
+
+// 每次运行的统计，在运行结束时输出
+#[derive(Debug, Default, Serialize)]
+pub struct RunReport {
+    pub summarized: usize,
+    pub updated: usize,
+    pub empty: usize,
+    pub gone: usize,
+    pub deferred: usize,
+    pub refused: usize,
+    pub paywalled: usize,
+    pub failed: usize,
+    pub skipped: BTreeMap<&'static str, usize>,
+    // 摘要被 Miniflux 覆盖的文章数，按订阅源统计
+    pub reverted: BTreeMap<String, usize>,
+    pub subrequests: usize,
+    pub estimated_tokens: usize,
+    // 其中全文翻译用掉的 token 数，单独统计
+    pub translation_tokens: usize,
+    // 按 SAMPLE_RATE 估算的全量处理 token 数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub full_rate_tokens: Option<usize>,
+    // 按 LENGTH_TIERS 选中的档位及文章数
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub tiers: BTreeMap<String, usize>,
+    // 需要提醒但不影响处理结果的已知问题
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<&'static str>,
+    // 处理失败、需要稍后重试的文章及原因，供队列消费者决定是否重新投递
+    #[serde(skip)]
+    pub retry: BTreeMap<u64, String>,
+    // 超出 MAX_TOKENS_PER_RUN 延后的文章，重新投递时不算失败
+    #[serde(skip)]
+    pub deferred_ids: BTreeSet<u64>,
+    // 按 AUTO_READ_SKIPPED 在定时任务结束时标为已读的文章
+    #[serde(skip)]
+    pub auto_read: Vec<u64>,
+    // 每篇文章实际执行的步骤
+    pub steps: BTreeMap<u64, Vec<&'static str>>,
+}
+
+impl RunReport {
+    pub fn record<E>(&mut self, result: &Result<EntryOutcome, E>) {
+        match result {
+            Ok(EntryOutcome::Summarized(_)) => self.summarized += 1,
+            Ok(EntryOutcome::Updated) => self.updated += 1,
+            Ok(EntryOutcome::Empty) => self.empty += 1,
+            Ok(EntryOutcome::Gone) => self.gone += 1,
+            Ok(EntryOutcome::Deferred) => self.deferred += 1,
+            Ok(EntryOutcome::Refused) => self.refused += 1,
+            Ok(EntryOutcome::Paywalled) => self.paywalled += 1,
+            Err(_) => self.failed += 1,
+        }
+    }
+
+    // 所有实际处理的文章都失败了，通常是模型服务整体不可用
+    // 失败太少时更可能是文章本身的问题，照常计入死信
+    pub fn is_outage(&self) -> bool {
+        self.failed >= OUTAGE_MIN_FAILURES
+            && self.summarized
+                + self.updated
+                + self.empty
+                + self.gone
+                + self.refused
+                + self.paywalled
+                == 0
+    }
+
+    pub fn record_steps(&mut self, entry_id: u64, steps: &[Step]) {
+        if !steps.is_empty() {
+            self.steps
+                .insert(entry_id, steps.iter().map(Step::as_str).collect());
+        }
+    }
+
+    pub fn steps_line(&self) -> String {
+        self.steps
+            .iter()
+            .map(|(id, steps)| format!("{}={}", id, steps.join("+")))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    pub fn record_reverted(&mut self, site_url: String) {
+        *self.reverted.entry(site_url).or_default() += 1;
+    }
+
+    pub fn record_skip(&mut self, reason: SkipReason) {
+        *self.skipped.entry(reason.as_str()).or_default() += 1;
+    }
+}
+
+impl fmt::Display for RunReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} summarized, {} updated, {} empty, {} gone, {} deferred, {} refused, {} failed",
+            self.summarized,
+            self.updated,
+            self.empty,
+            self.gone,
+            self.deferred,
+            self.refused,
+            self.failed
+        )?;
+        if self.paywalled > 0 {
+            write!(f, ", {} paywalled", self.paywalled)?;
+        }
+        let reverted: usize = self.reverted.values().sum();
+        if reverted > 0 {
+            write!(f, ", {} reverted", reverted)?;
+        }
+        for (reason, count) in &self.skipped {
+            write!(f, ", {} skipped ({})", count, reason)?;
+        }
+        write!(
+            f,
+            ", {} subrequests, ~{} tokens",
+            self.subrequests, self.estimated_tokens
+        )?;
+        if self.translation_tokens > 0 {
+            write!(f, " (~{} for translation)", self.translation_tokens)?;
+        }
+        if let Some(tokens) = self.full_rate_tokens {
+            write!(f, " (~{} tokens at full rate)", tokens)?;
+        }
+        for (tier, count) in &self.tiers {
+            write!(f, ", {} in tier {}", count, tier)?;
+        }
+        for note in &self.notes {
+            write!(f, "; note: {}", note)?;
+        }
+        Ok(())
+    }
+}
+
+// 长时间运行时每完成 `every` 篇输出一次进度，`every` 为 0 时不输出
+pub struct Progress {
+    total: usize,
+    every: usize,
+    concurrency: usize,
+    done: usize,
+    failed: usize,
+}
+
+impl Progress {
+    pub fn new(total: usize, every: usize, concurrency: usize) -> Self {
+        Progress {
+            total,
+            every,
+            concurrency,
+            done: 0,
+            failed: 0,
+        }
+    }
+
+    // 到达间隔时返回进度行，最后一篇由运行报告覆盖，不再输出
+    pub fn record<E>(&mut self, result: &Result<EntryOutcome, E>) -> Option<String> {
+        self.done += 1;
+        if result.is_err() {
+            self.failed += 1;
+        }
+        if self.every == 0 || !self.done.is_multiple_of(self.every) || self.done >= self.total {
+            return None;
+        }
+        let in_flight = self.concurrency.min(self.total - self.done);
+        Some(format!(
+            "Progress: {}/{} entries processed, {} failed, {} in flight",
+            self.done, self.total, self.failed, in_flight
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ok(outcome: EntryOutcome) -> Result<EntryOutcome, String> {
+        Ok(outcome)
+    }
+
+    #[test]
+    fn records_each_outcome() {
+        let mut report = RunReport::default();
+        report.record(&ok(EntryOutcome::Summarized("要点".to_string())));
+        report.record(&ok(EntryOutcome::Gone));
+        report.record(&ok(EntryOutcome::Gone));
+        report.record(&Err::<EntryOutcome, _>("timeout".to_string()));
+        assert_eq!((report.summarized, report.gone, report.failed), (1, 2, 1));
+        assert_eq!(
+            report.to_string(),
+            "1 summarized, 0 updated, 0 empty, 2 gone, 0 deferred, 0 refused, 1 failed, 0 subrequests, ~0 tokens"
+        );
+    }
+
+    #[test]
+    fn lists_steps_per_entry() {
+        let mut report = RunReport::default();
+        report.record_steps(2, &[Step::Summarize, Step::Tags]);
+        report.record_steps(1, &[Step::Score]);
+        report.record_steps(3, &[]);
+        assert_eq!(report.steps_line(), "1=score, 2=summarize+tags");
+    }
+
+    const REASONS: [SkipReason; 12] = [
+        SkipReason::AlreadyProcessed,
+        SkipReason::Status,
+        SkipReason::NotWhitelisted,
+        SkipReason::FeedDisabled,
+        SkipReason::EmptyFeed,
+        SkipReason::NoSteps,
+        SkipReason::TooShort,
+        SkipReason::ContentPattern,
+        SkipReason::DuplicateUrl,
+        SkipReason::SampledOut,
+        SkipReason::Aggregation,
+        SkipReason::Refused,
+    ];
+
+    // 每个原因在报告中都有独立的计数和说明
+    #[test]
+    fn skip_reasons_are_distinct() {
+        for (i, a) in REASONS.iter().enumerate() {
+            for b in &REASONS[i + 1..] {
+                assert_ne!(a.as_str(), b.as_str());
+                assert_ne!(a.description(), b.description());
+            }
+        }
+    }
+
+    #[test]
+    fn counts_skips_by_reason() {
+        let mut report = RunReport::default();
+        report.record_skip(SkipReason::TooShort);
+        report.record_skip(SkipReason::Status);
+        report.record_skip(SkipReason::TooShort);
+        assert_eq!(report.skipped["too_short"], 2);
+        assert!(report
+            .to_string()
+            .contains(", 1 skipped (status), 2 skipped (too_short)"));
+    }
+
+    #[test]
+    fn progress_is_logged_at_each_interval() {
+        let mut progress = Progress::new(5, 2, 3);
+        assert_eq!(progress.record(&ok(EntryOutcome::Updated)), None);
+        assert_eq!(
+            progress.record(&Err::<EntryOutcome, _>("timeout".to_string())),
+            Some("Progress: 2/5 entries processed, 1 failed, 3 in flight".to_string())
+        );
+        assert_eq!(progress.record(&ok(EntryOutcome::Empty)), None);
+        assert_eq!(
+            progress.record(&ok(EntryOutcome::Updated)),
+            Some("Progress: 4/5 entries processed, 1 failed, 1 in flight".to_string())
+        );
+        // 最后一篇由运行报告覆盖
+        assert_eq!(progress.record(&ok(EntryOutcome::Updated)), None);
+    }
+
+    #[test]
+    fn zero_interval_disables_progress() {
+        let mut progress = Progress::new(3, 0, 1);
+        for _ in 0..3 {
+            assert_eq!(progress.record(&ok(EntryOutcome::Updated)), None);
+        }
+    }
+
+    fn failed(report: &mut RunReport, times: usize) {
+        for _ in 0..times {
+            report.record(&Err::<EntryOutcome, _>("timeout".to_string()));
+        }
+    }
+
+    #[test]
+    fn all_failures_is_an_outage() {
+        let mut report = RunReport::default();
+        failed(&mut report, OUTAGE_MIN_FAILURES - 1);
+        assert!(!report.is_outage());
+        failed(&mut report, 1);
+        assert!(report.is_outage());
+        // 延后和跳过的文章没有实际处理，不影响判断
+        report.record(&ok(EntryOutcome::Deferred));
+        report.record_skip(SkipReason::AlreadyProcessed);
+        assert!(report.is_outage());
+    }
+
+    #[test]
+    fn any_processed_entry_rules_out_an_outage() {
+        for outcome in [
+            EntryOutcome::Summarized("要点".to_string()),
+            EntryOutcome::Updated,
+            EntryOutcome::Empty,
+            EntryOutcome::Gone,
+            EntryOutcome::Refused,
+            EntryOutcome::Paywalled,
+        ] {
+            let mut report = RunReport::default();
+            failed(&mut report, 5);
+            let label = format!("{:?}", outcome);
+            report.record(&ok(outcome));
+            assert!(!report.is_outage(), "{}", label);
+        }
+    }
+}
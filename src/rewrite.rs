@@ -0,0 +1,111 @@
+#[cfg(feature = "regex-filters")]
+use regex::{Regex, RegexBuilder};
+
+#[cfg(feature = "regex-filters")]
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+// SUMMARY_REPLACE 中的规则，格式为 `pattern => replacement`，按顺序作用于摘要
+#[derive(Debug, Default)]
+pub struct SummaryRewriter {
+    #[cfg(feature = "regex-filters")]
+    rules: Vec<(Regex, String)>,
+}
+
+impl SummaryRewriter {
+    pub fn new(rules: &[String]) -> Result<Self, String> {
+        let mut parsed = Vec::new();
+        for rule in rules {
+            let Some((pattern, replacement)) = rule.split_once("=>") else {
+                return Err(format!("Missing '=>' in rule: {}", rule));
+            };
+            let pattern = pattern.trim_end();
+            if pattern.is_empty() {
+                return Err(format!("Empty pattern in rule: {}", rule));
+            }
+            // 只去掉 `=>` 后面的一个空格，替换文本本身可以是空白
+            let replacement = replacement.strip_prefix(' ').unwrap_or(replacement);
+            parsed.push((pattern, replacement));
+        }
+
+        #[cfg(not(feature = "regex-filters"))]
+        if let Some((pattern, _)) = parsed.first() {
+            return Err(format!(
+                "{} needs the regex-filters feature, which is not enabled in this build",
+                pattern
+            ));
+        }
+
+        Ok(SummaryRewriter {
+            #[cfg(feature = "regex-filters")]
+            rules: parsed
+                .into_iter()
+                .map(|(pattern, replacement)| {
+                    RegexBuilder::new(pattern)
+                        .size_limit(REGEX_SIZE_LIMIT)
+                        .build()
+                        .map(|regex| (regex, replacement.to_string()))
+                        .map_err(|e| e.to_string())
+                })
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    // 替换文本支持 `$1`、`${name}` 引用分组
+    #[cfg(feature = "regex-filters")]
+    pub fn apply(&self, text: &str) -> String {
+        self.rules
+            .iter()
+            .fold(text.to_string(), |text, (regex, replacement)| {
+                regex.replace_all(&text, replacement.as_str()).into_owned()
+            })
+    }
+
+    #[cfg(not(feature = "regex-filters"))]
+    pub fn apply(&self, text: &str) -> String {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_malformed_rules() {
+        assert!(SummaryRewriter::new(&["no arrow".into()]).is_err());
+        assert!(SummaryRewriter::new(&[" => x".into()]).is_err());
+        assert!(SummaryRewriter::new(&[]).is_ok());
+    }
+
+    #[cfg(feature = "regex-filters")]
+    #[test]
+    fn applies_rules_in_order() {
+        let rewriter = SummaryRewriter::new(&[
+            r"(\d+)%  => $1 percent".into(),
+            "percent => pct".into(),
+            r"\s*广告.*$ =>".into(),
+        ])
+        .unwrap();
+        assert_eq!(rewriter.apply("Up 5% today 广告内容"), "Up 5 pct today");
+    }
+
+    // 只去掉 `=>` 后的一个空格，替换文本可以保留空白
+    #[cfg(feature = "regex-filters")]
+    #[test]
+    fn keeps_whitespace_replacements() {
+        let rewriter = SummaryRewriter::new(&["-- =>  ".into()]).unwrap();
+        assert_eq!(rewriter.apply("a--b"), "a b");
+    }
+
+    #[cfg(feature = "regex-filters")]
+    #[test]
+    fn rejects_invalid_regex() {
+        assert!(SummaryRewriter::new(&["(unclosed => x".into()]).is_err());
+    }
+
+    #[cfg(not(feature = "regex-filters"))]
+    #[test]
+    fn rules_need_the_regex_feature() {
+        assert!(SummaryRewriter::new(&["a => b".into()]).is_err());
+    }
+}
@@ -0,0 +1,75 @@
+use sha2::{Digest, Sha256};
+
+// 按文章 id 和盐值的哈希抽样，同一篇文章每次运行的结果一致，更换盐值可以换一批文章
+pub fn is_included(entry_id: u64, salt: &str, rate: f64) -> bool {
+    if rate >= 1.0 {
+        return true;
+    }
+    if rate <= 0.0 {
+        return false;
+    }
+
+    let digest = Sha256::digest(format!("{}:{}", salt, entry_id).as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    (u64::from_be_bytes(bytes) as f64 / u64::MAX as f64) < rate
+}
+
+// 按抽样比例估算全量处理时的用量
+pub fn extrapolate(used: usize, rate: f64) -> usize {
+    if rate <= 0.0 {
+        return 0;
+    }
+    (used as f64 / rate.min(1.0)).round() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sampling_is_deterministic_per_salt() {
+        for id in 0..100 {
+            assert_eq!(is_included(id, "a", 0.3), is_included(id, "a", 0.3));
+        }
+        let picked = |salt: &str| -> Vec<u64> {
+            (0..200).filter(|&id| is_included(id, salt, 0.3)).collect()
+        };
+        assert_ne!(picked("a"), picked("b"));
+    }
+
+    #[test]
+    fn sample_size_follows_the_rate() {
+        let included = (0..10_000)
+            .filter(|&id| is_included(id, "salt", 0.25))
+            .count();
+        assert!((2_250..2_750).contains(&included), "{}", included);
+    }
+
+    // 提高比例只会增加抽中的文章，已经抽中的文章不会被换掉
+    #[test]
+    fn raising_the_rate_keeps_earlier_picks() {
+        for id in 0..1_000 {
+            if is_included(id, "salt", 0.1) {
+                assert!(is_included(id, "salt", 0.5));
+            }
+        }
+    }
+
+    #[test]
+    fn rate_bounds() {
+        assert!((0..100).all(|id| is_included(id, "salt", 1.0)));
+        assert!((0..100).all(|id| is_included(id, "salt", 2.0)));
+        assert!((0..100).all(|id| !is_included(id, "salt", 0.0)));
+        assert!((0..100).all(|id| !is_included(id, "salt", -1.0)));
+    }
+
+    #[test]
+    fn extrapolates_usage_to_full_rate() {
+        assert_eq!(extrapolate(1_000, 0.25), 4_000);
+        assert_eq!(extrapolate(1_000, 1.0), 1_000);
+        assert_eq!(extrapolate(1_000, 2.0), 1_000);
+        assert_eq!(extrapolate(1_000, 0.0), 0);
+        assert_eq!(extrapolate(1, 0.3), 3);
+    }
+}
@@ -0,0 +1,102 @@
+use std::borrow::Cow;
+
+// JSON 中没有配对的代理项转义（例如 `\ud800`）会导致整个响应解析失败，替换为 `�`
+pub fn replace_lone_surrogates(json: &str) -> Cow<'_, str> {
+    let bytes = json.as_bytes();
+    let mut out: Option<String> = None;
+    let mut copied = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != b'\\' {
+            i += 1;
+            continue;
+        }
+        if bytes.get(i + 1) != Some(&b'u') {
+            // 其他转义（包括 `\\`）整体跳过
+            i += 2;
+            continue;
+        }
+        let Some(unit) = escape_unit(json, i) else {
+            i += 2;
+            continue;
+        };
+        let paired = (0xD800..=0xDBFF).contains(&unit)
+            && escape_unit(json, i + 6).is_some_and(|low| (0xDC00..=0xDFFF).contains(&low));
+        if paired {
+            i += 12;
+        } else if (0xD800..=0xDFFF).contains(&unit) {
+            let out = out.get_or_insert_with(|| String::with_capacity(json.len()));
+            out.push_str(&json[copied..i]);
+            out.push_str("\\ufffd");
+            i += 6;
+            copied = i;
+        } else {
+            i += 6;
+        }
+    }
+
+    match out {
+        Some(mut out) => {
+            out.push_str(&json[copied..]);
+            Cow::Owned(out)
+        }
+        None => Cow::Borrowed(json),
+    }
+}
+
+fn escape_unit(json: &str, at: usize) -> Option<u32> {
+    let hex = json.get(at..at + 6)?.strip_prefix("\\u")?;
+    u32::from_str_radix(hex, 16).ok()
+}
+
+// 去掉制表符和换行以外的控制字符，部分模型接口会拒绝这类字符
+pub fn strip_controls(text: &str) -> Cow<'_, str> {
+    if !text.chars().any(is_stripped) {
+        return Cow::Borrowed(text);
+    }
+    Cow::Owned(text.chars().filter(|&c| !is_stripped(c)).collect())
+}
+
+fn is_stripped(c: char) -> bool {
+    c.is_control() && !matches!(c, '\t' | '\n' | '\r')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_lone_surrogates() {
+        let fixed = replace_lone_surrogates(r#"{"a":"x\ud800y","b":"\udc00"}"#);
+        assert_eq!(fixed, r#"{"a":"x\ufffdy","b":"\ufffd"}"#);
+        let value: serde_json::Value = serde_json::from_str(&fixed).unwrap();
+        assert_eq!(value["a"], "x\u{fffd}y");
+    }
+
+    // 配对的代理项、普通转义和转义的反斜杠保持原样
+    #[test]
+    fn leaves_valid_escapes_untouched() {
+        for json in [r#""😀""#, r#""中\n\"""#, r#""\\ud800""#, r#""\u12""#] {
+            assert!(
+                matches!(replace_lone_surrogates(json), Cow::Borrowed(_)),
+                "{}",
+                json
+            );
+        }
+    }
+
+    #[test]
+    fn high_surrogate_before_a_valid_pair_is_replaced() {
+        assert_eq!(
+            replace_lone_surrogates(r#""\ud800\ud83d\ude00""#),
+            r#""\ufffd\ud83d\ude00""#
+        );
+    }
+
+    #[test]
+    fn strips_control_characters_but_keeps_whitespace() {
+        assert_eq!(strip_controls("a\u{0}b\u{1b}c\td\ne\r"), "abc\td\ne\r");
+        assert!(matches!(strip_controls("中文\n"), Cow::Borrowed(_)));
+    }
+}
@@ -0,0 +1,264 @@
+use serde::Deserialize;
+
+// 可以按订阅源覆盖的设置，未设置的字段沿用全局默认值
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedSettings {
+    pub prompt: String,
+    // FEED_SETTINGS 中指定了提示词，优先于 LENGTH_TIERS
+    pub prompt_overridden: bool,
+    pub fetch_full_content: bool,
+    pub summarize_comments: bool,
+    pub extract_entities: bool,
+    // 摘要被 Miniflux 覆盖时恢复缓存的摘要
+    pub sticky_summary: bool,
+    // 摘要时附上同一订阅源上一篇文章的摘要
+    pub feed_context: bool,
+    // 摘要时附上同一订阅源最近几篇文章的摘要，0 表示关闭
+    pub context_hints: usize,
+    // 摘要之外逐段翻译全文
+    pub translate_body: bool,
+    // 以引用和链接为主的聚合类文章的处理方式
+    pub aggregation: AggregationPolicy,
+    // 按订阅源分类选择的模型，未配置时为 OPENAI_MODEL
+    pub model: String,
+    // 按订阅源分类选择的语气，未配置时不追加到提示词
+    pub style: Option<String>,
+    pub features: Features,
+}
+
+// 每篇文章可独立开关的处理步骤
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Features {
+    pub summarize: bool,
+    pub tags: bool,
+    pub translate_title: bool,
+    pub score: bool,
+}
+
+impl Features {
+    pub fn any(&self) -> bool {
+        self.summarize || self.tags || self.translate_title || self.score
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AggregationPolicy {
+    Skip,
+    SummarizeAnyway,
+    // 改为列出文章中链接的条目，每条附一句说明
+    LinksOnly,
+}
+
+impl std::str::FromStr for AggregationPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "skip" => Ok(AggregationPolicy::Skip),
+            "summarize_anyway" => Ok(AggregationPolicy::SummarizeAnyway),
+            "links_only" => Ok(AggregationPolicy::LinksOnly),
+            other => Err(format!("Unknown aggregation policy: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Step {
+    Classify,
+    Summarize,
+    Tags,
+    TranslateTitle,
+    TranslateBody,
+    Score,
+}
+
+impl Step {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Step::Classify => "classify",
+            Step::Summarize => "summarize",
+            Step::Tags => "tags",
+            Step::TranslateTitle => "translate_title",
+            Step::TranslateBody => "translate_body",
+            Step::Score => "score",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FeedOverrides {
+    pub prompt: Option<String>,
+    pub fetch_full_content: Option<bool>,
+    pub summarize_comments: Option<bool>,
+    pub extract_entities: Option<bool>,
+    pub sticky_summary: Option<bool>,
+    pub feed_context: Option<bool>,
+    pub context_hints: Option<usize>,
+    pub translate_body: Option<bool>,
+    pub aggregation: Option<AggregationPolicy>,
+    #[serde(alias = "ticker_tags")]
+    pub tags: Option<bool>,
+}
+
+// FEED_FEATURES 中每条规则可以设置的步骤开关
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeatureOverrides {
+    pub summarize: Option<bool>,
+    pub tags: Option<bool>,
+    pub translate_title: Option<bool>,
+    pub score: Option<bool>,
+}
+
+impl FeedSettings {
+    // 提前发现处理时才会暴露的配置错误，例如未知的提示词预设
+    pub fn validate(&self) -> Result<(), String> {
+        crate::prompts::resolve(&self.prompt).map(|_| ())
+    }
+
+    pub fn with_model(&self, model: &str) -> FeedSettings {
+        FeedSettings {
+            model: model.to_string(),
+            ..self.clone()
+        }
+    }
+
+    pub fn with_style(&self, style: &str) -> FeedSettings {
+        FeedSettings {
+            style: Some(style.to_string()),
+            ..self.clone()
+        }
+    }
+
+    pub fn with_overrides(&self, overrides: &FeedOverrides) -> FeedSettings {
+        FeedSettings {
+            prompt: overrides
+                .prompt
+                .clone()
+                .unwrap_or_else(|| self.prompt.clone()),
+            prompt_overridden: self.prompt_overridden || overrides.prompt.is_some(),
+            fetch_full_content: overrides
+                .fetch_full_content
+                .unwrap_or(self.fetch_full_content),
+            summarize_comments: overrides
+                .summarize_comments
+                .unwrap_or(self.summarize_comments),
+            extract_entities: overrides.extract_entities.unwrap_or(self.extract_entities),
+            sticky_summary: overrides.sticky_summary.unwrap_or(self.sticky_summary),
+            feed_context: overrides.feed_context.unwrap_or(self.feed_context),
+            context_hints: overrides.context_hints.unwrap_or(self.context_hints),
+            translate_body: overrides.translate_body.unwrap_or(self.translate_body),
+            aggregation: overrides.aggregation.unwrap_or(self.aggregation),
+            model: self.model.clone(),
+            style: self.style.clone(),
+            features: Features {
+                tags: overrides.tags.unwrap_or(self.features.tags),
+                ..self.features
+            },
+        }
+    }
+
+    pub fn with_features(&self, overrides: &FeatureOverrides) -> FeedSettings {
+        FeedSettings {
+            features: Features {
+                summarize: overrides.summarize.unwrap_or(self.features.summarize),
+                tags: overrides.tags.unwrap_or(self.features.tags),
+                translate_title: overrides
+                    .translate_title
+                    .unwrap_or(self.features.translate_title),
+                score: overrides.score.unwrap_or(self.features.score),
+            },
+            ..self.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> FeedSettings {
+        FeedSettings {
+            prompt: "default".to_string(),
+            prompt_overridden: false,
+            fetch_full_content: false,
+            summarize_comments: false,
+            extract_entities: false,
+            sticky_summary: false,
+            feed_context: false,
+            context_hints: 0,
+            translate_body: false,
+            aggregation: AggregationPolicy::SummarizeAnyway,
+            model: "gpt-4o-mini".to_string(),
+            style: None,
+            features: Features {
+                summarize: true,
+                tags: false,
+                translate_title: false,
+                score: false,
+            },
+        }
+    }
+
+    #[test]
+    fn overrides_replace_only_the_fields_they_set() {
+        let overrides: FeedOverrides = serde_json::from_str(
+            r#"{"prompt": "concise_en", "summarize_comments": true, "fetch_full_content": true}"#,
+        )
+        .unwrap();
+        let settings = base().with_overrides(&overrides);
+        assert_eq!(
+            settings,
+            FeedSettings {
+                prompt: "concise_en".to_string(),
+                prompt_overridden: true,
+                summarize_comments: true,
+                fetch_full_content: true,
+                ..base()
+            }
+        );
+        assert_eq!(base().with_overrides(&FeedOverrides::default()), base());
+    }
+
+    #[test]
+    fn feature_overrides_toggle_individual_steps() {
+        let overrides: FeatureOverrides =
+            serde_json::from_str(r#"{"summarize": false, "score": true}"#).unwrap();
+        let settings = base().with_features(&overrides);
+        assert_eq!(
+            settings.features,
+            Features {
+                summarize: false,
+                tags: false,
+                translate_title: false,
+                score: true,
+            }
+        );
+        assert!(settings.features.any());
+        assert_eq!(settings.prompt, base().prompt);
+    }
+
+    #[test]
+    fn all_steps_disabled() {
+        let overrides: FeatureOverrides = serde_json::from_str(r#"{"summarize": false}"#).unwrap();
+        assert!(!base().with_features(&overrides).features.any());
+    }
+
+    #[test]
+    fn unknown_feature_names_are_rejected() {
+        assert!(serde_json::from_str::<FeatureOverrides>(r#"{"summarise": false}"#).is_err());
+    }
+
+    #[test]
+    fn parses_aggregation_policy() {
+        assert_eq!(" Skip ".parse(), Ok(AggregationPolicy::Skip));
+        assert_eq!(
+            "summarize_anyway".parse(),
+            Ok(AggregationPolicy::SummarizeAnyway)
+        );
+        assert_eq!("links_only".parse(), Ok(AggregationPolicy::LinksOnly));
+        assert!("ignore".parse::<AggregationPolicy>().is_err());
+    }
+}
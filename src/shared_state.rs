@@ -0,0 +1,185 @@
+use std::cell::RefCell;
+use std::fmt;
+
+// Workers 运行时是单线程的，buffer_unordered 中的任务只在 await 处切换
+// 状态只能在同步闭包中访问，借用不会跨过 await，其他任务也就看不到修改到一半的状态
+// 闭包中再次访问同一个 Shared 会 panic，需要的数据应在闭包外准备好
+#[derive(Default)]
+pub struct Shared<T> {
+    inner: RefCell<T>,
+}
+
+impl<T> Shared<T> {
+    pub fn new(value: T) -> Self {
+        Shared {
+            inner: RefCell::new(value),
+        }
+    }
+
+    pub fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        f(&mut self.inner.borrow_mut())
+    }
+
+    // 检查和修改在同一个闭包中完成，返回 None 时状态不变
+    pub fn try_update(&self, f: impl FnOnce(&T) -> Option<T>) -> bool {
+        let mut inner = self.inner.borrow_mut();
+        match f(&inner) {
+            Some(next) => {
+                *inner = next;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get(&self) -> T
+    where
+        T: Clone,
+    {
+        self.inner.borrow().clone()
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner.into_inner()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Shared<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.inner.try_borrow() {
+            Ok(inner) => f.debug_tuple("Shared").field(&*inner).finish(),
+            Err(_) => f.write_str("Shared(<in use>)"),
+        }
+    }
+}
+
+// 测试中模拟 await 处的任务切换
+#[cfg(test)]
+pub(crate) async fn yield_now() {
+    let mut yielded = false;
+    std::future::poll_fn(|cx| {
+        if yielded {
+            return std::task::Poll::Ready(());
+        }
+        yielded = true;
+        cx.waker().wake_by_ref();
+        std::task::Poll::Pending
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::{stream, StreamExt};
+
+    #[test]
+    fn with_returns_closure_result() {
+        let shared = Shared::new(vec![1]);
+        let len = shared.with(|v| {
+            v.push(2);
+            v.len()
+        });
+        assert_eq!(len, 2);
+        assert_eq!(shared.get(), vec![1, 2]);
+        assert_eq!(shared.into_inner(), vec![1, 2]);
+    }
+
+    #[test]
+    fn try_update_keeps_state_on_none() {
+        let shared = Shared::new(5);
+        assert!(!shared.try_update(|_| None));
+        assert_eq!(shared.get(), 5);
+        assert!(shared.try_update(|n| Some(n + 1)));
+        assert_eq!(shared.get(), 6);
+    }
+
+    #[test]
+    fn debug_shows_value() {
+        assert_eq!(format!("{:?}", Shared::new(3)), "Shared(3)");
+    }
+
+    // 并发任务在每次修改之间切换，所有修改都不会丢失
+    #[test]
+    fn concurrent_tasks_interleave_without_lost_updates() {
+        let shared = Shared::new(Vec::new());
+        block_on(stream::iter(0..8).for_each_concurrent(None, |task| {
+            let shared = &shared;
+            async move {
+                for step in 0..10 {
+                    shared.with(|log| log.push((task, step)));
+                    yield_now().await;
+                }
+            }
+        }));
+        let log = shared.into_inner();
+        assert_eq!(log.len(), 80);
+        // 确实发生了交错，而不是逐个任务执行
+        assert!(log[..10].iter().any(|(task, _)| *task != log[0].0));
+        for task in 0..8 {
+            let steps: Vec<_> = log
+                .iter()
+                .filter(|(t, _)| *t == task)
+                .map(|(_, s)| *s)
+                .collect();
+            assert_eq!(steps, (0..10).collect::<Vec<_>>());
+        }
+    }
+
+    // 检查和修改被 await 隔开时，两个任务都会通过检查，计数超出上限
+    #[test]
+    fn check_then_act_across_await_overshoots() {
+        let used = Shared::new(0usize);
+        let limit = 10;
+        let reserved = block_on(
+            stream::iter(0..2)
+                .map(|_| {
+                    let used = &used;
+                    async move {
+                        let fits = used.get() + 6 <= limit;
+                        yield_now().await;
+                        if fits {
+                            used.with(|used| *used += 6);
+                        }
+                        fits
+                    }
+                })
+                .buffer_unordered(2)
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(reserved, vec![true, true]);
+        assert_eq!(used.get(), 12);
+    }
+
+    // 同样的交错下 try_update 在一次借用中完成检查和修改
+    #[test]
+    fn try_update_is_atomic_across_interleaving() {
+        let used = Shared::new(0usize);
+        let limit = 10;
+        let reserved = block_on(
+            stream::iter(0..2)
+                .map(|_| {
+                    let used = &used;
+                    async move {
+                        yield_now().await;
+                        let fits = used.try_update(|n| (n + 6 <= limit).then_some(n + 6));
+                        yield_now().await;
+                        fits
+                    }
+                })
+                .buffer_unordered(2)
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(reserved.iter().filter(|r| **r).count(), 1);
+        assert_eq!(used.get(), 6);
+    }
+
+    // 闭包内再次访问同一个 Shared 是编程错误
+    #[test]
+    #[should_panic]
+    fn reentrant_access_panics() {
+        let shared = Shared::new(0);
+        shared.with(|_| shared.with(|n| *n += 1));
+    }
+}
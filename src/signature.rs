@@ -0,0 +1,90 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+// 计算 Miniflux Webhook 签名：以密钥对请求体做 HMAC-SHA256，结果为十六进制字符串
+pub fn sign(secret: &str, payload: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC can take key of any size");
+    mac.update(payload.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+// 验证 Miniflux 的 Webhook 请求签名，使用常量时间比较
+pub fn verify(secret: &str, payload: &str, signature: &str) -> bool {
+    let Ok(expected) = hex::decode(signature.trim()) else {
+        return false;
+    };
+
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC can take key of any size");
+    mac.update(payload.as_bytes());
+    mac.verify_slice(&expected).is_ok()
+}
+
+// 常量时间比较令牌：两边先用同一个密钥做 HMAC，长度不同也不会提前返回
+pub fn tokens_match(expected: &str, given: &str) -> bool {
+    let mac = |token: &str| {
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"miniflux-ai token")
+            .expect("HMAC can take key of any size");
+        mac.update(token.as_bytes());
+        mac
+    };
+    let expected = mac(expected).finalize().into_bytes();
+    mac(given).verify_slice(&expected).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 4231 测试用例 2
+    #[test]
+    fn matches_rfc4231_vector() {
+        assert_eq!(
+            sign("Jefe", "what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn matches_known_vectors() {
+        assert_eq!(
+            sign("", ""),
+            "b613679a0814d9ec772f95d778c35fc5ff1697c493715653c6c712144292c5ad"
+        );
+        assert_eq!(
+            sign("secret", "摘要"),
+            "9d580f8be95ea8396697b684e3a15392842988349bb433532039514fa77136c9"
+        );
+    }
+
+    #[test]
+    fn sign_then_verify_round_trips() {
+        let payload = r#"{"event_type":"new_entries","entries":[]}"#;
+        let signature = sign("secret", payload);
+        assert!(verify("secret", payload, &signature));
+        // Miniflux 发来的签名可能带换行
+        assert!(verify("secret", payload, &format!(" {}\n", signature)));
+        assert!(verify("secret", payload, &signature.to_uppercase()));
+    }
+
+    #[test]
+    fn rejects_wrong_secret_payload_or_signature() {
+        let signature = sign("secret", "payload");
+        assert!(!verify("other", "payload", &signature));
+        assert!(!verify("secret", "payload!", &signature));
+        assert!(!verify("secret", "payload", &signature[..62]));
+        assert!(!verify("secret", "payload", "not hex"));
+        assert!(!verify("secret", "payload", ""));
+    }
+
+    #[test]
+    fn tokens_match_only_identical_tokens() {
+        assert!(tokens_match("token", "token"));
+        assert!(tokens_match("", ""));
+        assert!(!tokens_match("token", "Token"));
+        assert!(!tokens_match("token", "token "));
+        assert!(!tokens_match("token", "tok"));
+        assert!(!tokens_match("token", ""));
+    }
+}
@@ -0,0 +1,132 @@
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct Delta {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct StreamChoice {
+    delta: Delta,
+}
+
+#[derive(Deserialize)]
+struct StreamChunk {
+    #[serde(default)]
+    choices: Vec<StreamChoice>,
+    error: Option<serde_json::Value>,
+}
+
+// 逐块解析 `stream: true` 返回的 SSE 数据，把每个 delta 拼接为完整文本
+#[derive(Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    text: String,
+    done: bool,
+}
+
+impl SseParser {
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<(), String> {
+        self.buffer.extend_from_slice(chunk);
+
+        // 只处理完整的行，剩余部分等下一个分块，避免切断多字节字符
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            self.line(&String::from_utf8_lossy(&line))?;
+        }
+        Ok(())
+    }
+
+    fn line(&mut self, line: &str) -> Result<(), String> {
+        let Some(data) = line.trim().strip_prefix("data:") else {
+            return Ok(());
+        };
+        let data = data.trim();
+        if self.done || data.is_empty() {
+            return Ok(());
+        }
+        if data == "[DONE]" {
+            self.done = true;
+            return Ok(());
+        }
+
+        let chunk: StreamChunk =
+            serde_json::from_str(data).map_err(|e| format!("Invalid stream chunk: {}", e))?;
+        if let Some(error) = chunk.error {
+            return Err(format!("OpenAI error: {}", error));
+        }
+        for choice in chunk.choices {
+            if let Some(content) = choice.delta.content {
+                self.text.push_str(&content);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<String, String> {
+        if !self.buffer.is_empty() {
+            let rest = String::from_utf8_lossy(&std::mem::take(&mut self.buffer)).to_string();
+            self.line(&rest)?;
+        }
+        Ok(self.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(chunks: &[&[u8]]) -> Result<String, String> {
+        let mut parser = SseParser::default();
+        for chunk in chunks {
+            parser.feed(chunk)?;
+        }
+        parser.finish()
+    }
+
+    const STREAM: &str = "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n\
+        data: {\"choices\":[{\"delta\":{\"content\":\"你好\"}}]}\n\n\
+        : keep-alive\n\
+        data: {\"choices\":[{\"delta\":{\"content\":\", world\"}}]}\n\n\
+        data: [DONE]\n\n";
+
+    #[test]
+    fn joins_deltas_until_done() {
+        assert_eq!(parse(&[STREAM.as_bytes()]).unwrap(), "你好, world");
+    }
+
+    // 分块可能切在行中间甚至多字节字符中间
+    #[test]
+    fn handles_chunks_split_anywhere() {
+        let bytes = STREAM.as_bytes();
+        for size in [1, 2, 3, 7, 64] {
+            let chunks: Vec<&[u8]> = bytes.chunks(size).collect();
+            assert_eq!(
+                parse(&chunks).unwrap(),
+                "你好, world",
+                "chunk size {}",
+                size
+            );
+        }
+    }
+
+    #[test]
+    fn last_line_without_newline_is_parsed() {
+        let stream = "data: {\"choices\":[{\"delta\":{\"content\":\"a\"}}]}";
+        assert_eq!(parse(&[stream.as_bytes()]).unwrap(), "a");
+    }
+
+    #[test]
+    fn data_after_done_is_ignored() {
+        let stream = "data: [DONE]\ndata: not json\n";
+        assert_eq!(parse(&[stream.as_bytes()]).unwrap(), "");
+    }
+
+    #[test]
+    fn errors_in_the_stream_are_reported() {
+        let error = parse(&[b"data: {\"error\":{\"message\":\"quota\"}}\n"]).unwrap_err();
+        assert!(error.starts_with("OpenAI error:"), "{}", error);
+        assert!(parse(&[b"data: {broken\n"]).is_err());
+    }
+}
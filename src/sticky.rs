@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+// 写入成功的摘要，按文章 id 缓存，用于发现被 Miniflux 刷新覆盖的文章
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedSummary {
+    pub hash: String,
+    pub summary: String,
+    // 用于导出摘要订阅源，旧缓存中没有
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub url: String,
+}
+
+// 缓存中有摘要、内容哈希未变但文章又没有摘要标记，说明摘要被 Miniflux 刷新时覆盖了
+// 哈希不同说明原文确实更新了，需要重新生成
+pub fn is_reverted(cached: &CachedSummary, entry_hash: &str) -> bool {
+    cached.hash.is_empty() || entry_hash.is_empty() || cached.hash == entry_hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cached(hash: &str) -> CachedSummary {
+        CachedSummary {
+            hash: hash.to_string(),
+            summary: "要点".to_string(),
+            title: String::new(),
+            url: String::new(),
+        }
+    }
+
+    #[test]
+    fn unchanged_hash_means_the_summary_was_overwritten() {
+        assert!(is_reverted(&cached("abc"), "abc"));
+        assert!(!is_reverted(&cached("abc"), "def"));
+    }
+
+    // 缺少哈希时无法判断原文是否更新，按被覆盖处理
+    #[test]
+    fn missing_hashes_count_as_reverted() {
+        assert!(is_reverted(&cached(""), "abc"));
+        assert!(is_reverted(&cached("abc"), ""));
+    }
+
+    #[test]
+    fn old_cache_entries_without_title_still_parse() {
+        let cached: CachedSummary =
+            serde_json::from_str(r#"{"hash": "abc", "summary": "要点"}"#).unwrap();
+        assert_eq!((cached.title.as_str(), cached.url.as_str()), ("", ""));
+    }
+}
@@ -0,0 +1,81 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+// 模型输出的 JSON 可能包在 Markdown 代码块或前后带有说明文字，这里截取第一个完整对象再解析
+pub fn parse_json_object<T: DeserializeOwned>(raw: &str) -> Option<T> {
+    let start = raw.find('{')?;
+    let end = raw.rfind('}')?;
+    if end < start {
+        return None;
+    }
+
+    serde_json::from_str(&raw[start..=end]).ok()
+}
+
+// 按 `data.choices[0].message.content` 形式的路径读取 JSON 中的值
+pub fn value_at_path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    let mut current = value;
+    for segment in path.split('.').filter(|s| !s.is_empty()) {
+        let (key, indexes) = match segment.find('[') {
+            Some(i) => segment.split_at(i),
+            None => (segment, ""),
+        };
+        if !key.is_empty() {
+            current = current.get(key)?;
+        }
+        for index in indexes.split('[').filter(|s| !s.is_empty()) {
+            let index: usize = index.strip_suffix(']')?.trim().parse().ok()?;
+            current = current.get(index)?;
+        }
+    }
+    Some(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_the_object_inside_surrounding_text() {
+        let value: Value =
+            parse_json_object("Here you go:\n```json\n{\"a\": {\"b\": 1}}\n```\nDone.").unwrap();
+        assert_eq!(value, json!({"a": {"b": 1}}));
+    }
+
+    #[test]
+    fn rejects_missing_or_broken_objects() {
+        assert!(parse_json_object::<Value>("no braces").is_none());
+        assert!(parse_json_object::<Value>("} backwards {").is_none());
+        assert!(parse_json_object::<Value>("{\"a\": }").is_none());
+    }
+
+    #[test]
+    fn reads_values_at_dotted_paths() {
+        let value = json!({
+            "data": {"choices": [{"message": {"content": "a"}}, {"text": "b"}]},
+            "result": {"response": "c"},
+            "outputs": [[1, 2], [3]]
+        });
+        assert_eq!(
+            value_at_path(&value, "data.choices[0].message.content"),
+            Some(&json!("a"))
+        );
+        assert_eq!(
+            value_at_path(&value, "data.choices[ 1 ].text"),
+            Some(&json!("b"))
+        );
+        assert_eq!(value_at_path(&value, "result.response"), Some(&json!("c")));
+        assert_eq!(value_at_path(&value, "outputs[1][0]"), Some(&json!(3)));
+        assert_eq!(value_at_path(&value, ""), Some(&value));
+    }
+
+    #[test]
+    fn missing_paths_give_none() {
+        let value = json!({"choices": [{"text": "a"}]});
+        assert_eq!(value_at_path(&value, "choices[1].text"), None);
+        assert_eq!(value_at_path(&value, "choices.text"), None);
+        assert_eq!(value_at_path(&value, "choices[x]"), None);
+        assert_eq!(value_at_path(&value, "choices[0"), None);
+    }
+}
@@ -0,0 +1,88 @@
+use serde::Deserialize;
+
+// 签名只覆盖请求体，文章 ID 和 force 必须写在请求体中，否则一个签名可以对任意文章重放
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct SummarizeRequest {
+    pub entry_id: u64,
+    // 已有摘要时也重新生成
+    #[serde(default)]
+    pub force: bool,
+}
+
+// 返回路径和请求体中文章 ID 一致的请求
+pub fn parse_request(path_id: &str, payload: &str) -> Result<SummarizeRequest, String> {
+    let path_id: u64 = path_id
+        .parse()
+        .map_err(|_| "Invalid entry id".to_string())?;
+    let request: SummarizeRequest = serde_json::from_str(payload)
+        .map_err(|e| format!("Request body must be {{\"entry_id\": N}}: {}", e))?;
+    if request.entry_id != path_id {
+        return Err(format!(
+            "entry_id {} in the body does not match {} in the path",
+            request.entry_id, path_id
+        ));
+    }
+    Ok(request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::{sign, verify};
+
+    fn entry_id(path_id: &str, payload: &str) -> Result<u64, String> {
+        parse_request(path_id, payload).map(|request| request.entry_id)
+    }
+
+    #[test]
+    fn accepts_matching_entry_id() {
+        assert_eq!(entry_id("123", r#"{"entry_id": 123}"#), Ok(123));
+    }
+
+    #[test]
+    fn force_defaults_to_false() {
+        assert_eq!(
+            parse_request("123", r#"{"entry_id": 123}"#),
+            Ok(SummarizeRequest {
+                entry_id: 123,
+                force: false
+            })
+        );
+        assert_eq!(
+            parse_request("123", r#"{"entry_id": 123, "force": true}"#),
+            Ok(SummarizeRequest {
+                entry_id: 123,
+                force: true
+            })
+        );
+        assert!(parse_request("123", r#"{"entry_id": 123, "force": "yes"}"#).is_err());
+    }
+
+    #[test]
+    fn rejects_mismatched_entry_id() {
+        assert!(entry_id("124", r#"{"entry_id": 123}"#).is_err());
+    }
+
+    #[test]
+    fn rejects_empty_or_invalid_body() {
+        assert!(entry_id("123", "").is_err());
+        assert!(entry_id("123", "{}").is_err());
+        assert!(entry_id("123", r#"{"entry_id": "123"}"#).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_path_id() {
+        assert!(entry_id("abc", r#"{"entry_id": 1}"#).is_err());
+        assert!(entry_id("", r#"{"entry_id": 1}"#).is_err());
+    }
+
+    // 截获的签名请求体只能用于它自己的文章
+    #[test]
+    fn signed_body_cannot_be_replayed_for_another_entry() {
+        let payload = r#"{"entry_id": 1}"#;
+        let signature = sign("secret", payload);
+        assert!(verify("secret", payload, &signature));
+        assert_eq!(entry_id("1", payload), Ok(1));
+        assert!(entry_id("2", payload).is_err());
+    }
+}
@@ -0,0 +1,595 @@
+use crate::html::{decode_entities, escape, MediaStats};
+
+pub const SUMMARY_LABEL: &str = "💡AI 摘要：";
+pub const FAILURE_LABEL: &str = "⚠️AI 摘要失败：";
+// 模型不可用时的抽取式摘要，和 AI 摘要区分开
+pub const EXTRACT_LABEL: &str = "📝 摘录：";
+// 未生成摘要但已经处理过的文章打上这个标签，避免重复处理
+pub const PROCESSED_TAG: &str = "🤖AI";
+pub const SCORE_TAG_PREFIX: &str = "⭐AI 评分：";
+
+const LABELS: [&str; 3] = [SUMMARY_LABEL, FAILURE_LABEL, EXTRACT_LABEL];
+
+// SUMMARY_TARGET=tags 时用标签判断文章是否处理过
+pub fn is_processed_tag(tag: &str) -> bool {
+    tag == PROCESSED_TAG || LABELS.iter().any(|label| tag.starts_with(label))
+}
+
+// 摘要写成标签，付费墙提示等没有标签前缀的内容另外加上 PROCESSED_TAG，避免每次运行重复处理
+pub fn summary_tags(tags: &[String], label: &str, text: &str) -> Vec<String> {
+    let mut tags = tags.to_vec();
+    let tag = format!("{}{}", label, text);
+    if !is_processed_tag(&tag) && !tags.iter().any(|t| t == PROCESSED_TAG) {
+        tags.push(PROCESSED_TAG.to_string());
+    }
+    tags.push(tag);
+    tags
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlockStyle {
+    Pre,
+    // 不带 `<pre><code>` 样式的纯文本段落，方便朗读等辅助工具读取
+    Plaintext,
+}
+
+// 摘要块的文字方向，auto 时按摘要语言判断
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextDirection {
+    Auto,
+    Ltr,
+    Rtl,
+}
+
+impl std::str::FromStr for TextDirection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "auto" => Ok(TextDirection::Auto),
+            "ltr" => Ok(TextDirection::Ltr),
+            "rtl" => Ok(TextDirection::Rtl),
+            other => Err(format!("Unknown text direction: {}", other)),
+        }
+    }
+}
+
+// 摘要块上的隐藏标记，记录生成摘要时的设置哈希，格式为 `v1:ab12cd`
+pub const MARKER_ATTR: &str = "data-miniflux-ai";
+pub const MARKER_VERSION: &str = "v1";
+
+// 标记必须是第一个属性，has_summary_block 依赖它识别段落样式的摘要块
+pub fn render_block(
+    style: BlockStyle,
+    label: &str,
+    text: &str,
+    settings_hash: &str,
+    dir: Option<&str>,
+) -> String {
+    let mut marker = format!(" {}=\"{}:{}\"", MARKER_ATTR, MARKER_VERSION, settings_hash);
+    if let Some(dir) = dir {
+        marker.push_str(&format!(" dir=\"{}\"", dir));
+    }
+    match style {
+        BlockStyle::Pre => format!(
+            "<pre{} style=\"white-space: pre-wrap;\"><code>\n{}\n{}</code></pre>",
+            marker, label, text
+        ),
+        BlockStyle::Plaintext => format!(
+            "<p{}>{}{}</p>",
+            marker,
+            label,
+            escape(text.trim()).replace('\n', "<br>")
+        ),
+    }
+}
+
+// 摘要块放在原文前面，用分隔线隔开
+pub fn prepend_block(block: &str, content: &str) -> String {
+    format!("{}{}{}", block, BLOCK_SEPARATOR, content)
+}
+
+const BLOCK_SEPARATOR: &str = "<hr><br />";
+
+// 正文以摘要块开头说明已经处理过
+pub fn has_summary_block(content: &str) -> bool {
+    content
+        .strip_prefix("<pre")
+        .is_some_and(|rest| rest.starts_with(|c: char| c == '>' || c.is_whitespace()))
+        || content.starts_with(&format!("<p {}=", MARKER_ATTR))
+        || LABELS
+            .iter()
+            .any(|label| content.starts_with(&format!("<p>{}", label)))
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Marker<'a> {
+    pub version: &'a str,
+    pub hash: Option<&'a str>,
+}
+
+// 读取摘要块上的标记，旧版本生成的摘要块没有标记，返回不带哈希的结果
+pub fn parse_marker(content: &str) -> Option<Marker<'_>> {
+    if !has_summary_block(content) {
+        return None;
+    }
+    let tag = &content[..content.find('>')?];
+    let prefix = format!("{}=\"", MARKER_ATTR);
+    let Some(start) = tag.find(&prefix) else {
+        return Some(Marker {
+            version: "",
+            hash: None,
+        });
+    };
+
+    let value = tag[start + prefix.len()..].split('"').next()?;
+    Some(match value.split_once(':') {
+        Some((version, hash)) => Marker {
+            version,
+            hash: Some(hash),
+        },
+        None => Marker {
+            version: value,
+            hash: None,
+        },
+    })
+}
+
+// 从已有的摘要块中取出的内容，用于不调用模型直接按新的样式重新渲染
+#[derive(Debug, PartialEq)]
+pub struct ParsedBlock<'a> {
+    pub style: BlockStyle,
+    pub label: &'static str,
+    pub text: String,
+    pub dir: Option<&'a str>,
+    // 分隔线及其后的全部内容，重新渲染时原样保留
+    pub rest: &'a str,
+}
+
+// 只识别 render_block 生成过的结构，无法完整解析时返回 None，调用方不应修改正文
+pub fn parse_block(content: &str) -> Option<ParsedBlock<'_>> {
+    if !has_summary_block(content) {
+        return None;
+    }
+    let (block, _) = content.split_once(BLOCK_SEPARATOR)?;
+    let rest = &content[block.len()..];
+    let tag_end = block.find('>')?;
+    let (tag, body) = (&block[..tag_end], &block[tag_end + 1..]);
+    let dir = tag
+        .split_once(" dir=\"")
+        .and_then(|(_, value)| value.split('"').next());
+
+    if block.starts_with("<pre") {
+        let inner = body
+            .strip_prefix("<code>\n")?
+            .strip_suffix("</code></pre>")?;
+        let (label, text) = split_label(inner)?;
+        Some(ParsedBlock {
+            style: BlockStyle::Pre,
+            label,
+            text: text.strip_prefix('\n').unwrap_or(text).to_string(),
+            dir,
+            rest,
+        })
+    } else {
+        let inner = body.strip_suffix("</p>")?;
+        let (label, text) = split_label(inner)?;
+        Some(ParsedBlock {
+            style: BlockStyle::Plaintext,
+            label,
+            text: decode_entities(&text.replace("<br>", "\n")),
+            dir,
+            rest,
+        })
+    }
+}
+
+fn split_label(inner: &str) -> Option<(&'static str, &str)> {
+    LABELS
+        .into_iter()
+        .find_map(|label| inner.strip_prefix(label).map(|text| (label, text)))
+}
+
+// 整次运行模型都不可用时写在正文前的提示，不算作摘要块，下次运行照常处理
+const NOTICE_OPEN: &str = "<p data-miniflux-ai-notice>";
+
+pub fn with_notice(notice: &str, content: &str) -> String {
+    format!("{}{}</p>{}", NOTICE_OPEN, escape(notice), content)
+}
+
+// 没有提示时返回 None
+pub fn strip_notice(content: &str) -> Option<&str> {
+    content
+        .strip_prefix(NOTICE_OPEN)?
+        .split_once("</p>")
+        .map(|(_, original)| original)
+}
+
+// 去掉摘要块，返回原文，带全文翻译时返回折叠起来的原文
+pub fn strip_summary_block(content: &str) -> Option<&str> {
+    if !has_summary_block(content) {
+        return None;
+    }
+    let (_, original) = content.split_once(BLOCK_SEPARATOR)?;
+    if !original.starts_with(TRANSLATION_OPEN) {
+        return Some(original);
+    }
+    let start = original.find(ORIGINAL_OPEN)? + ORIGINAL_OPEN.len();
+    original[start..].strip_suffix("</details>")
+}
+
+const TRANSLATION_OPEN: &str = "<div data-miniflux-ai-translation>";
+const ORIGINAL_OPEN: &str = "<details data-miniflux-ai-original><summary>原文</summary>";
+
+// 摘要块后面放逐段翻译的正文，原文折叠在 `<details>` 中
+pub fn with_translation(content: &str, paragraphs: &[String]) -> Option<String> {
+    let (block, original) = content.split_once(BLOCK_SEPARATOR)?;
+    let translated: String = paragraphs
+        .iter()
+        .map(|p| format!("<p>{}</p>", escape(p)))
+        .collect();
+    Some(format!(
+        "{}{}{}{}</div>{}{}</details>",
+        block, BLOCK_SEPARATOR, TRANSLATION_OPEN, translated, ORIGINAL_OPEN, original
+    ))
+}
+
+pub const DEFAULT_MEDIA_TEMPLATE: &str =
+    "🖼️ {image_count} 张图片 · 🎬 {video_count} 个视频 {embed_hosts}";
+
+// 文章没有图片、视频和嵌入内容时不输出媒体行
+pub fn render_media_line(template: &str, stats: &MediaStats) -> Option<String> {
+    if stats.images == 0 && stats.videos == 0 && stats.embed_hosts.is_empty() {
+        return None;
+    }
+
+    let line = template
+        .replace("{image_count}", &stats.images.to_string())
+        .replace("{video_count}", &stats.videos.to_string())
+        .replace("{embed_hosts}", &stats.embed_hosts.join(", "));
+    Some(line.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paywall::DEFAULT_NOTICE;
+
+    #[test]
+    fn recognizes_processed_tags() {
+        assert!(is_processed_tag(PROCESSED_TAG));
+        assert!(is_processed_tag(&format!("{}摘要", SUMMARY_LABEL)));
+        assert!(is_processed_tag(&format!("{}timeout", FAILURE_LABEL)));
+        assert!(is_processed_tag(&format!("{}摘录", EXTRACT_LABEL)));
+        assert!(!is_processed_tag("rust"));
+        assert!(!is_processed_tag(DEFAULT_NOTICE));
+    }
+
+    // 付费墙提示没有标签前缀，写成标签时需要同时加上 PROCESSED_TAG
+    #[test]
+    fn paywall_notice_tags_are_recognized_as_processed() {
+        let tags = summary_tags(&["rust".to_string()], "", DEFAULT_NOTICE);
+        assert_eq!(tags, vec!["rust", PROCESSED_TAG, DEFAULT_NOTICE]);
+        assert!(tags.iter().any(|tag| is_processed_tag(tag)));
+    }
+
+    #[test]
+    fn labelled_summary_tags_do_not_add_processed_tag() {
+        let tags = summary_tags(&[], SUMMARY_LABEL, "摘要");
+        assert_eq!(tags, vec![format!("{}摘要", SUMMARY_LABEL)]);
+    }
+
+    #[test]
+    fn processed_tag_is_not_duplicated() {
+        let tags = summary_tags(&[PROCESSED_TAG.to_string()], "", DEFAULT_NOTICE);
+        assert_eq!(tags, vec![PROCESSED_TAG, DEFAULT_NOTICE]);
+    }
+
+    // 正文中的付费墙提示块同样会被识别为已处理
+    #[test]
+    fn paywall_notice_block_is_recognized_as_processed() {
+        for style in [BlockStyle::Pre, BlockStyle::Plaintext] {
+            let block = render_block(style, "", DEFAULT_NOTICE, "abc123", None);
+            assert!(has_summary_block(&prepend_block(&block, "<p>stub</p>")));
+        }
+    }
+
+    #[test]
+    fn renders_pre_and_plaintext_blocks() {
+        assert_eq!(
+            render_block(BlockStyle::Pre, SUMMARY_LABEL, "要点", "abc123", None),
+            format!(
+                "<pre data-miniflux-ai=\"v1:abc123\" style=\"white-space: pre-wrap;\"><code>\n{}\n要点</code></pre>",
+                SUMMARY_LABEL
+            )
+        );
+        assert_eq!(
+            render_block(
+                BlockStyle::Plaintext,
+                SUMMARY_LABEL,
+                " a < b\nc & d ",
+                "abc123",
+                None
+            ),
+            format!(
+                "<p data-miniflux-ai=\"v1:abc123\">{}a &lt; b<br>c &amp; d</p>",
+                SUMMARY_LABEL
+            )
+        );
+    }
+
+    #[test]
+    fn detects_summary_blocks_of_every_style() {
+        for style in [BlockStyle::Pre, BlockStyle::Plaintext] {
+            let block = render_block(style, SUMMARY_LABEL, "要点", "abc123", None);
+            assert!(has_summary_block(&prepend_block(&block, "<p>原文</p>")));
+        }
+        // 旧版本生成的摘要块没有标记
+        assert!(has_summary_block(
+            "<pre style=\"white-space: pre-wrap;\"><code>"
+        ));
+        assert!(has_summary_block(&format!("<p>{}要点</p>", SUMMARY_LABEL)));
+        assert!(has_summary_block(&format!(
+            "<p>{}timeout</p>",
+            FAILURE_LABEL
+        )));
+    }
+
+    #[test]
+    fn ordinary_content_is_not_a_summary_block() {
+        assert!(!has_summary_block(""));
+        assert!(!has_summary_block("<p>正文</p>"));
+        assert!(!has_summary_block("<pretty>"));
+        assert!(!has_summary_block("<p class=\"lead\">正文</p>"));
+    }
+
+    #[test]
+    fn parses_settings_hash_from_marker() {
+        for style in [BlockStyle::Pre, BlockStyle::Plaintext] {
+            let block = render_block(style, SUMMARY_LABEL, "要点", "abc123", Some("rtl"));
+            assert_eq!(
+                parse_marker(&prepend_block(&block, "<p>原文</p>")),
+                Some(Marker {
+                    version: MARKER_VERSION,
+                    hash: Some("abc123"),
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn legacy_blocks_have_no_hash() {
+        let legacy =
+            "<pre style=\"white-space: pre-wrap;\"><code>\n要点</code></pre><hr><br />原文";
+        assert_eq!(
+            parse_marker(legacy),
+            Some(Marker {
+                version: "",
+                hash: None,
+            })
+        );
+        assert_eq!(
+            parse_marker("<p data-miniflux-ai=\"v0\">要点</p>"),
+            Some(Marker {
+                version: "v0",
+                hash: None,
+            })
+        );
+        assert_eq!(parse_marker("<p>原文</p>"), None);
+    }
+
+    #[test]
+    fn strips_summary_block_back_to_original() {
+        let original = "<p>原文 <b>保留</b></p><hr><br /><p>第二段</p>";
+        for style in [BlockStyle::Pre, BlockStyle::Plaintext] {
+            let block = render_block(style, SUMMARY_LABEL, "要点", "abc123", None);
+            assert_eq!(
+                strip_summary_block(&prepend_block(&block, original)),
+                Some(original)
+            );
+        }
+        assert_eq!(strip_summary_block(original), None);
+    }
+
+    #[test]
+    fn parses_text_directions() {
+        assert_eq!(" RTL ".parse::<TextDirection>(), Ok(TextDirection::Rtl));
+        assert_eq!("ltr".parse::<TextDirection>(), Ok(TextDirection::Ltr));
+        assert_eq!("auto".parse::<TextDirection>(), Ok(TextDirection::Auto));
+        assert!("up".parse::<TextDirection>().is_err());
+    }
+
+    // dir 放在标记之后，标记仍是第一个属性
+    #[test]
+    fn renders_dir_after_the_marker() {
+        let block = render_block(
+            BlockStyle::Plaintext,
+            SUMMARY_LABEL,
+            "ملخص",
+            "abc123",
+            Some("rtl"),
+        );
+        assert!(block.starts_with("<p data-miniflux-ai=\"v1:abc123\" dir=\"rtl\">"));
+        assert!(has_summary_block(&block));
+        let block = render_block(
+            BlockStyle::Pre,
+            SUMMARY_LABEL,
+            "ملخص",
+            "abc123",
+            Some("auto"),
+        );
+        assert!(block.starts_with("<pre data-miniflux-ai=\"v1:abc123\" dir=\"auto\" style="));
+    }
+
+    #[test]
+    fn translation_keeps_the_original_folded() {
+        let block = render_block(BlockStyle::Pre, SUMMARY_LABEL, "要点", "abc123", None);
+        let content = prepend_block(&block, "<p>Hello <b>world</b></p>");
+        let translated =
+            with_translation(&content, &["你好，世界".to_string(), "a < b".to_string()]).unwrap();
+        assert!(translated.starts_with(&format!("{}{}", block, BLOCK_SEPARATOR)));
+        assert!(translated.contains("<p>你好，世界</p><p>a &lt; b</p>"));
+        // 去掉摘要块时返回折叠前的原文
+        assert_eq!(
+            strip_summary_block(&translated),
+            Some("<p>Hello <b>world</b></p>")
+        );
+    }
+
+    #[test]
+    fn translation_needs_a_summary_block() {
+        assert_eq!(
+            with_translation("<p>Hello</p>", &["你好".to_string()]),
+            None
+        );
+    }
+
+    #[test]
+    fn renders_media_line_from_template() {
+        let stats = MediaStats {
+            images: 3,
+            videos: 1,
+            embed_hosts: vec!["youtube.com".to_string(), "codepen.io".to_string()],
+        };
+        assert_eq!(
+            render_media_line(DEFAULT_MEDIA_TEMPLATE, &stats).as_deref(),
+            Some("🖼️ 3 张图片 · 🎬 1 个视频 youtube.com, codepen.io")
+        );
+        let images_only = MediaStats {
+            images: 2,
+            ..Default::default()
+        };
+        assert_eq!(
+            render_media_line(DEFAULT_MEDIA_TEMPLATE, &images_only).as_deref(),
+            Some("🖼️ 2 张图片 · 🎬 0 个视频")
+        );
+    }
+
+    #[test]
+    fn no_media_line_without_media() {
+        assert_eq!(
+            render_media_line(DEFAULT_MEDIA_TEMPLATE, &MediaStats::default()),
+            None
+        );
+    }
+
+    const ROUND_TRIP_TEXTS: [&str; 4] = [
+        "要点一\n要点二",
+        "a < b && c > \"d\"",
+        "literal <br> and &amp; stay literal",
+        "مرحبا\n- English bullet",
+    ];
+
+    // 解析出的内容按原样式重新渲染，结果和原来的摘要块完全一致
+    #[test]
+    fn parse_block_round_trips_rendered_blocks() {
+        let rest = "<hr><br /><p>原文</p>";
+        for style in [BlockStyle::Pre, BlockStyle::Plaintext] {
+            for label in LABELS {
+                for text in ROUND_TRIP_TEXTS {
+                    for dir in [None, Some("rtl"), Some("auto")] {
+                        let block = render_block(style, label, text, "abc123", dir);
+                        let content = prepend_block(&block, "<p>原文</p>");
+                        let parsed = parse_block(&content).unwrap();
+                        assert_eq!(
+                            parsed,
+                            ParsedBlock {
+                                style,
+                                label,
+                                text: text.to_string(),
+                                dir,
+                                rest,
+                            }
+                        );
+                        assert_eq!(
+                            render_block(
+                                parsed.style,
+                                parsed.label,
+                                &parsed.text,
+                                "abc123",
+                                parsed.dir
+                            ),
+                            block
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    // 切换样式后再切换回来，摘要内容不变
+    #[test]
+    fn style_migration_round_trips() {
+        for text in ROUND_TRIP_TEXTS {
+            let pre = prepend_block(
+                &render_block(BlockStyle::Pre, SUMMARY_LABEL, text, "abc123", None),
+                "<p>原文</p>",
+            );
+            let parsed = parse_block(&pre).unwrap();
+            let plain = format!(
+                "{}{}",
+                render_block(
+                    BlockStyle::Plaintext,
+                    parsed.label,
+                    &parsed.text,
+                    "abc123",
+                    parsed.dir
+                ),
+                parsed.rest
+            );
+            let parsed = parse_block(&plain).unwrap();
+            let back = format!(
+                "{}{}",
+                render_block(
+                    BlockStyle::Pre,
+                    parsed.label,
+                    &parsed.text,
+                    "abc123",
+                    parsed.dir
+                ),
+                parsed.rest
+            );
+            assert_eq!(back, pre);
+            assert_eq!(strip_summary_block(&back), Some("<p>原文</p>"));
+        }
+    }
+
+    #[test]
+    fn parse_block_rejects_unknown_structures() {
+        assert_eq!(parse_block("<p>原文</p>"), None);
+        // 没有分隔线
+        assert_eq!(
+            parse_block(&render_block(
+                BlockStyle::Pre,
+                SUMMARY_LABEL,
+                "要点",
+                "abc123",
+                None
+            )),
+            None
+        );
+        // 未知标签前缀
+        let block = render_block(BlockStyle::Plaintext, "自定义：", "要点", "abc123", None);
+        assert_eq!(parse_block(&prepend_block(&block, "<p>原文</p>")), None);
+        // 被改动过的摘要块
+        let edited = prepend_block(
+            &format!(
+                "<pre><code>\n{}要点</code></pre><p>extra</p>",
+                SUMMARY_LABEL
+            ),
+            "<p>原文</p>",
+        );
+        assert_eq!(parse_block(&edited), None);
+    }
+
+    #[test]
+    fn notice_round_trips_and_is_not_a_summary_block() {
+        let original = "<p>原文</p>";
+        let noticed = with_notice("AI <summary> unavailable", original);
+        assert!(noticed.contains("AI &lt;summary&gt; unavailable"));
+        assert!(!has_summary_block(&noticed));
+        assert_eq!(strip_notice(&noticed), Some(original));
+        assert_eq!(strip_notice(original), None);
+        assert_eq!(strip_notice(""), None);
+    }
+}
@@ -0,0 +1,96 @@
+use crate::html::is_cjk;
+use serde::Deserialize;
+
+// 按文章长度选择提示词预设，`max_words` 为该档的字数上限（含）
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LengthTier {
+    pub max_words: usize,
+    pub preset: String,
+}
+
+impl LengthTier {
+    pub fn prompt(&self) -> String {
+        format!("preset:{}", self.preset)
+    }
+
+    pub fn label(&self) -> String {
+        format!("{}<={}", self.preset, self.max_words)
+    }
+}
+
+// 检查预设名称并按字数上限排序，任何一项无效时整体报错
+pub fn parse(raw: &str) -> Result<Vec<LengthTier>, String> {
+    let mut tiers: Vec<LengthTier> = serde_json::from_str(raw).map_err(|e| e.to_string())?;
+    for tier in &tiers {
+        crate::prompts::preset(&tier.preset)?;
+    }
+    tiers.sort_by_key(|tier| tier.max_words);
+    Ok(tiers)
+}
+
+// 字数超过所有档位时不选择，沿用订阅源的提示词
+pub fn select(tiers: &[LengthTier], words: usize) -> Option<&LengthTier> {
+    tiers.iter().find(|tier| words <= tier.max_words)
+}
+
+// 中日韩文字每个字算一个词，其他文字按空白分词
+pub fn word_count(text: &str) -> usize {
+    let mut count = 0;
+    let mut in_word = false;
+    for c in text.chars() {
+        if is_cjk(c) {
+            count += 1;
+            in_word = false;
+        } else if c.is_whitespace() {
+            in_word = false;
+        } else if !in_word {
+            count += 1;
+            in_word = true;
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_sorts_tiers() {
+        let tiers =
+            parse(r#"[{"max_words": 2000, "preset": "detailed_zh"}, {"max_words": 300, "preset": "eli5"}]"#)
+                .unwrap();
+        assert_eq!(tiers[0].max_words, 300);
+        assert_eq!(tiers[0].prompt(), "preset:eli5");
+        assert_eq!(tiers[1].label(), "detailed_zh<=2000");
+    }
+
+    #[test]
+    fn rejects_unknown_presets_and_fields() {
+        assert!(parse(r#"[{"max_words": 300, "preset": "nope"}]"#).is_err());
+        assert!(parse(r#"[{"max_words": 300, "preset": "eli5", "model": "x"}]"#).is_err());
+        assert!(parse("not json").is_err());
+    }
+
+    #[test]
+    fn selects_first_tier_that_fits() {
+        let tiers = parse(
+            r#"[{"max_words": 300, "preset": "eli5"}, {"max_words": 2000, "preset": "detailed_zh"}]"#,
+        )
+        .unwrap();
+        assert_eq!(select(&tiers, 300).unwrap().preset, "eli5");
+        assert_eq!(select(&tiers, 301).unwrap().preset, "detailed_zh");
+        // 超过所有档位时沿用原来的提示词
+        assert_eq!(select(&tiers, 2001), None);
+        assert_eq!(select(&[], 1), None);
+    }
+
+    #[test]
+    fn counts_cjk_characters_as_words() {
+        assert_eq!(word_count("Hello,  world!\nAgain"), 3);
+        assert_eq!(word_count("中文摘要"), 4);
+        assert_eq!(word_count("Rust语言 2024"), 4);
+        assert_eq!(word_count("  "), 0);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::html;
+use serde::Deserialize;
+
+pub const HINT_EMOJI: &str = "💡";
+pub const MAX_HINT_CHARS: usize = 15;
+pub const TLDR_PREFIX: &str = "TL;DR: ";
+pub const MAX_TLDR_CHARS: usize = 80;
+
+// 开启 TITLE_HINT 时追加到系统提示词后，让模型在一次请求里同时返回摘要和标题提示
+pub const HINT_PROMPT: &str = "Respond with a JSON object only, in the form {\"summary\": \"...\", \"hint\": \"...\"}. \"summary\" is the summary described above. \"hint\" is an ultra-short hook of at most 15 characters (e.g. \"FTC起诉Meta\") in the same language as the summary, without punctuation at the end.";
+
+// 开启 TITLE_SUFFIX 时使用，标题后缀是一句话的 TL;DR
+pub const TLDR_PROMPT: &str = "Respond with a JSON object only, in the form {\"summary\": \"...\", \"hint\": \"...\"}. \"summary\" is the summary described above. \"hint\" is a one-line TL;DR of the article of at most 80 characters in the same language as the summary, without punctuation at the end.";
+
+// 追加到标题后的内容：TITLE_HINT 为极短的钩子，TITLE_SUFFIX 为一句话的 TL;DR
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HintKind {
+    Hook,
+    Tldr,
+}
+
+impl HintKind {
+    pub fn prompt(&self) -> &'static str {
+        match self {
+            HintKind::Hook => HINT_PROMPT,
+            HintKind::Tldr => TLDR_PROMPT,
+        }
+    }
+
+    fn marker(&self) -> &'static str {
+        match self {
+            HintKind::Hook => HINT_EMOJI,
+            HintKind::Tldr => TLDR_PREFIX,
+        }
+    }
+
+    fn max_chars(&self) -> usize {
+        match self {
+            HintKind::Hook => MAX_HINT_CHARS,
+            HintKind::Tldr => MAX_TLDR_CHARS,
+        }
+    }
+
+    pub fn default_separator(&self) -> &'static str {
+        match self {
+            HintKind::Hook => " · ",
+            HintKind::Tldr => " — ",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SummaryWithHint {
+    pub summary: String,
+    #[serde(default)]
+    pub hint: String,
+}
+
+// 按字符截断提示，英文等以空格分词的文本尽量在词边界截断
+pub fn truncate_hint(hint: &str, max_chars: usize) -> String {
+    let hint = html::collapse_whitespace(hint);
+    if hint.chars().count() <= max_chars {
+        return hint.trim_end_matches(is_trailing_punctuation).to_string();
+    }
+
+    let cut: String = hint.chars().take(max_chars).collect();
+    let next = hint.chars().nth(max_chars);
+    let mid_word = next.is_some_and(|c| !c.is_whitespace() && !html::is_cjk(c))
+        && cut.chars().last().is_some_and(|c| !html::is_cjk(c));
+    let cut = match cut.rfind(' ') {
+        Some(i) if mid_word => &cut[..i],
+        _ => cut.as_str(),
+    };
+
+    cut.trim_end_matches(is_trailing_punctuation).to_string()
+}
+
+fn is_trailing_punctuation(c: char) -> bool {
+    c.is_whitespace()
+        || matches!(c, '。' | '，' | '、' | '；' | '：' | '！' | '？' | '…')
+        || c.is_ascii_punctuation()
+}
+
+// 标题里已经有 分隔符 + 💡（或 TL;DR）时说明已追加过
+pub fn has_hint(title: &str, separator: &str, kind: HintKind) -> bool {
+    title.contains(&format!("{}{}", separator, kind.marker()))
+}
+
+pub fn append_hint(title: &str, separator: &str, hint: &str, kind: HintKind) -> Option<String> {
+    let hint = truncate_hint(hint, kind.max_chars());
+    if hint.is_empty() || has_hint(title, separator, kind) {
+        return None;
+    }
+
+    Some(format!("{}{}{}{}", title, separator, kind.marker(), hint))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncates_cjk_hints_by_characters() {
+        assert_eq!(
+            truncate_hint("FTC起诉Meta垄断案今日开庭审理进入第二阶段", 15),
+            "FTC起诉Meta垄断案今日开"
+        );
+        assert_eq!(truncate_hint("短提示。", 15), "短提示");
+    }
+
+    #[test]
+    fn truncates_english_hints_at_word_boundaries() {
+        assert_eq!(truncate_hint("Rust 2024 edition released", 15), "Rust 2024");
+        assert_eq!(truncate_hint("Supercalifragilistic", 5), "Super");
+        assert_eq!(truncate_hint("  spaced \n out!  ", 15), "spaced out");
+    }
+
+    #[test]
+    fn appends_hint_once() {
+        let title = append_hint("Meta 被起诉", " · ", "FTC起诉Meta", HintKind::Hook).unwrap();
+        assert_eq!(title, "Meta 被起诉 · 💡FTC起诉Meta");
+        assert!(has_hint(&title, " · ", HintKind::Hook));
+        assert_eq!(append_hint(&title, " · ", "again", HintKind::Hook), None);
+        assert_eq!(append_hint("Title", " · ", " 。", HintKind::Hook), None);
+    }
+
+    #[test]
+    fn parses_summary_with_hint() {
+        let parsed: SummaryWithHint = serde_json::from_str(r#"{"summary": "要点"}"#).unwrap();
+        assert_eq!(
+            (parsed.summary.as_str(), parsed.hint.as_str()),
+            ("要点", "")
+        );
+    }
+
+    #[test]
+    fn appends_tldr_suffix_once() {
+        let kind = HintKind::Tldr;
+        let title = append_hint(
+            "Rust 2024",
+            kind.default_separator(),
+            "New edition ships async closures.",
+            kind,
+        )
+        .unwrap();
+        assert_eq!(title, "Rust 2024 — TL;DR: New edition ships async closures");
+        assert!(has_hint(&title, " — ", kind));
+        assert!(!has_hint(&title, " — ", HintKind::Hook));
+        assert_eq!(append_hint(&title, " — ", "again", kind), None);
+    }
+
+    // TL;DR 的长度上限比钩子宽得多
+    #[test]
+    fn tldr_allows_longer_suffixes() {
+        let tldr = "word ".repeat(30);
+        let title = append_hint("T", " — ", &tldr, HintKind::Tldr).unwrap();
+        let suffix = title.strip_prefix("T — TL;DR: ").unwrap();
+        assert!(suffix.chars().count() <= MAX_TLDR_CHARS);
+        assert!(suffix.chars().count() > MAX_HINT_CHARS);
+        assert!(suffix.ends_with("word"));
+        assert_ne!(HintKind::Tldr.prompt(), HintKind::Hook.prompt());
+    }
+}
@@ -0,0 +1,76 @@
+use serde_json::{json, Value};
+use worker::js_sys::{Function, Promise, Reflect, JSON};
+use worker::wasm_bindgen::{JsCast, JsValue};
+use worker::wasm_bindgen_futures::JsFuture;
+use worker::Env;
+
+// Workers AI 绑定，直接在 Cloudflare 上运行模型，不需要外部接口地址和密钥
+pub struct WorkersAi {
+    binding: JsValue,
+}
+
+impl WorkersAi {
+    // workers-rs 还没有封装 AI 绑定，直接从 env 上读取
+    pub fn from_env(env: &Env, name: &str) -> Option<Self> {
+        let binding = Reflect::get(env, &JsValue::from(name)).ok()?;
+        (!binding.is_undefined()).then_some(WorkersAi { binding })
+    }
+
+    pub async fn run(&self, model: &str, messages: Value) -> Result<String, String> {
+        let run: Function = Reflect::get(&self.binding, &JsValue::from("run"))
+            .and_then(|run| run.dyn_into())
+            .map_err(|e| format!("AI binding has no run method: {:?}", e))?;
+        let input = JSON::parse(&request_body(messages).to_string())
+            .map_err(|e| format!("Invalid Workers AI input: {:?}", e))?;
+        let promise: Promise = run
+            .call2(&self.binding, &JsValue::from(model), &input)
+            .and_then(|promise| promise.dyn_into())
+            .map_err(|e| format!("Workers AI request failed: {:?}", e))?;
+        let output = JsFuture::from(promise)
+            .await
+            .map_err(|e| format!("Workers AI request failed: {:?}", e))?;
+        let output: String = JSON::stringify(&output)
+            .map_err(|e| format!("Invalid Workers AI output: {:?}", e))?
+            .into();
+        let output: Value = serde_json::from_str(&output).map_err(|e| e.to_string())?;
+        parse_response(&output)
+    }
+}
+
+// 文本生成模型接受和 OpenAI 相同结构的 messages
+pub fn request_body(messages: Value) -> Value {
+    json!({ "messages": messages })
+}
+
+// 返回格式为 `{"response": "..."}`
+pub fn parse_response(output: &Value) -> Result<String, String> {
+    output
+        .get("response")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| format!("Unexpected Workers AI output: {}", output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_messages() {
+        let messages = json!([{"role": "user", "content": "hi"}]);
+        assert_eq!(
+            request_body(messages.clone()),
+            json!({ "messages": messages })
+        );
+    }
+
+    #[test]
+    fn reads_response_text() {
+        assert_eq!(
+            parse_response(&json!({"response": "摘要", "usage": {}})),
+            Ok("摘要".to_string())
+        );
+        assert!(parse_response(&json!({"result": "x"})).is_err());
+        assert!(parse_response(&json!({"response": null})).is_err());
+    }
+}